@@ -1,20 +1,31 @@
 pub mod array;
+pub mod binding;
 pub mod class;
+pub mod complex;
+pub mod coverage;
+pub mod dir;
 pub mod enumerator;
+pub mod env;
 pub mod errorobj;
 pub mod fiber;
 pub mod file;
 pub mod float;
 pub mod hash;
 pub mod integer;
+pub mod io;
 pub mod kernel;
 pub mod math;
 pub mod method;
 pub mod module;
+pub mod numeric;
 pub mod object;
 pub mod process;
 pub mod procobj;
 pub mod range;
+pub mod rational;
 pub mod regexp;
+pub mod rubyvm;
 pub mod string;
+pub mod stringio;
 pub mod structobj;
+pub mod time;
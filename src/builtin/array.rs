@@ -50,6 +50,7 @@ pub fn init_array(globals: &mut Globals) -> Value {
     globals.add_builtin_instance_method(class, "zip", zip);
     globals.add_builtin_instance_method(class, "grep", grep);
     globals.add_builtin_instance_method(class, "sort", sort);
+    globals.add_builtin_instance_method(class, "deconstruct", deconstruct);
     globals.add_builtin_class_method(obj, "new", array_new);
     obj
 }
@@ -323,26 +324,10 @@ fn each(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
         }
     };
 
-    let mut arg = Args::new(vm.get_iseq(method)?.params.req_params);
+    // An Array element is splatted for the block parameters by the block itself.
+    let mut arg = Args::new1(Value::nil());
     for i in &aref.elements {
-        match i.as_array() {
-            Some(aref) if arg.len() != 1 => {
-                for j in 0..arg.len() {
-                    arg[j] = if j < aref.elements.len() {
-                        aref.elements[j]
-                    } else {
-                        Value::nil()
-                    };
-                }
-            }
-            _ => {
-                arg[0] = *i;
-                for j in 1..arg.len() {
-                    arg[j] = Value::nil();
-                }
-            }
-        };
-
+        arg[0] = *i;
         vm.eval_block(method, &arg)?;
     }
     Ok(self_val)
@@ -561,6 +546,11 @@ fn dup(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
     Ok(Value::array_from(&vm.globals, aref.elements.clone()))
 }
 
+fn deconstruct(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    Ok(self_val)
+}
+
 fn pack(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
     vm.check_args_range(args.len(), 0, 1)?;
     let aref = vm.expect_array(self_val, "Receiver")?;
@@ -0,0 +1,35 @@
+use crate::*;
+
+/// Binding: currently only used to stop in the debugger with `binding.break`.
+pub fn init_binding(globals: &mut Globals) -> Value {
+    let id = globals.get_ident_id("Binding");
+    let class = ClassRef::from(id, globals.builtins.object);
+    let obj = Value::class(globals, class);
+    globals.add_builtin_instance_method(class, "break", break_);
+    globals.add_builtin_instance_method(class, "b", break_);
+    obj
+}
+
+/// Kernel#binding
+pub fn binding(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let id = vm.globals.get_ident_id("Binding");
+    let class = vm.globals.builtins.object.get_var(id).unwrap();
+    Ok(Value::ordinary_object(class))
+}
+
+/// Kernel#debugger
+pub fn debugger(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    vm.debugger_break();
+    Ok(Value::nil())
+}
+
+// Instance methods
+
+/// Stop in the debugger at the caller of `break`.
+fn break_(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    vm.debugger_break();
+    Ok(Value::nil())
+}
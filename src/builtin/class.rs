@@ -37,10 +37,15 @@ fn class_new(vm: &mut VM, _: Value, args: &Args) -> VMResult {
 /// Create new instance of `self`.
 pub fn new(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
     let new_instance = Value::ordinary_object(self_val);
-    // Call initialize method if it exists.
-    if let Some(method) = self_val.get_instance_method(IdentId::INITIALIZE) {
-        vm.eval_send(method, new_instance, args)?;
-    };
+    // Call initialize method if it exists in the class or its superclasses.
+    let mut class = Some(self_val);
+    while let Some(c) = class {
+        if let Some(method) = c.get_instance_method(IdentId::INITIALIZE) {
+            vm.eval_send(method, new_instance, args)?;
+            break;
+        }
+        class = c.superclass();
+    }
     Ok(new_instance)
 }
 
@@ -0,0 +1,522 @@
+use super::rational::real_value;
+use crate::*;
+use std::cmp::Ordering;
+
+/// A complex number with real parts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ComplexInfo {
+    pub re: Real,
+    pub im: Real,
+}
+
+impl ComplexInfo {
+    pub fn new(re: Real, im: Real) -> Self {
+        ComplexInfo { re, im }
+    }
+
+    pub fn from_real(re: Real) -> Self {
+        ComplexInfo::new(re, Real::Integer(0))
+    }
+
+    /// Converts an Integer, a Float, a Rational or a Complex.
+    pub fn from_value(val: Value) -> Option<Self> {
+        match Real::from_value(val) {
+            Some(re) => Some(ComplexInfo::from_real(re)),
+            None => val.as_complex(),
+        }
+    }
+
+    pub fn polar(abs: f64, arg: f64) -> Self {
+        ComplexInfo::new(Real::Float(abs * arg.cos()), Real::Float(abs * arg.sin()))
+    }
+
+    pub fn is_exact_zero(&self) -> bool {
+        self.re.is_exact_zero() && self.im.is_exact_zero()
+    }
+
+    /// `==` of the values, which compares Integer, Float and Rational parts numerically.
+    pub fn num_eq(&self, other: &Self) -> bool {
+        self.re.partial_cmp(other.re) == Some(Ordering::Equal)
+            && self.im.partial_cmp(other.im) == Some(Ordering::Equal)
+    }
+
+    pub fn add(&self, other: &Self) -> Option<Self> {
+        Some(ComplexInfo::new(
+            self.re.checked_add(other.re)?,
+            self.im.checked_add(other.im)?,
+        ))
+    }
+
+    pub fn sub(&self, other: &Self) -> Option<Self> {
+        Some(ComplexInfo::new(
+            self.re.checked_sub(other.re)?,
+            self.im.checked_sub(other.im)?,
+        ))
+    }
+
+    pub fn mul(&self, other: &Self) -> Option<Self> {
+        let re = self
+            .re
+            .checked_mul(other.re)?
+            .checked_sub(self.im.checked_mul(other.im)?)?;
+        let im = self
+            .re
+            .checked_mul(other.im)?
+            .checked_add(self.im.checked_mul(other.re)?)?;
+        Some(ComplexInfo::new(re, im))
+    }
+
+    /// Division as `quo`. An exact zero `other` must be checked by the caller.
+    pub fn quo(&self, other: &Self) -> Option<Self> {
+        let den = other
+            .re
+            .checked_mul(other.re)?
+            .checked_add(other.im.checked_mul(other.im)?)?;
+        let re = self
+            .re
+            .checked_mul(other.re)?
+            .checked_add(self.im.checked_mul(other.im)?)?;
+        let im = self
+            .im
+            .checked_mul(other.re)?
+            .checked_sub(self.re.checked_mul(other.im)?)?;
+        Some(ComplexInfo::new(re.quo(den)?, im.quo(den)?))
+    }
+
+    /// `self` must not be an exact zero if `exp` is negative.
+    pub fn pow_int(&self, exp: i64) -> Option<Self> {
+        let mut res = ComplexInfo::from_real(Real::Integer(1));
+        let mut base = *self;
+        let mut n = exp.checked_abs()?;
+        while n > 0 {
+            if n & 1 == 1 {
+                res = res.mul(&base)?;
+            }
+            n >>= 1;
+            if n > 0 {
+                base = base.mul(&base)?;
+            }
+        }
+        if exp < 0 {
+            ComplexInfo::from_real(Real::Integer(1)).quo(&res)
+        } else {
+            Some(res)
+        }
+    }
+
+    /// `self ** exp` for a non-integer exponent, computed in Float by exp(exp * log(self)).
+    pub fn pow(&self, exp: &Self) -> Self {
+        if self.re.is_zero() && self.im.is_zero() {
+            return ComplexInfo::from_real(Real::Float(0.0));
+        }
+        let log_abs = self.abs().ln();
+        let arg = self.arg();
+        let (c, d) = (exp.re.to_f(), exp.im.to_f());
+        let abs = (c * log_abs - d * arg).exp();
+        ComplexInfo::polar(abs, d * log_abs + c * arg)
+    }
+
+    pub fn conj(&self) -> Option<Self> {
+        Some(ComplexInfo::new(self.re, self.im.checked_neg()?))
+    }
+
+    pub fn abs(&self) -> f64 {
+        self.re.to_f().hypot(self.im.to_f())
+    }
+
+    pub fn abs2(&self) -> Option<Real> {
+        self.re
+            .checked_mul(self.re)?
+            .checked_add(self.im.checked_mul(self.im)?)
+    }
+
+    pub fn arg(&self) -> f64 {
+        self.im.to_f().atan2(self.re.to_f())
+    }
+
+    fn sign_and_abs_im(&self) -> (&str, Real) {
+        if self.im.is_negative() {
+            ("-", self.im.checked_neg().unwrap_or(self.im))
+        } else {
+            ("+", self.im)
+        }
+    }
+
+    pub fn to_s(&self) -> String {
+        let (sign, im) = self.sign_and_abs_im();
+        format!("{}{}{}i", self.re.to_s(), sign, im.to_s())
+    }
+
+    pub fn inspect(&self) -> String {
+        let (sign, im) = self.sign_and_abs_im();
+        let mul = match im {
+            Real::Rational(_) => "*",
+            _ => "",
+        };
+        format!("({}{}{}{}i)", self.re.inspect(), sign, im.inspect(), mul)
+    }
+}
+
+pub fn init_complex(globals: &mut Globals) -> Value {
+    let id = globals.get_ident_id("Complex");
+    let class = ClassRef::from(id, globals.builtins.numeric);
+    let mut obj = Value::class(globals, class);
+    globals.add_builtin_class_method(obj, "rectangular", rectangular);
+    globals.add_builtin_class_method(obj, "rect", rectangular);
+    globals.add_builtin_class_method(obj, "polar", polar);
+    globals.add_builtin_instance_method(class, "real", real);
+    globals.add_builtin_instance_method(class, "imaginary", imaginary);
+    globals.add_builtin_instance_method(class, "imag", imaginary);
+    globals.add_builtin_instance_method(class, "+", add);
+    globals.add_builtin_instance_method(class, "-", sub);
+    globals.add_builtin_instance_method(class, "*", mul);
+    globals.add_builtin_instance_method(class, "/", div);
+    globals.add_builtin_instance_method(class, "quo", div);
+    globals.add_builtin_instance_method(class, "**", pow);
+    globals.add_builtin_instance_method(class, "-@", neg);
+    globals.add_builtin_instance_method(class, "==", eq);
+    globals.add_builtin_instance_method(class, "coerce", coerce);
+    globals.add_builtin_instance_method(class, "abs", abs);
+    globals.add_builtin_instance_method(class, "magnitude", abs);
+    globals.add_builtin_instance_method(class, "abs2", abs2);
+    globals.add_builtin_instance_method(class, "arg", arg);
+    globals.add_builtin_instance_method(class, "angle", arg);
+    globals.add_builtin_instance_method(class, "phase", arg);
+    globals.add_builtin_instance_method(class, "conjugate", conj);
+    globals.add_builtin_instance_method(class, "conj", conj);
+    globals.add_builtin_instance_method(class, "polar", to_polar);
+    globals.add_builtin_instance_method(class, "rectangular", to_rect);
+    globals.add_builtin_instance_method(class, "rect", to_rect);
+    globals.add_builtin_instance_method(class, "real?", is_real);
+    globals.add_builtin_instance_method(class, "to_i", to_i);
+    globals.add_builtin_instance_method(class, "to_f", to_f);
+    globals.add_builtin_instance_method(class, "to_r", to_r);
+    globals.add_builtin_instance_method(class, "to_c", to_c);
+    globals.add_builtin_instance_method(class, "to_s", to_s);
+    globals.add_builtin_instance_method(class, "inspect", inspect);
+    // Complex::I is an instance, so the class must be registered first.
+    globals.builtins.complex = obj;
+    let i = ComplexInfo::new(Real::Integer(0), Real::Integer(1));
+    let id = globals.get_ident_id("I");
+    obj.set_var(id, Value::complex(globals, i));
+    obj
+}
+
+// Utils
+
+fn expect_complex(vm: &mut VM, val: Value) -> Result<ComplexInfo, RubyError> {
+    match val.as_complex() {
+        Some(c) => Ok(c),
+        None => Err(vm.error_type("Receiver must be Complex.")),
+    }
+}
+
+fn expect_real(vm: &mut VM, val: Value) -> Result<Real, RubyError> {
+    match Real::from_value(val) {
+        Some(real) => Ok(real),
+        None => {
+            let class = vm.globals.get_class_name(val);
+            Err(vm.error_type(format!("not a real: {}", class)))
+        }
+    }
+}
+
+/// Returns the result of exact arithmetic, which is None on overflow.
+fn complex_value(vm: &mut VM, c: Option<ComplexInfo>) -> VMResult {
+    match c {
+        Some(c) => Ok(Value::complex(&vm.globals, c)),
+        None => Err(vm.error_range("integer overflow")),
+    }
+}
+
+/// Applies `op` to a Complex and a number, or dispatches `rhs.coerce(self)` for others.
+fn binop(
+    vm: &mut VM,
+    self_val: Value,
+    args: &Args,
+    method: &str,
+    op: fn(&ComplexInfo, &ComplexInfo) -> Option<ComplexInfo>,
+) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let lhs = expect_complex(vm, self_val)?;
+    match ComplexInfo::from_value(args[0]) {
+        Some(rhs) => complex_value(vm, op(&lhs, &rhs)),
+        None => {
+            let id = vm.globals.get_ident_id(method);
+            vm.coerce_binop(id, self_val, args[0])
+        }
+    }
+}
+
+/// Converts `self` into a real number if the imaginary part is an exact zero.
+fn expect_real_part(vm: &mut VM, self_val: Value, to: &str) -> Result<Real, RubyError> {
+    let c = expect_complex(vm, self_val)?;
+    if c.im.is_exact_zero() {
+        Ok(c.re)
+    } else {
+        Err(vm.error_range(format!("can't convert {} into {}", c.to_s(), to)))
+    }
+}
+
+// Kernel methods
+
+/// Complex(re, im = 0)
+pub fn complex(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_range(args.len(), 1, 2)?;
+    if args.len() == 1 {
+        if let Some(c) = args[0].as_complex() {
+            return Ok(Value::complex(&vm.globals, c));
+        }
+    }
+    rectangular(vm, Value::nil(), args)
+}
+
+// Class methods
+
+fn rectangular(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_range(args.len(), 1, 2)?;
+    let re = expect_real(vm, args[0])?;
+    let im = if args.len() == 2 {
+        expect_real(vm, args[1])?
+    } else {
+        Real::Integer(0)
+    };
+    Ok(Value::complex(&vm.globals, ComplexInfo::new(re, im)))
+}
+
+fn polar(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_range(args.len(), 1, 2)?;
+    let abs = expect_real(vm, args[0])?;
+    if args.len() == 1 {
+        return Ok(Value::complex(&vm.globals, ComplexInfo::from_real(abs)));
+    }
+    let arg = expect_real(vm, args[1])?;
+    let c = ComplexInfo::polar(abs.to_f(), arg.to_f());
+    Ok(Value::complex(&vm.globals, c))
+}
+
+// Instance methods
+
+fn real(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let c = expect_complex(vm, self_val)?;
+    Ok(c.re.into_value(&vm.globals))
+}
+
+fn imaginary(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let c = expect_complex(vm, self_val)?;
+    Ok(c.im.into_value(&vm.globals))
+}
+
+fn add(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    binop(vm, self_val, args, "+", ComplexInfo::add)
+}
+
+fn sub(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    binop(vm, self_val, args, "-", ComplexInfo::sub)
+}
+
+fn mul(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    binop(vm, self_val, args, "*", ComplexInfo::mul)
+}
+
+fn div(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    match ComplexInfo::from_value(args[0]) {
+        Some(rhs) if rhs.is_exact_zero() => Err(vm.error_zero_div("divided by 0")),
+        _ => binop(vm, self_val, args, "/", ComplexInfo::quo),
+    }
+}
+
+fn pow(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let lhs = expect_complex(vm, self_val)?;
+    let exp = match Real::from_value(args[0]) {
+        Some(Real::Integer(i)) => Some(i),
+        Some(Real::Rational(r)) if r.is_integer() => Some(r.num),
+        _ => None,
+    };
+    match exp {
+        Some(exp) => {
+            if lhs.is_exact_zero() && exp < 0 {
+                return Err(vm.error_zero_div("divided by 0"));
+            }
+            complex_value(vm, lhs.pow_int(exp))
+        }
+        None => match ComplexInfo::from_value(args[0]) {
+            Some(exp) => Ok(Value::complex(&vm.globals, lhs.pow(&exp))),
+            None => vm.coerce_binop(IdentId::_POW, self_val, args[0]),
+        },
+    }
+}
+
+fn neg(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let c = expect_complex(vm, self_val)?;
+    let res =
+        c.re.checked_neg()
+            .and_then(|re| Some(ComplexInfo::new(re, c.im.checked_neg()?)));
+    complex_value(vm, res)
+}
+
+fn eq(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    Ok(Value::bool(self_val.equal(args[0])))
+}
+
+/// Complex#coerce(Numeric) -> [Complex, Complex]
+fn coerce(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    expect_complex(vm, self_val)?;
+    match ComplexInfo::from_value(args[0]) {
+        Some(c) => {
+            let lhs = Value::complex(&vm.globals, c);
+            Ok(Value::array_from(&vm.globals, vec![lhs, self_val]))
+        }
+        None => {
+            let class = vm.globals.get_class_name(args[0]);
+            Err(vm.error_type(format!("{} can't be coerced into Complex", class)))
+        }
+    }
+}
+
+fn abs(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    Ok(Value::flonum(expect_complex(vm, self_val)?.abs()))
+}
+
+fn abs2(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let c = expect_complex(vm, self_val)?;
+    real_value(vm, c.abs2())
+}
+
+fn arg(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    Ok(Value::flonum(expect_complex(vm, self_val)?.arg()))
+}
+
+fn conj(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let c = expect_complex(vm, self_val)?;
+    complex_value(vm, c.conj())
+}
+
+/// Complex#polar -> [abs, arg]
+fn to_polar(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let c = expect_complex(vm, self_val)?;
+    let ary = vec![Value::flonum(c.abs()), Value::flonum(c.arg())];
+    Ok(Value::array_from(&vm.globals, ary))
+}
+
+/// Complex#rectangular -> [real, imaginary]
+fn to_rect(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let c = expect_complex(vm, self_val)?;
+    let ary = vec![c.re.into_value(&vm.globals), c.im.into_value(&vm.globals)];
+    Ok(Value::array_from(&vm.globals, ary))
+}
+
+fn is_real(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    expect_complex(vm, self_val)?;
+    Ok(Value::false_val())
+}
+
+fn to_i(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let re = expect_real_part(vm, self_val, "Integer")?;
+    match re {
+        Real::Integer(i) => Ok(Value::fixnum(i)),
+        Real::Float(f) => crate::builtin::float::float_to_integer(vm, f),
+        Real::Rational(r) => Ok(Value::fixnum(r.truncate())),
+    }
+}
+
+fn to_f(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let re = expect_real_part(vm, self_val, "Float")?;
+    Ok(Value::flonum(re.to_f()))
+}
+
+fn to_r(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let re = expect_real_part(vm, self_val, "Rational")?;
+    let r = match re {
+        Real::Float(f) => RationalInfo::from_f64(f),
+        re => re.to_rational(),
+    };
+    real_value(vm, r.map(Real::Rational))
+}
+
+fn to_c(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    expect_complex(vm, self_val)?;
+    Ok(self_val)
+}
+
+fn to_s(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let s = expect_complex(vm, self_val)?.to_s();
+    Ok(Value::string(&vm.globals, s))
+}
+
+fn inspect(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let s = expect_complex(vm, self_val)?.inspect();
+    Ok(Value::string(&vm.globals, s))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test::*;
+
+    #[test]
+    fn complex_literal() {
+        let program = r#"
+        assert(Complex, 2i.class)
+        assert("(0+2i)", 2i.inspect)
+        assert("1+2i", (1 + 2i).to_s)
+        assert("(1.5-2.5i)", (1.5 - 2.5i).inspect)
+        assert("((1/2)+(1/3)*i)", Complex(1/2r, 1/3r).inspect)
+        assert(Complex(0, Rational(1, 2)), 0.5ri)
+        assert(Complex(0, 1), Complex::I)
+        "#;
+        assert_script(program);
+    }
+
+    #[test]
+    fn complex_arithmetic() {
+        let program = r#"
+        a = Complex(1, 2)
+        b = Complex(3, -1)
+        assert(Complex(4, 1), a + b)
+        assert(Complex(-2, 3), a - b)
+        assert(Complex(5, 5), a * b)
+        assert(Complex(Rational(1, 10), Rational(7, 10)), a / b)
+        assert(Complex(-3, 4), a ** 2)
+        assert(Complex(-1, 0), Complex::I ** 2)
+        assert(Complex(2, 2), 1 + a)
+        assert(Complex(2, 4), 2 * a)
+        assert(Complex(Rational(3, 2), 2), Rational(1, 2) + a)
+        assert(Complex(1, -2), a.conj)
+        assert(5.0, Complex(3, 4).abs)
+        assert(25, Complex(3, 4).abs2)
+        assert(Math::PI / 2, Complex(0, 1).arg)
+        assert([5.0, 0.0], Complex(5, 0).polar)
+        assert([1, 2], a.rect)
+        assert(true, Complex(3, 0) == 3)
+        assert(false, Complex(3, 1) == 3)
+        assert(1, Complex.polar(1, 0).real)
+        assert(3, Complex(3, 0).to_i)
+        begin
+          Complex(3, 1).to_i
+        rescue RangeError => e
+          assert("can't convert 3+1i into Integer", e.message)
+        end
+        "#;
+        assert_script(program);
+    }
+}
@@ -0,0 +1,241 @@
+use crate::*;
+use std::collections::HashMap;
+
+/// Coverage: line, branch and method coverage of files loaded after `Coverage.start`.
+pub fn init_coverage(globals: &mut Globals) -> Value {
+    let id = globals.get_ident_id("Coverage");
+    let class = ClassRef::from(id, None);
+    let obj = Value::module(globals, class);
+    globals.add_builtin_class_method(obj, "start", start);
+    globals.add_builtin_class_method(obj, "result", result);
+    globals.add_builtin_class_method(obj, "peek_result", peek_result);
+    globals.add_builtin_class_method(obj, "running?", running);
+    obj
+}
+
+// Utils
+
+/// Read the measurement mode from `Coverage.start(:all)` or
+/// `Coverage.start(lines: true, branches: true, methods: true)`.
+fn coverage_mode(vm: &mut VM, args: &Args) -> Result<CoverageMode, RubyError> {
+    if args.len() == 1 {
+        match args[0].as_symbol() {
+            Some(id) if vm.globals.get_ident_name(id) == "all" => return Ok(CoverageMode::all()),
+            _ => {}
+        }
+    }
+    let hash = match (args.len(), args.kw_arg) {
+        (0, None) => {
+            return Ok(CoverageMode {
+                legacy: true,
+                lines: true,
+                branches: false,
+                methods: false,
+            })
+        }
+        (0, Some(hash)) => vm.expect_hash(hash, "Keyword arguments")?,
+        (1, None) => vm.expect_hash(args[0], "1st arg")?,
+        _ => return Err(vm.error_argument("Wrong number of arguments.")),
+    };
+    let mut flag = |name: &str| {
+        let key = Value::symbol(vm.globals.get_ident_id(name));
+        match hash.get(&key) {
+            Some(val) => vm.val_to_bool(*val),
+            None => false,
+        }
+    };
+    Ok(CoverageMode {
+        legacy: false,
+        lines: flag("lines"),
+        branches: flag("branches"),
+        methods: flag("methods"),
+    })
+}
+
+fn new_hash(vm: &VM) -> Value {
+    Value::hash_from(&vm.globals, HashMap::new())
+}
+
+fn symbol(vm: &mut VM, name: &str) -> Value {
+    Value::symbol(vm.globals.get_ident_id(name))
+}
+
+/// Convert the coverage data into a Hash which maps a file path to its result.
+fn coverage_to_value(vm: &mut VM, coverage: &Coverage) -> Value {
+    let res = new_hash(vm);
+    for file in coverage.files() {
+        let mut lines = vec![Value::nil(); file.line_count];
+        for (line, count) in &file.lines {
+            if *line <= lines.len() {
+                lines[*line - 1] = Value::fixnum(*count as i64);
+            }
+        }
+        let lines = Value::array_from(&vm.globals, lines);
+        let path = Value::string(&vm.globals, file.path.to_string_lossy().to_string());
+        if coverage.mode.legacy {
+            res.as_hash().unwrap().insert(path, lines);
+            continue;
+        }
+        let file_res = new_hash(vm);
+        if coverage.mode.lines {
+            let key = symbol(vm, "lines");
+            file_res.as_hash().unwrap().insert(key, lines);
+        }
+        if coverage.mode.branches {
+            let branches = new_hash(vm);
+            let mut id = 0;
+            for ((line, _, _), counts) in &file.branches {
+                let line = Value::fixnum(*line as i64);
+                let arms = new_hash(vm);
+                for (i, arm) in ["then", "else"].iter().enumerate() {
+                    let arm = vec![symbol(vm, arm), Value::fixnum(id + 1 + i as i64), line];
+                    let arm = Value::array_from(&vm.globals, arm);
+                    arms.as_hash()
+                        .unwrap()
+                        .insert(arm, Value::fixnum(counts[i] as i64));
+                }
+                let key = vec![symbol(vm, "if"), Value::fixnum(id), line];
+                let key = Value::array_from(&vm.globals, key);
+                branches.as_hash().unwrap().insert(key, arms);
+                id += 3;
+            }
+            let key = symbol(vm, "branches");
+            file_res.as_hash().unwrap().insert(key, branches);
+        }
+        if coverage.mode.methods {
+            let methods = new_hash(vm);
+            for ((line, method), count) in &file.methods {
+                let (class, name) = match vm.globals.get_method_info(MethodRef::from(*method)) {
+                    MethodInfo::RubyFunc { iseq } => {
+                        let class = match iseq.class_defined {
+                            Some(list) => list.class,
+                            None => vm.globals.builtins.object,
+                        };
+                        let name = match iseq.kind {
+                            ISeqKind::Method(id) => Value::symbol(id),
+                            _ => Value::nil(),
+                        };
+                        (class, name)
+                    }
+                    _ => continue,
+                };
+                let key = vec![class, name, Value::fixnum(*line as i64)];
+                let key = Value::array_from(&vm.globals, key);
+                methods
+                    .as_hash()
+                    .unwrap()
+                    .insert(key, Value::fixnum(*count as i64));
+            }
+            let key = symbol(vm, "methods");
+            file_res.as_hash().unwrap().insert(key, methods);
+        }
+        res.as_hash().unwrap().insert(path, file_res);
+    }
+    res
+}
+
+// Class methods
+
+/// Coverage.start / Coverage.start(:all) / Coverage.start(lines:, branches:, methods:)
+fn start(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    if vm.globals.coverage.is_some() {
+        return Err(vm.error_internal("Coverage measurement is already setup."));
+    }
+    let mode = coverage_mode(vm, args)?;
+    vm.globals.coverage = Some(Coverage::new(mode));
+    Ok(Value::nil())
+}
+
+/// Returns the coverage result and stops the measurement.
+fn result(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    match vm.globals.coverage.take() {
+        Some(coverage) => Ok(coverage_to_value(vm, &coverage)),
+        None => Err(vm.error_internal("Coverage measurement is not enabled.")),
+    }
+}
+
+/// Returns the coverage result without stopping the measurement.
+fn peek_result(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    match vm.globals.coverage.clone() {
+        Some(coverage) => Ok(coverage_to_value(vm, &coverage)),
+        None => Err(vm.error_internal("Coverage measurement is not enabled.")),
+    }
+}
+
+fn running(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    Ok(Value::bool(vm.globals.coverage.is_some()))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::*;
+
+    #[test]
+    fn coverage() {
+        let path = std::env::temp_dir().join("ruruby_coverage_test.rb");
+        let lib = r#"class Foo
+  def bar(x)
+    if x > 2
+      puts "big"
+    else
+      puts "small"
+    end
+  end
+  def baz
+    puts "baz"
+  end
+end
+3.times { |i| Foo.new.bar(i) }
+"#;
+        std::fs::write(&path, lib).unwrap();
+        let program = format!(
+            r#"
+        assert(false, Coverage.running?)
+        Coverage.start(:all)
+        assert(true, Coverage.running?)
+        require "{0}"
+        res = Coverage.result["{0}"]
+        assert(false, Coverage.running?)
+        assert([1, 1, 3, 0, nil, 3, nil, nil, 1, 0, nil, nil, 4], res[:lines])
+        assert({{[:then, 1, 3] => 0, [:else, 2, 3] => 3}}, res[:branches][[:if, 0, 3]])
+        assert(3, res[:methods][[Foo, :bar, 2]])
+        assert(0, res[:methods][[Foo, :baz, 9]])
+        Coverage.start
+        require "{0}"
+        assert([1, 1, 3, 0, nil, 3, nil, nil, 1, 0, nil, nil, 4], Coverage.peek_result["{0}"])
+        "#,
+            path.to_string_lossy()
+        );
+        assert_script(&program);
+    }
+
+    #[test]
+    fn coverage_statement_lines() {
+        let path = std::env::temp_dir().join("ruruby_coverage_lines_test.rb");
+        let lib = r#"def f(a)
+  if a
+    y = 1
+  else
+    y = 2
+  end
+  y
+end
+f(true)
+"#;
+        std::fs::write(&path, lib).unwrap();
+        let program = format!(
+            r#"
+        Coverage.start(:all)
+        require "{0}"
+        res = Coverage.result["{0}"]
+        assert([1, 1, 1, nil, 0, nil, 1, nil, 1], res[:lines])
+        assert({{[:then, 1, 2] => 1, [:else, 2, 2] => 0}}, res[:branches][[:if, 0, 2]])
+        "#,
+            path.to_string_lossy()
+        );
+        assert_script(&program);
+    }
+}
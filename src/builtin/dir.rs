@@ -0,0 +1,157 @@
+use crate::*;
+use std::path::*;
+
+pub fn init_dir(globals: &mut Globals) -> Value {
+    let id = globals.get_ident_id("Dir");
+    let class = ClassRef::from(id, globals.builtins.object);
+    let obj = Value::class(globals, class);
+    globals.add_builtin_class_method(obj, "pwd", pwd);
+    globals.add_builtin_class_method(obj, "getwd", pwd);
+    globals.add_builtin_class_method(obj, "exist?", exist);
+    globals.add_builtin_class_method(obj, "mkdir", mkdir);
+    globals.add_builtin_class_method(obj, "rmdir", rmdir);
+    globals.add_builtin_class_method(obj, "entries", entries);
+    globals.add_builtin_class_method(obj, "children", children);
+    globals.add_builtin_class_method(obj, "glob", glob);
+    globals.add_builtin_class_method(obj, "[]", glob);
+    obj
+}
+
+// Utils
+
+fn string_to_path(vm: &mut VM, string: Value) -> Result<PathBuf, RubyError> {
+    let dir = vm.expect_string(&string, "Must be string.")?;
+    Ok(PathBuf::from(dir))
+}
+
+/// Returns sorted file names in `path`, not including "." and "..".
+fn read_dir(vm: &mut VM, path: Value) -> Result<Vec<String>, RubyError> {
+    let path = string_to_path(vm, path)?;
+    let dir = match std::fs::read_dir(&path) {
+        Ok(dir) => dir,
+        Err(err) => return Err(vm.error_internal(format!("{} @ dir_initialize - {:?}", err, path))),
+    };
+    let mut names = vec![];
+    for entry in dir {
+        match entry {
+            Ok(entry) => names.push(entry.file_name().to_string_lossy().into_owned()),
+            Err(err) => return Err(vm.error_internal(format!("{}", err))),
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+// Class methods
+
+fn pwd(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    match std::env::current_dir() {
+        Ok(dir) => Ok(Value::string(&vm.globals, dir.to_string_lossy().into_owned())),
+        Err(err) => Err(vm.error_internal(format!("{}", err))),
+    }
+}
+
+fn exist(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let path = string_to_path(vm, args[0])?;
+    Ok(Value::bool(path.is_dir()))
+}
+
+fn mkdir(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_range(args.len(), 1, 2)?;
+    let path = string_to_path(vm, args[0])?;
+    match std::fs::create_dir(&path) {
+        Ok(()) => Ok(Value::fixnum(0)),
+        Err(err) => Err(vm.error_internal(format!("{} @ dir_s_mkdir - {:?}", err, path))),
+    }
+}
+
+fn rmdir(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let path = string_to_path(vm, args[0])?;
+    match std::fs::remove_dir(&path) {
+        Ok(()) => Ok(Value::fixnum(0)),
+        Err(err) => Err(vm.error_internal(format!("{} @ dir_s_rmdir - {:?}", err, path))),
+    }
+}
+
+/// Dir.entries(path)
+fn entries(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let mut names = vec![".".to_string(), "..".to_string()];
+    names.append(&mut read_dir(vm, args[0])?);
+    let names = names
+        .into_iter()
+        .map(|name| Value::string(&vm.globals, name))
+        .collect();
+    Ok(Value::array_from(&vm.globals, names))
+}
+
+/// Dir.children(path)
+fn children(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let names = read_dir(vm, args[0])?
+        .into_iter()
+        .map(|name| Value::string(&vm.globals, name))
+        .collect();
+    Ok(Value::array_from(&vm.globals, names))
+}
+
+/// Dir.glob(pattern) / Dir.glob([pattern, ..])
+fn glob(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let patterns = match args[0].as_array() {
+        Some(aref) => aref.elements.clone(),
+        None => vec![args[0]],
+    };
+    let mut res = vec![];
+    for pat in patterns {
+        let pat = vm.expect_string(&pat, "Pattern")?;
+        let paths = match glob::glob(pat) {
+            Ok(paths) => paths,
+            Err(err) => return Err(vm.error_argument(format!("{}", err))),
+        };
+        for path in paths {
+            if let Ok(path) = path {
+                let path = path.to_string_lossy().into_owned();
+                res.push(Value::string(&vm.globals, path));
+            }
+        }
+    }
+    let val = Value::array_from(&vm.globals, res);
+    match args.block {
+        Some(method) => {
+            for path in val.as_array().unwrap().elements.clone() {
+                vm.eval_block(method, &Args::new1(path))?;
+            }
+            Ok(Value::nil())
+        }
+        None => Ok(val),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::*;
+
+    #[test]
+    fn dir() {
+        let program = r#"
+        dir = "/tmp/ruruby_test_dir"
+        Dir.mkdir(dir)
+        assert(true, Dir.exist?(dir))
+        File.write(dir + "/a.rb", "a")
+        File.write(dir + "/b.txt", "b")
+        File.write(dir + "/c.rb", "c")
+        assert([".", "..", "a.rb", "b.txt", "c.rb"], Dir.entries(dir))
+        assert(["a.rb", "b.txt", "c.rb"], Dir.children(dir))
+        assert([dir + "/a.rb", dir + "/c.rb"], Dir.glob(dir + "/*.rb"))
+        Dir.children(dir).each { |f| File.delete(dir + "/" + f) }
+        Dir.rmdir(dir)
+        assert(false, Dir.exist?(dir))
+        assert(File.expand_path("."), Dir.pwd)
+        "#;
+        assert_script(program);
+    }
+}
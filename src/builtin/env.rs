@@ -0,0 +1,191 @@
+use crate::*;
+
+/// ENV: a hash-like object which reads and writes the process environment.
+pub fn init_env(globals: &mut Globals) -> Value {
+    let obj = Value::ordinary_object(globals.builtins.object);
+    globals.add_builtin_class_method(obj, "[]", get);
+    globals.add_builtin_class_method(obj, "[]=", set);
+    globals.add_builtin_class_method(obj, "store", set);
+    globals.add_builtin_class_method(obj, "fetch", fetch);
+    globals.add_builtin_class_method(obj, "key?", has_key);
+    globals.add_builtin_class_method(obj, "has_key?", has_key);
+    globals.add_builtin_class_method(obj, "include?", has_key);
+    globals.add_builtin_class_method(obj, "member?", has_key);
+    globals.add_builtin_class_method(obj, "delete", delete);
+    globals.add_builtin_class_method(obj, "keys", keys);
+    globals.add_builtin_class_method(obj, "values", values);
+    globals.add_builtin_class_method(obj, "to_h", to_h);
+    globals.add_builtin_class_method(obj, "to_hash", to_h);
+    globals.add_builtin_class_method(obj, "each", each);
+    globals.add_builtin_class_method(obj, "each_pair", each);
+    globals.add_builtin_class_method(obj, "size", size);
+    globals.add_builtin_class_method(obj, "length", size);
+    globals.add_builtin_class_method(obj, "empty?", empty);
+    globals.add_builtin_class_method(obj, "inspect", inspect);
+    globals.add_builtin_class_method(obj, "to_s", to_s);
+    obj
+}
+
+// Utils
+
+fn env_vars(vm: &mut VM) -> Vec<(Value, Value)> {
+    std::env::vars_os()
+        .map(|(k, v)| {
+            (
+                Value::string(&vm.globals, k.to_string_lossy().into_owned()),
+                Value::string(&vm.globals, v.to_string_lossy().into_owned()),
+            )
+        })
+        .collect()
+}
+
+fn expect_name(vm: &mut VM, name: &Value) -> Result<String, RubyError> {
+    let name = vm.expect_string(name, "Environment variable name")?;
+    if name.is_empty() || name.contains('=') || name.contains('\0') {
+        return Err(vm.error_argument(format!("Invalid environment variable name {:?}.", name)));
+    }
+    Ok(name.clone())
+}
+
+fn get_var(vm: &mut VM, name: &Value) -> Result<Option<Value>, RubyError> {
+    let name = expect_name(vm, name)?;
+    Ok(std::env::var_os(name)
+        .map(|v| Value::string(&vm.globals, v.to_string_lossy().into_owned())))
+}
+
+// Singleton methods
+
+fn get(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    Ok(get_var(vm, &args[0])?.unwrap_or(Value::nil()))
+}
+
+/// ENV[name] = value. Assigning nil removes the variable.
+fn set(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 2)?;
+    let name = expect_name(vm, &args[0])?;
+    if args[1].is_nil() {
+        std::env::remove_var(name);
+    } else {
+        let val = vm.expect_string(&args[1], "Environment variable value")?;
+        std::env::set_var(name, val);
+    }
+    Ok(args[1])
+}
+
+/// ENV.fetch(name) / ENV.fetch(name, default) / ENV.fetch(name) { |name| .. }
+fn fetch(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_range(args.len(), 1, 2)?;
+    if let Some(val) = get_var(vm, &args[0])? {
+        return Ok(val);
+    }
+    if let Some(method) = args.block {
+        vm.eval_block(method, &Args::new1(args[0]))
+    } else if args.len() == 2 {
+        Ok(args[1])
+    } else {
+        let name = vm.val_inspect(args[0]);
+        Err(vm.error_internal(format!("Key not found: {} (KeyError)", name)))
+    }
+}
+
+fn has_key(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    Ok(Value::bool(get_var(vm, &args[0])?.is_some()))
+}
+
+/// ENV.delete(name) -> old value or nil
+fn delete(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let val = get_var(vm, &args[0])?;
+    let name = expect_name(vm, &args[0])?;
+    std::env::remove_var(name);
+    Ok(val.unwrap_or(Value::nil()))
+}
+
+fn keys(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let keys = env_vars(vm).into_iter().map(|(k, _)| k).collect();
+    Ok(Value::array_from(&vm.globals, keys))
+}
+
+fn values(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let values = env_vars(vm).into_iter().map(|(_, v)| v).collect();
+    Ok(Value::array_from(&vm.globals, values))
+}
+
+fn to_h(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let hash = Value::hash_from(&vm.globals, std::collections::HashMap::new());
+    let mut href = hash.as_hash().unwrap();
+    for (k, v) in env_vars(vm) {
+        href.insert(k, v);
+    }
+    Ok(hash)
+}
+
+fn each(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let method = match args.block {
+        Some(method) => method,
+        None => {
+            let id = vm.globals.get_ident_id("each");
+            let val = Value::enumerator(&vm.globals, id, self_val, args.clone());
+            return Ok(val);
+        }
+    };
+    for (k, v) in env_vars(vm) {
+        vm.eval_block(method, &Args::new2(k, v))?;
+    }
+    Ok(self_val)
+}
+
+fn size(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    Ok(Value::fixnum(std::env::vars_os().count() as i64))
+}
+
+fn empty(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    Ok(Value::bool(std::env::vars_os().next().is_none()))
+}
+
+fn inspect(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    let hash = to_h(vm, self_val, args)?;
+    let s = vm.val_inspect(hash);
+    Ok(Value::string(&vm.globals, s))
+}
+
+fn to_s(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    Ok(Value::string(&vm.globals, "ENV".to_string()))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::*;
+
+    #[test]
+    fn env() {
+        let program = r#"
+        ENV["RURUBY_ENV_TEST"] = "foo"
+        assert("foo", ENV["RURUBY_ENV_TEST"])
+        assert(true, ENV.key?("RURUBY_ENV_TEST"))
+        assert(true, ENV.keys.include?("RURUBY_ENV_TEST"))
+        assert("foo", ENV.to_h["RURUBY_ENV_TEST"])
+        assert("foo\n", `echo $RURUBY_ENV_TEST`)
+        assert("foo", ENV.delete("RURUBY_ENV_TEST"))
+        assert(nil, ENV["RURUBY_ENV_TEST"])
+        assert("bar", ENV.fetch("RURUBY_ENV_TEST", "bar"))
+        assert("RURUBY_ENV_TEST!", ENV.fetch("RURUBY_ENV_TEST") { |k| k + "!" })
+        ENV.store("RURUBY_ENV_TEST", "baz")
+        ENV["RURUBY_ENV_TEST"] = nil
+        assert(false, ENV.include?("RURUBY_ENV_TEST"))
+        count = 0
+        ENV.each { |k, v| count += 1 }
+        assert(ENV.size, count)
+        "#;
+        assert_script(program);
+    }
+}
@@ -1,7 +1,385 @@
 use crate::*;
 
-pub fn init_error(globals: &mut Globals) -> Value {
-    let id = globals.get_ident_id("RuntimeError");
+/// Exception and its subclasses, and Thread::Backtrace::Location.
+///
+/// An exception is an ordinary object which holds its message and backtrace
+/// in hidden instance variables. The backtrace is an Array of Locations,
+/// set when the exception is raised for the first time.
+pub fn init_error(globals: &mut Globals) {
+    let id = globals.get_ident_id("Exception");
     let class = ClassRef::from(id, globals.builtins.object);
-    Value::class(globals, class)
+    globals.add_builtin_instance_method(class, "initialize", initialize);
+    globals.add_builtin_instance_method(class, "message", message);
+    globals.add_builtin_instance_method(class, "to_s", to_s);
+    globals.add_builtin_instance_method(class, "inspect", inspect);
+    globals.add_builtin_instance_method(class, "backtrace", backtrace);
+    globals.add_builtin_instance_method(class, "backtrace_locations", backtrace_locations);
+    globals.add_builtin_instance_method(class, "full_message", full_message);
+    let exception = Value::class(globals, class);
+    globals.builtins.object.set_var(id, exception);
+    globals.builtins.exception = exception;
+
+    for (name, superclass) in &[
+        ("ScriptError", "Exception"),
+        ("LoadError", "ScriptError"),
+        ("NotImplementedError", "ScriptError"),
+        ("SyntaxError", "ScriptError"),
+        ("StandardError", "Exception"),
+        ("ArgumentError", "StandardError"),
+        ("FiberError", "StandardError"),
+        ("IndexError", "StandardError"),
+        ("LocalJumpError", "StandardError"),
+        ("NameError", "StandardError"),
+        ("NoMethodError", "NameError"),
+        ("NoMatchingPatternError", "StandardError"),
+        ("RangeError", "StandardError"),
+        ("FloatDomainError", "RangeError"),
+        ("RegexpError", "StandardError"),
+        ("RuntimeError", "StandardError"),
+        ("TypeError", "StandardError"),
+        ("ZeroDivisionError", "StandardError"),
+    ] {
+        let superclass = globals.get_ident_id(*superclass);
+        let superclass = globals.builtins.object.get_var(superclass).unwrap();
+        let id = globals.get_ident_id(*name);
+        let class = ClassRef::from(id, superclass);
+        let class = Value::class(globals, class);
+        globals.builtins.object.set_var(id, class);
+    }
+    let id = globals.get_ident_id("StandardError");
+    globals.builtins.standard_error = globals.builtins.object.get_var(id).unwrap();
+
+    let id = globals.get_ident_id("Thread::Backtrace::Location");
+    let class = ClassRef::from(id, globals.builtins.object);
+    globals.add_builtin_instance_method(class, "path", location_path);
+    globals.add_builtin_instance_method(class, "lineno", location_lineno);
+    globals.add_builtin_instance_method(class, "label", location_label);
+    globals.add_builtin_instance_method(class, "to_s", location_to_s);
+    globals.add_builtin_instance_method(class, "inspect", location_inspect);
+    globals.builtins.location = Value::class(globals, class);
+}
+
+/// Create a new exception of `class` with the message `msg`.
+pub fn new_exception(globals: &mut Globals, class: Value, msg: &str) -> Value {
+    let mut exception = Value::ordinary_object(class);
+    let id = globals.get_ident_id("__message");
+    exception.set_var(id, Value::string(globals, msg.to_string()));
+    exception
+}
+
+/// Create a new Thread::Backtrace::Location.
+pub fn new_location(globals: &mut Globals, path: String, lineno: usize, label: String) -> Value {
+    let mut location = Value::ordinary_object(globals.builtins.location);
+    let path = Value::string(globals, path);
+    let label = Value::string(globals, label);
+    for (name, val) in &[
+        ("path", path),
+        ("lineno", Value::fixnum(lineno as i64)),
+        ("label", label),
+    ] {
+        let id = globals.get_ident_id(*name);
+        location.set_var(id, *val);
+    }
+    location
+}
+
+/// Returns true if `val` is an instance of Exception or its subclasses.
+pub fn is_exception(globals: &Globals, val: Value) -> bool {
+    let mut class = val.get_class_object(globals);
+    while !class.is_nil() {
+        if class.id() == globals.builtins.exception.id() {
+            return true;
+        }
+        class = class.as_class().superclass;
+    }
+    false
+}
+
+/// Returns the backtrace (an Array of Locations) of `exception`, or None if it has not been raised.
+pub fn get_backtrace(globals: &mut Globals, exception: Value) -> Option<Value> {
+    let id = globals.get_ident_id("__backtrace");
+    match exception.get_var(id) {
+        Some(backtrace) if !backtrace.is_nil() => Some(backtrace),
+        _ => None,
+    }
+}
+
+pub fn set_backtrace(globals: &mut Globals, mut exception: Value, backtrace: Value) {
+    let id = globals.get_ident_id("__backtrace");
+    exception.set_var(id, backtrace);
+}
+
+/// Returns the message of `exception`, which defaults to the class name.
+pub fn get_message(vm: &mut VM, exception: Value) -> String {
+    let id = vm.globals.get_ident_id("__message");
+    match exception.get_var(id) {
+        Some(msg) if !msg.is_nil() => vm.val_to_s(msg),
+        _ => vm.globals.get_class_name(exception),
+    }
+}
+
+/// Returns "path:lineno:in 'label'".
+pub fn format_location(path: &str, lineno: impl std::fmt::Display, label: &str) -> String {
+    format!("{}:{}:in '{}'", path, lineno, label)
+}
+
+/// Returns "path:lineno:in 'label'" of the Thread::Backtrace::Location `location`.
+pub fn location_string(vm: &mut VM, location: Value) -> String {
+    let mut var = |name: &str| {
+        let id = vm.globals.get_ident_id(name);
+        location.get_var(id).unwrap_or_default()
+    };
+    let (path, lineno, label) = (var("path"), var("lineno"), var("label"));
+    format_location(&vm.val_to_s(path), vm.val_to_s(lineno), &vm.val_to_s(label))
+}
+
+/// Returns the error report of `exception` in the format of MRI:
+///
+/// ```text
+/// foo.rb:2:in 'Foo#bar': message (RuntimeError)
+///         from foo.rb:5:in '<main>'
+/// ```
+pub fn full_message_string(vm: &mut VM, exception: Value) -> String {
+    let msg = format!(
+        "{} ({})",
+        get_message(vm, exception),
+        vm.globals.get_class_name(exception)
+    );
+    let backtrace = match get_backtrace(&mut vm.globals, exception) {
+        Some(backtrace) => backtrace.as_array().unwrap().elements.clone(),
+        None => vec![],
+    };
+    let mut s = match backtrace.first() {
+        Some(location) => format!("{}: {}\n", location_string(vm, *location), msg),
+        None => format!("{}\n", msg),
+    };
+    for location in backtrace.iter().skip(1) {
+        s += &format!("\tfrom {}\n", location_string(vm, *location));
+    }
+    s
+}
+
+// Instance methods
+
+fn initialize(vm: &mut VM, mut self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_range(args.len(), 0, 1)?;
+    if args.len() == 1 {
+        let id = vm.globals.get_ident_id("__message");
+        self_val.set_var(id, args[0]);
+    }
+    Ok(Value::nil())
+}
+
+fn message(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let id = vm.globals.get_ident_id("to_s");
+    vm.send0(self_val, id)
+}
+
+fn to_s(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let msg = get_message(vm, self_val);
+    Ok(Value::string(&vm.globals, msg))
+}
+
+fn inspect(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let class_name = vm.globals.get_class_name(self_val);
+    let msg = get_message(vm, self_val);
+    let s = if msg.is_empty() {
+        class_name
+    } else {
+        format!("#<{}: {}>", class_name, msg)
+    };
+    Ok(Value::string(&vm.globals, s))
+}
+
+fn backtrace(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let backtrace = match get_backtrace(&mut vm.globals, self_val) {
+        Some(backtrace) => backtrace.as_array().unwrap().elements.clone(),
+        None => return Ok(Value::nil()),
+    };
+    let lines = backtrace
+        .iter()
+        .map(|location| {
+            let s = location_string(vm, *location);
+            Value::string(&vm.globals, s)
+        })
+        .collect();
+    Ok(Value::array_from(&vm.globals, lines))
+}
+
+fn backtrace_locations(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    match get_backtrace(&mut vm.globals, self_val) {
+        Some(backtrace) => {
+            let locations = backtrace.as_array().unwrap().elements.clone();
+            Ok(Value::array_from(&vm.globals, locations))
+        }
+        None => Ok(Value::nil()),
+    }
+}
+
+fn full_message(vm: &mut VM, self_val: Value, _args: &Args) -> VMResult {
+    let s = full_message_string(vm, self_val);
+    Ok(Value::string(&vm.globals, s))
+}
+
+// Thread::Backtrace::Location
+
+fn location_var(vm: &mut VM, self_val: Value, args: &Args, name: &str) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let id = vm.globals.get_ident_id(name);
+    Ok(self_val.get_var(id).unwrap_or_default())
+}
+
+fn location_path(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    location_var(vm, self_val, args, "path")
+}
+
+fn location_lineno(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    location_var(vm, self_val, args, "lineno")
+}
+
+fn location_label(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    location_var(vm, self_val, args, "label")
+}
+
+fn location_to_s(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let s = location_string(vm, self_val);
+    Ok(Value::string(&vm.globals, s))
+}
+
+fn location_inspect(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let s = format!("{:?}", location_string(vm, self_val));
+    Ok(Value::string(&vm.globals, s))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::*;
+    use crate::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn exception_rescue() {
+        let program = r##"
+        class MyError < StandardError
+          def initialize(msg = "default")
+            super
+          end
+        end
+        def div(x)
+          10 / x
+        rescue ZeroDivisionError => e
+          e.message
+        else
+          :else
+        ensure
+          @ensured = true
+        end
+        assert("divided by 0", div(0))
+        assert(:else, div(2))
+        assert(true, @ensured)
+        x = begin
+          raise MyError
+        rescue TypeError, MyError => e
+          e
+        end
+        assert("#<MyError: default>", x.inspect)
+        assert(true, x.is_a?(StandardError))
+        a = [1, begin; nil.foo; rescue NoMethodError; 2; end, 3]
+        assert([1, 2, 3], a)
+        res = begin
+          begin
+            raise IndexError, "inner"
+          rescue ArgumentError
+            :wrong
+          ensure
+            @inner_ensure = true
+          end
+        rescue => e
+          [e.class, e.message]
+        end
+        assert([IndexError, "inner"], res)
+        assert(true, @inner_ensure)
+        res = begin
+          raise 1
+        rescue TypeError => e
+          e.message
+        end
+        assert("exception class/object expected", res)
+        res = begin
+          Math.sqrt(-1)
+        rescue ArgumentError => e
+          e.class
+        end
+        assert(Math::DomainError, res)
+        "##;
+        assert_script(program);
+    }
+
+    #[test]
+    fn exception_backtrace() {
+        let program = r#"
+        class Foo
+          def bar
+            [1].each { baz }
+          end
+          def baz
+            raise "error in baz"
+          end
+        end
+        e = begin
+          Foo.new.bar
+        rescue => e
+          e
+        end
+        assert([":7:in 'Foo#baz'", ":4:in 'block in Foo#bar'", ":4:in 'Foo#bar'", ":11:in '<main>'"], e.backtrace)
+        loc = e.backtrace_locations[1]
+        assert(["", 4, "block in Foo#bar"], [loc.path, loc.lineno, loc.label])
+        assert(nil, RuntimeError.new.backtrace)
+        def reraise
+          begin
+            raise "original"
+          rescue => e
+            raise e
+          end
+        end
+        e = begin
+          reraise
+        rescue => e
+          e
+        end
+        assert(":21:in 'Object#reraise'", e.backtrace[0])
+        "#;
+        assert_script(program);
+    }
+
+    #[test]
+    fn exception_full_message() {
+        let program = r#"
+        class Foo
+          def bar(x)
+            baz(x)
+          end
+          def baz(x)
+            x.no_such_method
+          end
+        end
+        Foo.new.bar(1)
+        "#;
+        let mut vm = VM::new();
+        let err = vm
+            .run(PathBuf::from("trace.rb"), program, None)
+            .unwrap_err();
+        let exception = vm.exception_from_error(&err);
+        let expected = "trace.rb:7:in 'Foo#baz': no method `no_such_method' found for Integer (NoMethodError)\n\
+                        \tfrom trace.rb:4:in 'Foo#bar'\n\
+                        \tfrom trace.rb:10:in '<main>'\n";
+        assert_eq!(expected, super::full_message_string(&mut vm, exception));
+    }
 }
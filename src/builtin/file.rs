@@ -1,4 +1,4 @@
-use std::fs::File;
+use std::fs::{File, OpenOptions};
 use std::io::Read;
 use std::path::*;
 //#[macro_use]
@@ -6,14 +6,47 @@ use crate::*;
 
 pub fn init_file(globals: &mut Globals) -> Value {
     let id = globals.get_ident_id("File");
-    let class = ClassRef::from(id, globals.builtins.object);
-    let obj = Value::class(globals, class);
+    let class = ClassRef::from(id, globals.builtins.io);
+    let mut obj = Value::class(globals, class);
+    globals.add_builtin_class_method(obj, "new", new);
+    globals.add_builtin_class_method(obj, "open", open);
     globals.add_builtin_class_method(obj, "join", join);
     globals.add_builtin_class_method(obj, "basename", basename);
     globals.add_builtin_class_method(obj, "extname", extname);
+    globals.add_builtin_class_method(obj, "dirname", dirname);
+    globals.add_builtin_class_method(obj, "expand_path", expand_path);
     globals.add_builtin_class_method(obj, "binread", binread);
     globals.add_builtin_class_method(obj, "read", read);
+    globals.add_builtin_class_method(obj, "readlines", readlines);
+    globals.add_builtin_class_method(obj, "foreach", foreach);
     globals.add_builtin_class_method(obj, "write", write);
+    globals.add_builtin_class_method(obj, "exist?", exist);
+    globals.add_builtin_class_method(obj, "file?", file);
+    globals.add_builtin_class_method(obj, "directory?", directory);
+    globals.add_builtin_class_method(obj, "size", size);
+    globals.add_builtin_class_method(obj, "mtime", mtime);
+    globals.add_builtin_class_method(obj, "delete", delete);
+    globals.add_builtin_class_method(obj, "unlink", delete);
+    globals.add_builtin_class_method(obj, "rename", rename);
+    globals.add_builtin_class_method(obj, "stat", stat);
+
+    let id = globals.get_ident_id("Stat");
+    let stat_class = init_stat(globals);
+    obj.set_var(id, stat_class);
+    obj
+}
+
+fn init_stat(globals: &mut Globals) -> Value {
+    let id = globals.get_ident_id("File::Stat");
+    let class = ClassRef::from(id, globals.builtins.object);
+    let obj = Value::class(globals, class);
+    globals.add_builtin_class_method(obj, "new", stat);
+    globals.add_builtin_instance_method(class, "size", stat_size);
+    globals.add_builtin_instance_method(class, "file?", stat_file);
+    globals.add_builtin_instance_method(class, "directory?", stat_directory);
+    globals.add_builtin_instance_method(class, "mtime", stat_mtime);
+    globals.add_builtin_instance_method(class, "mode", stat_mode);
+    globals.add_builtin_instance_method(class, "ftype", stat_ftype);
     obj
 }
 
@@ -24,8 +57,80 @@ fn string_to_path(vm: &mut VM, string: Value) -> Result<PathBuf, RubyError> {
     Ok(PathBuf::from(file))
 }
 
+fn metadata(vm: &mut VM, path: Value) -> Result<std::fs::Metadata, RubyError> {
+    let path = string_to_path(vm, path)?;
+    match std::fs::metadata(&path) {
+        Ok(metadata) => Ok(metadata),
+        Err(err) => Err(vm.error_internal(format!("{} @ rb_file_s_stat - {:?}", err, path))),
+    }
+}
+
+fn mtime_to_value(vm: &mut VM, metadata: &std::fs::Metadata) -> VMResult {
+    let mtime = match metadata.modified() {
+        Ok(time) => time,
+        Err(err) => return Err(vm.error_internal(format!("{}", err))),
+    };
+    Ok(Value::time(&vm.globals, TimeInfo::from_system_time(mtime)))
+}
+
+/// Open the file at `path` with `mode`("r", "w", "a", "r+", "w+", "a+"), and returns a File object.
+fn open_file(vm: &mut VM, path: Value, mode: Option<Value>) -> VMResult {
+    let path = vm.expect_string(&path, "Path")?.clone();
+    let mode = match mode {
+        Some(mode) => vm.expect_string(&mode, "Mode")?.replace("b", ""),
+        None => "r".to_string(),
+    };
+    let mut option = OpenOptions::new();
+    let (readable, writable) = match mode.as_str() {
+        "r" => (true, false),
+        "w" => (false, true),
+        "a" => (false, true),
+        "r+" => (true, true),
+        "w+" => (true, true),
+        "a+" => (true, true),
+        _ => return Err(vm.error_argument(format!("invalid access mode {}", mode))),
+    };
+    option.read(readable).write(writable);
+    match mode.as_str() {
+        "w" | "w+" => option.create(true).truncate(true),
+        "a" | "a+" => option.create(true).append(true),
+        _ => &mut option,
+    };
+    let file = match option.open(&path) {
+        Ok(file) => file,
+        Err(err) => {
+            return Err(vm.error_internal(format!("{} @ rb_sysopen - {}", err, path)));
+        }
+    };
+    let info = IOInfo::new(path, file, readable, writable);
+    Ok(Value::io(vm.globals.builtins.file, info))
+}
+
 // Class methods
 
+/// File.new(path, mode = "r")
+fn new(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_range(args.len(), 1, 2)?;
+    let mode = if args.len() == 2 { Some(args[1]) } else { None };
+    open_file(vm, args[0], mode)
+}
+
+/// File.open(path, mode = "r") { |file| ... }
+/// When a block is given, the file is closed after the block is evaluated.
+fn open(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_range(args.len(), 1, 2)?;
+    let mode = if args.len() == 2 { Some(args[1]) } else { None };
+    let file = open_file(vm, args[0], mode)?;
+    let method = match args.block {
+        Some(method) => method,
+        None => return Ok(file),
+    };
+    let res = vm.eval_block(method, &Args::new1(file));
+    let mut ioref = file.as_io().unwrap();
+    ioref.close();
+    res
+}
+
 fn join(vm: &mut VM, _: Value, args: &Args) -> VMResult {
     vm.check_args_num(args.len(), 2)?;
     let mut path = string_to_path(vm, args[0])?;
@@ -66,6 +171,52 @@ fn extname(vm: &mut VM, _: Value, args: &Args) -> VMResult {
     Ok(Value::string(&vm.globals, extname))
 }
 
+fn dirname(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let filename = vm.expect_string(&args[0], "Path")?;
+    let dirname = match Path::new(filename).parent() {
+        Some(dir) if dir.as_os_str().is_empty() => ".".to_string(),
+        Some(dir) => dir.to_string_lossy().into_owned(),
+        None if filename.starts_with('/') => "/".to_string(),
+        None => ".".to_string(),
+    };
+    Ok(Value::string(&vm.globals, dirname))
+}
+
+/// File.expand_path(path, dir = nil)
+fn expand_path(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_range(args.len(), 1, 2)?;
+    let path = vm.expect_string(&args[0], "Path")?.clone();
+    let home = std::env::var("HOME").unwrap_or_default();
+    let path = if path == "~" {
+        PathBuf::from(home)
+    } else if path.starts_with("~/") {
+        PathBuf::from(home).join(&path[2..])
+    } else {
+        PathBuf::from(path)
+    };
+    let base = if args.len() == 2 && !args[1].is_nil() {
+        let dir = vm.expect_string(&args[1], "Dir")?;
+        PathBuf::from(dir)
+    } else {
+        match std::env::current_dir() {
+            Ok(dir) => dir,
+            Err(err) => return Err(vm.error_internal(format!("{}", err))),
+        }
+    };
+    let mut res = PathBuf::new();
+    for component in base.join(path).components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                res.pop();
+            }
+            c => res.push(c),
+        }
+    }
+    Ok(Value::string(&vm.globals, res.to_string_lossy().into_owned()))
+}
+
 fn binread(vm: &mut VM, _: Value, args: &Args) -> VMResult {
     let len = args.len();
     vm.check_args_range(len, 1, 1)?;
@@ -111,6 +262,50 @@ fn read(vm: &mut VM, _: Value, args: &Args) -> VMResult {
     Ok(Value::string(&vm.globals, contents))
 }
 
+/// IO.readlines(path)
+fn readlines(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let file = open_file(vm, args[0], None)?;
+    let mut ioref = file.as_io().unwrap();
+    let mut lines = vec![];
+    loop {
+        match ioref.read_line() {
+            Ok(Some(line)) => lines.push(builtin::io::bytes_to_value(vm, line)),
+            Ok(None) => break,
+            Err(err) => return Err(vm.error_internal(format!("{}", err))),
+        }
+    }
+    ioref.close();
+    Ok(Value::array_from(&vm.globals, lines))
+}
+
+/// IO.foreach(path) { |line| ... }
+fn foreach(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let method = match args.block {
+        Some(method) => method,
+        None => {
+            let id = vm.globals.get_ident_id("foreach");
+            let val = Value::enumerator(&vm.globals, id, self_val, args.clone());
+            return Ok(val);
+        }
+    };
+    let file = open_file(vm, args[0], None)?;
+    let mut ioref = file.as_io().unwrap();
+    let res = loop {
+        let line = match ioref.read_line() {
+            Ok(Some(line)) => builtin::io::bytes_to_value(vm, line),
+            Ok(None) => break Ok(Value::nil()),
+            Err(err) => break Err(vm.error_internal(format!("{}", err))),
+        };
+        if let Err(err) = vm.eval_block(method, &Args::new1(line)) {
+            break Err(err);
+        }
+    };
+    ioref.close();
+    res
+}
+
 /// IO.write(path, string)
 fn write(vm: &mut VM, _: Value, args: &Args) -> VMResult {
     let len = args.len();
@@ -128,3 +323,188 @@ fn write(vm: &mut VM, _: Value, args: &Args) -> VMResult {
     };
     Ok(Value::fixnum(contents.len() as i64))
 }
+
+fn exist(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let path = string_to_path(vm, args[0])?;
+    Ok(Value::bool(path.exists()))
+}
+
+fn file(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let path = string_to_path(vm, args[0])?;
+    Ok(Value::bool(path.is_file()))
+}
+
+fn directory(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let path = string_to_path(vm, args[0])?;
+    Ok(Value::bool(path.is_dir()))
+}
+
+fn size(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let metadata = metadata(vm, args[0])?;
+    Ok(Value::fixnum(metadata.len() as i64))
+}
+
+fn mtime(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let metadata = metadata(vm, args[0])?;
+    mtime_to_value(vm, &metadata)
+}
+
+/// File.delete(*paths)
+fn delete(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    for arg in args.iter() {
+        let path = string_to_path(vm, *arg)?;
+        if let Err(err) = std::fs::remove_file(&path) {
+            return Err(vm.error_internal(format!("{} @ unlink_internal - {:?}", err, path)));
+        }
+    }
+    Ok(Value::fixnum(args.len() as i64))
+}
+
+/// File.rename(from, to)
+fn rename(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 2)?;
+    let from = string_to_path(vm, args[0])?;
+    let to = string_to_path(vm, args[1])?;
+    if let Err(err) = std::fs::rename(&from, &to) {
+        return Err(vm.error_internal(format!("{} @ rb_file_s_rename - ({:?}, {:?})", err, from, to)));
+    }
+    Ok(Value::fixnum(0))
+}
+
+/// File.stat(path) / File::Stat.new(path)
+fn stat(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let metadata = metadata(vm, args[0])?;
+    let stat_id = vm.globals.get_ident_id("Stat");
+    let class = vm.globals.builtins.file.get_var(stat_id).unwrap();
+    let mut stat = Value::ordinary_object(class);
+    let ftype = if metadata.is_dir() {
+        "directory"
+    } else if metadata.is_file() {
+        "file"
+    } else if metadata.file_type().is_symlink() {
+        "link"
+    } else {
+        "unknown"
+    };
+    let mtime = mtime_to_value(vm, &metadata)?;
+    #[cfg(unix)]
+    let mode = {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.permissions().mode() as i64
+    };
+    #[cfg(not(unix))]
+    let mode = if metadata.permissions().readonly() { 0o100444 } else { 0o100644 };
+    let entries = [
+        ("_size", Value::fixnum(metadata.len() as i64)),
+        ("_ftype", Value::string(&vm.globals, ftype.to_string())),
+        ("_mtime", mtime),
+        ("_mode", Value::fixnum(mode)),
+    ];
+    for (name, val) in entries.iter() {
+        let id = vm.globals.get_ident_id(*name);
+        stat.set_var(id, *val);
+    }
+    Ok(stat)
+}
+
+// File::Stat instance methods
+
+fn stat_var(vm: &mut VM, self_val: Value, name: &str) -> VMResult {
+    let id = vm.globals.get_ident_id(name);
+    match self_val.get_var(id) {
+        Some(val) => Ok(val),
+        None => Err(vm.error_internal("Uninitialized File::Stat.")),
+    }
+}
+
+fn stat_size(vm: &mut VM, self_val: Value, _args: &Args) -> VMResult {
+    stat_var(vm, self_val, "_size")
+}
+
+fn stat_mtime(vm: &mut VM, self_val: Value, _args: &Args) -> VMResult {
+    stat_var(vm, self_val, "_mtime")
+}
+
+fn stat_mode(vm: &mut VM, self_val: Value, _args: &Args) -> VMResult {
+    stat_var(vm, self_val, "_mode")
+}
+
+fn stat_ftype(vm: &mut VM, self_val: Value, _args: &Args) -> VMResult {
+    stat_var(vm, self_val, "_ftype")
+}
+
+fn stat_file(vm: &mut VM, self_val: Value, _args: &Args) -> VMResult {
+    let ftype = stat_var(vm, self_val, "_ftype")?;
+    Ok(Value::bool(ftype.as_string().unwrap() == "file"))
+}
+
+fn stat_directory(vm: &mut VM, self_val: Value, _args: &Args) -> VMResult {
+    let ftype = stat_var(vm, self_val, "_ftype")?;
+    Ok(Value::bool(ftype.as_string().unwrap() == "directory"))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::*;
+
+    #[test]
+    fn file_open() {
+        let program = r#"
+        path = "/tmp/ruruby_test_file_open.txt"
+        File.open(path, "w") do |f|
+          f.puts "foo", "bar"
+          f.print "baz", 1
+          f << "\n" << "qux\n"
+        end
+        assert(true, File.exist?(path))
+        assert(true, File.file?(path))
+        assert(false, File.directory?(path))
+        assert(17, File.size(path))
+        assert(17, File.stat(path).size)
+        assert(true, File.stat(path).file?)
+        assert(["foo\n", "bar\n", "baz1\n", "qux\n"], File.readlines(path))
+        f = File.open(path)
+        assert("foo\n", f.gets)
+        assert(4, f.pos)
+        assert("bar\nbaz1\n", f.read(9))
+        assert(false, f.eof?)
+        f.seek(-4, IO::SEEK_END)
+        assert("qux\n", f.read)
+        assert(true, f.eof?)
+        assert(nil, f.gets)
+        f.rewind
+        lines = []
+        f.each_line { |line| lines << line }
+        assert(["foo\n", "bar\n", "baz1\n", "qux\n"], lines)
+        f.close
+        assert(true, f.closed?)
+        assert(nil, f.close)
+        res = File.open(path, "a") { |f| f.write("quux\n") }
+        assert(5, res)
+        lines = []
+        File.foreach(path) { |line| lines << line.chomp }
+        assert(["foo", "bar", "baz1", "qux", "quux"], lines)
+        File.rename(path, path + ".bak")
+        assert(false, File.exist?(path))
+        assert(1, File.delete(path + ".bak"))
+        "#;
+        assert_script(program);
+    }
+
+    #[test]
+    fn file_path() {
+        let program = r#"
+        assert("/usr/bin", File.dirname("/usr/bin/ruby"))
+        assert(".", File.dirname("ruby"))
+        assert("/usr/lib", File.expand_path("../lib", "/usr/bin"))
+        assert("/usr/bin/ruby", File.expand_path("ruby", "/usr/bin/."))
+        "#;
+        assert_script(program);
+    }
+}
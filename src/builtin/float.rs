@@ -2,10 +2,211 @@ use crate::*;
 
 pub fn init(globals: &mut Globals) -> Value {
     let id = globals.get_ident_id("Float");
-    let class = ClassRef::from(id, globals.builtins.object);
+    let class = ClassRef::from(id, globals.builtins.numeric);
     globals.add_builtin_instance_method(class, "<=>", cmp);
     globals.add_builtin_instance_method(class, "floor", floor);
-    Value::class(globals, class)
+    globals.add_builtin_instance_method(class, "ceil", ceil);
+    globals.add_builtin_instance_method(class, "round", round);
+    globals.add_builtin_instance_method(class, "truncate", truncate);
+    globals.add_builtin_instance_method(class, "to_i", toi);
+    globals.add_builtin_instance_method(class, "to_int", toi);
+    globals.add_builtin_instance_method(class, "to_f", tof);
+    globals.add_builtin_instance_method(class, "to_s", to_s);
+    globals.add_builtin_instance_method(class, "inspect", to_s);
+    globals.add_builtin_instance_method(class, "abs", abs);
+    globals.add_builtin_instance_method(class, "magnitude", abs);
+    globals.add_builtin_instance_method(class, "nan?", nan);
+    globals.add_builtin_instance_method(class, "infinite?", infinite);
+    globals.add_builtin_instance_method(class, "finite?", finite);
+    globals.add_builtin_instance_method(class, "zero?", zero);
+    globals.add_builtin_instance_method(class, "positive?", positive);
+    globals.add_builtin_instance_method(class, "negative?", negative);
+    globals.add_builtin_instance_method(class, "divmod", divmod);
+    globals.add_builtin_instance_method(class, "%", rem);
+    globals.add_builtin_instance_method(class, "modulo", rem);
+    globals.add_builtin_instance_method(class, "**", pow);
+    globals.add_builtin_instance_method(class, "to_r", to_r);
+    globals.add_builtin_instance_method(class, "rationalize", rationalize);
+    globals.add_builtin_instance_method(class, "to_c", to_c);
+    let mut obj = Value::class(globals, class);
+    let constants = [
+        ("INFINITY", std::f64::INFINITY),
+        ("NAN", std::f64::NAN),
+        ("EPSILON", std::f64::EPSILON),
+        ("MAX", std::f64::MAX),
+        ("MIN", std::f64::MIN_POSITIVE),
+    ];
+    for (name, f) in constants.iter() {
+        let id = globals.get_ident_id(*name);
+        obj.set_var(id, Value::flonum(*f));
+    }
+    let id = globals.get_ident_id("DIG");
+    obj.set_var(id, Value::fixnum(std::f64::DIGITS as i64));
+    obj
+}
+
+// Utils
+
+/// Format `f` as Ruby does: the shortest representation which round-trips,
+/// with exponent notation for very large or small numbers. (e.g. "1.0e+20")
+pub fn float_to_s(f: f64) -> String {
+    if f.is_nan() {
+        return "NaN".to_string();
+    }
+    if f.is_infinite() {
+        return if f > 0.0 { "Infinity" } else { "-Infinity" }.to_string();
+    }
+    if f == 0.0 {
+        return if f.is_sign_negative() { "-0.0" } else { "0.0" }.to_string();
+    }
+    // "{:e}" gives the shortest digits. (e.g. "-1.2345e-5")
+    let s = format!("{:e}", f);
+    let (mantissa, exp) = s.split_at(s.find('e').unwrap());
+    let exp: i32 = exp[1..].parse().unwrap();
+    let (sign, mantissa) = match mantissa.strip_prefix('-') {
+        Some(mantissa) => ("-", mantissa),
+        None => ("", mantissa),
+    };
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+    if -4 <= exp && exp < 16 {
+        let s = if exp < 0 {
+            format!("0.{}{}", "0".repeat((-exp - 1) as usize), digits)
+        } else {
+            let exp = exp as usize;
+            if digits.len() > exp + 1 {
+                format!("{}.{}", &digits[..=exp], &digits[exp + 1..])
+            } else {
+                format!("{}{}.0", digits, "0".repeat(exp + 1 - digits.len()))
+            }
+        };
+        format!("{}{}", sign, s)
+    } else {
+        let frac = if digits.len() > 1 { &digits[1..] } else { "0" };
+        let exp_sign = if exp < 0 { '-' } else { '+' };
+        format!("{}{}.{}e{}{:02}", sign, &digits[..1], frac, exp_sign, exp.abs())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RoundMode {
+    Up,
+    Even,
+    Down,
+}
+
+/// Get the rounding mode from `half:` keyword argument.
+pub fn round_mode(vm: &mut VM, args: &Args) -> Result<RoundMode, RubyError> {
+    let hash = match args.kw_arg {
+        Some(hash) => vm.expect_hash(hash, "Keyword arguments")?,
+        None => return Ok(RoundMode::Up),
+    };
+    let half = Value::symbol(vm.globals.get_ident_id("half"));
+    let mode = match hash.get(&half) {
+        Some(mode) if !mode.is_nil() => *mode,
+        _ => return Ok(RoundMode::Up),
+    };
+    let name = match mode.as_symbol() {
+        Some(id) => vm.globals.get_ident_name(id).to_string(),
+        None => match mode.as_string() {
+            Some(s) => s.to_string(),
+            None => String::new(),
+        },
+    };
+    match name.as_str() {
+        "up" => Ok(RoundMode::Up),
+        "even" => Ok(RoundMode::Even),
+        "down" => Ok(RoundMode::Down),
+        _ => {
+            let inspect = vm.val_inspect(mode);
+            Err(vm.error_argument(format!("invalid rounding mode: {}", inspect)))
+        }
+    }
+}
+
+/// Round `f` to the nearest integer, rounding halves according to `mode`.
+pub fn round_half(f: f64, mode: RoundMode) -> f64 {
+    let is_half = (f - f.trunc()).abs() == 0.5;
+    match mode {
+        RoundMode::Up => f.round(),
+        RoundMode::Even if is_half => 2.0 * (f / 2.0).round(),
+        RoundMode::Down if is_half => f.trunc(),
+        _ => f.round(),
+    }
+}
+
+/// Convert `f` to Integer. NaN and Infinity raise FloatDomainError,
+/// and values out of the range of i64 raise RangeError.
+pub fn float_to_integer(vm: &mut VM, f: f64) -> VMResult {
+    if f.is_nan() || f.is_infinite() {
+        return Err(vm.error_float_domain(float_to_s(f)));
+    }
+    // `i64::MAX as f64` is 2**63, which is out of the range.
+    if f.trunc() < i64::MIN as f64 || f.trunc() >= i64::MAX as f64 {
+        return Err(vm.error_range(format!(
+            "float {} out of range of integer",
+            float_to_s(f)
+        )));
+    }
+    Ok(Value::fixnum(f as i64))
+}
+
+/// Round `f` with `ndigits` digits using `round_fn`.
+/// Returns a Float if `ndigits` is positive, otherwise returns an Integer.
+fn round_digits(
+    vm: &mut VM,
+    f: f64,
+    ndigits: i64,
+    round_fn: impl Fn(f64) -> f64,
+) -> VMResult {
+    if ndigits > 0 {
+        if f.is_nan() || f.is_infinite() || ndigits >= std::f64::DIGITS as i64 + 2 {
+            return Ok(Value::flonum(f));
+        }
+        let s = 10f64.powi(ndigits as i32);
+        let x = f * s;
+        if x.is_infinite() {
+            return Ok(Value::flonum(f));
+        }
+        Ok(Value::flonum(round_fn(x) / s))
+    } else {
+        let s = 10f64.powi(-ndigits as i32);
+        float_to_integer(vm, round_fn(f / s) * s)
+    }
+}
+
+fn ndigits(vm: &mut VM, args: &Args) -> Result<i64, RubyError> {
+    vm.check_args_range(args.len(), 0, 1)?;
+    if args.len() == 0 {
+        Ok(0)
+    } else {
+        vm.expect_integer(args[0], "ndigits")
+    }
+}
+
+fn expect_number(vm: &mut VM, val: Value) -> Result<f64, RubyError> {
+    match val.unpack() {
+        RV::Integer(i) => Ok(i as f64),
+        RV::Float(f) => Ok(f),
+        _ => {
+            let class = vm.globals.get_class_name(val);
+            Err(vm.error_type(format!("{} can't be coerced into Float", class)))
+        }
+    }
+}
+
+/// Returns (floored quotient, modulus) like Ruby's flodivmod.
+pub fn float_divmod(x: f64, y: f64) -> (f64, f64) {
+    let mut modulo = x % y;
+    let mut div = if x.is_infinite() && !y.is_infinite() {
+        x
+    } else {
+        (x - modulo) / y
+    };
+    if y * modulo < 0.0 {
+        modulo += y;
+        div -= 1.0;
+    }
+    (div.round(), modulo)
 }
 
 // Class methods
@@ -19,7 +220,7 @@ fn cmp(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
     let res = match args[0].unpack() {
         RV::Integer(rhs) => lhs.partial_cmp(&(rhs as f64)),
         RV::Float(rhs) => lhs.partial_cmp(&rhs),
-        _ => return Ok(Value::nil()),
+        _ => return vm.eval_cmp(args[0], self_val),
     };
     match res {
         Some(ord) => Ok(Value::fixnum(ord as i64)),
@@ -28,12 +229,250 @@ fn cmp(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
 }
 
 fn floor(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    let f = vm.expect_flonum(self_val, "Receiver")?;
+    let ndigits = ndigits(vm, args)?;
+    round_digits(vm, f, ndigits, f64::floor)
+}
+
+fn ceil(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    let f = vm.expect_flonum(self_val, "Receiver")?;
+    let ndigits = ndigits(vm, args)?;
+    round_digits(vm, f, ndigits, f64::ceil)
+}
+
+/// Float#round(ndigits = 0, half: :up)
+fn round(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    let f = vm.expect_flonum(self_val, "Receiver")?;
+    let ndigits = ndigits(vm, args)?;
+    let mode = round_mode(vm, args)?;
+    round_digits(vm, f, ndigits, |x| round_half(x, mode))
+}
+
+fn truncate(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    let f = vm.expect_flonum(self_val, "Receiver")?;
+    let ndigits = ndigits(vm, args)?;
+    round_digits(vm, f, ndigits, f64::trunc)
+}
+
+fn toi(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
     vm.check_args_num(args.len(), 0)?;
-    match self_val.unpack() {
-        RV::Float(f) => {
-            let i = f.floor() as i64;
-            Ok(Value::fixnum(i))
-        }
-        _ => Err(vm.error_type("Receiver must be a Float.")),
+    let f = vm.expect_flonum(self_val, "Receiver")?;
+    float_to_integer(vm, f.trunc())
+}
+
+fn tof(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    Ok(self_val)
+}
+
+fn to_s(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let f = vm.expect_flonum(self_val, "Receiver")?;
+    Ok(Value::string(&vm.globals, float_to_s(f)))
+}
+
+fn abs(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let f = vm.expect_flonum(self_val, "Receiver")?;
+    Ok(Value::flonum(f.abs()))
+}
+
+fn nan(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let f = vm.expect_flonum(self_val, "Receiver")?;
+    Ok(Value::bool(f.is_nan()))
+}
+
+/// Returns 1 for +Infinity, -1 for -Infinity, otherwise nil.
+fn infinite(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let f = vm.expect_flonum(self_val, "Receiver")?;
+    if f.is_infinite() {
+        Ok(Value::fixnum(if f > 0.0 { 1 } else { -1 }))
+    } else {
+        Ok(Value::nil())
+    }
+}
+
+fn finite(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let f = vm.expect_flonum(self_val, "Receiver")?;
+    Ok(Value::bool(f.is_finite()))
+}
+
+fn zero(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let f = vm.expect_flonum(self_val, "Receiver")?;
+    Ok(Value::bool(f == 0.0))
+}
+
+fn positive(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let f = vm.expect_flonum(self_val, "Receiver")?;
+    Ok(Value::bool(f > 0.0))
+}
+
+fn negative(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let f = vm.expect_flonum(self_val, "Receiver")?;
+    Ok(Value::bool(f < 0.0))
+}
+
+/// Float#divmod(other) -> [quotient, modulus]
+fn divmod(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let x = vm.expect_flonum(self_val, "Receiver")?;
+    let y = expect_number(vm, args[0])?;
+    if y == 0.0 {
+        return Err(vm.error_zero_div("divided by 0"));
+    }
+    let (div, modulo) = float_divmod(x, y);
+    let div = float_to_integer(vm, div)?;
+    Ok(Value::array_from(
+        &vm.globals,
+        vec![div, Value::flonum(modulo)],
+    ))
+}
+
+fn rem(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let x = vm.expect_flonum(self_val, "Receiver")?;
+    let y = expect_number(vm, args[0])?;
+    Ok(Value::flonum(float_divmod(x, y).1))
+}
+
+fn pow(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let x = vm.expect_flonum(self_val, "Receiver")?;
+    let y = expect_number(vm, args[0])?;
+    Ok(Value::flonum(x.powf(y)))
+}
+
+/// Float#to_r -> the exact Rational of self.
+fn to_r(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let f = vm.expect_flonum(self_val, "Receiver")?;
+    if !f.is_finite() {
+        return Err(vm.error_float_domain(float_to_s(f)));
+    }
+    match RationalInfo::from_f64(f) {
+        Some(r) => Ok(Value::rational(&vm.globals, r)),
+        None => Err(vm.error_range("integer overflow")),
+    }
+}
+
+/// Float#rationalize([eps]) -> the simplest Rational which is equal to self
+/// as a Float, or within `eps` from self.
+fn rationalize(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_range(args.len(), 0, 1)?;
+    let f = vm.expect_flonum(self_val, "Receiver")?;
+    if !f.is_finite() {
+        return Err(vm.error_float_domain(float_to_s(f)));
+    }
+    if args.len() == 0 {
+        return match RationalInfo::rationalize_f64(f) {
+            Some(r) => Ok(Value::rational(&vm.globals, r)),
+            None => Err(vm.error_range("integer overflow")),
+        };
+    }
+    let eps = super::rational::to_rational(vm, args[0])?;
+    match RationalInfo::from_f64(f) {
+        Some(r) => super::rational::rationalize_within(vm, r, eps),
+        None => Err(vm.error_range("integer overflow")),
+    }
+}
+
+fn to_c(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let f = vm.expect_flonum(self_val, "Receiver")?;
+    let c = ComplexInfo::from_real(Real::Float(f));
+    Ok(Value::complex(&vm.globals, c))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test::*;
+
+    #[test]
+    fn float_to_s() {
+        let program = r#"
+        assert("1.0", 1.0.to_s)
+        assert("-2.5", -2.5.to_s)
+        assert("0.1", 0.1.to_s)
+        assert("0.30000000000000004", (0.1 + 0.2).to_s)
+        assert("1.0e+16", 10000000000000000.0.to_s)
+        assert("1000000000000000.0", 1000000000000000.0.to_s)
+        assert("1.0e-05", 0.00001.to_s)
+        assert("0.0001", 0.0001.to_s)
+        assert("1.5e+300", (1.5 * 10.0 ** 300).to_s)
+        assert("Infinity", Float::INFINITY.to_s)
+        assert("-Infinity", (-Float::INFINITY).inspect)
+        assert("NaN", Float::NAN.to_s)
+        "#;
+        assert_script(program);
+    }
+
+    #[test]
+    fn float_round() {
+        let program = r#"
+        assert(3, 2.5.round)
+        assert(-3, -2.5.round)
+        assert(2, 2.5.round(half: :even))
+        assert(4, 3.5.round(half: :even))
+        assert(2, 2.5.round(half: :down))
+        assert(1.24, 1.235.round(2))
+        assert(1.0, 1.005.round(2))
+        assert(12300, 12345.6789.round(-2))
+        assert(3, 2.1.ceil)
+        assert(-2, -2.1.ceil)
+        assert(2.2, 2.11.ceil(1))
+        assert(2, 2.9.floor)
+        assert(2.9, 2.99.floor(1))
+        assert(-2, -2.9.truncate)
+        assert(2.9, 2.99.truncate(1))
+        assert(3, 3.99.to_i)
+        msg = begin
+          1e20.to_i
+        rescue RangeError => e
+          e.message
+        end
+        assert("float 1.0e+20 out of range of integer", msg)
+        err = begin
+          Float::MAX.to_i
+        rescue => e
+          e.class
+        end
+        assert(RangeError, err)
+        "#;
+        assert_script(program);
+    }
+
+    #[test]
+    fn float_methods() {
+        let program = r#"
+        assert(2.5, -2.5.abs)
+        assert(true, Float::NAN.nan?)
+        assert(false, 1.0.nan?)
+        assert(1, Float::INFINITY.infinite?)
+        assert(-1, (-Float::INFINITY).infinite?)
+        assert(nil, 1.0.infinite?)
+        assert(true, 1.0.finite?)
+        assert(false, Float::INFINITY.finite?)
+        assert(true, Float::EPSILON > 0)
+        assert(true, Float::MAX > 1e308)
+        assert(true, Float::MIN < 1e-307)
+        assert([3, 1.5], 7.5.divmod(2))
+        assert([-4, 0.5], -7.5.divmod(2))
+        assert([-4, -0.5], 7.5.divmod(-2))
+        assert(1.5, 7.5 % 2)
+        assert(0.5, -7.5.modulo(2))
+        assert(6.25, 2.5 ** 2)
+        assert(6.25, 2.5.send(:"**", 2))
+        assert(1.5, 7.5.send(:"%", 2))
+        assert(true, 0.0.zero?)
+        assert(true, 1.5.positive?)
+        assert(true, -1.5.negative?)
+        "#;
+        assert_script(program);
     }
 }
@@ -30,6 +30,7 @@ pub fn init_hash(globals: &mut Globals) -> Value {
     globals.add_builtin_instance_method(class, "compare_by_identity", compare_by_identity);
     globals.add_builtin_instance_method(class, "sort", sort);
     globals.add_builtin_instance_method(class, "invert", invert);
+    globals.add_builtin_instance_method(class, "deconstruct_keys", deconstruct_keys);
     Value::class(globals, class)
 }
 
@@ -53,6 +54,11 @@ fn clone(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
     Ok(Value::hash(&vm.globals, hash.dup()))
 }
 
+fn deconstruct_keys(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    Ok(self_val)
+}
+
 fn compact(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
     vm.check_args_num(args.len(), 0)?;
     let hash = vm.expect_hash(self_val, "Receiver")?.dup();
@@ -2,7 +2,7 @@ use crate::*;
 
 pub fn init(globals: &mut Globals) -> Value {
     let id = globals.get_ident_id("Integer");
-    let class = ClassRef::from(id, globals.builtins.object);
+    let class = ClassRef::from(id, globals.builtins.numeric);
     globals.add_builtin_instance_method(class, "==", eq);
     globals.add_builtin_instance_method(class, "!=", neq);
     globals.add_builtin_instance_method(class, ">=", ge);
@@ -17,11 +17,77 @@ pub fn init(globals: &mut Globals) -> Value {
     globals.add_builtin_instance_method(class, "to_f", tof);
     globals.add_builtin_instance_method(class, "floor", floor);
     globals.add_builtin_instance_method(class, "even?", even);
-    Value::class(globals, class)
+    globals.add_builtin_instance_method(class, "odd?", odd);
+    globals.add_builtin_instance_method(class, "zero?", zero);
+    globals.add_builtin_instance_method(class, "integer?", integer);
+    globals.add_builtin_instance_method(class, "abs", abs);
+    globals.add_builtin_instance_method(class, "magnitude", abs);
+    globals.add_builtin_instance_method(class, "pow", pow);
+    globals.add_builtin_instance_method(class, "gcd", gcd);
+    globals.add_builtin_instance_method(class, "lcm", lcm);
+    globals.add_builtin_instance_method(class, "divmod", divmod);
+    globals.add_builtin_instance_method(class, "digits", digits);
+    globals.add_builtin_instance_method(class, "bit_length", bit_length);
+    globals.add_builtin_instance_method(class, "pred", pred);
+    globals.add_builtin_instance_method(class, "succ", succ);
+    globals.add_builtin_instance_method(class, "next", succ);
+    globals.add_builtin_instance_method(class, "upto", upto);
+    globals.add_builtin_instance_method(class, "downto", downto);
+    globals.add_builtin_instance_method(class, "to_i", toi);
+    globals.add_builtin_instance_method(class, "to_int", toi);
+    globals.add_builtin_instance_method(class, "to_s", to_s);
+    globals.add_builtin_instance_method(class, "inspect", to_s);
+    globals.add_builtin_instance_method(class, "round", round);
+    globals.add_builtin_instance_method(class, "to_r", to_r);
+    globals.add_builtin_instance_method(class, "rationalize", rationalize);
+    globals.add_builtin_instance_method(class, "to_c", to_c);
+    globals.add_builtin_instance_method(class, "numerator", toi);
+    globals.add_builtin_instance_method(class, "denominator", denominator);
+    let obj = Value::class(globals, class);
+    globals.add_builtin_class_method(obj, "sqrt", sqrt);
+    obj
+}
+
+// Utils
+
+fn gcd_i64(a: i64, b: i64) -> i64 {
+    let (mut a, mut b) = (a.abs(), b.abs());
+    while b != 0 {
+        let r = a % b;
+        a = b;
+        b = r;
+    }
+    a
+}
+
+fn expect_radix(vm: &mut VM, val: Value) -> Result<i64, RubyError> {
+    let radix = vm.expect_integer(val, "Radix")?;
+    if radix < 2 || radix > 36 {
+        return Err(vm.error_argument(format!("invalid radix {}", radix)));
+    }
+    Ok(radix)
 }
 
 // Class methods
 
+/// Integer.sqrt(n) -> the integer square root of n.
+fn sqrt(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let n = vm.expect_integer(args[0], "1st arg")?;
+    if n < 0 {
+        return Err(vm.error_domain(r#"Numerical argument is out of domain - "isqrt""#));
+    }
+    // The float approximation may be off by one for large n.
+    let mut r = (n as f64).sqrt() as i64;
+    while r > 0 && r.checked_mul(r).map_or(true, |sq| sq > n) {
+        r -= 1;
+    }
+    while (r + 1).checked_mul(r + 1).map_or(false, |sq| sq <= n) {
+        r += 1;
+    }
+    Ok(Value::fixnum(r))
+}
+
 // Instance methods
 
 fn eq(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
@@ -84,7 +150,7 @@ fn cmp(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
     let res = match args[0].unpack() {
         RV::Integer(rhs) => lhs.partial_cmp(&rhs),
         RV::Float(rhs) => (lhs as f64).partial_cmp(&rhs),
-        _ => return Ok(Value::nil()),
+        _ => return vm.eval_cmp(args[0], self_val),
     };
     match res {
         Some(ord) => Ok(Value::fixnum(ord as i64)),
@@ -189,6 +255,313 @@ fn even(_vm: &mut VM, self_val: Value, _: &Args) -> VMResult {
     Ok(Value::bool(num % 2 == 0))
 }
 
+fn odd(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let num = vm.expect_integer(self_val, "Receiver")?;
+    Ok(Value::bool(num % 2 != 0))
+}
+
+fn zero(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let num = vm.expect_integer(self_val, "Receiver")?;
+    Ok(Value::bool(num == 0))
+}
+
+fn integer(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    Ok(Value::true_val())
+}
+
+fn abs(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let num = vm.expect_integer(self_val, "Receiver")?;
+    Ok(Value::fixnum(num.abs()))
+}
+
+/// Integer#pow(other) / Integer#pow(other, modulo)
+fn pow(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_range(args.len(), 1, 2)?;
+    let base = vm.expect_integer(self_val, "Receiver")?;
+    if args.len() == 1 {
+        return match args[0].unpack() {
+            RV::Integer(exp) if 0 <= exp && exp <= std::u32::MAX as i64 => {
+                match base.checked_pow(exp as u32) {
+                    Some(res) => Ok(Value::fixnum(res)),
+                    None => Ok(Value::flonum((base as f64).powf(exp as f64))),
+                }
+            }
+            RV::Integer(exp) => Ok(Value::flonum((base as f64).powf(exp as f64))),
+            RV::Float(exp) => Ok(Value::flonum((base as f64).powf(exp))),
+            _ => {
+                let class = vm.globals.get_class_name(args[0]);
+                Err(vm.error_type(format!("{} can't be coerced into Integer", class)))
+            }
+        };
+    }
+    let exp = vm.expect_integer(args[0], "2nd arg")?;
+    let modulo = vm.expect_integer(args[1], "3rd arg")?;
+    if exp < 0 {
+        return Err(vm.error_argument(
+            "Integer#pow() 2nd argument not allowed to be negative when 3rd argument specified",
+        ));
+    }
+    if modulo == 0 {
+        return Err(vm.error_zero_div("divided by 0"));
+    }
+    let m = modulo as i128;
+    let mut result = 1i128;
+    let mut base = (base as i128).rem_euclid(m);
+    let mut exp = exp;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = result * base % m;
+        }
+        base = base * base % m;
+        exp >>= 1;
+    }
+    // The result has the same sign as the modulo.
+    let result = result.rem_euclid(m);
+    let result = if m < 0 && result != 0 { result + m } else { result };
+    Ok(Value::fixnum(result as i64))
+}
+
+fn gcd(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let lhs = vm.expect_integer(self_val, "Receiver")?;
+    let rhs = vm.expect_integer(args[0], "1st arg")?;
+    Ok(Value::fixnum(gcd_i64(lhs, rhs)))
+}
+
+fn lcm(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let lhs = vm.expect_integer(self_val, "Receiver")?;
+    let rhs = vm.expect_integer(args[0], "1st arg")?;
+    if lhs == 0 || rhs == 0 {
+        return Ok(Value::fixnum(0));
+    }
+    match (lhs / gcd_i64(lhs, rhs))
+        .checked_mul(rhs)
+        .and_then(|lcm| lcm.checked_abs())
+    {
+        Some(lcm) => Ok(Value::fixnum(lcm)),
+        None => Err(vm.error_range("integer overflow")),
+    }
+}
+
+/// Integer#divmod(other) -> [quotient, modulus]
+fn divmod(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    use divrem::*;
+    vm.check_args_num(args.len(), 1)?;
+    let lhs = vm.expect_integer(self_val, "Receiver")?;
+    let (div, modulo) = match args[0].unpack() {
+        RV::Integer(0) => return Err(vm.error_zero_div("divided by 0")),
+        RV::Integer(rhs) => {
+            let (div, modulo) = (DivFloor::div_floor(lhs, rhs), lhs.rem_floor(rhs));
+            (Value::fixnum(div), Value::fixnum(modulo))
+        }
+        RV::Float(rhs) => {
+            if rhs == 0.0 {
+                return Err(vm.error_zero_div("divided by 0"));
+            }
+            let (div, modulo) = super::float::float_divmod(lhs as f64, rhs);
+            let div = super::float::float_to_integer(vm, div)?;
+            (div, Value::flonum(modulo))
+        }
+        _ => {
+            let class = vm.globals.get_class_name(args[0]);
+            return Err(vm.error_type(format!("{} can't be coerced into Integer", class)));
+        }
+    };
+    Ok(Value::array_from(&vm.globals, vec![div, modulo]))
+}
+
+/// Integer#digits(base = 10) -> the digits in the place-value order. (least significant first)
+fn digits(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_range(args.len(), 0, 1)?;
+    let mut num = vm.expect_integer(self_val, "Receiver")?;
+    let base = if args.len() == 1 {
+        let base = vm.expect_integer(args[0], "Base")?;
+        if base < 0 {
+            return Err(vm.error_argument("negative radix"));
+        }
+        if base < 2 {
+            return Err(vm.error_argument(format!("invalid radix {}", base)));
+        }
+        base
+    } else {
+        10
+    };
+    if num < 0 {
+        return Err(vm.error_domain("out of domain"));
+    }
+    let mut ary = vec![Value::fixnum(num % base)];
+    num /= base;
+    while num > 0 {
+        ary.push(Value::fixnum(num % base));
+        num /= base;
+    }
+    Ok(Value::array_from(&vm.globals, ary))
+}
+
+/// Integer#bit_length -> the number of bits of the value, except the sign bit.
+fn bit_length(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let num = vm.expect_integer(self_val, "Receiver")?;
+    let num = if num < 0 { !num } else { num };
+    Ok(Value::fixnum(64 - num.leading_zeros() as i64))
+}
+
+fn pred(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let num = vm.expect_integer(self_val, "Receiver")?;
+    Ok(Value::fixnum(num - 1))
+}
+
+fn succ(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let num = vm.expect_integer(self_val, "Receiver")?;
+    Ok(Value::fixnum(num + 1))
+}
+
+fn upto(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let method = match args.block {
+        Some(method) => method,
+        None => {
+            let id = vm.globals.get_ident_id("upto");
+            let val = Value::enumerator(&vm.globals, id, self_val, args.clone());
+            return Ok(val);
+        }
+    };
+    let start = vm.expect_integer(self_val, "Receiver")?;
+    let limit = vm.expect_integer(args[0], "Limit")?;
+    if method == MethodRef::from(0) {
+        let ary = (start..=limit).map(Value::fixnum).collect();
+        return Ok(Value::array_from(&vm.globals, ary));
+    }
+    let mut arg = Args::new1(Value::nil());
+    for i in start..=limit {
+        arg[0] = Value::fixnum(i);
+        vm.eval_block(method, &arg)?;
+    }
+    Ok(self_val)
+}
+
+fn downto(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let method = match args.block {
+        Some(method) => method,
+        None => {
+            let id = vm.globals.get_ident_id("downto");
+            let val = Value::enumerator(&vm.globals, id, self_val, args.clone());
+            return Ok(val);
+        }
+    };
+    let start = vm.expect_integer(self_val, "Receiver")?;
+    let limit = vm.expect_integer(args[0], "Limit")?;
+    if method == MethodRef::from(0) {
+        let ary = ((limit..=start).rev()).map(Value::fixnum).collect();
+        return Ok(Value::array_from(&vm.globals, ary));
+    }
+    let mut arg = Args::new1(Value::nil());
+    for i in (limit..=start).rev() {
+        arg[0] = Value::fixnum(i);
+        vm.eval_block(method, &arg)?;
+    }
+    Ok(self_val)
+}
+
+fn toi(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    Ok(self_val)
+}
+
+/// Integer#to_s(base = 10)
+fn to_s(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_range(args.len(), 0, 1)?;
+    let num = vm.expect_integer(self_val, "Receiver")?;
+    let radix = if args.len() == 1 {
+        expect_radix(vm, args[0])?
+    } else {
+        10
+    };
+    let mut n = (num as i128).abs();
+    let mut digits = vec![];
+    loop {
+        digits.push(std::char::from_digit((n % radix as i128) as u32, radix as u32).unwrap());
+        n /= radix as i128;
+        if n == 0 {
+            break;
+        }
+    }
+    if num < 0 {
+        digits.push('-');
+    }
+    let s: String = digits.iter().rev().collect();
+    Ok(Value::string(&vm.globals, s))
+}
+
+/// Integer#round(ndigits = 0, half: :up)
+/// Rounds to the nearest multiple of 10**(-ndigits) when `ndigits` is negative.
+fn round(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    use super::float::RoundMode;
+    vm.check_args_range(args.len(), 0, 1)?;
+    let num = vm.expect_integer(self_val, "Receiver")?;
+    let ndigits = if args.len() == 1 {
+        vm.expect_integer(args[0], "ndigits")?
+    } else {
+        0
+    };
+    let mode = super::float::round_mode(vm, args)?;
+    if ndigits >= 0 {
+        return Ok(self_val);
+    }
+    let s = match 10i64.checked_pow(-ndigits as u32) {
+        Some(s) => s as i128,
+        None => return Ok(Value::fixnum(0)),
+    };
+    let abs = (num as i128).abs();
+    let (q, r) = (abs / s, abs % s);
+    let round_up = match (r * 2).cmp(&s) {
+        std::cmp::Ordering::Greater => true,
+        std::cmp::Ordering::Less => false,
+        std::cmp::Ordering::Equal => match mode {
+            RoundMode::Up => true,
+            RoundMode::Even => q % 2 == 1,
+            RoundMode::Down => false,
+        },
+    };
+    let res = if round_up { (q + 1) * s } else { q * s };
+    let res = if num < 0 { -res } else { res };
+    Ok(Value::fixnum(res as i64))
+}
+
+fn to_r(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let num = vm.expect_integer(self_val, "Receiver")?;
+    Ok(Value::rational(&vm.globals, RationalInfo::from_integer(num)))
+}
+
+/// Integer#rationalize([eps]) -> the Rational of self. `eps` is ignored.
+fn rationalize(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_range(args.len(), 0, 1)?;
+    let num = vm.expect_integer(self_val, "Receiver")?;
+    Ok(Value::rational(&vm.globals, RationalInfo::from_integer(num)))
+}
+
+fn to_c(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let num = vm.expect_integer(self_val, "Receiver")?;
+    let c = ComplexInfo::from_real(Real::Integer(num));
+    Ok(Value::complex(&vm.globals, c))
+}
+
+fn denominator(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    vm.expect_integer(self_val, "Receiver")?;
+    Ok(Value::fixnum(1))
+}
+
 #[cfg(test)]
 mod tests {
     use crate::test::*;
@@ -263,4 +636,70 @@ mod tests {
         "#;
         assert_script(program);
     }
+
+    #[test]
+    fn integer_methods() {
+        let program = r#"
+        assert(5, -5.abs)
+        assert(true, 3.odd?)
+        assert(false, 4.odd?)
+        assert(true, 0.zero?)
+        assert(true, 1.integer?)
+        assert(false, 1.5.integer?)
+        assert(1024, 2.pow(10))
+        assert(24, 2.pow(10, 1000))
+        assert(1, 3.pow(4, 5))
+        assert(-4, 3.pow(4, -5))
+        assert(6, 12.gcd(18))
+        assert(6, -12.gcd(18))
+        assert(36, 12.lcm(18))
+        msg = begin
+          4611686018427387903.lcm(4611686018427387902)
+        rescue RangeError => e
+          e.message
+        end
+        assert("integer overflow", msg)
+        assert([3, 1], 7.divmod(2))
+        assert([-4, 1], -7.divmod(2))
+        assert(-4, -7 / 2)
+        assert(1, -7 % 2)
+        assert([5, 4, 3, 2, 1], 12345.digits)
+        assert([1, 0, 1, 1], 13.digits(2))
+        assert(0, 0.bit_length)
+        assert(8, 255.bit_length)
+        assert(9, 256.bit_length)
+        assert(8, -256.bit_length)
+        assert(4, 5.pred)
+        assert(6, 5.succ)
+        assert(6, 5.next)
+        assert("ff", 255.to_s(16))
+        assert("-101", -5.to_s(2))
+        assert("z", 35.to_s(36))
+        assert("255", 255.inspect)
+        assert(4, Integer.sqrt(24))
+        assert(5, Integer.sqrt(25))
+        assert(3037000499, Integer.sqrt(9223372036854775807))
+        assert(12300, 12345.round(-2))
+        assert(12400, 12350.round(-2))
+        assert(-12400, -12350.round(-2))
+        assert(12400, 12450.round(-2, half: :even))
+        assert(12300, 12350.round(-2, half: :down))
+        assert(15, 15.round(1))
+        "#;
+        assert_script(program);
+    }
+
+    #[test]
+    fn integer_upto() {
+        let program = r#"
+        res = []
+        assert 1, 1.upto(4) {|x| res << x}
+        assert [1, 2, 3, 4], res
+        res = []
+        assert 4, 4.downto(1) {|x| res << x}
+        assert [4, 3, 2, 1], res
+        assert [2, 3], 2.upto(3).map {|x| x}
+        "#;
+        assert_script(program);
+    }
 }
@@ -0,0 +1,624 @@
+use crate::*;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+
+const READ_BUF_SIZE: usize = 8192;
+
+#[derive(Debug)]
+enum Stream {
+    File(File),
+    /// In-memory stream backed by a String object. (for StringIO)
+    Buffer(Value),
+    Stdin,
+    Stdout,
+    Stderr,
+    Closed,
+}
+
+#[derive(Debug)]
+pub struct IOInfo {
+    pub path: String,
+    stream: Stream,
+    readable: bool,
+    writable: bool,
+    /// Read-ahead buffer for File. `rbuf[rpos..]` is not consumed yet.
+    rbuf: Vec<u8>,
+    /// For File, a position in `rbuf`. For Buffer, a position in the string.
+    rpos: usize,
+    pub lineno: i64,
+}
+
+pub type IORef = Ref<IOInfo>;
+
+impl IOInfo {
+    fn new_stream(path: impl Into<String>, stream: Stream, readable: bool, writable: bool) -> Self {
+        IOInfo {
+            path: path.into(),
+            stream,
+            readable,
+            writable,
+            rbuf: vec![],
+            rpos: 0,
+            lineno: 0,
+        }
+    }
+
+    pub fn new(path: impl Into<String>, file: File, readable: bool, writable: bool) -> Self {
+        IOInfo::new_stream(path, Stream::File(file), readable, writable)
+    }
+
+    /// Create an in-memory stream on `string`.
+    pub fn new_buffer(string: Value, readable: bool, writable: bool) -> Self {
+        IOInfo::new_stream("", Stream::Buffer(string), readable, writable)
+    }
+
+    pub fn new_stdin() -> Self {
+        IOInfo::new_stream("<STDIN>", Stream::Stdin, true, false)
+    }
+
+    pub fn new_stdout() -> Self {
+        IOInfo::new_stream("<STDOUT>", Stream::Stdout, false, true)
+    }
+
+    pub fn new_stderr() -> Self {
+        IOInfo::new_stream("<STDERR>", Stream::Stderr, false, true)
+    }
+
+    pub fn is_closed(&self) -> bool {
+        match self.stream {
+            Stream::Closed => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_stdout(&self) -> bool {
+        match self.stream {
+            Stream::Stdout => true,
+            _ => false,
+        }
+    }
+
+    pub fn close(&mut self) {
+        self.rbuf.clear();
+        self.rpos = 0;
+        self.stream = Stream::Closed;
+    }
+
+    /// Returns the String object of an in-memory stream.
+    pub fn buffer(&self) -> Option<Value> {
+        match self.stream {
+            Stream::Buffer(val) => Some(val),
+            _ => None,
+        }
+    }
+
+    fn error(msg: &str) -> std::io::Error {
+        std::io::Error::new(std::io::ErrorKind::Other, msg)
+    }
+
+    fn check_readable(&self) -> std::io::Result<()> {
+        if self.is_closed() {
+            Err(IOInfo::error("closed stream"))
+        } else if !self.readable {
+            Err(IOInfo::error("not opened for reading"))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn check_writable(&self) -> std::io::Result<()> {
+        if self.is_closed() {
+            Err(IOInfo::error("closed stream"))
+        } else if !self.writable {
+            Err(IOInfo::error("not opened for writing"))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Fill the read-ahead buffer, and returns unconsumed bytes. Returns an empty slice if reached EOF.
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.check_readable()?;
+        match &mut self.stream {
+            Stream::Buffer(val) => {
+                let bytes = val.as_bytes().unwrap();
+                let pos = std::cmp::min(self.rpos, bytes.len());
+                return Ok(&bytes[pos..]);
+            }
+            _ if self.rpos < self.rbuf.len() => {}
+            Stream::File(file) => {
+                let mut buf = vec![0; READ_BUF_SIZE];
+                let len = file.read(&mut buf)?;
+                buf.truncate(len);
+                self.rbuf = buf;
+                self.rpos = 0;
+            }
+            Stream::Stdin => {
+                use std::io::BufRead;
+                let mut buf = vec![];
+                std::io::stdin().lock().read_until(b'\n', &mut buf)?;
+                self.rbuf = buf;
+                self.rpos = 0;
+            }
+            _ => return Err(IOInfo::error("not opened for reading")),
+        };
+        Ok(&self.rbuf[self.rpos..])
+    }
+
+    /// Discard the read-ahead buffer, and move the cursor of the file to the logical position.
+    fn discard_buf(&mut self) -> std::io::Result<()> {
+        if let Stream::File(file) = &mut self.stream {
+            let rest = (self.rbuf.len() - self.rpos) as i64;
+            self.rbuf.clear();
+            self.rpos = 0;
+            if rest != 0 {
+                file.seek(SeekFrom::Current(-rest))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Read one line including a line terminator. Returns None if reached EOF.
+    pub fn read_line(&mut self) -> std::io::Result<Option<Vec<u8>>> {
+        let mut line = vec![];
+        loop {
+            let rest = self.fill_buf()?;
+            if rest.is_empty() {
+                break;
+            }
+            match rest.iter().position(|b| *b == b'\n') {
+                Some(i) => {
+                    line.extend_from_slice(&rest[..=i]);
+                    self.rpos += i + 1;
+                    self.lineno += 1;
+                    return Ok(Some(line));
+                }
+                None => {
+                    line.extend_from_slice(rest);
+                    let len = rest.len();
+                    self.rpos += len;
+                }
+            }
+        }
+        if line.is_empty() {
+            Ok(None)
+        } else {
+            self.lineno += 1;
+            Ok(Some(line))
+        }
+    }
+
+    /// Read at most `len` bytes. If `len` is None, read until EOF.
+    pub fn read(&mut self, len: Option<usize>) -> std::io::Result<Vec<u8>> {
+        let mut res = vec![];
+        while len.map_or(true, |len| res.len() < len) {
+            let rest = self.fill_buf()?;
+            if rest.is_empty() {
+                break;
+            }
+            let n = match len {
+                Some(len) => std::cmp::min(len - res.len(), rest.len()),
+                None => rest.len(),
+            };
+            res.extend_from_slice(&rest[..n]);
+            self.rpos += n;
+        }
+        Ok(res)
+    }
+
+    pub fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+        self.check_writable()?;
+        self.discard_buf()?;
+        match &mut self.stream {
+            Stream::File(file) => file.write_all(bytes)?,
+            Stream::Stdout => std::io::stdout().write_all(bytes)?,
+            Stream::Stderr => std::io::stderr().write_all(bytes)?,
+            Stream::Buffer(val) => {
+                val.as_mut_rstring().unwrap().write_at(self.rpos, bytes);
+                self.rpos += bytes.len();
+            }
+            _ => return Err(IOInfo::error("not opened for writing")),
+        };
+        Ok(bytes.len())
+    }
+
+    pub fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.discard_buf()?;
+        match &mut self.stream {
+            Stream::File(file) => file.seek(pos),
+            Stream::Buffer(val) => {
+                let len = val.as_bytes().unwrap().len() as i64;
+                let new_pos = match pos {
+                    SeekFrom::Start(pos) => pos as i64,
+                    SeekFrom::Current(offset) => self.rpos as i64 + offset,
+                    SeekFrom::End(offset) => len + offset,
+                };
+                if new_pos < 0 {
+                    return Err(IOInfo::error("Invalid argument"));
+                }
+                self.rpos = new_pos as usize;
+                Ok(self.rpos as u64)
+            }
+            Stream::Closed => Err(IOInfo::error("closed stream")),
+            _ => Err(IOInfo::error("Illegal seek")),
+        }
+    }
+
+    pub fn pos(&mut self) -> std::io::Result<u64> {
+        match &mut self.stream {
+            Stream::File(file) => {
+                let rest = (self.rbuf.len() - self.rpos) as u64;
+                Ok(file.seek(SeekFrom::Current(0))? - rest)
+            }
+            Stream::Buffer(_) => Ok(self.rpos as u64),
+            Stream::Closed => Err(IOInfo::error("closed stream")),
+            _ => Err(IOInfo::error("Illegal seek")),
+        }
+    }
+
+    pub fn is_eof(&mut self) -> std::io::Result<bool> {
+        Ok(self.fill_buf()?.is_empty())
+    }
+
+    /// Truncate an in-memory stream to `len` bytes.
+    pub fn truncate(&mut self, len: usize) -> std::io::Result<()> {
+        self.check_writable()?;
+        match &mut self.stream {
+            Stream::Buffer(val) => {
+                let rstring = val.as_mut_rstring().unwrap();
+                let mut buf = rstring.as_bytes().to_vec();
+                buf.resize(len, 0);
+                *rstring = match String::from_utf8(buf) {
+                    Ok(s) => RString::Str(s),
+                    Err(err) => RString::Bytes(err.into_bytes()),
+                };
+                Ok(())
+            }
+            Stream::File(file) => {
+                file.flush()?;
+                file.set_len(len as u64)
+            }
+            _ => Err(IOInfo::error("Invalid argument")),
+        }
+    }
+
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        match &mut self.stream {
+            Stream::File(file) => file.flush(),
+            Stream::Stdout => std::io::stdout().flush(),
+            Stream::Stderr => std::io::stderr().flush(),
+            Stream::Closed => Err(IOInfo::error("closed stream")),
+            _ => Ok(()),
+        }
+    }
+}
+
+pub fn init_io(globals: &mut Globals) -> Value {
+    let id = globals.get_ident_id("IO");
+    let class = ClassRef::from(id, globals.builtins.object);
+    let mut obj = Value::class(globals, class);
+    for (name, num) in &[("SEEK_SET", 0), ("SEEK_CUR", 1), ("SEEK_END", 2)] {
+        let id = globals.get_ident_id(*name);
+        obj.set_var(id, Value::fixnum(*num));
+    }
+    globals.add_builtin_instance_method(class, "inspect", inspect);
+    globals.add_builtin_instance_method(class, "path", path);
+    add_io_methods(globals, class);
+
+    let std_streams = vec![
+        ("$stdin", "STDIN", IOInfo::new_stdin()),
+        ("$stdout", "STDOUT", IOInfo::new_stdout()),
+        ("$stderr", "STDERR", IOInfo::new_stderr()),
+    ];
+    for (var, constant, info) in std_streams {
+        let io = Value::io(obj, info);
+        let id = globals.get_ident_id(var);
+        globals.global_var.insert(id, io);
+        let id = globals.get_ident_id(constant);
+        globals.builtins.object.set_var(id, io);
+    }
+    obj
+}
+
+/// Add the reading and writing protocol of IO to `class`.
+pub fn add_io_methods(globals: &mut Globals, class: ClassRef) {
+    globals.add_builtin_instance_method(class, "gets", gets);
+    globals.add_builtin_instance_method(class, "read", read);
+    globals.add_builtin_instance_method(class, "readlines", readlines);
+    globals.add_builtin_instance_method(class, "each_line", each_line);
+    globals.add_builtin_instance_method(class, "each", each_line);
+    globals.add_builtin_instance_method(class, "write", write);
+    globals.add_builtin_instance_method(class, "print", print);
+    globals.add_builtin_instance_method(class, "puts", puts);
+    globals.add_builtin_instance_method(class, "<<", shl);
+    globals.add_builtin_instance_method(class, "pos", pos);
+    globals.add_builtin_instance_method(class, "tell", pos);
+    globals.add_builtin_instance_method(class, "pos=", set_pos);
+    globals.add_builtin_instance_method(class, "seek", seek);
+    globals.add_builtin_instance_method(class, "rewind", rewind);
+    globals.add_builtin_instance_method(class, "eof?", eof);
+    globals.add_builtin_instance_method(class, "eof", eof);
+    globals.add_builtin_instance_method(class, "lineno", lineno);
+    globals.add_builtin_instance_method(class, "flush", flush);
+    globals.add_builtin_instance_method(class, "close", close);
+    globals.add_builtin_instance_method(class, "closed?", closed);
+    globals.add_builtin_instance_method(class, "truncate", truncate);
+}
+
+// Utils
+
+fn expect_io(vm: &VM, val: Value) -> Result<IORef, RubyError> {
+    match val.as_io() {
+        Some(ioref) if ioref.is_closed() => Err(vm.error_internal("closed stream")),
+        Some(ioref) => Ok(ioref),
+        None => Err(vm.error_argument("Receiver must be IO.")),
+    }
+}
+
+fn io_result<T>(vm: &VM, res: std::io::Result<T>) -> Result<T, RubyError> {
+    res.map_err(|err| vm.error_internal(format!("{}", err)))
+}
+
+pub fn bytes_to_value(vm: &VM, bytes: Vec<u8>) -> Value {
+    match String::from_utf8(bytes) {
+        Ok(s) => Value::string(&vm.globals, s),
+        Err(err) => Value::bytes(&vm.globals, err.into_bytes()),
+    }
+}
+
+fn write_val(vm: &mut VM, ioref: &mut IORef, val: Value) -> Result<usize, RubyError> {
+    let res = match val.as_bytes() {
+        Some(bytes) => ioref.write(bytes),
+        None => {
+            let s = vm.val_to_s(val);
+            ioref.write(s.as_bytes())
+        }
+    };
+    io_result(vm, res)
+}
+
+/// Write `val` in the manner of Kernel#puts.
+pub fn puts_val(vm: &mut VM, ioref: &mut IORef, val: Value) -> Result<(), RubyError> {
+    match val.as_array() {
+        Some(aref) => {
+            for elem in aref.elements.clone() {
+                puts_val(vm, ioref, elem)?;
+            }
+        }
+        None => {
+            write_val(vm, ioref, val)?;
+            let ends_with_lf = match val.as_bytes() {
+                Some(bytes) => bytes.last() == Some(&b'\n'),
+                None => false,
+            };
+            if !ends_with_lf {
+                io_result(vm, ioref.write(b"\n"))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Instance methods
+
+fn inspect(vm: &mut VM, self_val: Value, _args: &Args) -> VMResult {
+    let ioref = match self_val.as_io() {
+        Some(ioref) => ioref,
+        None => return Err(vm.error_argument("Receiver must be IO.")),
+    };
+    let class_name = self_val.as_object().class_name(&vm.globals).to_string();
+    let inspect = if ioref.is_closed() {
+        format!("#<{}:{} (closed)>", class_name, ioref.path)
+    } else {
+        format!("#<{}:{}>", class_name, ioref.path)
+    };
+    Ok(Value::string(&vm.globals, inspect))
+}
+
+fn path(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    match self_val.as_io() {
+        Some(ioref) => Ok(Value::string(&vm.globals, ioref.path.clone())),
+        None => Err(vm.error_argument("Receiver must be IO.")),
+    }
+}
+
+/// IO#gets
+fn gets(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let mut ioref = expect_io(vm, self_val)?;
+    match io_result(vm, ioref.read_line())? {
+        Some(line) => Ok(bytes_to_value(vm, line)),
+        None => Ok(Value::nil()),
+    }
+}
+
+/// IO#read([length])
+fn read(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_range(args.len(), 0, 1)?;
+    let mut ioref = expect_io(vm, self_val)?;
+    let len = if args.len() == 0 || args[0].is_nil() {
+        None
+    } else {
+        let len = vm.expect_integer(args[0], "Length")?;
+        if len < 0 {
+            return Err(vm.error_argument(format!("negative length {} given", len)));
+        }
+        Some(len as usize)
+    };
+    let bytes = io_result(vm, ioref.read(len))?;
+    match len {
+        Some(len) if len != 0 && bytes.is_empty() => Ok(Value::nil()),
+        _ => Ok(bytes_to_value(vm, bytes)),
+    }
+}
+
+fn readlines(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let mut ioref = expect_io(vm, self_val)?;
+    let mut lines = vec![];
+    while let Some(line) = io_result(vm, ioref.read_line())? {
+        lines.push(bytes_to_value(vm, line));
+    }
+    Ok(Value::array_from(&vm.globals, lines))
+}
+
+fn each_line(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let method = match args.block {
+        Some(method) => method,
+        None => {
+            let id = vm.globals.get_ident_id("each_line");
+            let val = Value::enumerator(&vm.globals, id, self_val, args.clone());
+            return Ok(val);
+        }
+    };
+    let mut ioref = expect_io(vm, self_val)?;
+    while let Some(line) = io_result(vm, ioref.read_line())? {
+        let arg = Args::new1(bytes_to_value(vm, line));
+        vm.eval_block(method, &arg)?;
+    }
+    Ok(self_val)
+}
+
+/// IO#write(*objects)
+fn write(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    let mut ioref = expect_io(vm, self_val)?;
+    let mut len = 0;
+    for arg in args.iter() {
+        len += write_val(vm, &mut ioref, *arg)?;
+    }
+    Ok(Value::fixnum(len as i64))
+}
+
+fn print(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    let mut ioref = expect_io(vm, self_val)?;
+    for arg in args.iter() {
+        write_val(vm, &mut ioref, *arg)?;
+    }
+    Ok(Value::nil())
+}
+
+fn puts(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    let mut ioref = expect_io(vm, self_val)?;
+    if args.len() == 0 {
+        io_result(vm, ioref.write(b"\n"))?;
+    }
+    for arg in args.iter() {
+        puts_val(vm, &mut ioref, *arg)?;
+    }
+    Ok(Value::nil())
+}
+
+fn shl(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let mut ioref = expect_io(vm, self_val)?;
+    write_val(vm, &mut ioref, args[0])?;
+    Ok(self_val)
+}
+
+fn pos(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let mut ioref = expect_io(vm, self_val)?;
+    let pos = io_result(vm, ioref.pos())?;
+    Ok(Value::fixnum(pos as i64))
+}
+
+fn set_pos(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let mut ioref = expect_io(vm, self_val)?;
+    let pos = vm.expect_integer(args[0], "Position")?;
+    if pos < 0 {
+        return Err(vm.error_argument("Invalid argument. (negative position)"));
+    }
+    io_result(vm, ioref.seek(SeekFrom::Start(pos as u64)))?;
+    Ok(args[0])
+}
+
+/// IO#seek(amount, whence = IO::SEEK_SET)
+fn seek(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_range(args.len(), 1, 2)?;
+    let mut ioref = expect_io(vm, self_val)?;
+    let amount = vm.expect_integer(args[0], "Amount")?;
+    let whence = if args.len() == 2 {
+        match args[1].as_symbol() {
+            Some(sym) => match vm.globals.get_ident_name(sym) {
+                "SET" => 0,
+                "CUR" => 1,
+                "END" => 2,
+                _ => return Err(vm.error_argument("Unknown whence.")),
+            },
+            None => vm.expect_integer(args[1], "Whence")?,
+        }
+    } else {
+        0
+    };
+    let pos = match whence {
+        0 if amount >= 0 => SeekFrom::Start(amount as u64),
+        0 => return Err(vm.error_argument("Invalid argument. (negative position)")),
+        1 => SeekFrom::Current(amount),
+        2 => SeekFrom::End(amount),
+        _ => return Err(vm.error_argument(format!("Unknown whence. {}", whence))),
+    };
+    io_result(vm, ioref.seek(pos))?;
+    Ok(Value::fixnum(0))
+}
+
+fn rewind(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let mut ioref = expect_io(vm, self_val)?;
+    io_result(vm, ioref.seek(SeekFrom::Start(0)))?;
+    ioref.lineno = 0;
+    Ok(Value::fixnum(0))
+}
+
+fn eof(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let mut ioref = expect_io(vm, self_val)?;
+    let eof = io_result(vm, ioref.is_eof())?;
+    Ok(Value::bool(eof))
+}
+
+fn lineno(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let ioref = expect_io(vm, self_val)?;
+    Ok(Value::fixnum(ioref.lineno))
+}
+
+fn flush(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let mut ioref = expect_io(vm, self_val)?;
+    io_result(vm, ioref.flush())?;
+    Ok(self_val)
+}
+
+/// IO#close. Closing a closed stream does nothing.
+fn close(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    match self_val.as_io() {
+        Some(mut ioref) => {
+            ioref.close();
+            Ok(Value::nil())
+        }
+        None => Err(vm.error_argument("Receiver must be IO.")),
+    }
+}
+
+fn truncate(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let mut ioref = expect_io(vm, self_val)?;
+    let len = vm.expect_integer(args[0], "Length")?;
+    if len < 0 {
+        return Err(vm.error_argument(format!("negative length {} given", len)));
+    }
+    io_result(vm, ioref.truncate(len as usize))?;
+    Ok(Value::fixnum(0))
+}
+
+fn closed(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    match self_val.as_io() {
+        Some(ioref) => Ok(Value::bool(ioref.is_closed())),
+        None => Err(vm.error_argument("Receiver must be IO.")),
+    }
+}
@@ -1,3 +1,4 @@
+use super::errorobj;
 use crate::loader::*;
 use crate::*;
 use rand;
@@ -16,20 +17,58 @@ pub fn init(globals: &mut Globals) -> Value {
     globals.add_builtin_instance_method(kernel_class, "method", method);
     globals.add_builtin_instance_method(kernel_class, "is_a?", isa);
     globals.add_builtin_instance_method(kernel_class, "Integer", integer);
+    globals.add_builtin_instance_method(kernel_class, "Rational", super::rational::rational);
+    globals.add_builtin_instance_method(kernel_class, "Complex", super::complex::complex);
     globals.add_builtin_instance_method(kernel_class, "__dir__", dir);
     globals.add_builtin_instance_method(kernel_class, "__FILE__", file_);
     globals.add_builtin_instance_method(kernel_class, "raise", raise);
+    globals.add_builtin_instance_method(kernel_class, "caller", caller);
+    globals.add_builtin_instance_method(kernel_class, "caller_locations", caller_locations);
     globals.add_builtin_instance_method(kernel_class, "rand", rand);
     globals.add_builtin_instance_method(kernel_class, "loop", loop_);
     globals.add_builtin_instance_method(kernel_class, "exit", exit);
+    globals.add_builtin_instance_method(kernel_class, "at_exit", at_exit);
+    globals.add_builtin_instance_method(kernel_class, "`", super::process::command);
+    globals.add_builtin_instance_method(kernel_class, "system", super::process::system);
+    globals.add_builtin_instance_method(kernel_class, "spawn", super::process::spawn);
+    globals.add_builtin_instance_method(kernel_class, "exec", super::process::exec);
+    globals.add_builtin_instance_method(kernel_class, "binding", super::binding::binding);
+    globals.add_builtin_instance_method(kernel_class, "debugger", super::binding::debugger);
     let kernel = Value::class(globals, kernel_class);
     return kernel;
 
+    /// If `$stdout` was reassigned, returns it.
+    fn redirected_stdout(vm: &mut VM) -> Option<Value> {
+        let id = vm.globals.get_ident_id("$stdout");
+        let stdout = vm.get_global_var(id);
+        match stdout.as_io() {
+            Some(ioref) if ioref.is_stdout() => None,
+            _ => Some(stdout),
+        }
+    }
+
+    fn send_to_stdout(vm: &mut VM, stdout: Value, method: &str, args: &Args) -> VMResult {
+        let id = vm.globals.get_ident_id(method);
+        let method = vm.get_method(stdout, id)?;
+        vm.eval_send(method, stdout, args)
+    }
+
     /// Built-in function "puts".
     fn puts(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+        if let Some(stdout) = redirected_stdout(vm) {
+            send_to_stdout(vm, stdout, "puts", args)?;
+            return Ok(Value::nil());
+        }
         fn flatten(vm: &mut VM, val: Value) {
             match val.as_array() {
-                None => println!("{}", vm.val_to_s(val)),
+                None => {
+                    let s = vm.val_to_s(val);
+                    if s.ends_with('\n') {
+                        print!("{}", s);
+                    } else {
+                        println!("{}", s);
+                    }
+                }
                 Some(aref) => {
                     for val in &aref.elements {
                         flatten(vm, val.clone());
@@ -44,8 +83,19 @@ pub fn init(globals: &mut Globals) -> Value {
     }
 
     fn p(vm: &mut VM, _: Value, args: &Args) -> VMResult {
-        for arg in args.iter() {
-            println!("{}", vm.val_inspect(*arg));
+        match redirected_stdout(vm) {
+            Some(stdout) => {
+                for arg in args.iter() {
+                    let inspect = format!("{}\n", vm.val_inspect(*arg));
+                    let inspect = Args::new1(Value::string(&vm.globals, inspect));
+                    send_to_stdout(vm, stdout, "write", &inspect)?;
+                }
+            }
+            None => {
+                for arg in args.iter() {
+                    println!("{}", vm.val_inspect(*arg));
+                }
+            }
         }
         if args.len() == 1 {
             Ok(args[0])
@@ -56,6 +106,10 @@ pub fn init(globals: &mut Globals) -> Value {
 
     /// Built-in function "print".
     fn print(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+        if let Some(stdout) = redirected_stdout(vm) {
+            send_to_stdout(vm, stdout, "print", args)?;
+            return Ok(Value::nil());
+        }
         for arg in args.iter() {
             match arg.as_bytes() {
                 Some(bytes) => {
@@ -89,6 +143,10 @@ pub fn init(globals: &mut Globals) -> Value {
             Some(string) => string,
             None => return Err(vm.error_argument("file name must be a string.")),
         };
+        // Libraries implemented as built-in classes.
+        if ["coverage", "open3", "stringio", "time"].contains(&file_name.as_str()) {
+            return Ok(Value::false_val());
+        }
         let mut path = std::env::current_dir().unwrap();
         path.push(file_name);
         require_main(vm, path)?;
@@ -139,7 +197,7 @@ pub fn init(globals: &mut Globals) -> Value {
         eprintln!("reading:{}", absolute_path.to_string_lossy());
         vm.root_path.push(path);
         vm.class_push(vm.globals.builtins.object);
-        vm.run(absolute_path, &program, None)?;
+        vm.run_file(absolute_path, &program, None)?;
         vm.class_pop();
         vm.root_path.pop().unwrap();
         Ok(())
@@ -236,12 +294,71 @@ pub fn init(globals: &mut Globals) -> Value {
         ))
     }
 
+    /// raise / raise(message) / raise(class [, message]) / raise(exception)
     fn raise(vm: &mut VM, _: Value, args: &Args) -> VMResult {
         vm.check_args_range(args.len(), 0, 2)?;
-        for arg in args.iter() {
-            eprintln!("{}", vm.val_inspect(*arg));
+        let id = vm.globals.get_ident_id("RuntimeError");
+        let runtime_error = vm.globals.builtins.object.get_var(id).unwrap();
+        let exception = if args.len() == 0 {
+            errorobj::new_exception(&mut vm.globals, runtime_error, "unhandled exception")
+        } else if args.len() == 1 && args[0].as_string().is_some() {
+            let msg = vm.val_to_s(args[0]);
+            errorobj::new_exception(&mut vm.globals, runtime_error, &msg)
+        } else if args[0].as_module().is_some() {
+            let new_args = match args.len() {
+                1 => Args::new0(),
+                _ => Args::new1(args[1]),
+            };
+            let method = vm.get_method(args[0], IdentId::NEW)?;
+            vm.eval_send(method, args[0], &new_args)?
+        } else {
+            args[0]
+        };
+        if !errorobj::is_exception(&vm.globals, exception) {
+            return Err(vm.error_type("exception class/object expected"));
         }
-        Err(vm.error_unimplemented("error"))
+        Err(vm.error_exception(exception))
+    }
+
+    /// Returns Thread::Backtrace::Locations of the call stack, skipping `start` frames (default 1).
+    fn caller_frames(vm: &mut VM, args: &Args) -> Result<Vec<Value>, RubyError> {
+        vm.check_args_range(args.len(), 0, 2)?;
+        let start = match args.len() {
+            0 => 1,
+            _ => vm.expect_integer(args[0], "1st arg")?,
+        };
+        if start < 0 {
+            return Err(vm.error_argument(format!("negative level ({})", start)));
+        }
+        let mut frames: Vec<(String, usize, String)> =
+            vm.stack_frames().into_iter().skip(start as usize).collect();
+        if args.len() == 2 {
+            let len = vm.expect_integer(args[1], "2nd arg")?;
+            if len < 0 {
+                return Err(vm.error_argument(format!("negative size ({})", len)));
+            }
+            frames.truncate(len as usize);
+        }
+        Ok(frames
+            .into_iter()
+            .map(|(path, line, label)| errorobj::new_location(&mut vm.globals, path, line, label))
+            .collect())
+    }
+
+    fn caller(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+        let lines = caller_frames(vm, args)?
+            .iter()
+            .map(|location| {
+                let s = errorobj::location_string(vm, *location);
+                Value::string(&vm.globals, s)
+            })
+            .collect();
+        Ok(Value::array_from(&vm.globals, lines))
+    }
+
+    fn caller_locations(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+        let locations = caller_frames(vm, args)?;
+        Ok(Value::array_from(&vm.globals, locations))
     }
 
     fn rand(_vm: &mut VM, _: Value, _args: &Args) -> VMResult {
@@ -264,13 +381,25 @@ pub fn init(globals: &mut Globals) -> Value {
         } else {
             args[0].expect_integer(vm, "Expect Integer.")?
         };
+        vm.run_at_exit()?;
         std::process::exit(code as i32);
     }
+
+    /// Register the block to be executed when the program exits.
+    fn at_exit(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+        vm.check_args_num(args.len(), 0)?;
+        let method = vm.expect_block(args.block)?;
+        let procobj = vm.create_proc(method)?;
+        vm.globals.at_exit.push(procobj);
+        Ok(procobj)
+    }
 }
 
 #[cfg(test)]
 mod test {
     use crate::test::*;
+    use crate::*;
+    use std::path::PathBuf;
 
     #[test]
     fn is_a() {
@@ -292,4 +421,49 @@ mod test {
         ";
         assert_script(program);
     }
+
+    #[test]
+    fn caller() {
+        let program = r#"
+        def foo
+          [1].map { bar }[0]
+        end
+        def bar
+          caller
+        end
+        assert([":3:in 'block in Object#foo'", ":3:in 'Object#foo'", ":8:in '<main>'"], foo)
+        locs = caller_locations(0)
+        assert([[9, "<main>"]], locs.map { |l| [l.lineno, l.label] })
+        assert([], caller)
+        def baz
+          caller(0, 1)
+        end
+        assert([":13:in 'Object#baz'"], baz)
+        "#;
+        assert_script(program);
+    }
+
+    #[test]
+    fn at_exit() {
+        let program = "
+        $log = []
+        at_exit { $log << 1 }
+        END { $log << 2 }
+        at_exit { $log << 3 }
+        $log << 0
+        ";
+        let mut vm = VM::new();
+        vm.run(PathBuf::from(""), program, None).unwrap();
+        vm.run_at_exit().unwrap();
+        let id = vm.globals.get_ident_id("$log");
+        let log = vm.get_global_var(id);
+        let log: Vec<i64> = log
+            .as_array()
+            .unwrap()
+            .elements
+            .iter()
+            .map(|v| v.as_fixnum().unwrap())
+            .collect();
+        assert_eq!(vec![0, 3, 2, 1], log);
+    }
 }
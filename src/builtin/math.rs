@@ -1,60 +1,231 @@
 use crate::*;
 
+extern "C" {
+    fn erf(x: f64) -> f64;
+    fn erfc(x: f64) -> f64;
+    fn tgamma(x: f64) -> f64;
+    fn lgamma_r(x: f64, sign: *mut i32) -> f64;
+}
+
 pub fn init_math(globals: &mut Globals) -> Value {
     let id = globals.get_ident_id("Math");
     let class = ClassRef::from(id, globals.builtins.object);
-    let obj = Value::class(globals, class);
+    let mut obj = Value::class(globals, class);
+    let id = globals.get_ident_id("PI");
+    obj.set_var(id, Value::flonum(std::f64::consts::PI));
+    let id = globals.get_ident_id("E");
+    obj.set_var(id, Value::flonum(std::f64::consts::E));
+    let id = globals.get_ident_id("ArgumentError");
+    let argument_error = globals.builtins.object.get_var(id).unwrap();
+    let id = globals.get_ident_id("Math::DomainError");
+    let class = ClassRef::from(id, argument_error);
+    let err = Value::class(globals, class);
+    let id = globals.get_ident_id("DomainError");
+    obj.set_var(id, err);
+
     globals.add_builtin_class_method(obj, "sqrt", sqrt);
+    globals.add_builtin_class_method(obj, "cbrt", cbrt);
     globals.add_builtin_class_method(obj, "cos", cos);
     globals.add_builtin_class_method(obj, "sin", sin);
+    globals.add_builtin_class_method(obj, "tan", tan);
+    globals.add_builtin_class_method(obj, "acos", acos);
+    globals.add_builtin_class_method(obj, "asin", asin);
+    globals.add_builtin_class_method(obj, "atan", atan);
+    globals.add_builtin_class_method(obj, "atan2", atan2);
+    globals.add_builtin_class_method(obj, "cosh", cosh);
+    globals.add_builtin_class_method(obj, "sinh", sinh);
+    globals.add_builtin_class_method(obj, "tanh", tanh);
+    globals.add_builtin_class_method(obj, "exp", exp);
+    globals.add_builtin_class_method(obj, "log", log);
+    globals.add_builtin_class_method(obj, "log2", log2);
+    globals.add_builtin_class_method(obj, "log10", log10);
+    globals.add_builtin_class_method(obj, "pow", pow);
+    globals.add_builtin_class_method(obj, "hypot", hypot);
+    globals.add_builtin_class_method(obj, "erf", erf_);
+    globals.add_builtin_class_method(obj, "erfc", erfc_);
+    globals.add_builtin_class_method(obj, "gamma", gamma);
+    globals.add_builtin_class_method(obj, "lgamma", lgamma);
     obj
 }
 
-// Class methods
+// Utils
+
+fn expect_num(vm: &mut VM, arg: Value) -> Result<f64, RubyError> {
+    match arg.unpack() {
+        RV::Integer(i) => Ok(i as f64),
+        RV::Float(f) => Ok(f),
+        _ => {
+            let class = vm.globals.get_class_name(arg);
+            Err(vm.error_type(format!("can't convert {} into Float", class)))
+        }
+    }
+}
 
-// Instance methods
+fn error_domain(vm: &mut VM, name: &str) -> RubyError {
+    vm.error_domain(format!(
+        "Numerical argument is out of domain - \"{}\"",
+        name
+    ))
+}
 
-fn sqrt(vm: &mut VM, _: Value, args: &Args) -> VMResult {
-    let arg = args[0];
-    let num = if arg.is_packed_num() {
-        if arg.is_packed_fixnum() {
-            arg.as_packed_fixnum() as f64
-        } else {
-            arg.as_packed_flonum()
+/// Define a Math function which takes one argument.
+/// The optional `$domain` closure returns false if the argument is out of domain.
+macro_rules! math_func {
+    ($fname:ident, $name:expr, $f:expr) => {
+        math_func!($fname, $name, $f, |_: f64| true);
+    };
+    ($fname:ident, $name:expr, $f:expr, $domain:expr) => {
+        fn $fname(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+            vm.check_args_num(args.len(), 1)?;
+            let x = expect_num(vm, args[0])?;
+            if !$domain(x) {
+                return Err(error_domain(vm, $name));
+            }
+            Ok(Value::flonum($f(x)))
         }
-    } else {
-        return Err(vm.error_type("Must be a number."));
     };
-    let res = Value::flonum(num.sqrt());
-    Ok(res)
 }
 
-fn cos(vm: &mut VM, _: Value, args: &Args) -> VMResult {
-    let arg = args[0];
-    let num = if arg.is_packed_num() {
-        if arg.is_packed_fixnum() {
-            arg.as_packed_fixnum() as f64
-        } else {
-            arg.as_packed_flonum()
+// Class methods
+
+math_func!(sqrt, "sqrt", f64::sqrt, |x: f64| !(x < 0.0));
+math_func!(cbrt, "cbrt", f64::cbrt);
+math_func!(cos, "cos", f64::cos);
+math_func!(sin, "sin", f64::sin);
+math_func!(tan, "tan", f64::tan);
+math_func!(acos, "acos", f64::acos, |x: f64| !(x < -1.0 || x > 1.0));
+math_func!(asin, "asin", f64::asin, |x: f64| !(x < -1.0 || x > 1.0));
+math_func!(atan, "atan", f64::atan);
+math_func!(cosh, "cosh", f64::cosh);
+math_func!(sinh, "sinh", f64::sinh);
+math_func!(tanh, "tanh", f64::tanh);
+math_func!(exp, "exp", f64::exp);
+math_func!(log2, "log2", f64::log2, |x: f64| !(x < 0.0));
+math_func!(log10, "log10", f64::log10, |x: f64| !(x < 0.0));
+math_func!(erf_, "erf", |x| unsafe { erf(x) });
+math_func!(erfc_, "erfc", |x| unsafe { erfc(x) });
+
+fn atan2(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 2)?;
+    let y = expect_num(vm, args[0])?;
+    let x = expect_num(vm, args[1])?;
+    Ok(Value::flonum(y.atan2(x)))
+}
+
+/// Math.log(x) / Math.log(x, base)
+fn log(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_range(args.len(), 1, 2)?;
+    let x = expect_num(vm, args[0])?;
+    if x < 0.0 {
+        return Err(error_domain(vm, "log"));
+    }
+    if args.len() == 2 {
+        let base = expect_num(vm, args[1])?;
+        if base < 0.0 {
+            return Err(error_domain(vm, "log"));
         }
+        Ok(Value::flonum(x.ln() / base.ln()))
     } else {
-        return Err(vm.error_type("Must be a number."));
-    };
-    let res = Value::flonum(num.cos());
-    Ok(res)
+        Ok(Value::flonum(x.ln()))
+    }
+}
+
+fn pow(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 2)?;
+    let x = expect_num(vm, args[0])?;
+    let y = expect_num(vm, args[1])?;
+    Ok(Value::flonum(x.powf(y)))
+}
+
+fn hypot(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 2)?;
+    let x = expect_num(vm, args[0])?;
+    let y = expect_num(vm, args[1])?;
+    Ok(Value::flonum(x.hypot(y)))
+}
+
+/// Math.gamma(x). Returns the exact factorial for small positive integers.
+fn gamma(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let x = expect_num(vm, args[0])?;
+    if x == std::f64::NEG_INFINITY || (x < 0.0 && x.fract() == 0.0) {
+        return Err(error_domain(vm, "gamma"));
+    }
+    if x.fract() == 0.0 && 1.0 <= x && x <= 23.0 {
+        // (x - 1)! is exactly representable as f64 in this range.
+        let fact = (1..x as u64).fold(1.0, |acc, i| acc * i as f64);
+        return Ok(Value::flonum(fact));
+    }
+    Ok(Value::flonum(unsafe { tgamma(x) }))
+}
+
+/// Math.lgamma(x) -> [log(|gamma(x)|), sign of gamma(x)]
+fn lgamma(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let x = expect_num(vm, args[0])?;
+    if x == std::f64::NEG_INFINITY {
+        return Err(error_domain(vm, "lgamma"));
+    }
+    let mut sign = 1;
+    let res = unsafe { lgamma_r(x, &mut sign) };
+    let sign = if sign < 0 { -1 } else { 1 };
+    Ok(Value::array_from(
+        &vm.globals,
+        vec![Value::flonum(res), Value::fixnum(sign)],
+    ))
 }
 
-fn sin(vm: &mut VM, _: Value, args: &Args) -> VMResult {
-    let arg = args[0];
-    let num = if arg.is_packed_num() {
-        if arg.is_packed_fixnum() {
-            arg.as_packed_fixnum() as f64
-        } else {
-            arg.as_packed_flonum()
+#[cfg(test)]
+mod test {
+    use crate::test::*;
+    use crate::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn math_functions() {
+        let program = r#"
+        assert(3.141592653589793, Math::PI)
+        assert(2.718281828459045, Math::E)
+        assert(2.0, Math.sqrt(4))
+        assert(3.0, Math.cbrt(27))
+        assert(1.0, Math.cos(0))
+        assert(0.0, Math.sin(0.0))
+        assert(0.0, Math.tan(0))
+        assert(Math::PI / 4, Math.atan(1))
+        assert(Math::PI / 2, Math.atan2(1, 0))
+        assert(1.0, Math.exp(0))
+        assert(1.0, Math.log(Math::E))
+        assert(3.0, Math.log(8, 2))
+        assert(10.0, Math.log2(1024))
+        assert(3.0, Math.log10(1000))
+        assert(8.0, Math.pow(2, 3))
+        assert(5.0, Math.hypot(3, 4))
+        assert(0.0, Math.erf(0))
+        assert(1.0, Math.erfc(0))
+        assert(120.0, Math.gamma(6))
+        assert(1124000727777607680000.0, Math.gamma(23))
+        assert(true, (Math.gamma(0.5) - Math.sqrt(Math::PI)).abs < 1e-15)
+        assert([0.0, 1], Math.lgamma(1))
+        "#;
+        assert_script(program);
+    }
+
+    #[test]
+    fn math_domain_error() {
+        let program = r#"
+        assert("Math::DomainError", Math::DomainError.inspect)
+        assert(true, Math.sqrt(Float::NAN).nan?)
+        "#;
+        assert_script(program);
+        for program in &["Math.sqrt(-1)", "Math.log(-1)", "Math.gamma(-1)", "Math.acos(2)"] {
+            let mut vm = VM::new();
+            match vm.run(PathBuf::from(""), program, None) {
+                Err(RubyError {
+                    kind: RubyErrorKind::RuntimeErr(RuntimeErrKind::Domain(_)),
+                    ..
+                }) => {}
+                res => panic!("{}: expected Math::DomainError, got {:?}", program, res),
+            }
         }
-    } else {
-        return Err(vm.error_type("Must be a number."));
-    };
-    let res = Value::flonum(num.sin());
-    Ok(res)
+    }
 }
@@ -14,6 +14,8 @@ pub fn init(globals: &mut Globals) {
     globals.add_builtin_instance_method(class, "include", include);
     globals.add_builtin_instance_method(class, "included_modules", included_modules);
     globals.add_builtin_instance_method(class, "ancestors", ancestors);
+    globals.add_builtin_instance_method(class, "alias_method", alias_method);
+    globals.add_builtin_instance_method(class, "undef_method", undef_method);
 }
 
 fn constants(vm: &mut VM, self_val: Value, _: &Args) -> VMResult {
@@ -23,17 +25,16 @@ fn constants(vm: &mut VM, self_val: Value, _: &Args) -> VMResult {
         v.append(
             &mut class
                 .as_object()
-                .var_table()
-                .keys()
-                .filter(|x| {
+                .vars()
+                .filter(|(x, _)| {
                     vm.globals
-                        .get_ident_name(**x)
+                        .get_ident_name(*x)
                         .chars()
                         .nth(0)
                         .unwrap()
                         .is_ascii_uppercase()
                 })
-                .map(|k| Value::symbol(*k))
+                .map(|(k, _)| Value::symbol(k))
                 .collect(),
         );
         match class.superclass() {
@@ -68,8 +69,9 @@ fn instance_methods(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
         false => {
             let v = class
                 .method_table
-                .keys()
-                .map(|k| Value::symbol(*k))
+                .iter()
+                .filter(|(_, method)| !method.is_none())
+                .map(|(k, _)| Value::symbol(*k))
                 .collect();
             Ok(Value::array_from(&vm.globals, v))
         }
@@ -80,8 +82,9 @@ fn instance_methods(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
                     .union(
                         &class
                             .method_table
-                            .keys()
-                            .map(|k| Value::symbol(*k))
+                            .iter()
+                            .filter(|(_, method)| !method.is_none())
+                            .map(|(k, _)| Value::symbol(*k))
                             .collect(),
                     )
                     .cloned()
@@ -96,6 +99,37 @@ fn instance_methods(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
     }
 }
 
+/// Module#alias_method(new_name, old_name)
+fn alias_method(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 2)?;
+    let new = method_name(vm, args[0])?;
+    let old = method_name(vm, args[1])?;
+    vm.alias_method(self_val, new, old)?;
+    Ok(Value::symbol(new))
+}
+
+/// Module#undef_method(*names)
+fn undef_method(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    for arg in args.iter() {
+        let id = method_name(vm, *arg)?;
+        vm.undef_method(self_val, id)?;
+    }
+    Ok(self_val)
+}
+
+fn method_name(vm: &mut VM, name: Value) -> Result<IdentId, RubyError> {
+    if let Some(id) = name.as_symbol() {
+        return Ok(id);
+    }
+    match name.as_string() {
+        Some(s) => Ok(vm.globals.get_ident_id(s)),
+        None => {
+            let inspect = vm.val_inspect(name);
+            Err(vm.error_type(format!("{} is not a symbol nor a string", inspect)))
+        }
+    }
+}
+
 pub fn attr_accessor(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
     for arg in args.iter() {
         if arg.is_packed_symbol() {
@@ -173,6 +207,7 @@ fn include(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
     let mut class = vm.expect_module(self_val)?;
     let module = args[0];
     class.include.push(module);
+    class.invalidate_method_cache();
     Ok(Value::nil())
 }
 
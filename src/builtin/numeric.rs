@@ -0,0 +1,254 @@
+use crate::*;
+
+/// Numeric is the superclass of Integer, Float, Rational and Complex.
+/// The instance methods are written with operators of the receiver,
+/// so that user-defined subclasses work with only a few operators defined.
+pub fn init_numeric(globals: &mut Globals) -> Value {
+    let id = globals.get_ident_id("Numeric");
+    let class = ClassRef::from(id, globals.builtins.object);
+    globals.add_builtin_instance_method(class, "coerce", coerce);
+    globals.add_builtin_instance_method(class, "+@", plus);
+    globals.add_builtin_instance_method(class, "-@", minus);
+    globals.add_builtin_instance_method(class, "zero?", zero);
+    globals.add_builtin_instance_method(class, "nonzero?", nonzero);
+    globals.add_builtin_instance_method(class, "positive?", positive);
+    globals.add_builtin_instance_method(class, "negative?", negative);
+    globals.add_builtin_instance_method(class, "abs", abs);
+    globals.add_builtin_instance_method(class, "magnitude", abs);
+    globals.add_builtin_instance_method(class, "integer?", integer);
+    globals.add_builtin_instance_method(class, "real?", real);
+    globals.add_builtin_instance_method(class, "to_int", to_int);
+    Value::class(globals, class)
+}
+
+// Utils
+
+/// Converts `val` into f64 with `to_f` for coerce.
+fn to_float(vm: &mut VM, val: Value) -> Result<f64, RubyError> {
+    if let Some(real) = Real::from_value(val) {
+        return Ok(real.to_f());
+    }
+    let id = vm.globals.get_ident_id("to_f");
+    if vm.get_method(val, id).is_ok() {
+        if let Some(f) = vm.send0(val, id)?.as_flonum() {
+            return Ok(f);
+        }
+    }
+    let class = vm.globals.get_class_name(val);
+    Err(vm.error_type(format!("can't convert {} into Float", class)))
+}
+
+fn is_true(vm: &mut VM, method: IdentId, self_val: Value) -> Result<bool, RubyError> {
+    let res = vm.eval_binop(method, self_val, Value::fixnum(0))?;
+    Ok(vm.val_to_bool(res))
+}
+
+// Instance methods
+
+/// Numeric#coerce(other) -> [other, self] if they are of the same class, otherwise both as Float.
+fn coerce(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let self_class = self_val.get_class_object(&vm.globals);
+    let other_class = args[0].get_class_object(&vm.globals);
+    let pair = if self_class.id() == other_class.id() {
+        vec![args[0], self_val]
+    } else {
+        let other = to_float(vm, args[0])?;
+        let this = to_float(vm, self_val)?;
+        vec![Value::flonum(other), Value::flonum(this)]
+    };
+    Ok(Value::array_from(&vm.globals, pair))
+}
+
+fn plus(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    Ok(self_val)
+}
+
+/// Numeric#-@ -> 0 - self, where 0 is coerced by self.
+fn minus(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    vm.coerce_binop(IdentId::_SUB, Value::fixnum(0), self_val)
+}
+
+fn zero(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    Ok(Value::bool(is_true(vm, IdentId::_EQ, self_val)?))
+}
+
+fn nonzero(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let id = vm.globals.get_ident_id("zero?");
+    let zero = vm.send0(self_val, id)?;
+    if vm.val_to_bool(zero) {
+        Ok(Value::nil())
+    } else {
+        Ok(self_val)
+    }
+}
+
+fn positive(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    Ok(Value::bool(is_true(vm, IdentId::_GT, self_val)?))
+}
+
+fn negative(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let id = vm.globals.get_ident_id("<");
+    Ok(Value::bool(is_true(vm, id, self_val)?))
+}
+
+fn abs(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let id = vm.globals.get_ident_id("<");
+    if is_true(vm, id, self_val)? {
+        let id = vm.globals.get_ident_id("-@");
+        vm.send0(self_val, id)
+    } else {
+        Ok(self_val)
+    }
+}
+
+fn integer(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    Ok(Value::false_val())
+}
+
+fn real(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    Ok(Value::true_val())
+}
+
+fn to_int(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let id = vm.globals.get_ident_id("to_i");
+    vm.send0(self_val, id)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test::*;
+
+    #[test]
+    fn numeric_coerce() {
+        let program = r#"
+        class Vec < Numeric
+          attr_reader :x, :y
+          def initialize(x, y)
+            @x = x
+            @y = y
+          end
+          def +(other)
+            Vec.new(@x + other.x, @y + other.y)
+          end
+          def *(other)
+            Vec.new(@x * other, @y * other)
+          end
+          def >(other)
+            @x * @x + @y * @y > other * other
+          end
+          def <(other)
+            @x * @x + @y * @y < other * other
+          end
+          def ==(other)
+            other.is_a?(Vec) && @x == other.x && @y == other.y
+          end
+          def coerce(other)
+            [self, other]
+          end
+          def to_a
+            [@x, @y]
+          end
+        end
+        v = Vec.new(1, 2)
+        assert([2, 4], (2 * v).to_a)
+        assert([2, 4], (v * 2).to_a)
+        assert([1.5, 3.0], (1.5 * v).to_a)
+        assert([2, 4], (v + v).to_a)
+        assert(true, v.is_a?(Numeric))
+        assert(true, 1.is_a?(Numeric))
+        assert(true, 1.5.is_a?(Numeric))
+        assert(true, Rational(1, 2).is_a?(Numeric))
+        assert(Numeric, Integer.superclass)
+        assert(true, v.positive?)
+        assert(false, v.negative?)
+        assert(false, v.zero?)
+        assert(v, v.nonzero?)
+        assert(false, v.integer?)
+        assert([2, 1], 1.coerce(2))
+        assert([2.5, 1.0], 1.coerce(2.5))
+        begin
+          1 + "a"
+        rescue TypeError => e
+          assert("String can't be coerced into Integer", e.message)
+        end
+        begin
+          1.0 * nil
+        rescue TypeError => e
+          assert("nil can't be coerced into Float", e.message)
+        end
+        "#;
+        assert_script(program);
+    }
+
+    #[test]
+    fn numeric_coerce_cmp() {
+        let program = r#"
+        class Meter < Numeric
+          attr_reader :value
+          def initialize(value)
+            @value = value
+          end
+          def coerce(other)
+            [Meter.new(other), self]
+          end
+          def <=>(other)
+            @value <=> other.value
+          end
+          def >(other)
+            @value > other.value
+          end
+          def >=(other)
+            @value >= other.value
+          end
+          def <(other)
+            @value < other.value
+          end
+          def <=(other)
+            @value <= other.value
+          end
+          def -(other)
+            Meter.new(@value - other.value)
+          end
+        end
+        m = Meter.new(3)
+        assert(true, 5 > m)
+        assert(false, 2 >= m)
+        assert(true, 3 >= m)
+        assert(true, 2 < m)
+        assert(false, 3 < m)
+        assert(true, 3 <= m)
+        assert(false, 4 <= m)
+        assert(true, 2.5 < m)
+        [-> { 1 > "a" }, -> { 1 <= "a" }, -> { 1.5 < nil }].each do |f|
+          err = begin
+            f.call
+          rescue => e
+            e.class
+          end
+          assert(ArgumentError, err)
+        end
+        msg = begin
+          1 < "a"
+        rescue ArgumentError => e
+          e.message
+        end
+        assert("Comparison of Integer with String failed.", msg)
+        assert(-1, 2 <=> m)
+        assert(nil, 1 <=> "a")
+        assert(-3, m.send("-@".to_sym).value)
+        assert(2, (5 - m).value)
+        "#;
+        assert_script(program);
+    }
+}
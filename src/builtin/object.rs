@@ -134,10 +134,9 @@ fn instance_variables(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
     vm.check_args_num(args.len(), 0)?;
     let receiver = self_val.as_object();
     let res = receiver
-        .var_table()
-        .keys()
-        .filter(|x| vm.globals.get_ident_name(**x).chars().nth(0) == Some('@'))
-        .map(|x| Value::symbol(*x))
+        .vars()
+        .filter(|(x, _)| vm.globals.get_ident_name(*x).chars().nth(0) == Some('@'))
+        .map(|(x, _)| Value::symbol(x))
         .collect();
     Ok(Value::array_from(&vm.globals, res))
 }
@@ -1,19 +1,537 @@
 use crate::*;
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::process::{Command, ExitStatus, Stdio};
 
 pub fn init_process(globals: &mut Globals) -> Value {
     let id = globals.get_ident_id("Process");
     let class = ClassRef::from(id, globals.builtins.object);
     let mut obj = Value::class(globals, class);
     globals.add_builtin_class_method(obj, "clock_gettime", clock_gettime);
-    let id = globals.get_ident_id("CLOCK_MONOTONIC");
-    obj.set_var(id, Value::fixnum(0));
+    globals.add_builtin_class_method(obj, "pid", pid);
+    globals.add_builtin_class_method(obj, "ppid", ppid);
+    globals.add_builtin_class_method(obj, "spawn", spawn);
+    globals.add_builtin_class_method(obj, "wait", wait);
+    globals.add_builtin_class_method(obj, "waitpid", wait);
+    globals.add_builtin_class_method(obj, "wait2", wait2);
+    globals.add_builtin_class_method(obj, "kill", kill);
+    globals.add_builtin_class_method(obj, "last_status", last_status);
+    for (name, clock) in &[
+        ("CLOCK_REALTIME", libc::CLOCK_REALTIME),
+        ("CLOCK_MONOTONIC", libc::CLOCK_MONOTONIC),
+        ("CLOCK_PROCESS_CPUTIME_ID", libc::CLOCK_PROCESS_CPUTIME_ID),
+        ("CLOCK_THREAD_CPUTIME_ID", libc::CLOCK_THREAD_CPUTIME_ID),
+    ] {
+        let id = globals.get_ident_id(*name);
+        obj.set_var(id, Value::fixnum(*clock as i64));
+    }
+    let id = globals.get_ident_id("Status");
+    let status_class = init_status(globals);
+    obj.set_var(id, status_class);
+    let id = globals.get_ident_id("$$");
+    globals
+        .global_var
+        .insert(id, Value::fixnum(std::process::id() as i64));
     obj
 }
 
+fn init_status(globals: &mut Globals) -> Value {
+    let id = globals.get_ident_id("Process::Status");
+    let class = ClassRef::from(id, globals.builtins.object);
+    let obj = Value::class(globals, class);
+    globals.add_builtin_instance_method(class, "success?", status_success);
+    globals.add_builtin_instance_method(class, "exitstatus", status_exitstatus);
+    globals.add_builtin_instance_method(class, "pid", status_pid);
+    globals.add_builtin_instance_method(class, "to_i", status_to_i);
+    globals.add_builtin_instance_method(class, "exited?", status_exited);
+    globals.add_builtin_instance_method(class, "signaled?", status_signaled);
+    globals.add_builtin_instance_method(class, "termsig", status_termsig);
+    globals.add_builtin_instance_method(class, "to_s", status_to_s);
+    globals.add_builtin_instance_method(class, "inspect", status_inspect);
+    globals.add_builtin_instance_method(class, "==", status_eq);
+    obj
+}
+
+pub fn init_open3(globals: &mut Globals) -> Value {
+    let id = globals.get_ident_id("Open3");
+    let class = ClassRef::from(id, None);
+    let obj = Value::module(globals, class);
+    globals.add_builtin_class_method(obj, "capture2", capture2);
+    globals.add_builtin_class_method(obj, "capture2e", capture2e);
+    globals.add_builtin_class_method(obj, "capture3", capture3);
+    obj
+}
+
+// Utils
+
+/// Build a Command from `args`: ([env,] command_line) or ([env,] program, arg, ..).
+/// A single command line is run by /bin/sh.
+pub fn build_command(vm: &mut VM, args: &Args) -> Result<Command, RubyError> {
+    let mut args: Vec<Value> = args.iter().cloned().collect();
+    let env = match args.first().and_then(|arg| arg.as_hash()) {
+        Some(href) => {
+            args.remove(0);
+            Some(href)
+        }
+        None => None,
+    };
+    if args.is_empty() {
+        return Err(vm.error_argument("Wrong number of arguments. (given 0, expected 1+)"));
+    }
+    let mut strings = vec![];
+    for arg in &args {
+        strings.push(vm.expect_string(arg, "Command")?.clone());
+    }
+    let mut command = if strings.len() == 1 {
+        let mut command = Command::new("/bin/sh");
+        command.arg("-c").arg(&strings[0]);
+        command
+    } else {
+        let mut command = Command::new(&strings[0]);
+        command.args(&strings[1..]);
+        command
+    };
+    if let Some(href) = env {
+        for (k, v) in href.iter() {
+            let k = vm.expect_string(&k, "Environment variable name")?.clone();
+            if v.is_nil() {
+                command.env_remove(k);
+            } else {
+                let v = vm.expect_string(&v, "Environment variable value")?.clone();
+                command.env(k, v);
+            }
+        }
+    }
+    Ok(command)
+}
+
+fn status_class(vm: &mut VM) -> Value {
+    let process_id = vm.globals.get_ident_id("Process");
+    let process = vm.globals.builtins.object.get_var(process_id).unwrap();
+    let status_id = vm.globals.get_ident_id("Status");
+    process.get_var(status_id).unwrap()
+}
+
+/// Create a Process::Status for the process `pid` and store it in `$?`.
+pub fn set_last_status(vm: &mut VM, pid: u32, status: ExitStatus) -> Value {
+    let class = status_class(vm);
+    let mut val = Value::ordinary_object(class);
+    let exitstatus = match status.code() {
+        Some(code) => Value::fixnum(code as i64),
+        None => Value::nil(),
+    };
+    let termsig = match status.signal() {
+        Some(sig) => Value::fixnum(sig as i64),
+        None => Value::nil(),
+    };
+    let vars = [
+        ("_pid", Value::fixnum(pid as i64)),
+        ("_status", Value::fixnum(status.into_raw() as i64)),
+        ("_exitstatus", exitstatus),
+        ("_termsig", termsig),
+    ];
+    for (name, v) in vars.iter() {
+        let id = vm.globals.get_ident_id(*name);
+        val.set_var(id, *v);
+    }
+    let id = vm.globals.get_ident_id("$?");
+    vm.globals.global_var.insert(id, val);
+    val
+}
+
+fn spawn_error(vm: &mut VM, command: &Command, err: std::io::Error) -> RubyError {
+    vm.error_internal(format!("{} - {:?}", err, command))
+}
+
+/// Run `command` capturing its stdout (and stderr if `capture_stderr`), wait for it and set `$?`.
+fn run_output(
+    vm: &mut VM,
+    mut command: Command,
+    capture_stderr: bool,
+) -> Result<(Vec<u8>, Vec<u8>), RubyError> {
+    command.stdout(Stdio::piped());
+    if capture_stderr {
+        command.stderr(Stdio::piped());
+    }
+    let child = match command.spawn() {
+        Ok(child) => child,
+        Err(err) => return Err(spawn_error(vm, &command, err)),
+    };
+    let pid = child.id();
+    match child.wait_with_output() {
+        Ok(output) => {
+            set_last_status(vm, pid, output.status);
+            Ok((output.stdout, output.stderr))
+        }
+        Err(err) => Err(vm.error_internal(format!("{}", err))),
+    }
+}
+
+/// Wrap `command` with /bin/sh so that its stderr is redirected to stdout.
+fn merge_stderr(command: Command) -> Command {
+    let mut merged = Command::new("/bin/sh");
+    merged.arg("-c").arg("exec \"$@\" 2>&1").arg("sh");
+    merged.arg(command.get_program()).args(command.get_args());
+    for (k, v) in command.get_envs() {
+        match v {
+            Some(v) => merged.env(k, v),
+            None => merged.env_remove(k),
+        };
+    }
+    merged
+}
+
+fn wait_pid(vm: &mut VM, pid: i64) -> Result<(u32, ExitStatus), RubyError> {
+    let mut status = 0;
+    let res = unsafe { libc::waitpid(pid as libc::pid_t, &mut status, 0) };
+    if res < 0 {
+        return Err(vm.error_internal("No child processes (Errno::ECHILD)"));
+    }
+    let status = ExitStatus::from_raw(status);
+    set_last_status(vm, res as u32, status);
+    Ok((res as u32, status))
+}
+
+// Kernel methods
+
+/// `cmd` / %x{cmd}
+pub fn command(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let command = build_command(vm, args)?;
+    let (stdout, _) = run_output(vm, command, false)?;
+    Ok(super::io::bytes_to_value(vm, stdout))
+}
+
+/// system(cmd) -> true / false / nil
+pub fn system(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_min(args.len(), 1)?;
+    let mut command = build_command(vm, args)?;
+    match command.spawn() {
+        Ok(mut child) => {
+            let pid = child.id();
+            match child.wait() {
+                Ok(status) => {
+                    set_last_status(vm, pid, status);
+                    Ok(Value::bool(status.success()))
+                }
+                Err(err) => Err(vm.error_internal(format!("{}", err))),
+            }
+        }
+        Err(_) => {
+            // Same as MRI: a command that could not be run exits with 127.
+            set_last_status(vm, 0, ExitStatus::from_raw(127 << 8));
+            Ok(Value::nil())
+        }
+    }
+}
+
+/// spawn(cmd) -> pid
+pub fn spawn(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_min(args.len(), 1)?;
+    let mut command = build_command(vm, args)?;
+    match command.spawn() {
+        Ok(child) => Ok(Value::fixnum(child.id() as i64)),
+        Err(err) => Err(spawn_error(vm, &command, err)),
+    }
+}
+
+/// exec(cmd): replace the current process.
+pub fn exec(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_min(args.len(), 1)?;
+    let mut command = build_command(vm, args)?;
+    let err = command.exec();
+    Err(spawn_error(vm, &command, err))
+}
+
 // Class methods
 
+/// Process.clock_gettime(clock_id, unit = :float_second)
 fn clock_gettime(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_range(args.len(), 1, 2)?;
+    let clock_id = args[0].expect_integer(vm, "Clock id")?;
+    let mut ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    if unsafe { libc::clock_gettime(clock_id as libc::clockid_t, &mut ts) } < 0 {
+        return Err(vm.error_argument(format!("Invalid clock id {}.", clock_id)));
+    }
+    let nanos = ts.tv_sec as i64 * 1_000_000_000 + ts.tv_nsec as i64;
+    let unit = if args.len() == 2 {
+        match args[1].as_symbol() {
+            Some(id) => vm.globals.get_ident_name(id).to_string(),
+            None => return Err(vm.error_argument("Unit must be a Symbol.")),
+        }
+    } else {
+        "float_second".to_string()
+    };
+    let val = match unit.as_str() {
+        "float_second" => Value::flonum(nanos as f64 / 1e9),
+        "float_millisecond" => Value::flonum(nanos as f64 / 1e6),
+        "float_microsecond" => Value::flonum(nanos as f64 / 1e3),
+        "second" => Value::fixnum(nanos / 1_000_000_000),
+        "millisecond" => Value::fixnum(nanos / 1_000_000),
+        "microsecond" => Value::fixnum(nanos / 1000),
+        "nanosecond" => Value::fixnum(nanos),
+        _ => return Err(vm.error_argument(format!("Unexpected unit: {}", unit))),
+    };
+    Ok(val)
+}
+
+fn pid(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    Ok(Value::fixnum(std::process::id() as i64))
+}
+
+fn ppid(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    Ok(Value::fixnum(unsafe { libc::getppid() } as i64))
+}
+
+/// Process.wait(pid = -1) -> pid
+fn wait(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_range(args.len(), 0, 1)?;
+    let pid = if args.len() == 0 {
+        -1
+    } else {
+        args[0].expect_integer(vm, "Pid")?
+    };
+    let (pid, _) = wait_pid(vm, pid)?;
+    Ok(Value::fixnum(pid as i64))
+}
+
+/// Process.wait2(pid = -1) -> [pid, status]
+fn wait2(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_range(args.len(), 0, 1)?;
+    let pid = if args.len() == 0 {
+        -1
+    } else {
+        args[0].expect_integer(vm, "Pid")?
+    };
+    let (pid, _) = wait_pid(vm, pid)?;
+    let id = vm.globals.get_ident_id("$?");
+    let status = vm.get_global_var(id);
+    Ok(Value::array_from(
+        &vm.globals,
+        vec![Value::fixnum(pid as i64), status],
+    ))
+}
+
+/// Process.kill(signal, pid, ..) -> number of signaled processes
+fn kill(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_min(args.len(), 2)?;
+    let signal = match args[0].as_fixnum() {
+        Some(sig) => sig as i32,
+        None => {
+            let name = match args[0].as_symbol() {
+                Some(id) => vm.globals.get_ident_name(id).to_string(),
+                None => vm.expect_string(&args[0], "Signal")?.clone(),
+            };
+            let name = name.trim_start_matches("SIG");
+            match name {
+                "HUP" => libc::SIGHUP,
+                "INT" => libc::SIGINT,
+                "QUIT" => libc::SIGQUIT,
+                "KILL" => libc::SIGKILL,
+                "USR1" => libc::SIGUSR1,
+                "USR2" => libc::SIGUSR2,
+                "TERM" => libc::SIGTERM,
+                "CONT" => libc::SIGCONT,
+                "STOP" => libc::SIGSTOP,
+                "EXIT" => 0,
+                _ => return Err(vm.error_argument(format!("Unsupported signal SIG{}.", name))),
+            }
+        }
+    };
+    for pid in args.iter().skip(1) {
+        let pid = pid.expect_integer(vm, "Pid")?;
+        if unsafe { libc::kill(pid as libc::pid_t, signal) } < 0 {
+            return Err(vm.error_internal("No such process (Errno::ESRCH)"));
+        }
+    }
+    Ok(Value::fixnum(args.len() as i64 - 1))
+}
+
+fn last_status(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let id = vm.globals.get_ident_id("$?");
+    Ok(vm.get_global_var(id))
+}
+
+// Process::Status instance methods
+
+fn status_var(vm: &mut VM, self_val: Value, name: &str) -> VMResult {
+    let id = vm.globals.get_ident_id(name);
+    match self_val.get_var(id) {
+        Some(val) => Ok(val),
+        None => Err(vm.error_internal("Uninitialized Process::Status.")),
+    }
+}
+
+fn status_success(vm: &mut VM, self_val: Value, _args: &Args) -> VMResult {
+    let exitstatus = status_var(vm, self_val, "_exitstatus")?;
+    match exitstatus.as_fixnum() {
+        Some(code) => Ok(Value::bool(code == 0)),
+        None => Ok(Value::nil()),
+    }
+}
+
+fn status_exitstatus(vm: &mut VM, self_val: Value, _args: &Args) -> VMResult {
+    status_var(vm, self_val, "_exitstatus")
+}
+
+fn status_pid(vm: &mut VM, self_val: Value, _args: &Args) -> VMResult {
+    status_var(vm, self_val, "_pid")
+}
+
+fn status_to_i(vm: &mut VM, self_val: Value, _args: &Args) -> VMResult {
+    status_var(vm, self_val, "_status")
+}
+
+fn status_exited(vm: &mut VM, self_val: Value, _args: &Args) -> VMResult {
+    let exitstatus = status_var(vm, self_val, "_exitstatus")?;
+    Ok(Value::bool(!exitstatus.is_nil()))
+}
+
+fn status_signaled(vm: &mut VM, self_val: Value, _args: &Args) -> VMResult {
+    let termsig = status_var(vm, self_val, "_termsig")?;
+    Ok(Value::bool(!termsig.is_nil()))
+}
+
+fn status_termsig(vm: &mut VM, self_val: Value, _args: &Args) -> VMResult {
+    status_var(vm, self_val, "_termsig")
+}
+
+fn status_string(vm: &mut VM, self_val: Value) -> Result<String, RubyError> {
+    let pid = status_var(vm, self_val, "_pid")?.as_fixnum().unwrap();
+    let exitstatus = status_var(vm, self_val, "_exitstatus")?;
+    let termsig = status_var(vm, self_val, "_termsig")?;
+    Ok(match (exitstatus.as_fixnum(), termsig.as_fixnum()) {
+        (Some(code), _) => format!("pid {} exit {}", pid, code),
+        (None, Some(sig)) => format!("pid {} signal {}", pid, sig),
+        _ => format!("pid {}", pid),
+    })
+}
+
+fn status_to_s(vm: &mut VM, self_val: Value, _args: &Args) -> VMResult {
+    let s = status_string(vm, self_val)?;
+    Ok(Value::string(&vm.globals, s))
+}
+
+fn status_inspect(vm: &mut VM, self_val: Value, _args: &Args) -> VMResult {
+    let s = format!("#<Process::Status: {}>", status_string(vm, self_val)?);
+    Ok(Value::string(&vm.globals, s))
+}
+
+fn status_eq(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
     vm.check_args_num(args.len(), 1)?;
-    let duration = vm.globals.instant.elapsed();
-    Ok(Value::flonum(duration.as_secs_f64()))
+    let status = status_var(vm, self_val, "_status")?;
+    let other = match args[0].as_fixnum() {
+        Some(_) => args[0],
+        None => match args[0].get_var(vm.globals.get_ident_id("_status")) {
+            Some(val) => val,
+            None => return Ok(Value::false_val()),
+        },
+    };
+    Ok(Value::bool(status.as_fixnum() == other.as_fixnum()))
+}
+
+// Open3 module methods
+
+/// Open3.capture2(cmd) -> [stdout, status]
+fn capture2(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_min(args.len(), 1)?;
+    let command = build_command(vm, args)?;
+    let (stdout, _) = run_output(vm, command, false)?;
+    let stdout = super::io::bytes_to_value(vm, stdout);
+    let status = last_status(vm, Value::nil(), &Args::new0())?;
+    Ok(Value::array_from(&vm.globals, vec![stdout, status]))
+}
+
+/// Open3.capture2e(cmd) -> [stdout_and_stderr, status]
+fn capture2e(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_min(args.len(), 1)?;
+    let command = merge_stderr(build_command(vm, args)?);
+    let (stdout, _) = run_output(vm, command, false)?;
+    let stdout = super::io::bytes_to_value(vm, stdout);
+    let status = last_status(vm, Value::nil(), &Args::new0())?;
+    Ok(Value::array_from(&vm.globals, vec![stdout, status]))
+}
+
+/// Open3.capture3(cmd) -> [stdout, stderr, status]
+fn capture3(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_min(args.len(), 1)?;
+    let command = build_command(vm, args)?;
+    let (stdout, stderr) = run_output(vm, command, true)?;
+    let stdout = super::io::bytes_to_value(vm, stdout);
+    let stderr = super::io::bytes_to_value(vm, stderr);
+    let status = last_status(vm, Value::nil(), &Args::new0())?;
+    Ok(Value::array_from(
+        &vm.globals,
+        vec![stdout, stderr, status],
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::*;
+
+    #[test]
+    fn process_command() {
+        let program = r#"
+        assert("hello\n", `echo hello`)
+        assert(true, $?.success?)
+        assert(0, $?.exitstatus)
+        x = "world"
+        assert("hello world\n", %x{echo hello #{x}})
+        assert("a\nb\n", %x(printf 'a\nb\n'))
+        `exit 3`
+        assert(false, $?.success?)
+        assert(3, $?.exitstatus)
+        assert(true, system("true"))
+        assert(false, system("exit 2"))
+        assert(2, $?.exitstatus)
+        assert(nil, system("/nonexistent/command", "arg"))
+        assert(127, $?.exitstatus)
+        "#;
+        assert_script(program);
+    }
+
+    #[test]
+    fn process_spawn() {
+        let program = r#"
+        assert(true, Process.pid > 0)
+        assert(Process.pid, $$)
+        pid = spawn("exit 5")
+        assert(pid, Process.wait(pid))
+        assert(pid, $?.pid)
+        assert(5, $?.exitstatus)
+        pid = Process.spawn("sleep 10")
+        assert(1, Process.kill(:TERM, pid))
+        pid2, status = Process.wait2(pid)
+        assert(pid, pid2)
+        assert(true, status.signaled?)
+        assert(15, status.termsig)
+        assert(nil, status.success?)
+        "#;
+        assert_script(program);
+    }
+
+    #[test]
+    fn open3() {
+        let program = r#"
+        require "open3"
+        out, status = Open3.capture2("echo out; echo err 1>&2")
+        assert("out\n", out)
+        assert(true, status.success?)
+        out, err, status = Open3.capture3("echo out; echo err 1>&2; exit 1")
+        assert("out\n", out)
+        assert("err\n", err)
+        assert(1, status.exitstatus)
+        out, status = Open3.capture2e("echo out; echo err 1>&2")
+        assert("out\nerr\n", out)
+        out, err, status = Open3.capture3({"RURUBY_OPEN3" => "env"}, "echo $RURUBY_OPEN3")
+        assert("env\n", out)
+        "#;
+        assert_script(program);
+    }
 }
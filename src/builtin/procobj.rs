@@ -24,6 +24,8 @@ pub fn init_proc(globals: &mut Globals) -> Value {
     let class = ClassRef::from(proc_id, globals.builtins.object);
     let obj = Value::class(globals, class);
     globals.add_builtin_instance_method(class, "call", proc_call);
+    globals.add_builtin_instance_method(class, "===", proc_call);
+    globals.add_builtin_instance_method(class, "arity", proc_arity);
     globals.add_builtin_class_method(obj, "new", proc_new);
     obj
 }
@@ -53,3 +55,19 @@ fn proc_call(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
     let res = vm.run_context(ContextRef::from_local(&context))?;
     Ok(res)
 }
+
+fn proc_arity(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let pref = match self_val.as_proc() {
+        Some(pref) => pref,
+        None => return Err(vm.error_unimplemented("Expected Proc object.")),
+    };
+    let params = &pref.context.iseq_ref.params;
+    let req = (params.req_params + params.post_params) as i64;
+    let arity = if params.rest_param || params.opt_params > 0 {
+        -req - 1
+    } else {
+        req
+    };
+    Ok(Value::fixnum(arity))
+}
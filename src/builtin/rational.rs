@@ -0,0 +1,853 @@
+use crate::*;
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+
+/// A rational number in lowest terms with a positive denominator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RationalInfo {
+    pub num: i64,
+    pub den: i64,
+}
+
+/// A real number which is an operand of Rational and Complex arithmetic.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Real {
+    Integer(i64),
+    Float(f64),
+    Rational(RationalInfo),
+}
+
+fn gcd_i128(a: i128, b: i128) -> i128 {
+    let (mut a, mut b) = (a.abs(), b.abs());
+    while b != 0 {
+        let r = a % b;
+        a = b;
+        b = r;
+    }
+    a
+}
+
+/// Returns (mantissa, exponent) where `f` == mantissa * 2 ** exponent exactly.
+fn decode_f64(f: f64) -> (i64, i32) {
+    let bits = f.to_bits();
+    let exp = ((bits >> 52) & 0x7ff) as i32;
+    let fraction = bits & 0xf_ffff_ffff_ffff;
+    let mantissa = if exp == 0 {
+        (fraction << 1) as i64
+    } else {
+        (fraction | 0x10_0000_0000_0000) as i64
+    };
+    let mantissa = if bits >> 63 == 0 { mantissa } else { -mantissa };
+    (mantissa, exp - 1075)
+}
+
+impl RationalInfo {
+    pub fn from_integer(num: i64) -> Self {
+        RationalInfo { num, den: 1 }
+    }
+
+    /// Returns `num/den` in lowest terms, or None if it does not fit in i64.
+    /// `den` must not be zero.
+    pub fn new(num: i128, den: i128) -> Option<Self> {
+        let gcd = gcd_i128(num, den);
+        let (num, den) = if den < 0 {
+            (-num / gcd, -den / gcd)
+        } else {
+            (num / gcd, den / gcd)
+        };
+        Some(RationalInfo {
+            num: i64::try_from(num).ok()?,
+            den: i64::try_from(den).ok()?,
+        })
+    }
+
+    /// Converts `f` exactly. Returns None for NaN and Infinity, or if it does not fit in i64.
+    pub fn from_f64(f: f64) -> Option<Self> {
+        if !f.is_finite() {
+            return None;
+        }
+        let (mut mantissa, mut exp) = decode_f64(f);
+        if mantissa == 0 {
+            return Some(RationalInfo::from_integer(0));
+        }
+        while mantissa % 2 == 0 && exp < 0 {
+            mantissa /= 2;
+            exp += 1;
+        }
+        if exp >= 64 || exp <= -64 {
+            None
+        } else if exp >= 0 {
+            RationalInfo::new((mantissa as i128) << exp, 1)
+        } else {
+            RationalInfo::new(mantissa as i128, 1i128 << -exp)
+        }
+    }
+
+    /// Parses "3", "-1/3", "0.75" or "1.5/2". Returns None if `s` is not a rational number.
+    pub fn parse(s: &str) -> Option<Self> {
+        fn decimal(s: &str) -> Option<(i128, i128)> {
+            let (int, frac) = match s.find('.') {
+                Some(pos) => (&s[..pos], &s[pos + 1..]),
+                None => (s, ""),
+            };
+            let valid = |s: &str| s.chars().all(|c| c.is_ascii_digit() || c == '_');
+            if int.is_empty() || !valid(int) || !valid(frac) || s.ends_with('.') {
+                return None;
+            }
+            let digits: String = format!("{}{}", int, frac).replace('_', "");
+            let num = digits.parse::<i128>().ok()?;
+            let den = 10i128.checked_pow(frac.replace('_', "").len() as u32)?;
+            Some((num, den))
+        }
+        let s = s.trim();
+        let (sign, s) = match s.chars().next() {
+            Some('-') => (-1, &s[1..]),
+            Some('+') => (1, &s[1..]),
+            _ => (1, s),
+        };
+        let (num, den) = match s.find('/') {
+            Some(pos) => {
+                let (num, num_den) = decimal(&s[..pos])?;
+                let den = s[pos + 1..].replace('_', "").parse::<i128>().ok()?;
+                if den <= 0 {
+                    return None;
+                }
+                (num, num_den.checked_mul(den)?)
+            }
+            None => decimal(s)?,
+        };
+        RationalInfo::new(sign * num, den)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.num == 0
+    }
+
+    pub fn is_integer(&self) -> bool {
+        self.den == 1
+    }
+
+    pub fn add(&self, other: &Self) -> Option<Self> {
+        RationalInfo::new(
+            self.num as i128 * other.den as i128 + other.num as i128 * self.den as i128,
+            self.den as i128 * other.den as i128,
+        )
+    }
+
+    pub fn sub(&self, other: &Self) -> Option<Self> {
+        RationalInfo::new(
+            self.num as i128 * other.den as i128 - other.num as i128 * self.den as i128,
+            self.den as i128 * other.den as i128,
+        )
+    }
+
+    pub fn mul(&self, other: &Self) -> Option<Self> {
+        RationalInfo::new(
+            self.num as i128 * other.num as i128,
+            self.den as i128 * other.den as i128,
+        )
+    }
+
+    /// `other` must not be zero.
+    pub fn div(&self, other: &Self) -> Option<Self> {
+        RationalInfo::new(
+            self.num as i128 * other.den as i128,
+            self.den as i128 * other.num as i128,
+        )
+    }
+
+    /// `self` must not be zero if `exp` is negative.
+    pub fn pow(&self, exp: i64) -> Option<Self> {
+        let e = u32::try_from(exp.checked_abs()?).ok()?;
+        let num = (self.num as i128).checked_pow(e)?;
+        let den = (self.den as i128).checked_pow(e)?;
+        if exp >= 0 {
+            RationalInfo::new(num, den)
+        } else {
+            RationalInfo::new(den, num)
+        }
+    }
+
+    pub fn neg(&self) -> Option<Self> {
+        RationalInfo::new(-(self.num as i128), self.den as i128)
+    }
+
+    pub fn abs(&self) -> Option<Self> {
+        RationalInfo::new((self.num as i128).abs(), self.den as i128)
+    }
+
+    pub fn to_f(&self) -> f64 {
+        self.num as f64 / self.den as f64
+    }
+
+    pub fn floor(&self) -> i64 {
+        self.num.div_euclid(self.den)
+    }
+
+    pub fn ceil(&self) -> i64 {
+        -(-(self.num as i128)).div_euclid(self.den as i128) as i64
+    }
+
+    pub fn truncate(&self) -> i64 {
+        self.num / self.den
+    }
+
+    /// Rounds half away from zero.
+    pub fn round(&self) -> i64 {
+        let num = (self.num as i128).abs() * 2 + self.den as i128;
+        let abs = (num / (self.den as i128 * 2)) as i64;
+        if self.num < 0 {
+            -abs
+        } else {
+            abs
+        }
+    }
+
+    /// Returns the simplest rational number in the closed interval [a, b].
+    pub fn simplest_between(a: &Self, b: &Self) -> Option<Self> {
+        if a.cmp(b) == Ordering::Greater {
+            return RationalInfo::simplest_between(b, a);
+        }
+        if a.num > 0 {
+            simplest_positive((a.num as i128, a.den as i128), (b.num as i128, b.den as i128))
+        } else if b.num < 0 {
+            simplest_positive(
+                (-(b.num as i128), b.den as i128),
+                (-(a.num as i128), a.den as i128),
+            )?
+            .neg()
+        } else {
+            Some(RationalInfo::from_integer(0))
+        }
+    }
+
+    /// The simplest rational number which approximates `f` within its precision,
+    /// as Float#rationalize without arguments.
+    pub fn rationalize_f64(f: f64) -> Option<Self> {
+        if !f.is_finite() {
+            return None;
+        }
+        let (mantissa, exp) = decode_f64(f);
+        if mantissa == 0 || exp >= 0 {
+            return RationalInfo::from_f64(f);
+        }
+        // The interval of the real numbers which are rounded to `f`.
+        if 1 - exp >= 120 {
+            return None;
+        }
+        let den = 1i128 << (1 - exp);
+        let m = (mantissa as i128).abs() * 2;
+        let res = simplest_positive((m - 1, den), (m + 1, den))?;
+        if mantissa < 0 {
+            res.neg()
+        } else {
+            Some(res)
+        }
+    }
+
+    pub fn to_s(&self) -> String {
+        format!("{}/{}", self.num, self.den)
+    }
+
+    pub fn inspect(&self) -> String {
+        format!("({}/{})", self.num, self.den)
+    }
+}
+
+impl Ord for RationalInfo {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.num as i128 * other.den as i128).cmp(&(other.num as i128 * self.den as i128))
+    }
+}
+
+impl PartialOrd for RationalInfo {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Returns the simplest rational number in the closed interval [a, b] (0 < a <= b),
+/// whose bounds are (numerator, denominator) pairs with positive denominators.
+/// This is the continued fraction algorithm which CRuby uses for `rationalize`.
+fn simplest_positive(a: (i128, i128), b: (i128, i128)) -> Option<RationalInfo> {
+    if a.0 * b.1 == b.0 * a.1 {
+        return RationalInfo::new(a.0, a.1);
+    }
+    let (mut a, mut b) = (a, b);
+    let (mut p0, mut p1, mut q0, mut q1) = (0i128, 1i128, 1i128, 0i128);
+    let c = loop {
+        // ceil(a)
+        let c = -(-a.0).div_euclid(a.1);
+        if c * b.1 < b.0 {
+            break c;
+        }
+        let k = c - 1;
+        let p2 = k.checked_mul(p1)?.checked_add(p0)?;
+        let q2 = k.checked_mul(q1)?.checked_add(q0)?;
+        // a, b = 1 / (b - k), 1 / (a - k)
+        let t = (b.1, b.0.checked_sub(k.checked_mul(b.1)?)?);
+        b = (a.1, a.0.checked_sub(k.checked_mul(a.1)?)?);
+        a = t;
+        p0 = p1;
+        q0 = q1;
+        p1 = p2;
+        q1 = q2;
+    };
+    RationalInfo::new(
+        c.checked_mul(p1)?.checked_add(p0)?,
+        c.checked_mul(q1)?.checked_add(q0)?,
+    )
+}
+
+impl Real {
+    pub fn from_value(val: Value) -> Option<Self> {
+        match val.unpack() {
+            RV::Integer(i) => Some(Real::Integer(i)),
+            RV::Float(f) => Some(Real::Float(f)),
+            _ => val.as_rational().map(Real::Rational),
+        }
+    }
+
+    pub fn into_value(self, globals: &Globals) -> Value {
+        match self {
+            Real::Integer(i) => Value::fixnum(i),
+            Real::Float(f) => Value::flonum(f),
+            Real::Rational(r) => Value::rational(globals, r),
+        }
+    }
+
+    /// Returns None for Float.
+    pub fn to_rational(self) -> Option<RationalInfo> {
+        match self {
+            Real::Integer(i) => Some(RationalInfo::from_integer(i)),
+            Real::Float(_) => None,
+            Real::Rational(r) => Some(r),
+        }
+    }
+
+    pub fn to_f(self) -> f64 {
+        match self {
+            Real::Integer(i) => i as f64,
+            Real::Float(f) => f,
+            Real::Rational(r) => r.to_f(),
+        }
+    }
+
+    /// Integer or Rational zero, which raises ZeroDivisionError as a divisor.
+    pub fn is_exact_zero(self) -> bool {
+        match self {
+            Real::Integer(i) => i == 0,
+            Real::Float(_) => false,
+            Real::Rational(r) => r.is_zero(),
+        }
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.to_f() == 0.0
+    }
+
+    pub fn is_negative(self) -> bool {
+        match self {
+            Real::Integer(i) => i < 0,
+            Real::Float(f) => f.is_sign_negative(),
+            Real::Rational(r) => r.num < 0,
+        }
+    }
+
+    pub fn checked_neg(self) -> Option<Self> {
+        match self {
+            Real::Integer(i) => i.checked_neg().map(Real::Integer),
+            Real::Float(f) => Some(Real::Float(-f)),
+            Real::Rational(r) => r.neg().map(Real::Rational),
+        }
+    }
+
+    pub fn checked_add(self, other: Self) -> Option<Self> {
+        match (self, other) {
+            (Real::Integer(lhs), Real::Integer(rhs)) => lhs.checked_add(rhs).map(Real::Integer),
+            (Real::Float(_), _) | (_, Real::Float(_)) => Some(Real::Float(self.to_f() + other.to_f())),
+            _ => self.to_rational()?.add(&other.to_rational()?).map(Real::Rational),
+        }
+    }
+
+    pub fn checked_sub(self, other: Self) -> Option<Self> {
+        match (self, other) {
+            (Real::Integer(lhs), Real::Integer(rhs)) => lhs.checked_sub(rhs).map(Real::Integer),
+            (Real::Float(_), _) | (_, Real::Float(_)) => Some(Real::Float(self.to_f() - other.to_f())),
+            _ => self.to_rational()?.sub(&other.to_rational()?).map(Real::Rational),
+        }
+    }
+
+    pub fn checked_mul(self, other: Self) -> Option<Self> {
+        match (self, other) {
+            (Real::Integer(lhs), Real::Integer(rhs)) => lhs.checked_mul(rhs).map(Real::Integer),
+            (Real::Float(_), _) | (_, Real::Float(_)) => Some(Real::Float(self.to_f() * other.to_f())),
+            _ => self.to_rational()?.mul(&other.to_rational()?).map(Real::Rational),
+        }
+    }
+
+    /// Division as `quo`, which divides Integers into a Rational.
+    /// An exact zero `other` must be checked by the caller.
+    pub fn quo(self, other: Self) -> Option<Self> {
+        match (self, other) {
+            (Real::Float(_), _) | (_, Real::Float(_)) => Some(Real::Float(self.to_f() / other.to_f())),
+            _ => self.to_rational()?.div(&other.to_rational()?).map(Real::Rational),
+        }
+    }
+
+    pub fn partial_cmp(self, other: Self) -> Option<Ordering> {
+        match (self, other) {
+            (Real::Float(_), _) | (_, Real::Float(_)) => self.to_f().partial_cmp(&other.to_f()),
+            _ => Some(self.to_rational()?.cmp(&other.to_rational()?)),
+        }
+    }
+
+    pub fn to_s(self) -> String {
+        match self {
+            Real::Integer(i) => i.to_string(),
+            Real::Float(f) => crate::builtin::float::float_to_s(f),
+            Real::Rational(r) => r.to_s(),
+        }
+    }
+
+    pub fn inspect(self) -> String {
+        match self {
+            Real::Rational(r) => r.inspect(),
+            _ => self.to_s(),
+        }
+    }
+}
+
+pub fn init_rational(globals: &mut Globals) -> Value {
+    let id = globals.get_ident_id("Rational");
+    let class = ClassRef::from(id, globals.builtins.numeric);
+    globals.add_builtin_instance_method(class, "numerator", numerator);
+    globals.add_builtin_instance_method(class, "denominator", denominator);
+    globals.add_builtin_instance_method(class, "+", add);
+    globals.add_builtin_instance_method(class, "-", sub);
+    globals.add_builtin_instance_method(class, "*", mul);
+    globals.add_builtin_instance_method(class, "/", div);
+    globals.add_builtin_instance_method(class, "quo", div);
+    globals.add_builtin_instance_method(class, "**", pow);
+    globals.add_builtin_instance_method(class, "-@", neg);
+    globals.add_builtin_instance_method(class, "==", eq);
+    globals.add_builtin_instance_method(class, "<=>", cmp);
+    globals.add_builtin_instance_method(class, "<", lt);
+    globals.add_builtin_instance_method(class, "<=", le);
+    globals.add_builtin_instance_method(class, ">", gt);
+    globals.add_builtin_instance_method(class, ">=", ge);
+    globals.add_builtin_instance_method(class, "coerce", coerce);
+    globals.add_builtin_instance_method(class, "abs", abs);
+    globals.add_builtin_instance_method(class, "magnitude", abs);
+    globals.add_builtin_instance_method(class, "zero?", zero);
+    globals.add_builtin_instance_method(class, "positive?", positive);
+    globals.add_builtin_instance_method(class, "negative?", negative);
+    globals.add_builtin_instance_method(class, "floor", floor);
+    globals.add_builtin_instance_method(class, "ceil", ceil);
+    globals.add_builtin_instance_method(class, "round", round);
+    globals.add_builtin_instance_method(class, "truncate", truncate);
+    globals.add_builtin_instance_method(class, "to_i", truncate);
+    globals.add_builtin_instance_method(class, "to_f", to_f);
+    globals.add_builtin_instance_method(class, "fdiv", fdiv);
+    globals.add_builtin_instance_method(class, "to_r", to_r);
+    globals.add_builtin_instance_method(class, "rationalize", rationalize);
+    globals.add_builtin_instance_method(class, "to_c", to_c);
+    globals.add_builtin_instance_method(class, "to_s", to_s);
+    globals.add_builtin_instance_method(class, "inspect", inspect);
+    Value::class(globals, class)
+}
+
+// Utils
+
+fn expect_rational(vm: &mut VM, val: Value) -> Result<RationalInfo, RubyError> {
+    match val.as_rational() {
+        Some(r) => Ok(r),
+        None => Err(vm.error_type("Receiver must be Rational.")),
+    }
+}
+
+/// Returns the result of exact arithmetic, which is None on overflow.
+pub fn real_value(vm: &mut VM, real: Option<Real>) -> VMResult {
+    match real {
+        Some(real) => Ok(real.into_value(&vm.globals)),
+        None => Err(vm.error_range("integer overflow")),
+    }
+}
+
+fn rational_value(vm: &mut VM, r: Option<RationalInfo>) -> VMResult {
+    real_value(vm, r.map(Real::Rational))
+}
+
+/// Converts an Integer, a Float, a Rational or a String into Rational.
+pub fn to_rational(vm: &mut VM, val: Value) -> Result<RationalInfo, RubyError> {
+    let res = match Real::from_value(val) {
+        Some(Real::Float(f)) => RationalInfo::from_f64(f),
+        Some(real) => real.to_rational(),
+        None => match val.as_string() {
+            Some(s) => match RationalInfo::parse(s) {
+                Some(r) => Some(r),
+                None => {
+                    return Err(vm.error_argument(format!("invalid value for convert(): {:?}", s)))
+                }
+            },
+            None => {
+                let class = vm.globals.get_class_name(val);
+                return Err(vm.error_type(format!("can't convert {} into Rational", class)));
+            }
+        },
+    };
+    match res {
+        Some(r) => Ok(r),
+        None => {
+            let f = vm.val_inspect(val);
+            Err(vm.error_float_domain(f))
+        }
+    }
+}
+
+/// Applies `op` to a Rational and a real number, or dispatches `rhs.coerce(self)` for others.
+fn binop(
+    vm: &mut VM,
+    self_val: Value,
+    args: &Args,
+    method: &str,
+    op: fn(Real, Real) -> Option<Real>,
+) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let lhs = expect_rational(vm, self_val)?;
+    match Real::from_value(args[0]) {
+        Some(rhs) => real_value(vm, op(Real::Rational(lhs), rhs)),
+        None => {
+            let id = vm.globals.get_ident_id(method);
+            vm.coerce_binop(id, self_val, args[0])
+        }
+    }
+}
+
+fn compare(vm: &mut VM, self_val: Value, args: &Args) -> Result<Option<Ordering>, RubyError> {
+    vm.check_args_num(args.len(), 1)?;
+    let lhs = expect_rational(vm, self_val)?;
+    match Real::from_value(args[0]) {
+        Some(rhs) => Ok(Real::Rational(lhs).partial_cmp(rhs)),
+        None => Ok(None),
+    }
+}
+
+fn expect_ordering(vm: &mut VM, self_val: Value, args: &Args) -> Result<Ordering, RubyError> {
+    match compare(vm, self_val, args)? {
+        Some(ord) => Ok(ord),
+        None => {
+            let rhs = vm.val_inspect(args[0]);
+            Err(vm.error_argument(format!("comparison of Rational with {} failed", rhs)))
+        }
+    }
+}
+
+// Kernel methods
+
+/// Rational(x, y = 1) -> x / y
+pub fn rational(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_range(args.len(), 1, 2)?;
+    let num = to_rational(vm, args[0])?;
+    let den = if args.len() == 2 {
+        to_rational(vm, args[1])?
+    } else {
+        RationalInfo::from_integer(1)
+    };
+    if den.is_zero() {
+        return Err(vm.error_zero_div("divided by 0"));
+    }
+    rational_value(vm, num.div(&den))
+}
+
+// Instance methods
+
+fn numerator(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    Ok(Value::fixnum(expect_rational(vm, self_val)?.num))
+}
+
+fn denominator(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    Ok(Value::fixnum(expect_rational(vm, self_val)?.den))
+}
+
+fn add(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    binop(vm, self_val, args, "+", Real::checked_add)
+}
+
+fn sub(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    binop(vm, self_val, args, "-", Real::checked_sub)
+}
+
+fn mul(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    binop(vm, self_val, args, "*", Real::checked_mul)
+}
+
+fn div(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    match Real::from_value(args[0]) {
+        Some(rhs) if rhs.is_exact_zero() => Err(vm.error_zero_div("divided by 0")),
+        _ => binop(vm, self_val, args, "/", Real::quo),
+    }
+}
+
+fn pow(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let lhs = expect_rational(vm, self_val)?;
+    let exp = match Real::from_value(args[0]) {
+        Some(Real::Integer(i)) => Some(i),
+        Some(Real::Rational(r)) if r.is_integer() => Some(r.num),
+        Some(rhs) => return Ok(Value::flonum(lhs.to_f().powf(rhs.to_f()))),
+        None => {
+            return vm.coerce_binop(IdentId::_POW, self_val, args[0]);
+        }
+    };
+    let exp = exp.unwrap();
+    if lhs.is_zero() && exp < 0 {
+        return Err(vm.error_zero_div("divided by 0"));
+    }
+    rational_value(vm, lhs.pow(exp))
+}
+
+fn neg(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let r = expect_rational(vm, self_val)?;
+    rational_value(vm, r.neg())
+}
+
+fn eq(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    Ok(Value::bool(self_val.equal(args[0])))
+}
+
+fn cmp(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    match compare(vm, self_val, args)? {
+        Some(ord) => Ok(Value::fixnum(ord as i64)),
+        None => Ok(Value::nil()),
+    }
+}
+
+fn lt(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    Ok(Value::bool(expect_ordering(vm, self_val, args)?.is_lt()))
+}
+
+fn le(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    Ok(Value::bool(expect_ordering(vm, self_val, args)?.is_le()))
+}
+
+fn gt(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    Ok(Value::bool(expect_ordering(vm, self_val, args)?.is_gt()))
+}
+
+fn ge(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    Ok(Value::bool(expect_ordering(vm, self_val, args)?.is_ge()))
+}
+
+/// Rational#coerce(Integer) -> [Rational, Rational] / Rational#coerce(Float) -> [Float, Float]
+fn coerce(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let r = expect_rational(vm, self_val)?;
+    let pair = match Real::from_value(args[0]) {
+        Some(Real::Integer(i)) => vec![
+            Value::rational(&vm.globals, RationalInfo::from_integer(i)),
+            self_val,
+        ],
+        Some(Real::Float(f)) => vec![Value::flonum(f), Value::flonum(r.to_f())],
+        Some(Real::Rational(_)) => vec![args[0], self_val],
+        None => match args[0].as_complex() {
+            Some(_) => {
+                let c = ComplexInfo::from_real(Real::Rational(r));
+                vec![args[0], Value::complex(&vm.globals, c)]
+            }
+            None => {
+                let class = vm.globals.get_class_name(args[0]);
+                return Err(vm.error_type(format!("{} can't be coerced into Rational", class)));
+            }
+        },
+    };
+    Ok(Value::array_from(&vm.globals, pair))
+}
+
+fn abs(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let r = expect_rational(vm, self_val)?;
+    rational_value(vm, r.abs())
+}
+
+fn zero(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    Ok(Value::bool(expect_rational(vm, self_val)?.is_zero()))
+}
+
+fn positive(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    Ok(Value::bool(expect_rational(vm, self_val)?.num > 0))
+}
+
+fn negative(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    Ok(Value::bool(expect_rational(vm, self_val)?.num < 0))
+}
+
+fn floor(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    Ok(Value::fixnum(expect_rational(vm, self_val)?.floor()))
+}
+
+fn ceil(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    Ok(Value::fixnum(expect_rational(vm, self_val)?.ceil()))
+}
+
+fn round(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    Ok(Value::fixnum(expect_rational(vm, self_val)?.round()))
+}
+
+fn truncate(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    Ok(Value::fixnum(expect_rational(vm, self_val)?.truncate()))
+}
+
+fn to_f(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    Ok(Value::flonum(expect_rational(vm, self_val)?.to_f()))
+}
+
+fn fdiv(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let lhs = expect_rational(vm, self_val)?;
+    match Real::from_value(args[0]) {
+        Some(rhs) => Ok(Value::flonum(lhs.to_f() / rhs.to_f())),
+        None => {
+            let class = vm.globals.get_class_name(args[0]);
+            Err(vm.error_type(format!("{} can't be coerced into Float", class)))
+        }
+    }
+}
+
+fn to_r(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    expect_rational(vm, self_val)?;
+    Ok(self_val)
+}
+
+/// Rational#rationalize(eps) -> the simplest rational number within `eps` from self.
+fn rationalize(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_range(args.len(), 0, 1)?;
+    let r = expect_rational(vm, self_val)?;
+    if args.len() == 0 {
+        return Ok(self_val);
+    }
+    let eps = to_rational(vm, args[0])?;
+    rationalize_within(vm, r, eps)
+}
+
+/// Returns the simplest rational number in [r - |eps|, r + |eps|].
+pub fn rationalize_within(vm: &mut VM, r: RationalInfo, eps: RationalInfo) -> VMResult {
+    let res = eps.abs().and_then(|eps| {
+        let a = r.sub(&eps)?;
+        let b = r.add(&eps)?;
+        RationalInfo::simplest_between(&a, &b)
+    });
+    rational_value(vm, res)
+}
+
+fn to_c(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let r = expect_rational(vm, self_val)?;
+    let c = ComplexInfo::from_real(Real::Rational(r));
+    Ok(Value::complex(&vm.globals, c))
+}
+
+fn to_s(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let s = expect_rational(vm, self_val)?.to_s();
+    Ok(Value::string(&vm.globals, s))
+}
+
+fn inspect(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let s = expect_rational(vm, self_val)?.inspect();
+    Ok(Value::string(&vm.globals, s))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test::*;
+
+    #[test]
+    fn rational_literal() {
+        let program = r#"
+        assert(Rational, 3r.class)
+        assert("(3/1)", 3r.inspect)
+        assert("1/3", (1/3r).to_s)
+        assert("(3/2)", 1.5r.inspect)
+        assert("(-1/3)", (-1/3r).inspect)
+        assert([2, 5], [(4/10r).numerator, (4/10r).denominator])
+        assert(Rational(1, 3), 1/3r)
+        "#;
+        assert_script(program);
+    }
+
+    #[test]
+    fn rational_arithmetic() {
+        let program = r#"
+        assert(Rational(5, 6), Rational(1, 2) + Rational(1, 3))
+        assert(Rational(1, 6), Rational(1, 2) - Rational(1, 3))
+        assert(Rational(1, 6), Rational(1, 2) * Rational(1, 3))
+        assert(Rational(3, 2), Rational(1, 2) / Rational(1, 3))
+        assert(Rational(3, 2), 1 + Rational(1, 2))
+        assert(Rational(1, 2), 1 - Rational(1, 2))
+        assert(Rational(3, 2), 3 * Rational(1, 2))
+        assert(Rational(4, 1), 2 / Rational(1, 2))
+        assert(1.5, 1.0 + Rational(1, 2))
+        assert(Float, (1.0 + Rational(1, 2)).class)
+        assert(Rational(1, 8), Rational(1, 2) ** 3)
+        assert(Rational(4, 1), Rational(1, 2) ** -2)
+        assert(Rational(-1, 2), -Rational(1, 2))
+        assert(Rational(3, 10), 0.1r * 3)
+        assert(true, 0.1r * 3 == 0.3r)
+        assert(false, 0.1 * 3 == 0.3)
+        assert(true, Rational(2, 1) == 2)
+        assert(true, Rational(1, 2) == 0.5)
+        assert(true, Rational(1, 3) < Rational(1, 2))
+        assert(true, 1 > Rational(1, 2))
+        assert(-1, Rational(1, 3) <=> Rational(1, 2))
+        assert(1, 1 <=> Rational(1, 2))
+        assert([Rational(1), Rational(1, 2)], Rational(1, 2).coerce(1))
+        begin
+          Rational(1, 2) / 0
+        rescue ZeroDivisionError => e
+          assert("divided by 0", e.message)
+        end
+        "#;
+        assert_script(program);
+    }
+
+    #[test]
+    fn rational_conversion() {
+        let program = r#"
+        assert(Rational(1, 3), Rational("1/3"))
+        assert(Rational(3, 4), Rational("0.75"))
+        assert(Rational(3, 4), Rational(0.75))
+        assert(Rational(2, 3), Rational(Rational(1, 3), Rational(1, 2)))
+        assert(Rational(3, 1), 3.to_r)
+        assert(Rational(1, 10), 0.1.rationalize)
+        assert(Rational(333, 1000), 0.333.rationalize)
+        assert(Rational(1, 3), 0.333.rationalize(Rational(1, 100)))
+        assert(Rational(3602879701896397, 36028797018963968), 0.1.to_r)
+        assert(Rational(1, 3), Rational(1, 3).rationalize)
+        assert(Rational(1, 3), Rational(33, 100).rationalize(Rational(1, 100)))
+        assert(0.5, Rational(1, 2).to_f)
+        assert([1, 2, 2, 1], [Rational(7, 4).floor, Rational(7, 4).ceil, Rational(7, 4).round, Rational(7, 4).to_i])
+        assert([-2, -1, -2, -1], [Rational(-7, 4).floor, Rational(-7, 4).ceil, Rational(-7, 4).round, Rational(-7, 4).to_i])
+        assert(3, Rational(5, 2).round)
+        assert(-3, Rational(-5, 2).round)
+        assert(true, Rational(0, 5).zero?)
+        assert(Rational(1, 2), Rational(-1, 2).abs)
+        "#;
+        assert_script(program);
+    }
+}
@@ -0,0 +1,241 @@
+use crate::*;
+use std::path::PathBuf;
+
+/// RubyVM: a module exposing VM internals. Only InstructionSequence is implemented.
+pub fn init_rubyvm(globals: &mut Globals) -> Value {
+    let id = globals.get_ident_id("RubyVM");
+    let class = ClassRef::from(id, None);
+    let mut obj = Value::module(globals, class);
+    let id = globals.get_ident_id("InstructionSequence");
+    let iseq_class = init_iseq(globals);
+    obj.set_var(id, iseq_class);
+    obj
+}
+
+fn init_iseq(globals: &mut Globals) -> Value {
+    let id = globals.get_ident_id("RubyVM::InstructionSequence");
+    let class = ClassRef::from(id, globals.builtins.object);
+    let obj = Value::class(globals, class);
+    globals.add_builtin_class_method(obj, "compile", compile);
+    globals.add_builtin_class_method(obj, "new", compile);
+    globals.add_builtin_class_method(obj, "compile_file", compile_file);
+    globals.add_builtin_class_method(obj, "of", of);
+    globals.add_builtin_class_method(obj, "disasm", class_disasm);
+    globals.add_builtin_class_method(obj, "disassemble", class_disasm);
+    globals.add_builtin_instance_method(class, "disasm", disasm);
+    globals.add_builtin_instance_method(class, "disassemble", disasm);
+    globals.add_builtin_instance_method(class, "to_a", to_a);
+    globals.add_builtin_instance_method(class, "eval", eval);
+    globals.add_builtin_instance_method(class, "label", label);
+    globals.add_builtin_instance_method(class, "path", path);
+    globals.add_builtin_instance_method(class, "inspect", inspect);
+    obj
+}
+
+// Utils
+
+fn new_iseq(vm: &mut VM, class: Value, entry: ISeqEntry) -> Value {
+    let mut val = Value::ordinary_object(class);
+    let kind = Value::symbol(vm.globals.get_ident_id(entry.kind));
+    let method: u32 = entry.method.into();
+    let vars = [
+        ("_method", Value::fixnum(method as i64)),
+        ("_label", Value::string(&vm.globals, entry.label)),
+        ("_kind", kind),
+    ];
+    for (name, v) in vars.iter() {
+        let id = vm.globals.get_ident_id(*name);
+        val.set_var(id, *v);
+    }
+    val
+}
+
+fn get_entry(vm: &mut VM, self_val: Value) -> Result<ISeqEntry, RubyError> {
+    let mut vars = vec![];
+    for name in &["_method", "_label", "_kind"] {
+        let id = vm.globals.get_ident_id(*name);
+        match self_val.get_var(id) {
+            Some(val) => vars.push(val),
+            None => return Err(vm.error_internal("Uninitialized InstructionSequence.")),
+        }
+    }
+    let method = MethodRef::from(vars[0].as_fixnum().unwrap() as u32);
+    let label = vars[1].as_string().unwrap().to_string();
+    let kind = match vm.globals.get_ident_name(vars[2].as_symbol().unwrap()) {
+        "top" => "top",
+        "method" => "method",
+        "class" => "class",
+        _ => "block",
+    };
+    Ok(ISeqEntry::new(method, label, kind))
+}
+
+/// Get the ISeq of a Proc or Method object.
+fn body_entry(vm: &mut VM, body: Value) -> Result<ISeqEntry, RubyError> {
+    if let Some(pref) = body.as_proc() {
+        let method = pref.context.iseq_ref.method;
+        return Ok(ISeqEntry::new(method, "block", "block"));
+    }
+    if let Some(mref) = body.as_method() {
+        let label = vm.globals.get_ident_name(mref.name).to_string();
+        return Ok(ISeqEntry::new(mref.method, label, "method"));
+    }
+    let inspect = vm.val_inspect(body);
+    Err(vm.error_type(format!(
+        "Expected Proc or Method, but {} was given.",
+        inspect
+    )))
+}
+
+// Class methods
+
+/// RubyVM::InstructionSequence.compile(source, file = "<compiled>")
+fn compile(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_range(args.len(), 1, 2)?;
+    let program = vm.expect_string(&args[0], "1st arg")?.to_string();
+    let file = if args.len() == 2 {
+        vm.expect_string(&args[1], "2nd arg")?.to_string()
+    } else {
+        "<compiled>".to_string()
+    };
+    let method = vm.parse_program(PathBuf::from(file), &program)?;
+    Ok(new_iseq(vm, self_val, ISeqEntry::new(method, "<compiled>", "top")))
+}
+
+/// RubyVM::InstructionSequence.compile_file(path)
+fn compile_file(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let file = vm.expect_string(&args[0], "1st arg")?.to_string();
+    let (path, program) = match loader::load_file(file.clone()) {
+        Ok(res) => res,
+        Err(_) => return Err(vm.error_argument(format!("Can not load file. {}", file))),
+    };
+    let method = vm.parse_program(path, &program)?;
+    Ok(new_iseq(vm, self_val, ISeqEntry::new(method, "<main>", "top")))
+}
+
+/// RubyVM::InstructionSequence.of(proc_or_method)
+fn of(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let entry = body_entry(vm, args[0])?;
+    Ok(new_iseq(vm, self_val, entry))
+}
+
+/// RubyVM::InstructionSequence.disasm(proc_or_method)
+fn class_disasm(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let entry = body_entry(vm, args[0])?;
+    let s = Disasm::disasm(&vm.globals, &entry);
+    Ok(Value::string(&vm.globals, s))
+}
+
+// Instance methods
+
+fn disasm(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let entry = get_entry(vm, self_val)?;
+    let s = Disasm::disasm(&vm.globals, &entry);
+    Ok(Value::string(&vm.globals, s))
+}
+
+fn to_a(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let entry = get_entry(vm, self_val)?;
+    Ok(Disasm::to_a(&mut vm.globals, &entry))
+}
+
+/// Evaluate the top-level ISeq in the main object.
+fn eval(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let entry = get_entry(vm, self_val)?;
+    if entry.kind != "top" {
+        return Err(vm.error_type("Only top-level InstructionSequence can be evaluated."));
+    }
+    let main = vm.globals.main_object;
+    vm.eval_send(entry.method, main, &Args::new0())
+}
+
+fn label(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let entry = get_entry(vm, self_val)?;
+    Ok(Value::string(&vm.globals, entry.label))
+}
+
+fn path(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let entry = get_entry(vm, self_val)?;
+    match Disasm::get_iseq(&vm.globals, entry.method) {
+        Some(iseq) => {
+            let path = iseq.source_info.path.to_string_lossy().to_string();
+            Ok(Value::string(&vm.globals, path))
+        }
+        None => Ok(Value::nil()),
+    }
+}
+
+fn inspect(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    let entry = get_entry(vm, self_val)?;
+    let path = path(vm, self_val, args)?;
+    let path = vm.val_to_s(path);
+    let s = format!("<RubyVM::InstructionSequence:{}@{}>", entry.label, path);
+    Ok(Value::string(&vm.globals, s))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::*;
+
+    #[test]
+    fn iseq_compile() {
+        let program = r#"
+        iseq = RubyVM::InstructionSequence.compile("a = 1 + 2\nputs a\na")
+        assert("<compiled>", iseq.label)
+        assert("<compiled>", iseq.path)
+        assert(3, iseq.eval)
+        lines = iseq.disasm.split("\n")
+        assert(true, lines[0].start_with?("== disasm: <compiled>@<compiled>"))
+        assert("[ 0] a", lines[2])
+        assert(true, lines.include?("00000 PUSH_FIXNUM 1"))
+        assert(true, lines.include?("0000e SET_LOCAL 'a' outer:0 LvarId:0"))
+        assert(true, (iseq.disasm =~ /SEND_SELF 'puts' 1 items cache:\d+ +\(   2\)/) > 0)
+        "#;
+        assert_script(program);
+    }
+
+    #[test]
+    fn iseq_nested() {
+        let program = r#"
+        src = "class Foo\n  def bar(x, y = 1)\n    [1].each { |z| x + z }\n  end\nend"
+        lines = RubyVM::InstructionSequence.compile(src).disasm.split("\n")
+        headers = lines.grep(/disasm:/).map { |l| l.split("@")[0] }
+        assert(["== disasm: <compiled>", "== disasm: <class:Foo>", "== disasm: bar", "== disasm: block in bar"], headers)
+        assert(true, lines.include?("[ 0] x<Arg>  [ 1] y<Opt>"))
+        assert(true, lines.include?("[ 0] z<Arg>"))
+        assert(true, lines.include?("00000 GET_LOCAL 'x' outer:1 LvarId:0"))
+        assert(true, lines.include?("00009 GET_LOCAL 'z' outer:0 LvarId:0"))
+        lines = RubyVM::InstructionSequence.disasm(Proc.new { |a| a }).split("\n")
+        assert("[ 0] a<Arg>", lines[2])
+        "#;
+        assert_script(program);
+    }
+
+    #[test]
+    fn iseq_to_a() {
+        let program = r#"
+        a = RubyVM::InstructionSequence.compile("x = 10\nif x > 5 then :big else :small end").to_a
+        assert("YARVInstructionSequence/SimpleDataFormat", a[0])
+        assert("<compiled>", a[5])
+        assert(:top, a[9])
+        assert([:x], a[10])
+        body = a[13]
+        assert([:push_fixnum, 10], body[0])
+        assert([:set_local, :x, 0], body[1])
+        assert(true, body.include?(2))
+        assert(true, body.include?([:push_symbol, :big]))
+        jmp = nil
+        body.each { |i| jmp = i if i.class == Array && i[0] == :jmp_if_false }
+        assert(true, body.include?(jmp[1]))
+        "#;
+        assert_script(program);
+    }
+}
@@ -50,6 +50,34 @@ impl RString {
         }
     }
 
+    /// Overwrite the bytes from `pos` with `bytes` in place, extending the string if needed.
+    /// The gap beyond the end is filled with NUL.
+    pub fn write_at(&mut self, pos: usize, bytes: &[u8]) {
+        if let RString::Str(s) = self {
+            if pos == s.len() {
+                if let Ok(appended) = std::str::from_utf8(bytes) {
+                    s.push_str(appended);
+                    return;
+                }
+            }
+        }
+        let is_str = matches!(self, RString::Str(_));
+        let mut buf = match std::mem::replace(self, RString::Bytes(vec![])) {
+            RString::Str(s) => s.into_bytes(),
+            RString::Bytes(bytes) => bytes,
+        };
+        if buf.len() < pos {
+            buf.resize(pos, 0);
+        }
+        let end = std::cmp::min(pos + bytes.len(), buf.len());
+        buf.splice(pos..end, bytes.iter().cloned());
+        *self = match String::from_utf8(buf) {
+            Ok(s) if is_str => RString::Str(s),
+            Ok(s) => RString::Bytes(s.into_bytes()),
+            Err(err) => RString::Bytes(err.into_bytes()),
+        };
+    }
+
     /// Parse string as i64 or f64.
     pub fn parse<F: FromStr>(&self) -> Option<F> {
         match self {
@@ -0,0 +1,137 @@
+use crate::*;
+
+pub fn init_stringio(globals: &mut Globals) -> Value {
+    let id = globals.get_ident_id("StringIO");
+    let class = ClassRef::from(id, globals.builtins.object);
+    let obj = Value::class(globals, class);
+    globals.add_builtin_class_method(obj, "new", new);
+    globals.add_builtin_instance_method(class, "inspect", inspect);
+    globals.add_builtin_instance_method(class, "string", string);
+    globals.add_builtin_instance_method(class, "string=", set_string);
+    builtin::io::add_io_methods(globals, class);
+    obj
+}
+
+// Class methods
+
+/// StringIO.new(string = "", mode = "r+")
+fn new(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_range(args.len(), 0, 2)?;
+    let string = if args.len() == 0 {
+        Value::string(&vm.globals, String::new())
+    } else {
+        vm.expect_string(&args[0], "1st arg")?;
+        args[0]
+    };
+    let mode = if args.len() == 2 {
+        vm.expect_string(&args[1], "Mode")?.replace("b", "")
+    } else {
+        "r+".to_string()
+    };
+    let (readable, writable) = match mode.as_str() {
+        "r" => (true, false),
+        "w" | "a" => (false, true),
+        "r+" | "w+" | "a+" => (true, true),
+        _ => return Err(vm.error_argument(format!("invalid access mode {}", mode))),
+    };
+    let mut info = IOInfo::new_buffer(string, readable, writable);
+    match mode.as_str() {
+        "w" | "w+" => {
+            if let Err(err) = info.truncate(0) {
+                return Err(vm.error_internal(format!("{}", err)));
+            }
+        }
+        "a" | "a+" => {
+            if let Err(err) = info.seek(std::io::SeekFrom::End(0)) {
+                return Err(vm.error_internal(format!("{}", err)));
+            }
+        }
+        _ => {}
+    };
+    Ok(Value::io(self_val, info))
+}
+
+// Instance methods
+
+fn inspect(vm: &mut VM, self_val: Value, _args: &Args) -> VMResult {
+    let inspect = format!("#<StringIO:0x{:016x}>", self_val.as_object().id());
+    Ok(Value::string(&vm.globals, inspect))
+}
+
+/// Returns the underlying String object.
+fn string(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    match self_val.as_io().and_then(|ioref| ioref.buffer()) {
+        Some(string) => Ok(string),
+        None => Err(vm.error_argument("Receiver must be StringIO.")),
+    }
+}
+
+/// Replace the underlying String object, and rewind.
+fn set_string(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    vm.expect_string(&args[0], "1st arg")?;
+    let mut ioref = match self_val.as_io() {
+        Some(ioref) if ioref.buffer().is_some() => ioref,
+        _ => return Err(vm.error_argument("Receiver must be StringIO.")),
+    };
+    *ioref = IOInfo::new_buffer(args[0], true, true);
+    Ok(args[0])
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::*;
+
+    #[test]
+    fn stringio() {
+        let program = r#"
+        io = StringIO.new
+        io.puts "foo", [1, 2]
+        io.print "bar", :baz
+        io << "\n" << 3.5
+        io.write("qux\n", 4)
+        assert("foo\n1\n2\nbarbaz\n3.5qux\n4", io.string)
+        assert(23, io.pos)
+        io.rewind
+        assert("foo\n", io.gets)
+        assert("1\n2", io.read(3))
+        lines = []
+        io.each_line { |l| lines << l }
+        assert(["\n", "barbaz\n", "3.5qux\n", "4"], lines)
+        assert(true, io.eof?)
+        assert(nil, io.gets)
+        assert("", io.read)
+        io.truncate(3)
+        assert("foo", io.string)
+        io = StringIO.new("hello world")
+        io.write("HELLO")
+        assert("HELLO world", io.string)
+        assert(" world", io.read)
+        io = StringIO.new("abc", "a")
+        io.write("def")
+        assert("abcdef", io.string)
+        io = StringIO.new
+        10000.times { io.write("abcd") }
+        assert(40000, io.string.size)
+        io.pos = 2
+        io.write("XY")
+        assert("abXYabcd", io.string[0, 8])
+        "#;
+        assert_script(program);
+    }
+
+    #[test]
+    fn stringio_stdout() {
+        let program = r#"
+        out = StringIO.new
+        $stdout = out
+        puts "hello", 1
+        print "a", "b"
+        p :sym
+        $stdout = STDOUT
+        assert("hello\n1\nab:sym\n", out.string)
+        "#;
+        assert_script(program);
+    }
+}
@@ -0,0 +1,1101 @@
+use crate::*;
+use chrono::{
+    DateTime, Datelike, Duration, FixedOffset, Local, NaiveDate, NaiveDateTime, Offset, TimeZone,
+    Timelike, Utc,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Zone {
+    Utc,
+    /// The system time zone.
+    Local,
+    /// Fixed offset given by the user. (e.g. Time.new(2000, 1, 1, 0, 0, 0, "+09:00"))
+    Fixed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeInfo {
+    pub time: DateTime<FixedOffset>,
+    pub zone: Zone,
+}
+
+const MONTH_NAMES: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+const DAY_NAMES: [&str; 7] = [
+    "Sunday",
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+];
+
+impl TimeInfo {
+    pub fn now() -> Self {
+        let now = Local::now();
+        TimeInfo {
+            time: now.with_timezone(&now.offset().fix()),
+            zone: Zone::Local,
+        }
+    }
+
+    fn local_offset(utc: &NaiveDateTime) -> FixedOffset {
+        Local.offset_from_utc_datetime(utc).fix()
+    }
+
+    /// Create a local time from seconds and nanoseconds since the Epoch.
+    pub fn from_timestamp(secs: i64, nsec: u32) -> Option<Self> {
+        let utc = Utc.timestamp_opt(secs, nsec).single()?;
+        Some(TimeInfo::from_utc(utc.naive_utc(), Zone::Local))
+    }
+
+    pub fn from_system_time(time: std::time::SystemTime) -> Self {
+        let utc: DateTime<Utc> = time.into();
+        TimeInfo::from_utc(utc.naive_utc(), Zone::Local)
+    }
+
+    /// Create a time in `zone` from the UTC date and time `utc`.
+    fn from_utc(utc: NaiveDateTime, zone: Zone) -> Self {
+        let offset = match zone {
+            Zone::Local => TimeInfo::local_offset(&utc),
+            _ => FixedOffset::east_opt(0).unwrap(),
+        };
+        TimeInfo {
+            time: offset.from_utc_datetime(&utc),
+            zone,
+        }
+    }
+
+    /// Create a time from the date and time `local` in `zone`. `offset` is used for Zone::Fixed.
+    fn from_local(local: NaiveDateTime, zone: Zone, offset: FixedOffset) -> Option<Self> {
+        let time = match zone {
+            Zone::Utc => Utc.from_utc_datetime(&local).with_timezone(&offset),
+            Zone::Local => {
+                let time = Local.from_local_datetime(&local).earliest()?;
+                time.with_timezone(&time.offset().fix())
+            }
+            Zone::Fixed => offset.from_local_datetime(&local).single()?,
+        };
+        Some(TimeInfo { time, zone })
+    }
+
+    pub fn to_utc(&self) -> Self {
+        TimeInfo::from_utc(self.time.naive_utc(), Zone::Utc)
+    }
+
+    pub fn to_local(&self) -> Self {
+        TimeInfo::from_utc(self.time.naive_utc(), Zone::Local)
+    }
+
+    pub fn with_offset(&self, offset: FixedOffset) -> Self {
+        TimeInfo {
+            time: self.time.with_timezone(&offset),
+            zone: Zone::Fixed,
+        }
+    }
+
+    /// Nanoseconds since the Epoch.
+    pub fn to_nanos(&self) -> i128 {
+        self.time.timestamp() as i128 * 1_000_000_000 + self.time.timestamp_subsec_nanos() as i128
+    }
+
+    pub fn to_f(&self) -> f64 {
+        self.time.timestamp() as f64 + self.time.timestamp_subsec_nanos() as f64 / 1e9
+    }
+
+    /// Returns None if the result is out of range.
+    pub fn add_nanos(&self, nanos: i128) -> Option<Self> {
+        let secs = i64::try_from(nanos.div_euclid(1_000_000_000)).ok()?;
+        // Duration::seconds() panics out of this range.
+        if !(-(i64::MAX / 1000)..=i64::MAX / 1000).contains(&secs) {
+            return None;
+        }
+        let nsec = nanos.rem_euclid(1_000_000_000) as i64;
+        let time = self
+            .time
+            .checked_add_signed(Duration::seconds(secs) + Duration::nanoseconds(nsec))?;
+        Some(self.with_time(time))
+    }
+
+    fn with_time(&self, time: DateTime<FixedOffset>) -> Self {
+        match self.zone {
+            // The offset of local time may change. (e.g. daylight saving time)
+            Zone::Local => TimeInfo::from_utc(time.naive_utc(), Zone::Local),
+            _ => TimeInfo {
+                time,
+                zone: self.zone,
+            },
+        }
+    }
+
+    pub fn offset_secs(&self) -> i32 {
+        self.time.offset().local_minus_utc()
+    }
+
+    /// Time zone name. (e.g. "UTC", "JST")
+    pub fn zone_name(&self) -> Option<String> {
+        match self.zone {
+            Zone::Utc => Some("UTC".to_string()),
+            Zone::Fixed => None,
+            Zone::Local => {
+                let secs = self.time.timestamp() as libc::time_t;
+                let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+                let res = unsafe { libc::localtime_r(&secs, &mut tm) };
+                if res.is_null() || tm.tm_zone.is_null() {
+                    return None;
+                }
+                let name = unsafe { std::ffi::CStr::from_ptr(tm.tm_zone) };
+                Some(name.to_string_lossy().into_owned())
+            }
+        }
+    }
+
+    /// "+0900" / "+09:00" / "+09:00:00"
+    fn offset_string(&self, colons: usize) -> String {
+        let offset = self.offset_secs();
+        let sign = if offset < 0 { '-' } else { '+' };
+        let offset = offset.abs();
+        let (h, m, s) = (offset / 3600, offset / 60 % 60, offset % 60);
+        match colons {
+            0 => format!("{}{:02}{:02}", sign, h, m),
+            1 => format!("{}{:02}:{:02}", sign, h, m),
+            _ => format!("{}{:02}:{:02}:{:02}", sign, h, m, s),
+        }
+    }
+
+    pub fn to_s(&self) -> String {
+        let zone = match self.zone {
+            Zone::Utc => "UTC".to_string(),
+            _ => self.offset_string(0),
+        };
+        format!("{} {}", self.time.format("%Y-%m-%d %H:%M:%S"), zone)
+    }
+
+    pub fn inspect(&self) -> String {
+        let nsec = self.time.nanosecond();
+        let subsec = if nsec == 0 {
+            "".to_string()
+        } else {
+            format!(".{:09}", nsec).trim_end_matches('0').to_string()
+        };
+        let zone = match self.zone {
+            Zone::Utc => "UTC".to_string(),
+            _ => self.offset_string(0),
+        };
+        format!(
+            "{}{} {}",
+            self.time.format("%Y-%m-%d %H:%M:%S"),
+            subsec,
+            zone
+        )
+    }
+
+    /// "2000-01-02T03:04:05+09:00" / "2000-01-02T03:04:05.123Z"
+    pub fn iso8601(&self, digits: usize) -> String {
+        let mut s = self.time.format("%Y-%m-%dT%H:%M:%S").to_string();
+        if digits > 0 {
+            let nsec = format!("{:09}", self.time.nanosecond());
+            s.push('.');
+            for i in 0..digits {
+                s.push(nsec.as_bytes().get(i).map(|b| *b as char).unwrap_or('0'));
+            }
+        }
+        match self.zone {
+            Zone::Utc => s.push('Z'),
+            _ => s += &self.offset_string(1),
+        }
+        s
+    }
+
+    /// Format the time according to the directives in `fmt`, like Time#strftime.
+    pub fn strftime(&self, fmt: &str) -> String {
+        let mut res = String::new();
+        let mut chars = fmt.chars().peekable();
+        while let Some(ch) = chars.next() {
+            if ch != '%' {
+                res.push(ch);
+                continue;
+            }
+            // flags
+            let mut no_pad = false;
+            let mut pad: Option<char> = None;
+            let mut upcase = false;
+            let mut swapcase = false;
+            while let Some(&c) = chars.peek() {
+                match c {
+                    '-' => no_pad = true,
+                    '_' => pad = Some(' '),
+                    '0' => pad = Some('0'),
+                    '^' => upcase = true,
+                    '#' => swapcase = true,
+                    _ => break,
+                }
+                chars.next();
+            }
+            // width
+            let mut width: Option<usize> = None;
+            while let Some(&c) = chars.peek() {
+                match c.to_digit(10) {
+                    Some(d) => width = Some(width.unwrap_or(0) * 10 + d as usize),
+                    None => break,
+                }
+                chars.next();
+            }
+            let mut colons = 0;
+            while chars.peek() == Some(&':') {
+                colons += 1;
+                chars.next();
+            }
+            let conv = match chars.next() {
+                Some(c) => c,
+                None => {
+                    res.push('%');
+                    break;
+                }
+            };
+            let t = &self.time;
+            let hour12 = match t.hour() % 12 {
+                0 => 12,
+                h => h,
+            };
+            // (formatted string, default padding char, default width)
+            let (s, default_pad, default_width): (String, char, usize) = match conv {
+                'Y' => (t.year().to_string(), '0', 4),
+                'C' => ((t.year() / 100).to_string(), '0', 2),
+                'y' => ((t.year() % 100).to_string(), '0', 2),
+                'm' => (t.month().to_string(), '0', 2),
+                'B' => (MONTH_NAMES[t.month0() as usize].to_string(), ' ', 0),
+                'b' | 'h' => (MONTH_NAMES[t.month0() as usize][0..3].to_string(), ' ', 0),
+                'd' => (t.day().to_string(), '0', 2),
+                'e' => (t.day().to_string(), ' ', 2),
+                'j' => (t.ordinal().to_string(), '0', 3),
+                'H' => (t.hour().to_string(), '0', 2),
+                'k' => (t.hour().to_string(), ' ', 2),
+                'I' => (hour12.to_string(), '0', 2),
+                'l' => (hour12.to_string(), ' ', 2),
+                'P' => ((if t.hour() < 12 { "am" } else { "pm" }).to_string(), ' ', 0),
+                'p' => {
+                    let s = if t.hour() < 12 { "AM" } else { "PM" };
+                    if swapcase {
+                        swapcase = false;
+                        (s.to_lowercase(), ' ', 0)
+                    } else {
+                        (s.to_string(), ' ', 0)
+                    }
+                }
+                'M' => (t.minute().to_string(), '0', 2),
+                'S' => (t.second().to_string(), '0', 2),
+                'L' | 'N' => {
+                    let digits = width.take().unwrap_or(if conv == 'L' { 3 } else { 9 });
+                    let nsec = format!("{:09}", t.nanosecond());
+                    let mut s: String = nsec.chars().take(digits).collect();
+                    while s.len() < digits {
+                        s.push('0');
+                    }
+                    (s, '0', 0)
+                }
+                'z' => (self.offset_string(colons), ' ', 0),
+                'Z' => (self.zone_name().unwrap_or_default(), ' ', 0),
+                'A' => (
+                    DAY_NAMES[t.weekday().num_days_from_sunday() as usize].to_string(),
+                    ' ',
+                    0,
+                ),
+                'a' => (
+                    DAY_NAMES[t.weekday().num_days_from_sunday() as usize][0..3].to_string(),
+                    ' ',
+                    0,
+                ),
+                'u' => (t.weekday().number_from_monday().to_string(), '0', 1),
+                'w' => (t.weekday().num_days_from_sunday().to_string(), '0', 1),
+                'U' => {
+                    let week = (t.ordinal0() + 7 - t.weekday().num_days_from_sunday()) / 7;
+                    (week.to_string(), '0', 2)
+                }
+                'W' => {
+                    let week = (t.ordinal0() + 7 - t.weekday().num_days_from_monday()) / 7;
+                    (week.to_string(), '0', 2)
+                }
+                'G' => (t.iso_week().year().to_string(), '0', 4),
+                'g' => ((t.iso_week().year() % 100).to_string(), '0', 2),
+                'V' => (t.iso_week().week().to_string(), '0', 2),
+                's' => (t.timestamp().to_string(), '0', 1),
+                'F' => (self.strftime("%Y-%m-%d"), ' ', 0),
+                'D' | 'x' => (self.strftime("%m/%d/%y"), ' ', 0),
+                'T' | 'X' => (self.strftime("%H:%M:%S"), ' ', 0),
+                'R' => (self.strftime("%H:%M"), ' ', 0),
+                'r' => (self.strftime("%I:%M:%S %p"), ' ', 0),
+                'c' => (self.strftime("%a %b %e %H:%M:%S %Y"), ' ', 0),
+                '+' => (self.strftime("%a %b %e %H:%M:%S %Z %Y"), ' ', 0),
+                'n' => ("\n".to_string(), ' ', 0),
+                't' => ("\t".to_string(), ' ', 0),
+                '%' => ("%".to_string(), ' ', 0),
+                c => {
+                    // Unknown directive is output as is.
+                    res.push('%');
+                    res.push(c);
+                    continue;
+                }
+            };
+            let mut s = if upcase || swapcase {
+                s.to_uppercase()
+            } else {
+                s
+            };
+            if !no_pad {
+                let width = width.unwrap_or(default_width);
+                let pad = pad.unwrap_or(default_pad);
+                while s.chars().count() < width {
+                    s.insert(0, pad);
+                }
+            }
+            res += &s;
+        }
+        res
+    }
+}
+
+pub fn init_time(globals: &mut Globals) -> Value {
+    let id = globals.get_ident_id("Time");
+    let class = ClassRef::from(id, globals.builtins.object);
+    let obj = Value::class(globals, class);
+    globals.add_builtin_class_method(obj, "now", now);
+    globals.add_builtin_class_method(obj, "new", new);
+    globals.add_builtin_class_method(obj, "local", local);
+    globals.add_builtin_class_method(obj, "mktime", local);
+    globals.add_builtin_class_method(obj, "utc", utc);
+    globals.add_builtin_class_method(obj, "gm", utc);
+    globals.add_builtin_class_method(obj, "at", at);
+    globals.add_builtin_class_method(obj, "parse", parse);
+    globals.add_builtin_class_method(obj, "iso8601", parse_iso8601);
+    globals.add_builtin_class_method(obj, "strptime", strptime);
+    globals.add_builtin_instance_method(class, "+", add);
+    globals.add_builtin_instance_method(class, "-", sub);
+    globals.add_builtin_instance_method(class, "<=>", cmp);
+    globals.add_builtin_instance_method(class, "==", eq);
+    globals.add_builtin_instance_method(class, "eql?", eq);
+    globals.add_builtin_instance_method(class, "<", lt);
+    globals.add_builtin_instance_method(class, "<=", le);
+    globals.add_builtin_instance_method(class, ">", gt);
+    globals.add_builtin_instance_method(class, ">=", ge);
+    globals.add_builtin_instance_method(class, "year", year);
+    globals.add_builtin_instance_method(class, "month", month);
+    globals.add_builtin_instance_method(class, "mon", month);
+    globals.add_builtin_instance_method(class, "day", day);
+    globals.add_builtin_instance_method(class, "mday", day);
+    globals.add_builtin_instance_method(class, "hour", hour);
+    globals.add_builtin_instance_method(class, "min", min);
+    globals.add_builtin_instance_method(class, "sec", sec);
+    globals.add_builtin_instance_method(class, "usec", usec);
+    globals.add_builtin_instance_method(class, "tv_usec", usec);
+    globals.add_builtin_instance_method(class, "nsec", nsec);
+    globals.add_builtin_instance_method(class, "tv_nsec", nsec);
+    globals.add_builtin_instance_method(class, "wday", wday);
+    globals.add_builtin_instance_method(class, "yday", yday);
+    globals.add_builtin_instance_method(class, "zone", zone);
+    globals.add_builtin_instance_method(class, "utc_offset", utc_offset);
+    globals.add_builtin_instance_method(class, "gmt_offset", utc_offset);
+    globals.add_builtin_instance_method(class, "gmtoff", utc_offset);
+    globals.add_builtin_instance_method(class, "utc?", is_utc);
+    globals.add_builtin_instance_method(class, "gmt?", is_utc);
+    globals.add_builtin_instance_method(class, "to_i", to_i);
+    globals.add_builtin_instance_method(class, "tv_sec", to_i);
+    globals.add_builtin_instance_method(class, "to_f", to_f);
+    globals.add_builtin_instance_method(class, "to_a", to_a);
+    globals.add_builtin_instance_method(class, "strftime", strftime);
+    globals.add_builtin_instance_method(class, "iso8601", iso8601);
+    globals.add_builtin_instance_method(class, "xmlschema", iso8601);
+    globals.add_builtin_instance_method(class, "utc", to_utc);
+    globals.add_builtin_instance_method(class, "gmtime", to_utc);
+    globals.add_builtin_instance_method(class, "getutc", getutc);
+    globals.add_builtin_instance_method(class, "getgm", getutc);
+    globals.add_builtin_instance_method(class, "localtime", localtime);
+    globals.add_builtin_instance_method(class, "getlocal", getlocal);
+    globals.add_builtin_instance_method(class, "to_s", to_s);
+    globals.add_builtin_instance_method(class, "inspect", inspect);
+    globals.add_builtin_instance_method(class, "sunday?", is_sunday);
+    globals.add_builtin_instance_method(class, "monday?", is_monday);
+    globals.add_builtin_instance_method(class, "tuesday?", is_tuesday);
+    globals.add_builtin_instance_method(class, "wednesday?", is_wednesday);
+    globals.add_builtin_instance_method(class, "thursday?", is_thursday);
+    globals.add_builtin_instance_method(class, "friday?", is_friday);
+    globals.add_builtin_instance_method(class, "saturday?", is_saturday);
+    obj
+}
+
+// Utils
+
+fn expect_time(vm: &mut VM, val: Value) -> Result<TimeInfo, RubyError> {
+    match val.as_time() {
+        Some(info) => Ok(info),
+        None => {
+            let inspect = vm.val_inspect(val);
+            Err(vm.error_type(format!("Expected Time. (given:{})", inspect)))
+        }
+    }
+}
+
+fn set_time(self_val: Value, info: TimeInfo) {
+    if let ObjKind::Time(time) = &mut self_val.as_object().kind {
+        *time = info;
+    }
+}
+
+/// Convert Integer or Float seconds to nanoseconds.
+fn expect_nanos(vm: &mut VM, val: Value) -> Result<i128, RubyError> {
+    if let Some(i) = val.as_fixnum() {
+        Ok(i as i128 * 1_000_000_000)
+    } else if let Some(f) = val.as_flonum() {
+        let nanos = (f * 1e9).round();
+        // i128 holds about 1.7e38 nanoseconds.
+        if !nanos.is_finite() || nanos.abs() >= 1e38 {
+            return Err(vm.error_range(format!("{} out of Time range", f)));
+        }
+        Ok(nanos as i128)
+    } else {
+        let inspect = vm.val_inspect(val);
+        Err(vm.error_type(format!("Can't convert {} into an exact number.", inspect)))
+    }
+}
+
+/// Parse an UTC offset. ("+09:00", "-0500", "UTC", "Z", 32400)
+fn expect_offset(vm: &mut VM, val: Value) -> Result<(Zone, FixedOffset), RubyError> {
+    let utc = FixedOffset::east_opt(0).unwrap();
+    let secs = match val.as_fixnum() {
+        Some(secs) => secs as i32,
+        None => {
+            let s = vm.expect_string(&val, "Utc offset")?.clone();
+            if s == "UTC" || s == "Z" || s == "-00:00" {
+                return Ok((Zone::Utc, utc));
+            }
+            match parse_offset(&s) {
+                Some(secs) => secs,
+                None => {
+                    return Err(vm.error_argument(
+                        r#"+HH:MM, "-HH:MM", "UTC" or "A".."I","K".."Z" expected for utc_offset"#,
+                    ))
+                }
+            }
+        }
+    };
+    match FixedOffset::east_opt(secs) {
+        Some(offset) => Ok((Zone::Fixed, offset)),
+        None => Err(vm.error_argument("utc_offset out of range")),
+    }
+}
+
+fn parse_offset(s: &str) -> Option<i32> {
+    let sign = match s.chars().next()? {
+        '+' => 1,
+        '-' => -1,
+        _ => return None,
+    };
+    let digits: String = s[1..].chars().filter(|c| *c != ':').collect();
+    if digits.len() != 2 && digits.len() != 4 && digits.len() != 6 {
+        return None;
+    }
+    if !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let h: i32 = digits[0..2].parse().ok()?;
+    let m: i32 = digits.get(2..4).map_or(Some(0), |s| s.parse().ok())?;
+    let sec: i32 = digits.get(4..6).map_or(Some(0), |s| s.parse().ok())?;
+    if h > 23 || m > 59 || sec > 59 {
+        return None;
+    }
+    Some(sign * (h * 3600 + m * 60 + sec))
+}
+
+/// Build a time from (year, month=1, day=1, hour=0, min=0, sec=0) in `args`.
+fn time_from_args(
+    vm: &mut VM,
+    args: &[Value],
+    zone: Zone,
+    offset: FixedOffset,
+) -> Result<TimeInfo, RubyError> {
+    let mut nums = [0i64, 1, 1, 0, 0];
+    for (i, arg) in args.iter().take(5).enumerate() {
+        if arg.is_nil() {
+            continue;
+        }
+        nums[i] = match arg.as_string() {
+            Some(s) => match s.parse() {
+                Ok(n) => n,
+                Err(_) => {
+                    let lower = s.to_lowercase();
+                    match MONTH_NAMES
+                        .iter()
+                        .position(|name| i == 1 && name[0..3].to_lowercase() == lower)
+                    {
+                        Some(m) => m as i64 + 1,
+                        None => return Err(vm.error_argument(format!("Invalid value {:?}.", s))),
+                    }
+                }
+            },
+            None => vm.expect_integer(*arg, "Time component")?,
+        };
+    }
+    let (sec, nsec) = match args.get(5) {
+        Some(sec) if !sec.is_nil() => {
+            let nanos = expect_nanos(vm, *sec)?;
+            (nanos / 1_000_000_000, (nanos % 1_000_000_000) as u32)
+        }
+        _ => (0, 0),
+    };
+    let date = NaiveDate::from_ymd_opt(nums[0] as i32, nums[1] as u32, nums[2] as u32)
+        .and_then(|date| date.and_hms_nano_opt(nums[3] as u32, nums[4] as u32, sec as u32, nsec));
+    let local = match date {
+        Some(date) => date,
+        None => return Err(vm.error_argument("Argument out of range.")),
+    };
+    match TimeInfo::from_local(local, zone, offset) {
+        Some(info) => Ok(info),
+        None => Err(vm.error_argument("Argument out of range.")),
+    }
+}
+
+fn time_value(vm: &mut VM, info: TimeInfo) -> Value {
+    Value::time(&vm.globals, info)
+}
+
+// Class methods
+
+fn now(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    Ok(time_value(vm, TimeInfo::now()))
+}
+
+/// Time.new / Time.new(year, month=1, day=1, hour=0, min=0, sec=0, zone=nil)
+fn new(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_range(args.len(), 0, 7)?;
+    if args.len() == 0 {
+        return Ok(time_value(vm, TimeInfo::now()));
+    }
+    let (zone, offset) = match args.get(6) {
+        Some(zone) if !zone.is_nil() => expect_offset(vm, *zone)?,
+        _ => (Zone::Local, FixedOffset::east_opt(0).unwrap()),
+    };
+    let args: Vec<Value> = args.iter().take(6).cloned().collect();
+    let info = time_from_args(vm, &args, zone, offset)?;
+    Ok(time_value(vm, info))
+}
+
+/// Time.local(year, month=1, day=1, hour=0, min=0, sec=0)
+fn local(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_range(args.len(), 1, 7)?;
+    let args: Vec<Value> = args.iter().cloned().collect();
+    let info = time_from_args(vm, &args, Zone::Local, FixedOffset::east_opt(0).unwrap())?;
+    Ok(time_value(vm, info))
+}
+
+/// Time.utc(year, month=1, day=1, hour=0, min=0, sec=0, usec=0)
+fn utc(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_range(args.len(), 1, 7)?;
+    let args: Vec<Value> = args.iter().cloned().collect();
+    let mut info = time_from_args(vm, &args, Zone::Utc, FixedOffset::east_opt(0).unwrap())?;
+    if let Some(usec) = args.get(6) {
+        let usec = vm.expect_integer(*usec, "usec")?;
+        info = match info.add_nanos(usec as i128 * 1000) {
+            Some(info) => info,
+            None => return Err(vm.error_range("Time out of range.")),
+        };
+    }
+    Ok(time_value(vm, info))
+}
+
+/// Time.at(time) / Time.at(seconds, usec=0)
+fn at(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_range(args.len(), 1, 2)?;
+    if let Some(info) = args[0].as_time() {
+        return Ok(time_value(vm, info));
+    }
+    let mut nanos = expect_nanos(vm, args[0])?;
+    if args.len() == 2 {
+        nanos += expect_nanos(vm, args[1])? / 1_000_000;
+    }
+    let secs = match i64::try_from(nanos.div_euclid(1_000_000_000)) {
+        Ok(secs) => secs,
+        Err(_) => return Err(vm.error_argument("Time out of range.")),
+    };
+    let nsec = nanos.rem_euclid(1_000_000_000) as u32;
+    match TimeInfo::from_timestamp(secs, nsec) {
+        Some(info) => Ok(time_value(vm, info)),
+        None => Err(vm.error_argument("Time out of range.")),
+    }
+}
+
+fn parse_str(s: &str) -> Option<TimeInfo> {
+    let s = s.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(s) {
+        let zone = if s.ends_with('Z') {
+            Zone::Utc
+        } else {
+            Zone::Fixed
+        };
+        return Some(TimeInfo { time, zone });
+    }
+    for fmt in &["%Y-%m-%d %H:%M:%S%.f %z", "%Y-%m-%dT%H:%M:%S%.f%z"] {
+        if let Ok(time) = DateTime::parse_from_str(s, fmt) {
+            return Some(TimeInfo {
+                time,
+                zone: Zone::Fixed,
+            });
+        }
+    }
+    let utc = FixedOffset::east_opt(0).unwrap();
+    if let Some(s) = s.strip_suffix(" UTC") {
+        if let Ok(local) = NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f") {
+            return TimeInfo::from_local(local, Zone::Utc, utc);
+        }
+    }
+    for fmt in &[
+        "%Y-%m-%d %H:%M:%S%.f",
+        "%Y-%m-%dT%H:%M:%S%.f",
+        "%Y/%m/%d %H:%M:%S%.f",
+        "%Y-%m-%d %H:%M",
+    ] {
+        if let Ok(local) = NaiveDateTime::parse_from_str(s, fmt) {
+            return TimeInfo::from_local(local, Zone::Local, utc);
+        }
+    }
+    for fmt in &["%Y-%m-%d", "%Y/%m/%d", "%Y%m%d"] {
+        if let Ok(date) = NaiveDate::parse_from_str(s, fmt) {
+            return TimeInfo::from_local(date.and_hms_opt(0, 0, 0)?, Zone::Local, utc);
+        }
+    }
+    None
+}
+
+/// Time.parse(string)
+fn parse(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let s = vm.expect_string(&args[0], "Time string")?.clone();
+    match parse_str(&s) {
+        Some(info) => Ok(time_value(vm, info)),
+        None => Err(vm.error_argument(format!("no time information in {:?}", s))),
+    }
+}
+
+/// Time.iso8601(string)
+fn parse_iso8601(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let s = vm.expect_string(&args[0], "Time string")?.clone();
+    match DateTime::parse_from_rfc3339(&s) {
+        Ok(time) => {
+            let zone = if s.ends_with('Z') {
+                Zone::Utc
+            } else {
+                Zone::Fixed
+            };
+            Ok(time_value(vm, TimeInfo { time, zone }))
+        }
+        Err(_) => Err(vm.error_argument(format!("invalid xmlschema format: {:?}", s))),
+    }
+}
+
+/// Time.strptime(string, format)
+fn strptime(vm: &mut VM, _: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 2)?;
+    let s = vm.expect_string(&args[0], "Time string")?.clone();
+    let fmt = vm.expect_string(&args[1], "Format")?.clone();
+    let utc = FixedOffset::east_opt(0).unwrap();
+    let info = if let Ok(time) = DateTime::parse_from_str(&s, &fmt) {
+        Some(TimeInfo {
+            time,
+            zone: Zone::Fixed,
+        })
+    } else if let Ok(local) = NaiveDateTime::parse_from_str(&s, &fmt) {
+        TimeInfo::from_local(local, Zone::Local, utc)
+    } else if let Ok(date) = NaiveDate::parse_from_str(&s, &fmt) {
+        date.and_hms_opt(0, 0, 0)
+            .and_then(|local| TimeInfo::from_local(local, Zone::Local, utc))
+    } else {
+        None
+    };
+    match info {
+        Some(info) => Ok(time_value(vm, info)),
+        None => Err(vm.error_argument(format!("invalid date or strptime format - `{}' `{}'", s, fmt))),
+    }
+}
+
+// Instance methods
+
+fn add(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let info = expect_time(vm, self_val)?;
+    if args[0].as_time().is_some() {
+        return Err(vm.error_type("time + time?"));
+    }
+    let nanos = expect_nanos(vm, args[0])?;
+    match info.add_nanos(nanos) {
+        Some(info) => Ok(time_value(vm, info)),
+        None => Err(vm.error_range("Time out of range.")),
+    }
+}
+
+/// Time - Numeric -> Time / Time - Time -> Float
+fn sub(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let info = expect_time(vm, self_val)?;
+    match args[0].as_time() {
+        Some(other) => {
+            let nanos = info.to_nanos() - other.to_nanos();
+            Ok(Value::flonum(nanos as f64 / 1e9))
+        }
+        None => {
+            let nanos = expect_nanos(vm, args[0])?;
+            match info.add_nanos(-nanos) {
+                Some(info) => Ok(time_value(vm, info)),
+                None => Err(vm.error_range("Time out of range.")),
+            }
+        }
+    }
+}
+
+fn cmp(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let info = expect_time(vm, self_val)?;
+    match args[0].as_time() {
+        Some(other) => {
+            let ord = info.to_nanos().cmp(&other.to_nanos());
+            Ok(Value::fixnum(ord as i64))
+        }
+        None => Ok(Value::nil()),
+    }
+}
+
+fn eq(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let info = expect_time(vm, self_val)?;
+    match args[0].as_time() {
+        Some(other) => Ok(Value::bool(info.to_nanos() == other.to_nanos())),
+        None => Ok(Value::false_val()),
+    }
+}
+
+fn compare(vm: &mut VM, self_val: Value, args: &Args) -> Result<std::cmp::Ordering, RubyError> {
+    vm.check_args_num(args.len(), 1)?;
+    let info = expect_time(vm, self_val)?;
+    let other = expect_time(vm, args[0])?;
+    Ok(info.to_nanos().cmp(&other.to_nanos()))
+}
+
+fn lt(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    Ok(Value::bool(compare(vm, self_val, args)?.is_lt()))
+}
+
+fn le(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    Ok(Value::bool(compare(vm, self_val, args)?.is_le()))
+}
+
+fn gt(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    Ok(Value::bool(compare(vm, self_val, args)?.is_gt()))
+}
+
+fn ge(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    Ok(Value::bool(compare(vm, self_val, args)?.is_ge()))
+}
+
+macro_rules! time_accessor {
+    ($name:ident, $time:ident => $expr:expr) => {
+        fn $name(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+            vm.check_args_num(args.len(), 0)?;
+            let $time = expect_time(vm, self_val)?.time;
+            Ok(Value::fixnum($expr as i64))
+        }
+    };
+}
+
+time_accessor!(year, t => t.year());
+time_accessor!(month, t => t.month());
+time_accessor!(day, t => t.day());
+time_accessor!(hour, t => t.hour());
+time_accessor!(min, t => t.minute());
+time_accessor!(sec, t => t.second());
+time_accessor!(usec, t => t.nanosecond() / 1000);
+time_accessor!(nsec, t => t.nanosecond());
+time_accessor!(wday, t => t.weekday().num_days_from_sunday());
+time_accessor!(yday, t => t.ordinal());
+time_accessor!(to_i, t => t.timestamp());
+
+macro_rules! wday_predicate {
+    ($name:ident, $wday:expr) => {
+        fn $name(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+            vm.check_args_num(args.len(), 0)?;
+            let info = expect_time(vm, self_val)?;
+            Ok(Value::bool(
+                info.time.weekday().num_days_from_sunday() == $wday,
+            ))
+        }
+    };
+}
+
+wday_predicate!(is_sunday, 0);
+wday_predicate!(is_monday, 1);
+wday_predicate!(is_tuesday, 2);
+wday_predicate!(is_wednesday, 3);
+wday_predicate!(is_thursday, 4);
+wday_predicate!(is_friday, 5);
+wday_predicate!(is_saturday, 6);
+
+fn zone(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let info = expect_time(vm, self_val)?;
+    match info.zone_name() {
+        Some(name) => Ok(Value::string(&vm.globals, name)),
+        None => Ok(Value::nil()),
+    }
+}
+
+fn utc_offset(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let info = expect_time(vm, self_val)?;
+    Ok(Value::fixnum(info.offset_secs() as i64))
+}
+
+fn is_utc(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let info = expect_time(vm, self_val)?;
+    Ok(Value::bool(info.zone == Zone::Utc))
+}
+
+fn to_f(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let info = expect_time(vm, self_val)?;
+    Ok(Value::flonum(info.to_f()))
+}
+
+/// [sec, min, hour, day, month, year, wday, yday, isdst, zone]
+fn to_a(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let info = expect_time(vm, self_val)?;
+    let t = info.time;
+    let mut ary: Vec<Value> = [
+        t.second() as i64,
+        t.minute() as i64,
+        t.hour() as i64,
+        t.day() as i64,
+        t.month() as i64,
+        t.year() as i64,
+        t.weekday().num_days_from_sunday() as i64,
+        t.ordinal() as i64,
+    ]
+    .iter()
+    .map(|n| Value::fixnum(*n))
+    .collect();
+    ary.push(Value::false_val());
+    ary.push(match info.zone_name() {
+        Some(name) => Value::string(&vm.globals, name),
+        None => Value::nil(),
+    });
+    Ok(Value::array_from(&vm.globals, ary))
+}
+
+fn strftime(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 1)?;
+    let info = expect_time(vm, self_val)?;
+    let fmt = vm.expect_string(&args[0], "Format")?;
+    let s = info.strftime(fmt);
+    Ok(Value::string(&vm.globals, s))
+}
+
+/// Time#iso8601(fraction_digits = 0)
+fn iso8601(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_range(args.len(), 0, 1)?;
+    let info = expect_time(vm, self_val)?;
+    let digits = if args.len() == 0 {
+        0
+    } else {
+        vm.expect_integer(args[0], "Fraction digits")?.max(0) as usize
+    };
+    Ok(Value::string(&vm.globals, info.iso8601(digits)))
+}
+
+/// Time#utc: convert self to UTC.
+fn to_utc(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let info = expect_time(vm, self_val)?;
+    set_time(self_val, info.to_utc());
+    Ok(self_val)
+}
+
+fn getutc(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let info = expect_time(vm, self_val)?;
+    Ok(time_value(vm, info.to_utc()))
+}
+
+fn get_local_time(vm: &mut VM, self_val: Value, args: &Args) -> Result<TimeInfo, RubyError> {
+    vm.check_args_range(args.len(), 0, 1)?;
+    let info = expect_time(vm, self_val)?;
+    if args.len() == 0 {
+        return Ok(info.to_local());
+    }
+    match expect_offset(vm, args[0])? {
+        (Zone::Utc, _) => Ok(info.to_utc()),
+        (_, offset) => Ok(info.with_offset(offset)),
+    }
+}
+
+/// Time#localtime(utc_offset = nil): convert self to local time.
+fn localtime(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    let info = get_local_time(vm, self_val, args)?;
+    set_time(self_val, info);
+    Ok(self_val)
+}
+
+fn getlocal(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    let info = get_local_time(vm, self_val, args)?;
+    Ok(time_value(vm, info))
+}
+
+fn to_s(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let info = expect_time(vm, self_val)?;
+    Ok(Value::string(&vm.globals, info.to_s()))
+}
+
+fn inspect(vm: &mut VM, self_val: Value, args: &Args) -> VMResult {
+    vm.check_args_num(args.len(), 0)?;
+    let info = expect_time(vm, self_val)?;
+    Ok(Value::string(&vm.globals, info.inspect()))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::*;
+
+    #[test]
+    fn time_new() {
+        let program = r#"
+        t = Time.utc(2000, 1, 2, 3, 4, 5)
+        assert([2000, 1, 2, 3, 4, 5], [t.year, t.month, t.day, t.hour, t.min, t.sec])
+        assert(0, t.wday)
+        assert(2, t.yday)
+        assert(true, t.utc?)
+        assert(true, t.sunday?)
+        assert("UTC", t.zone)
+        assert("2000-01-02 03:04:05 UTC", t.inspect)
+        assert("2000-01-02 03:04:05 UTC", t.to_s)
+        assert("at 2000-01-02 03:04:05 UTC", "at #{t}")
+        assert(946782245, t.to_i)
+        assert(t, Time.gm(2000, "jan", 2, 3, 4, 5))
+        t = Time.new(2000, 1, 2, 3, 4, 5.5, "+09:00")
+        assert(500000, t.usec)
+        assert(32400, t.utc_offset)
+        assert(nil, t.zone)
+        assert("2000-01-02 03:04:05.5 +0900", t.inspect)
+        assert("2000-01-02T03:04:05+09:00", t.iso8601)
+        assert("2000-01-02T03:04:05.500+09:00", t.iso8601(3))
+        assert("2000-01-01T18:04:05.5Z", t.getutc.iso8601(1))
+        assert(t, Time.at(t.to_f))
+        assert(true, Time.now.is_a?(Time))
+        assert(true, Time.now - Time.now < 1)
+        "#;
+        assert_script(program);
+    }
+
+    #[test]
+    fn time_arith() {
+        let program = r#"
+        t = Time.at(0).utc
+        assert("1970-01-01 00:00:00 UTC", t.inspect)
+        t2 = t + 1.5
+        assert("1970-01-01 00:00:01.5 UTC", t2.inspect)
+        assert(1.5, t2 - t)
+        assert(-1.5, t - t2)
+        assert(t, t2 - 1.5)
+        assert(-1, t <=> t2)
+        assert(0, t <=> Time.at(0))
+        assert(1, t2 <=> t)
+        assert(true, t < t2)
+        assert(false, t >= t2)
+        t3 = t + 86400 * 365
+        assert(1971, t3.year)
+        assert(1.0, t3.to_f - t3.to_i + 1)
+        assert("2286-11-20 17:46:40 UTC", Time.at(10**10).utc.inspect)
+        assert("2286-11-20 17:46:40 UTC", Time.at(1e10).utc.inspect)
+        assert("2286-11-20 17:46:40 UTC", (t + 10**10).inspect)
+        msg = begin
+          t + 10**18
+        rescue RangeError => e
+          e.message
+        end
+        assert("Time out of range.", msg)
+        "#;
+        assert_script(program);
+    }
+
+    #[test]
+    fn time_strftime() {
+        let program = r#"
+        t = Time.utc(2020, 3, 4, 15, 6, 7) + 0.123456
+        assert("2020-03-04 15:06:07", t.strftime("%Y-%m-%d %H:%M:%S"))
+        assert("2020-03-04T15:06:07", t.strftime("%FT%T"))
+        assert("03/04/20 03:06:07 PM", t.strftime("%D %r"))
+        assert("Wednesday Wed March Mar", t.strftime("%A %a %B %b"))
+        assert("WEDNESDAY pm", t.strftime("%^A %#p"))
+        assert("3/4 15h 064", t.strftime("%-m/%-d %kh %j"))
+        assert("123 123456 123456000", t.strftime("%L %6N %N"))
+        assert("+0000 +00:00 UTC", t.strftime("%z %:z %Z"))
+        assert("Wed Mar  4 15:06:07 2020", t.strftime("%c"))
+        assert("1583334367 100%", t.strftime("%s 100%%"))
+        assert("  3|0003|3", t.strftime("%_3m|%04m|%-m"))
+        assert("3 3 10 09", t.strftime("%u %w %V %U"))
+        "#;
+        assert_script(program);
+    }
+
+    #[test]
+    fn time_parse() {
+        let program = r#"
+        t = Time.parse("2000-01-02T03:04:05+09:00")
+        assert(Time.new(2000, 1, 2, 3, 4, 5, "+09:00"), t)
+        t = Time.parse("2000-01-02 03:04:05 UTC")
+        assert(true, t.utc?)
+        assert(Time.utc(2000, 1, 2, 3, 4, 5), t)
+        assert(Time.local(2000, 1, 2), Time.parse("2000-01-02"))
+        assert(Time.utc(2000, 1, 2, 3, 4, 5), Time.iso8601("2000-01-02T03:04:05Z"))
+        t = Time.strptime("02/01/2000 03:04", "%d/%m/%Y %H:%M")
+        assert(Time.local(2000, 1, 2, 3, 4), t)
+        t = Time.utc(2000, 1, 2, 3, 4, 5)
+        assert(t, Time.parse(t.to_s))
+        t2 = t.getlocal("-05:00")
+        assert("2000-01-01 22:04:05 -0500", t2.to_s)
+        assert(t, t2)
+        t.localtime("+01:00")
+        assert(4, t.hour)
+        t.utc
+        assert(3, t.hour)
+        "#;
+        assert_script(program);
+    }
+
+    #[test]
+    fn process_clock() {
+        let program = r#"
+        t1 = Process.clock_gettime(Process::CLOCK_MONOTONIC)
+        t2 = Process.clock_gettime(Process::CLOCK_MONOTONIC)
+        assert(true, t1 <= t2)
+        t = Process.clock_gettime(Process::CLOCK_REALTIME)
+        assert(true, Time.now.to_f - t < 1)
+        assert(true, Process.clock_gettime(Process::CLOCK_PROCESS_CPUTIME_ID) > 0)
+        assert(true, Process.clock_gettime(Process::CLOCK_REALTIME, :millisecond).is_a?(Integer))
+        "#;
+        assert_script(program);
+    }
+}
@@ -3,7 +3,9 @@ use crate::*;
 #[derive(Debug, Clone, PartialEq)]
 pub struct RubyError {
     pub kind: RubyErrorKind,
-    pub info: Vec<(SourceInfoRef, Loc)>,
+    /// Frames the error has unwound through, innermost first: (source, location, label).
+    /// The label (e.g. "Foo#bar") is filled in when the frame's context is unwound.
+    pub info: Vec<(SourceInfoRef, Loc, String)>,
     level: usize,
 }
 
@@ -12,7 +14,8 @@ pub enum RubyErrorKind {
     ParseErr(ParseErrKind),
     RuntimeErr(RuntimeErrKind),
     MethodReturn(MethodRef),
-    BlockReturn,
+    /// `break` in a block, which exits from the method call in the outer context.
+    BlockReturn(ContextRef),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -34,13 +37,19 @@ pub enum RuntimeErrKind {
     Type(String),
     Regexp(String),
     Fiber(String),
+    Domain(String),
+    FloatDomain(String),
+    Range(String),
+    ZeroDivision(String),
+    /// An exception object raised by `raise` or re-raised after `rescue`.
+    Exception(Value),
 }
 
 impl RubyError {
     pub fn new(kind: RubyErrorKind, source_info: SourceInfoRef, level: usize, loc: Loc) -> Self {
         RubyError {
             kind,
-            info: vec![(source_info, loc)],
+            info: vec![(source_info, loc, String::new())],
             level,
         }
     }
@@ -66,35 +75,72 @@ impl RubyError {
     }
 
     pub fn show_err(&self) {
+        eprintln!("{}", self.message());
+    }
+
+    /// Returns the error message, e.g. "NoMethodError (undefined method `foo'.)".
+    pub fn message(&self) -> String {
         match &self.kind {
             RubyErrorKind::ParseErr(e) => match e {
-                ParseErrKind::UnexpectedEOF => eprintln!("Unexpected EOF"),
-                ParseErrKind::UnexpectedToken => eprintln!("Unexpected token"),
-                ParseErrKind::SyntaxError(n) => eprintln!("SyntaxError: {}", n),
-                ParseErrKind::LoadError(n) => eprintln!("LoadError: {}", n),
+                ParseErrKind::UnexpectedEOF => "Unexpected EOF".to_string(),
+                ParseErrKind::UnexpectedToken => "Unexpected token".to_string(),
+                ParseErrKind::SyntaxError(n) => format!("SyntaxError: {}", n),
+                ParseErrKind::LoadError(n) => format!("LoadError: {}", n),
             },
             RubyErrorKind::RuntimeErr(e) => match e {
-                RuntimeErrKind::Name(n) => eprintln!("NoNameError ({})", n),
-                RuntimeErrKind::NoMethod(n) => eprintln!("NoMethodError ({})", n),
-                RuntimeErrKind::Type(n) => eprintln!("TypeError ({})", n),
-                RuntimeErrKind::Unimplemented(n) => eprintln!("UnimplementedError ({})", n),
-                RuntimeErrKind::Internal(n) => eprintln!("InternalError ({})", n),
-                RuntimeErrKind::Argument(n) => eprintln!("ArgumentError ({})", n),
-                RuntimeErrKind::Index(n) => eprintln!("IndexError ({})", n),
-                RuntimeErrKind::Regexp(n) => eprintln!("RegexpError ({})", n),
-                RuntimeErrKind::Fiber(n) => eprintln!("FiberError ({})", n),
+                RuntimeErrKind::Name(n) => format!("NameError ({})", n),
+                RuntimeErrKind::NoMethod(n) => format!("NoMethodError ({})", n),
+                RuntimeErrKind::Type(n) => format!("TypeError ({})", n),
+                RuntimeErrKind::Unimplemented(n) => format!("UnimplementedError ({})", n),
+                RuntimeErrKind::Internal(n) => format!("InternalError ({})", n),
+                RuntimeErrKind::Argument(n) => format!("ArgumentError ({})", n),
+                RuntimeErrKind::Index(n) => format!("IndexError ({})", n),
+                RuntimeErrKind::Regexp(n) => format!("RegexpError ({})", n),
+                RuntimeErrKind::Fiber(n) => format!("FiberError ({})", n),
+                RuntimeErrKind::Domain(n) => format!("Math::DomainError ({})", n),
+                RuntimeErrKind::FloatDomain(n) => format!("FloatDomainError ({})", n),
+                RuntimeErrKind::Range(n) => format!("RangeError ({})", n),
+                RuntimeErrKind::ZeroDivision(n) => format!("ZeroDivisionError ({})", n),
+                RuntimeErrKind::Exception(_) => "Exception".to_string(),
             },
-            RubyErrorKind::MethodReturn(_) => {
-                eprintln!("LocalJumpError");
-            }
-            RubyErrorKind::BlockReturn => {
-                eprintln!("LocalJumpError");
-            }
+            RubyErrorKind::MethodReturn(_) => "LocalJumpError".to_string(),
+            RubyErrorKind::BlockReturn(_) => "LocalJumpError".to_string(),
         }
     }
 }
 
 impl RubyError {
+    /// Returns the name of the exception class which corresponds to the error and its message.
+    /// Returns None for exception objects and non-local exits (`return` and `break`).
+    pub fn exception_class(&self) -> Option<(&'static str, String)> {
+        let (class, msg) = match &self.kind {
+            RubyErrorKind::ParseErr(e) => match e {
+                ParseErrKind::UnexpectedEOF => ("SyntaxError", "unexpected end-of-input".to_string()),
+                ParseErrKind::UnexpectedToken => ("SyntaxError", "unexpected token".to_string()),
+                ParseErrKind::SyntaxError(n) => ("SyntaxError", n.clone()),
+                ParseErrKind::LoadError(n) => ("LoadError", n.clone()),
+            },
+            RubyErrorKind::RuntimeErr(e) => match e {
+                RuntimeErrKind::Name(n) => ("NameError", n.clone()),
+                RuntimeErrKind::NoMethod(n) => ("NoMethodError", n.clone()),
+                RuntimeErrKind::Type(n) => ("TypeError", n.clone()),
+                RuntimeErrKind::Unimplemented(n) => ("NotImplementedError", n.clone()),
+                RuntimeErrKind::Internal(n) => ("RuntimeError", n.clone()),
+                RuntimeErrKind::Argument(n) => ("ArgumentError", n.clone()),
+                RuntimeErrKind::Index(n) => ("IndexError", n.clone()),
+                RuntimeErrKind::Regexp(n) => ("RegexpError", n.clone()),
+                RuntimeErrKind::Fiber(n) => ("FiberError", n.clone()),
+                RuntimeErrKind::Domain(n) => ("Math::DomainError", n.clone()),
+                RuntimeErrKind::FloatDomain(n) => ("FloatDomainError", n.clone()),
+                RuntimeErrKind::Range(n) => ("RangeError", n.clone()),
+                RuntimeErrKind::ZeroDivision(n) => ("ZeroDivisionError", n.clone()),
+                RuntimeErrKind::Exception(_) => return None,
+            },
+            RubyErrorKind::MethodReturn(_) | RubyErrorKind::BlockReturn(_) => return None,
+        };
+        Some((class, msg))
+    }
+
     pub fn new_runtime_err(err: RuntimeErrKind, source_info: SourceInfoRef, loc: Loc) -> Self {
         let kind = RubyErrorKind::RuntimeErr(err);
         RubyError::new(kind, source_info, 0, loc)
@@ -110,11 +156,15 @@ impl RubyError {
         RubyError::new(kind, source_info, level, loc)
     }
 
+    pub fn new_exception(exception: Value, source_info: SourceInfoRef, loc: Loc) -> Self {
+        RubyError::new_runtime_err(RuntimeErrKind::Exception(exception), source_info, loc)
+    }
+
     pub fn new_method_return(method: MethodRef, source_info: SourceInfoRef, loc: Loc) -> Self {
         RubyError::new(RubyErrorKind::MethodReturn(method), source_info, 0, loc)
     }
 
-    pub fn new_block_return(source_info: SourceInfoRef, loc: Loc) -> Self {
-        RubyError::new(RubyErrorKind::BlockReturn, source_info, 0, loc)
+    pub fn new_block_return(outer: ContextRef, source_info: SourceInfoRef, loc: Loc) -> Self {
+        RubyError::new(RubyErrorKind::BlockReturn(outer), source_info, 0, loc)
     }
 }
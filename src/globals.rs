@@ -6,12 +6,17 @@ pub struct Globals {
     // Global info
     pub ident_table: IdentifierTable,
     pub global_var: ValueTable,
+    /// Aliases of global variables made by `alias $new $old`.
+    pub global_alias: HashMap<IdentId, IdentId>,
+    /// Procs registered by `at_exit` or `END {}`.
+    pub at_exit: Vec<Value>,
     method_table: GlobalMethodTable,
     inline_cache: InlineCache,
+    ivar_cache: IvarCache,
     method_cache: MethodCache,
     pub instant: std::time::Instant,
-    /// version counter: increment when new instance / class methods are defined.
-    pub class_version: usize,
+    /// Code coverage, collected while `Coverage` is running.
+    pub coverage: Option<Coverage>,
     pub main_object: Value,
     pub builtins: BuiltinClass,
     pub class_class: ClassRef,
@@ -25,6 +30,7 @@ pub type GlobalsRef = Ref<Globals>;
 
 #[derive(Debug, Clone)]
 pub struct BuiltinClass {
+    pub numeric: Value,
     pub integer: Value,
     pub float: Value,
     pub array: Value,
@@ -39,12 +45,21 @@ pub struct BuiltinClass {
     pub fiber: Value,
     pub object: Value,
     pub enumerator: Value,
+    pub io: Value,
+    pub file: Value,
+    pub time: Value,
+    pub rational: Value,
+    pub complex: Value,
+    pub exception: Value,
+    pub standard_error: Value,
+    pub location: Value,
 }
 
 impl BuiltinClass {
     fn new(object: Value, module: Value, class: Value) -> Self {
         let nil = Value::nil();
         BuiltinClass {
+            numeric: nil,
             integer: nil,
             float: nil,
             array: nil,
@@ -58,6 +73,14 @@ impl BuiltinClass {
             string: nil,
             fiber: nil,
             enumerator: nil,
+            io: nil,
+            file: nil,
+            time: nil,
+            rational: nil,
+            complex: nil,
+            exception: nil,
+            standard_error: nil,
+            location: nil,
             object,
         }
     }
@@ -86,11 +109,14 @@ impl Globals {
         let mut globals = Globals {
             ident_table,
             global_var: HashMap::new(),
+            global_alias: HashMap::new(),
+            at_exit: vec![],
             method_table: GlobalMethodTable::new(),
             inline_cache: InlineCache::new(),
+            ivar_cache: IvarCache::new(),
             method_cache: MethodCache::new(),
             instant: std::time::Instant::now(),
-            class_version: 0,
+            coverage: None,
             main_object,
             object_class,
             module_class,
@@ -106,6 +132,7 @@ impl Globals {
 
         module::init(&mut globals);
         class::init(&mut globals);
+        globals.builtins.numeric = numeric::init_numeric(&mut globals);
         globals.builtins.integer = integer::init(&mut globals);
         globals.builtins.float = float::init(&mut globals);
         globals.builtins.array = array::init_array(&mut globals);
@@ -117,6 +144,12 @@ impl Globals {
         globals.builtins.regexp = regexp::init_regexp(&mut globals);
         globals.builtins.fiber = fiber::init_fiber(&mut globals);
         globals.builtins.enumerator = enumerator::init_enumerator(&mut globals);
+        globals.builtins.io = io::init_io(&mut globals);
+        globals.builtins.file = file::init_file(&mut globals);
+        globals.builtins.time = time::init_time(&mut globals);
+        globals.builtins.rational = rational::init_rational(&mut globals);
+        globals.builtins.complex = complex::init_complex(&mut globals);
+        errorobj::init_error(&mut globals);
         object::init(&mut globals);
         let kernel = kernel::init(&mut globals);
         object_class.include.push(kernel);
@@ -137,6 +170,12 @@ impl Globals {
 
     pub fn add_object_method(&mut self, id: IdentId, info: MethodRef) {
         self.object_class.method_table.insert(id, info);
+        self.object_class.invalidate_method_cache();
+    }
+
+    /// Returns the original name of the global variable `id`.
+    pub fn resolve_global_alias(&self, id: IdentId) -> IdentId {
+        *self.global_alias.get(&id).unwrap_or(&id)
     }
 
     pub fn add_method(&mut self, info: MethodInfo) -> MethodRef {
@@ -148,6 +187,9 @@ impl Globals {
     }
 
     pub fn set_method(&mut self, method: MethodRef, info: MethodInfo) {
+        if let (Some(coverage), MethodInfo::RubyFunc { iseq }) = (&mut self.coverage, &info) {
+            coverage.register(*iseq);
+        }
         self.method_table.set_method(method, info);
     }
 
@@ -195,8 +237,9 @@ impl Globals {
             func,
         };
         let func_ref = self.add_method(info);
-        let singleton = self.get_singleton_class(obj).unwrap();
-        singleton.as_class().method_table.insert(id, func_ref);
+        let mut singleton = self.get_singleton_class(obj).unwrap().as_class();
+        singleton.method_table.insert(id, func_ref);
+        singleton.invalidate_method_cache();
     }
 
     pub fn add_builtin_instance_method(
@@ -212,6 +255,7 @@ impl Globals {
         };
         let methodref = self.add_method(info);
         classref.method_table.insert(id, methodref);
+        classref.invalidate_method_cache();
     }
 
     pub fn get_class_name(&self, val: Value) -> String {
@@ -239,25 +283,42 @@ impl Globals {
                 ObjKind::Float(_) => "Float".to_string(),
                 ObjKind::Fiber(_) => "Fiber".to_string(),
                 ObjKind::Enumerator(_) => "Enumerator".to_string(),
+                ObjKind::IO(_) => oref.class_name(self).to_string(),
+                ObjKind::Time(_) => "Time".to_string(),
+                ObjKind::Rational(_) => "Rational".to_string(),
+                ObjKind::Complex(_) => "Complex".to_string(),
             },
         }
     }
 }
 
 impl Globals {
+    /// Add `method` for the receiver class `class` to the inline cache at `id`.
+    /// The call site becomes megamorphic and is no longer cached
+    /// when it has seen more than `INLINE_CACHE_SIZE` classes.
     pub fn set_inline_cache_entry(&mut self, id: u32, class: Value, method: MethodRef) {
-        self.inline_cache.table[id as usize] = Some(InlineCacheEntry {
-            class,
-            version: self.class_version,
-            method,
+        let entry = &mut self.inline_cache.table[id as usize];
+        if entry.megamorphic {
+            return;
+        }
+        // Drop the entries invalidated since they were cached.
+        entry.classes.retain(|(cached_class, serial, _)| {
+            cached_class.id() != class.id() && cached_class.as_module().unwrap().serial == *serial
         });
+        if entry.classes.len() == INLINE_CACHE_SIZE {
+            entry.classes.clear();
+            entry.megamorphic = true;
+            return;
+        }
+        let serial = class.as_module().unwrap().serial;
+        entry.classes.push((class, serial, method));
     }
 
     pub fn add_inline_cache_entry(&mut self) -> u32 {
         self.inline_cache.add_entry()
     }
 
-    fn get_inline_cache_entry(&self, id: u32) -> &Option<InlineCacheEntry> {
+    fn get_inline_cache_entry(&self, id: u32) -> &InlineCacheEntry {
         self.inline_cache.get_entry(id)
     }
 
@@ -266,21 +327,52 @@ impl Globals {
         cache_slot: u32,
         rec_class: Value,
     ) -> Option<MethodRef> {
-        match self.get_inline_cache_entry(cache_slot) {
-            Some(InlineCacheEntry {
-                class,
-                version,
-                method,
-            }) if class.id() == rec_class.id() && *version == self.class_version => Some(*method),
+        let entry = self.get_inline_cache_entry(cache_slot);
+        match entry
+            .classes
+            .iter()
+            .find(|(class, _, _)| class.id() == rec_class.id())
+        {
+            Some((class, serial, method)) if class.as_module().unwrap().serial == *serial => {
+                Some(*method)
+            }
             _ => None,
         }
     }
 }
 
+impl Globals {
+    pub fn add_ivar_cache_entry(&mut self) -> u32 {
+        self.ivar_cache.add_entry()
+    }
+
+    /// Get the entry for the object with `shape` from the inline instance variable cache.
+    pub fn get_ivar_cache_entry(&self, cache_slot: u32, shape: ShapeRef) -> Option<IvarCacheEntry> {
+        match self.ivar_cache.get_entry(cache_slot) {
+            Some(entry) if entry.shape == shape => Some(*entry),
+            _ => None,
+        }
+    }
+
+    pub fn set_ivar_cache_entry(
+        &mut self,
+        cache_slot: u32,
+        shape: ShapeRef,
+        new_shape: ShapeRef,
+        index: usize,
+    ) {
+        self.ivar_cache.table[cache_slot as usize] = Some(IvarCacheEntry {
+            shape,
+            new_shape,
+            index,
+        });
+    }
+}
+
 impl Globals {
     pub fn add_method_cache_entry(&mut self, class: Value, id: IdentId, method: MethodRef) {
-        self.method_cache
-            .add_entry(class, id, self.class_version, method);
+        let serial = class.as_module().unwrap().serial;
+        self.method_cache.add_entry(class, id, serial, method);
     }
 
     pub fn get_method_cache_entry(&self, class: Value, id: IdentId) -> Option<&MethodCacheEntry> {
@@ -315,7 +407,8 @@ pub struct MethodCache(HashMap<(Value, IdentId), MethodCacheEntry>);
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct MethodCacheEntry {
     pub method: MethodRef,
-    pub version: usize,
+    /// The serial of the class when the entry was added.
+    pub serial: usize,
 }
 
 impl MethodCache {
@@ -323,9 +416,9 @@ impl MethodCache {
         MethodCache(HashMap::new())
     }
 
-    fn add_entry(&mut self, class: Value, id: IdentId, version: usize, method: MethodRef) {
+    fn add_entry(&mut self, class: Value, id: IdentId, serial: usize, method: MethodRef) {
         self.0
-            .insert((class, id), MethodCacheEntry { method, version });
+            .insert((class, id), MethodCacheEntry { method, serial });
     }
 
     fn get_entry(&self, class: Value, id: IdentId) -> Option<&MethodCacheEntry> {
@@ -337,21 +430,25 @@ impl MethodCache {
 //
 //  Inline method cache
 //  This module supports inline method cache which is embedded in the instruction sequence directly.
+//  Each call site caches the methods for up to INLINE_CACHE_SIZE receiver classes.
 //
 //-------------------------------------------------------------------------------------------------------------
 
+pub const INLINE_CACHE_SIZE: usize = 4;
+
 #[derive(Debug, Clone)]
 pub struct InlineCache {
-    table: Vec<Option<InlineCacheEntry>>,
+    table: Vec<InlineCacheEntry>,
     id: u32,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct InlineCacheEntry {
-    class: Value,
-    version: usize,
-    //is_class_method: bool,
-    method: MethodRef,
+    /// Receiver classes, their serials when cached, and the methods found for them.
+    classes: Vec<(Value, usize, MethodRef)>,
+    /// Set when the call site has seen more than INLINE_CACHE_SIZE classes.
+    /// Methods are looked up in the global method cache instead.
+    megamorphic: bool,
 }
 
 impl InlineCache {
@@ -361,13 +458,56 @@ impl InlineCache {
             id: 0,
         }
     }
+    fn add_entry(&mut self) -> u32 {
+        self.id += 1;
+        self.table.push(InlineCacheEntry::default());
+        self.id - 1
+    }
+
+    fn get_entry(&self, id: u32) -> &InlineCacheEntry {
+        &self.table[id as usize]
+    }
+}
+
+//-------------------------------------------------------------------------------------------------------------
+//
+//  Inline instance variable cache
+//  This module supports inline caches for instance variable access keyed on the shape of objects.
+//
+//-------------------------------------------------------------------------------------------------------------
+
+#[derive(Debug, Clone)]
+pub struct IvarCache {
+    table: Vec<Option<IvarCacheEntry>>,
+    id: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct IvarCacheEntry {
+    /// The shape of the receiver.
+    pub shape: ShapeRef,
+    /// The shape of the receiver after the assignment.
+    /// This differs from `shape` when the assignment adds a new instance variable.
+    pub new_shape: ShapeRef,
+    /// The slot index of the instance variable.
+    pub index: usize,
+}
+
+impl IvarCache {
+    fn new() -> Self {
+        IvarCache {
+            table: vec![],
+            id: 0,
+        }
+    }
+
     fn add_entry(&mut self) -> u32 {
         self.id += 1;
         self.table.push(None);
         self.id - 1
     }
 
-    fn get_entry(&self, id: u32) -> &Option<InlineCacheEntry> {
+    fn get_entry(&self, id: u32) -> &Option<IvarCacheEntry> {
         &self.table[id as usize]
     }
 }
@@ -11,12 +11,16 @@ pub mod test;
 pub mod util;
 pub mod value;
 pub mod vm;
+pub use crate::builtin::complex::ComplexInfo;
 pub use crate::builtin::enumerator::*;
 pub use crate::builtin::fiber::*;
+pub use crate::builtin::io::{IOInfo, IORef};
 pub use crate::builtin::procobj::*;
 pub use crate::builtin::range::*;
+pub use crate::builtin::rational::{RationalInfo, Real};
 pub use crate::builtin::regexp::*;
 pub use crate::builtin::string::RString;
+pub use crate::builtin::time::TimeInfo;
 pub use crate::error::*;
 pub use crate::globals::*;
 pub use crate::parse::parser::{LvarCollector, LvarId, ParseResult, Parser};
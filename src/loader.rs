@@ -1,5 +1,7 @@
+use crate::vm::ISeqCache;
 use std::fs::*;
 use std::io::Read;
+use std::path::Path;
 
 pub enum LoadError {
     NotFound(String),
@@ -33,3 +35,15 @@ pub fn load_file(file_name: impl Into<String>) -> Result<(std::path::PathBuf, St
 
     Ok((absolute_path, file_body))
 }
+
+/// Read the compiled ISeq cache (.rbc) of the source file at `path`,
+/// if it exists and is newer than the source file.
+pub fn load_cache(path: &Path) -> Option<Vec<u8>> {
+    let cache_path = ISeqCache::cache_path(path);
+    let source_time = metadata(path).ok()?.modified().ok()?;
+    let cache_time = metadata(&cache_path).ok()?.modified().ok()?;
+    if cache_time <= source_time {
+        return None;
+    }
+    read(cache_path).ok()
+}
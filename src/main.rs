@@ -5,7 +5,9 @@ extern crate ruruby;
 extern crate rustyline;
 
 use clap::{App, AppSettings, Arg};
+use ruruby::builtin::errorobj;
 use ruruby::loader::{load_file, LoadError};
+use std::path::PathBuf;
 use std::thread;
 mod repl;
 use repl::*;
@@ -17,6 +19,39 @@ fn main() {
         .author("monochrome")
         .about("A toy Ruby interpreter")
         .setting(AppSettings::TrailingVarArg)
+        .arg(
+            Arg::from_usage("--dump=[TYPE] 'Dump debug information and exit'")
+                .possible_values(&["insns"]),
+        )
+        .arg(Arg::from_usage(
+            "--profile=[FILE] 'Sample call stacks every millisecond and write them in folded format to FILE'",
+        ))
+        .arg(Arg::from_usage(
+            "--coverage=[FILE] 'Measure code coverage and write it to FILE in LCOV format'",
+        ))
+        .arg(
+            Arg::from_usage(
+                "--coverage-mode=[MODE] 'Kinds of coverage to measure (comma-separated)'",
+            )
+            .possible_values(&["lines", "branches", "methods", "all"])
+            .use_delimiter(true),
+        )
+        .arg(Arg::from_usage("--debug 'Run the script in the debugger'"))
+        .arg(Arg::from_usage(
+            "--jit 'Compile hot methods into native code (x86-64 only)'",
+        ))
+        .arg(Arg::from_usage(
+            "--jit-threshold=[N] 'Number of calls after which a method is compiled by the JIT'",
+        ))
+        .arg(Arg::from_usage(
+            "--jit-stats 'Print statistics of the JIT compiler to stderr at exit'",
+        ))
+        .arg(Arg::from_usage(
+            "--compile 'Compile the file to ISeq cache (.rbc) and exit'",
+        ))
+        .arg(Arg::from_usage(
+            "-o, --output=[FILE] 'Write the ISeq cache to FILE instead of <file>.rbc'",
+        ))
         .arg(Arg::from_usage("[file]... 'Input file name'").multiple(true));
     let m = app.get_matches();
     let args: Vec<&str> = match m.values_of("file") {
@@ -26,6 +61,10 @@ fn main() {
             return;
         }
     };
+    if m.is_present("profile") && m.is_present("coverage") {
+        eprintln!("--profile and --coverage cannot be used together.");
+        return;
+    }
     let mut vm = VMRef::new(VM::new());
     let id = vm.globals.get_ident_id("ARGV");
     let mut res: Vec<Value> = args
@@ -35,26 +74,154 @@ fn main() {
     res.remove(0);
     let argv = Value::array_from(&vm.globals, res);
     vm.globals.builtins.object.set_var(id, argv);
-    exec_file(&mut vm, args[0]);
+    if m.is_present("debug") {
+        vm.debugger = Some(Debugger::new(Box::new(DebuggerRepl::new())));
+    }
+    if m.is_present("jit") || m.is_present("jit-stats") || m.is_present("jit-threshold") {
+        let threshold = match m.value_of("jit-threshold").map(|n| n.parse::<usize>()) {
+            Some(Ok(n)) if n > 0 => n,
+            Some(_) => {
+                eprintln!("--jit-threshold must be a positive integer.");
+                return;
+            }
+            None => JIT_THRESHOLD,
+        };
+        vm.jit = Some(Jit::new(threshold));
+    }
+    if m.is_present("compile") {
+        // `-o` after the file name is taken as a trailing argument.
+        let out = m.value_of("output").or_else(|| {
+            let i = args.iter().position(|arg| *arg == "-o" || *arg == "--output")?;
+            args.get(i + 1).cloned()
+        });
+        compile_file(&mut vm, args[0], out);
+        return;
+    }
+    match m.value_of("dump") {
+        Some("insns") => dump_insns(&mut vm, args[0]),
+        _ => match (m.value_of("profile"), m.value_of("coverage")) {
+            (Some(out), _) => profile_file(&mut vm, args[0], out),
+            (None, Some(out)) => {
+                let modes: Vec<&str> = match m.values_of("coverage-mode") {
+                    Some(modes) => modes.collect(),
+                    None => vec!["lines"],
+                };
+                let mode = CoverageMode {
+                    legacy: false,
+                    lines: true,
+                    branches: modes.contains(&"branches") || modes.contains(&"all"),
+                    methods: modes.contains(&"methods") || modes.contains(&"all"),
+                };
+                coverage_file(&mut vm, args[0], out, mode)
+            }
+            (None, None) => exec_file(&mut vm, args[0]),
+        },
+    }
+    if m.is_present("jit-stats") {
+        if let Some(jit) = &vm.jit {
+            eprint!("{}", jit.summary());
+        }
+    }
     return;
 }
 
+fn load(file_name: &str) -> Option<(std::path::PathBuf, String)> {
+    match load_file(file_name.to_string()) {
+        Ok((path, program)) => Some((path, program)),
+        Err(err) => {
+            match err {
+                LoadError::NotFound(msg) => {
+                    eprintln!("No such file or directory --- {} (LoadError)", file_name);
+                    eprintln!("{}", msg);
+                }
+                LoadError::CouldntOpen(msg) => {
+                    eprintln!("Cannot open file. '{}'", file_name);
+                    eprintln!("{}", msg);
+                }
+            };
+            None
+        }
+    }
+}
+
+/// Compile the file and print ISeqs of the top level and all methods, blocks and class bodies.
+fn dump_insns(vm: &mut VMRef, file_name: &str) {
+    let (absolute_path, program) = match load(file_name) {
+        Some(res) => res,
+        None => return,
+    };
+    match vm.parse_program(absolute_path, &program) {
+        Ok(method) => {
+            let entry = ISeqEntry::new(method, "<main>", "top");
+            print!("{}", Disasm::disasm(&vm.globals, &entry));
+        }
+        Err(err) => {
+            err.show_err();
+            err.show_loc(0);
+        }
+    }
+}
+
+/// Compile the file and write the ISeq cache to `out`, or `<file>.rbc` if omitted.
+fn compile_file(vm: &mut VMRef, file_name: &str, out: Option<&str>) {
+    let (absolute_path, program) = match load(file_name) {
+        Some(res) => res,
+        None => return,
+    };
+    let out = match out {
+        Some(out) => PathBuf::from(out),
+        None => ISeqCache::cache_path(std::path::Path::new(file_name)),
+    };
+    match vm.parse_program(absolute_path, &program) {
+        Ok(method) => {
+            let bytes = ISeqCache::dump(&vm.globals, method);
+            if let Err(err) = std::fs::write(&out, bytes) {
+                eprintln!("Cannot write ISeq cache to '{}'. {}", out.to_string_lossy(), err);
+            }
+        }
+        Err(err) => {
+            err.show_err();
+            err.show_loc(0);
+        }
+    }
+}
+
+/// Execute the file with the profiler enabled, then write the folded call stacks
+/// to `out` and print the summary to stderr.
+fn profile_file(vm: &mut VMRef, file_name: &str, out: &str) {
+    vm.profiler = Some(Profiler::new());
+    exec_file(vm, file_name);
+    let profiler = vm.profiler.take().unwrap();
+    if let Err(err) = std::fs::write(out, profiler.folded()) {
+        eprintln!("Cannot write profile to '{}'. {}", out, err);
+    }
+    eprint!("{}", profiler.summary());
+}
+
+/// Execute the file measuring code coverage, then write the LCOV file to `out`
+/// and print the summary to stderr.
+fn coverage_file(vm: &mut VMRef, file_name: &str, out: &str, mode: CoverageMode) {
+    vm.globals.coverage = Some(Coverage::new(mode));
+    exec_file(vm, file_name);
+    let coverage = match vm.globals.coverage.take() {
+        Some(coverage) => coverage,
+        // Stopped by `Coverage.result` in the script.
+        None => return,
+    };
+    if let Err(err) = std::fs::write(out, coverage.lcov(&vm.globals)) {
+        eprintln!("Cannot write coverage to '{}'. {}", out, err);
+    }
+    eprint!("{}", coverage.summary());
+}
+
 fn exec_file(vm: &mut VMRef, file_name: impl Into<String>) {
     let file_name = file_name.into();
-    let (absolute_path, program) = match load_file(file_name.clone()) {
-        Ok((path, program)) => (path, program),
-        Err(err) => match err {
-            LoadError::NotFound(msg) => {
-                eprintln!("No such file or directory --- {} (LoadError)", &file_name);
-                eprintln!("{}", msg);
-                return;
-            }
-            LoadError::CouldntOpen(msg) => {
-                eprintln!("Cannot open file. '{}'", &file_name);
-                eprintln!("{}", msg);
-                return;
-            }
-        },
+    if file_name.ends_with(".rbc") {
+        return exec_cache_file(vm, &file_name);
+    }
+    let (absolute_path, program) = match load(&file_name) {
+        Some(res) => res,
+        None => return,
     };
 
     let root_path = absolute_path.clone();
@@ -62,18 +229,64 @@ fn exec_file(vm: &mut VMRef, file_name: impl Into<String>) {
     eprintln!("load file: {:?}", root_path);
     vm.root_path.push(root_path);
     let mut vm2 = vm.clone();
-    let res = thread::spawn(move || vm2.run(absolute_path, &program, None))
-        .join()
-        .unwrap();
-    match res {
-        Ok(_) => {}
+    let res = thread::spawn(move || {
+        let res = vm2.compile_file(absolute_path, &program).and_then(|method| {
+            // Backtraces show the path of the main script as given, like MRI.
+            let mut source_info = vm2.get_iseq(method)?.source_info;
+            source_info.given_path = Some(PathBuf::from(file_name));
+            vm2.run_method(method, None)
+        });
+        vm2.run_at_exit().and(res)
+    })
+    .join()
+    .unwrap();
+    show_result(vm, res);
+    vm.root_path.pop();
+}
+
+/// Execute the ISeq cache file compiled by `--compile`.
+fn exec_cache_file(vm: &mut VMRef, file_name: &str) {
+    let bytes = match std::fs::read(file_name) {
+        Ok(bytes) => bytes,
         Err(err) => {
-            err.show_err();
-            for i in 0..err.info.len() {
-                eprint!("{}:", i);
-                err.show_loc(i);
-            }
+            eprintln!("Cannot open file. '{}'", file_name);
+            eprintln!("{}", err);
+            return;
         }
     };
+    let method = match ISeqCache::load(&mut vm.globals, &bytes, None) {
+        Ok(method) => method,
+        Err(err) => {
+            eprintln!("Cannot load ISeq cache '{}'. {}", file_name, err);
+            return;
+        }
+    };
+    let iseq = vm.get_iseq(method).unwrap();
+    vm.root_path.push(iseq.source_info.path.clone());
+    let mut vm2 = vm.clone();
+    let res = thread::spawn(move || {
+        let res = vm2.run_method(method, None);
+        vm2.run_at_exit().and(res)
+    })
+    .join()
+    .unwrap();
+    show_result(vm, res);
     vm.root_path.pop();
 }
+
+/// Report the error which was not rescued in the program.
+fn show_result(vm: &mut VMRef, res: VMResult) {
+    match res {
+        Ok(_) => {}
+        Err(err) => match err.kind {
+            RubyErrorKind::ParseErr(_) => {
+                err.show_err();
+                err.show_loc(0);
+            }
+            _ => {
+                let exception = vm.exception_from_error(&err);
+                eprint!("{}", errorobj::full_message_string(vm, exception));
+            }
+        },
+    };
+}
@@ -15,12 +15,15 @@ pub struct Lexer {
     quote_state: Vec<QuoteState>,
     pub source_info: SourceInfoRef,
     state_save: Vec<(u32, u32)>, // (token_start_pos, pos)
+    /// The text after `__END__`, if any.
+    pub data: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 enum QuoteState {
     DoubleQuote,
     RegEx,
+    Command(char),
     Brace,
     //Expr,
 }
@@ -55,6 +58,7 @@ impl Lexer {
             "BEGIN" => Reserved::BEGIN,
             "END" => Reserved::END,
             "alias" => Reserved::Alias,
+            "and" => Reserved::And,
             "begin" => Reserved::Begin,
             "break" => Reserved::Break,
             "case" => Reserved::Case,
@@ -73,11 +77,16 @@ impl Lexer {
             "module" => Reserved::Module,
             "next" => Reserved::Next,
             "nil" => Reserved::Nil,
+            "not" => Reserved::Not,
+            "or" => Reserved::Or,
+            "redo" => Reserved::Redo,
             "return" => Reserved::Return,
             "rescue" => Reserved::Rescue,
+            "retry" => Reserved::Retry,
             "self" => Reserved::Self_,
             "then" => Reserved::Then,
             "true" => Reserved::True,
+            "undef" => Reserved::Undef,
             "until" => Reserved::Until,
             "unless" => Reserved::Unless,
             "when" => Reserved::When,
@@ -95,6 +104,7 @@ impl Lexer {
             quote_state: vec![],
             source_info: SourceInfoRef::new(SourceInfo::new(std::path::PathBuf::default())),
             state_save: vec![],
+            data: None,
         }
     }
 
@@ -216,6 +226,9 @@ impl Lexer {
         let state = self.state_save.pop().unwrap();
         self.token_start_pos = state.0;
         self.pos = state.1;
+        // Peeked tokens are no longer valid.
+        self.buf = None;
+        self.buf_skip_lt = None;
     }
 
     pub fn discard_state(&mut self) {
@@ -237,7 +250,9 @@ impl Lexer {
                 Err(_) => return Ok(self.new_eof(self.pos)),
             };
 
-            if ch.is_ascii_alphabetic() || ch == '_' {
+            if ch == '_' && self.is_end_of_program(pos) {
+                return Ok(self.new_eof(pos));
+            } else if ch.is_ascii_alphabetic() || ch == '_' {
                 return self.lex_identifier(ch, VarKind::Identifier);
             } else if ch.is_numeric() {
                 return self.lex_number_literal(ch);
@@ -313,6 +328,10 @@ impl Lexer {
                     '}' => match self.quote_state.last() {
                         Some(QuoteState::DoubleQuote) => return self.lex_interpolate_string(),
                         Some(QuoteState::RegEx) => return self.lex_interpolate_regexp(),
+                        Some(QuoteState::Command(term)) => {
+                            let term = *term;
+                            return self.lex_interpolate_command(term);
+                        }
                         Some(QuoteState::Brace) => return Ok(self.new_punct(Punct::RBrace)),
                         _ => return Err(self.error_unexpected(pos)),
                     },
@@ -329,6 +348,7 @@ impl Lexer {
                     }
                     '?' => return Ok(self.new_punct(Punct::Question)),
                     '\\' => return Ok(self.new_punct(Punct::Backslash)),
+                    '`' => return Ok(self.new_punct(Punct::BackQuote)),
                     '=' => {
                         if self.consume('=') {
                             if self.consume('=') {
@@ -383,9 +403,15 @@ impl Lexer {
                     }
                     '&' => {
                         if self.consume('&') {
-                            return Ok(self.new_punct(Punct::LAnd));
+                            if self.consume('=') {
+                                return Ok(self.new_punct(Punct::AssignOp(BinOp::LAnd)));
+                            } else {
+                                return Ok(self.new_punct(Punct::LAnd));
+                            }
                         } else if self.consume('=') {
                             return Ok(self.new_punct(Punct::AssignOp(BinOp::BitAnd)));
+                        } else if self.consume('.') {
+                            return Ok(self.new_punct(Punct::SafeNav));
                         } else {
                             return Ok(self.new_punct(Punct::BitAnd));
                         }
@@ -437,7 +463,11 @@ impl Lexer {
             None => {
                 match self.get() {
                     Ok(ch) => {
-                        if ch.is_alphanumeric() || ch == '_' || ch == '&' || ch == '\'' {
+                        if var_kind == VarKind::GlobalVar && "?!$~;,./\\<>*:\"@".contains(ch) {
+                            // Special global variables: $?, $!, $$, ..
+                            tok.push(ch);
+                            return Ok(self.new_global_var(tok));
+                        } else if ch.is_alphanumeric() || ch == '_' || ch == '&' || ch == '\'' {
                             tok.push(ch);
                         } else {
                             return Err(self.error_unexpected(self.pos));
@@ -487,6 +517,10 @@ impl Lexer {
                     match self.peek() {
                         Ok(ch) if ch == '!' || ch == '?' => {
                             tok.push(self.get()?);
+                            // `defined?`
+                            if let Some(reserved) = self.reserved.get(&tok) {
+                                return Ok(self.new_reserved(*reserved));
+                            }
                         }
                         _ => {}
                     };
@@ -512,6 +546,7 @@ impl Lexer {
         };
         let mut s = ch.to_string();
         let mut decimal_flag = false;
+        let mut exp_flag = false;
         loop {
             if let Some(ch) = self.consume_numeric() {
                 s.push(ch);
@@ -548,21 +583,64 @@ impl Lexer {
                     break;
                 }
             }
-            decimal_flag = true;
+            exp_flag = true;
         }
-        if decimal_flag {
+        let kind = if !exp_flag && self.consume_number_suffix('r') {
+            // `1.5r` is read as 15/10.
+            let (int, frac) = match s.find('.') {
+                Some(i) => (&s[..i], &s[i + 1..]),
+                None => (&s[..], ""),
+            };
+            let num = format!("{}{}", int, frac).parse::<i64>();
+            let den = 10i64.checked_pow(frac.len() as u32);
+            match (num, den) {
+                (Ok(num), Some(den)) => TokenKind::RationalLit(num, den),
+                _ => return Err(self.error_parse("Rational literal is too big.", self.pos)),
+            }
+        } else if decimal_flag || exp_flag {
             match s.parse::<f64>() {
-                Ok(f) => Ok(self.new_floatlit(f)),
-                Err(err) => Err(self.error_parse(&format!("{:?}", err), self.pos)),
+                Ok(f) => TokenKind::FloatLit(f),
+                Err(err) => return Err(self.error_parse(&format!("{:?}", err), self.pos)),
             }
         } else {
             match s.parse::<i64>() {
-                Ok(i) => Ok(self.new_numlit(i)),
-                Err(err) => Err(self.error_parse(&format!("{:?}", err), self.pos)),
+                Ok(i) => TokenKind::NumLit(i),
+                Err(err) => return Err(self.error_parse(&format!("{:?}", err), self.pos)),
             }
+        };
+        if self.consume_number_suffix('i') {
+            return Ok(self.new_imaginarylit(kind));
+        }
+        match kind {
+            TokenKind::NumLit(i) => Ok(self.new_numlit(i)),
+            TokenKind::FloatLit(f) => Ok(self.new_floatlit(f)),
+            TokenKind::RationalLit(num, den) => Ok(self.new_rationallit(num, den)),
+            _ => unreachable!(),
         }
     }
 
+    /// Consume a suffix `ch` of number literal (e.g. `r` of `3r`),
+    /// if the suffixes are not followed by an identifier char.
+    fn consume_number_suffix(&mut self, ch: char) -> bool {
+        let pos = self.pos as usize;
+        if pos >= self.len || self.source_info.code[pos] != ch {
+            return false;
+        }
+        let mut next = pos + 1;
+        // `r` may be followed by `i` as `3ri`.
+        if ch == 'r' && next < self.len && self.source_info.code[next] == 'i' {
+            next += 1;
+        }
+        if next < self.len {
+            let next = self.source_info.code[next];
+            if next.is_ascii_alphanumeric() || next == '_' {
+                return false;
+            }
+        }
+        self.pos += 1;
+        true
+    }
+
     fn lex_hex_number(&mut self) -> Result<Token, RubyError> {
         let mut val = match self.get() {
             Ok(ch @ '0'..='9') => (ch as u64 - '0' as u64),
@@ -715,6 +793,57 @@ impl Lexer {
         }
     }
 
+    /// Read command literal (`cmd` or %x{cmd}) terminated by `term`.
+    pub fn lex_command(&mut self, term: char) -> Result<Token, RubyError> {
+        let mut s = "".to_string();
+        loop {
+            match self.get()? {
+                c if c == term => return Ok(self.new_stringlit(s)),
+                '\\' => s.push(self.read_escaped_char()?),
+                '#' => {
+                    if self.consume('{') {
+                        self.quote_state.push(QuoteState::Command(term));
+                        return Ok(self.new_open_dq(s));
+                    } else {
+                        s.push('#');
+                    }
+                }
+                c => s.push(c),
+            }
+        }
+    }
+
+    fn lex_interpolate_command(&mut self, term: char) -> Result<Token, RubyError> {
+        let mut s = "".to_string();
+        loop {
+            match self.get()? {
+                c if c == term => return Ok(self.new_close_dq(s)),
+                '\\' => s.push(self.read_escaped_char()?),
+                '#' => {
+                    if self.consume('{') {
+                        return Ok(self.new_inter_dq(s));
+                    } else {
+                        s.push('#');
+                    }
+                }
+                c => s.push(c),
+            }
+        }
+    }
+
+    /// Read %x{cmd} / %x(cmd) / %x[cmd] / %x|cmd| command literal.
+    fn lex_percent_command(&mut self) -> Result<Token, RubyError> {
+        let term = match self.get()? {
+            '{' => '}',
+            '(' => ')',
+            '[' => ']',
+            '<' => '>',
+            c if c.is_ascii_punctuation() => c,
+            _ => return Err(self.error_unexpected(self.pos - 1)),
+        };
+        self.lex_command(term)
+    }
+
     pub fn lex_percent_notation(&mut self) -> Result<Token, RubyError> {
         if self.consume('w') {
             let mut s = "".to_string();
@@ -727,6 +856,8 @@ impl Lexer {
                     ch => s.push(ch),
                 }
             }
+        } else if self.consume('x') {
+            self.lex_percent_command()
         } else {
             return Err(self.error_unexpected(self.pos));
         }
@@ -856,6 +987,29 @@ impl Lexer {
         }
     }
 
+    /// Check whether `__END__` at `pos` stands alone on a line.
+    /// If so, save the rest of the source as the data section and skip to the end of the source.
+    fn is_end_of_program(&mut self, pos: u32) -> bool {
+        let pos = pos as usize;
+        let code = &self.source_info.code;
+        if pos != 0 && code[pos - 1] != '\n' {
+            return false;
+        }
+        let end = pos + "__END__".len();
+        if end > self.len || code[pos..end].iter().collect::<String>() != "__END__" {
+            return false;
+        }
+        let data_start = match code.get(end) {
+            None => end,
+            Some('\n') => end + 1,
+            Some('\r') if code.get(end + 1) == Some(&'\n') => end + 2,
+            _ => return false,
+        };
+        self.data = Some(code[data_start..self.len].iter().collect());
+        self.pos = self.len as u32;
+        true
+    }
+
     fn goto_eol(&mut self) {
         loop {
             match self.peek() {
@@ -903,6 +1057,14 @@ impl Lexer {
         Token::new_floatlit(num, self.cur_loc())
     }
 
+    fn new_rationallit(&self, num: i64, den: i64) -> Token {
+        Token::new_rationallit(num, den, self.cur_loc())
+    }
+
+    fn new_imaginarylit(&self, num: TokenKind) -> Token {
+        Token::new_imaginarylit(num, self.cur_loc())
+    }
+
     fn new_stringlit(&self, string: impl Into<String>) -> Token {
         Annot::new(TokenKind::StringLit(string.into()), self.cur_loc())
     }
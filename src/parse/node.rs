@@ -7,6 +7,8 @@ pub enum NodeKind {
     Nil,
     Integer(i64),
     Float(f64),
+    Rational(i64, i64),
+    Imaginary(Box<Node>),
     Bool(bool),
     String(String),
     InterporatedString(Vec<Node>),
@@ -38,8 +40,12 @@ pub enum NodeKind {
     },
     Splat(Box<Node>),
     Assign(Box<Node>, Box<Node>),
+    /// `lhs op= rhs` for an attribute or an index, evaluating the receiver only once.
     AssignOp(BinOp, Box<Node>, Box<Node>),
+    /// `mlhs = mrhs`. A target in `mlhs` may be `Splat(target)` or a nested `Mlhs`.
     MulAssign(Vec<Node>, Vec<Node>),
+    /// A nested target of multiple assignment, e.g. `(b, c)` in `a, (b, c) = ...`.
+    Mlhs(Vec<Node>),
 
     CompStmt(NodeVec),
     If {
@@ -55,15 +61,23 @@ pub enum NodeKind {
     While {
         cond: Box<Node>,
         body: Box<Node>,
+        /// `begin ... end while cond`: the body is executed before the first check.
+        do_while: bool,
     },
     Case {
         cond: Box<Node>,
         when_: Vec<CaseBranch>,
         else_: Box<Node>,
     },
+    /// `case/in`. NoMatchingPatternError is raised if no pattern matches and `else_` is None.
+    CaseIn {
+        cond: Box<Node>,
+        in_: Vec<InBranch>,
+        else_: Option<Box<Node>>,
+    },
     Begin {
         body: Box<Node>,
-        rescue: Vec<(Node, Node)>, // (ex_class_list, ex_param)
+        rescue: Vec<RescueEntry>,
         else_: Box<Node>,
         ensure: Box<Node>,
     },
@@ -74,14 +88,25 @@ pub enum NodeKind {
     },
     Break(Box<Node>),
     Next(Box<Node>),
+    Redo,
+    /// `retry` in a rescue clause.
+    Retry,
     Return(Box<Node>),
     Yield(SendArgs),
+    /// `alias new old`. Global variables are aliased if the names begin with `$`.
+    Alias(IdentId, IdentId),
+    Undef(Vec<IdentId>),
+    /// `defined?(expr)`
+    Defined(Box<Node>),
 
     Param(IdentId),
     PostParam(IdentId),
     OptionalParam(IdentId, Box<Node>),
     RestParam(IdentId),
     KeywordParam(IdentId, Box<Option<Node>>),
+    KWRestParam(IdentId),
+    /// `**nil`, which declares that the method accepts no keyword arguments.
+    NoKWParam,
     BlockParam(IdentId),
 
     MethodDef(IdentId, NodeVec, Box<Node>, LvarCollector), // id, params, body
@@ -98,6 +123,8 @@ pub enum NodeKind {
         method: IdentId,
         send_args: SendArgs,
         completed: bool,
+        /// `receiver&.method`
+        safe_nav: bool,
     }, //receiver, method_name, args
 }
 
@@ -105,6 +132,8 @@ pub enum NodeKind {
 pub struct SendArgs {
     pub args: NodeVec,
     pub kw_args: Vec<(IdentId, Node)>,
+    /// `**hash` arguments, which are merged into the keyword arguments after `kw_args`.
+    pub kw_splat: NodeVec,
     pub block: Option<Box<Node>>,
 }
 
@@ -113,6 +142,7 @@ impl SendArgs {
         SendArgs {
             args: vec![],
             kw_args: vec![],
+            kw_splat: vec![],
             block: None,
         }
     }
@@ -133,6 +163,87 @@ impl CaseBranch {
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct InBranch {
+    pub pattern: Pattern,
+    /// The guard clause. `unless` guards are negated by the parser.
+    pub guard: Option<Box<Node>>,
+    pub body: Box<Node>,
+}
+
+impl InBranch {
+    pub fn new(pattern: Pattern, guard: Option<Node>, body: Node) -> Self {
+        InBranch {
+            pattern,
+            guard: guard.map(Box::new),
+            body: Box::new(body),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pattern {
+    /// Matches if `node === value`. Literals, ranges, constants and pinned expressions.
+    Value(Node),
+    /// Binds the value to the local variable.
+    Bind(IdentId),
+    /// `pat | pat`
+    Alt(Vec<Pattern>),
+    /// `pat => var`
+    As(Box<Pattern>, IdentId),
+    /// `[pre, *rest, post]` or `Const(pre, *rest, post)`.
+    Array {
+        constant: Option<Box<Node>>,
+        pre: Vec<Pattern>,
+        rest: PatternRest,
+        post: Vec<Pattern>,
+    },
+    /// `[*pre, mid, *post]` or `Const(*pre, mid, *post)`.
+    Find {
+        constant: Option<Box<Node>>,
+        pre: Option<IdentId>,
+        mid: Vec<Pattern>,
+        post: Option<IdentId>,
+    },
+    /// `{key: pat, **rest}` or `Const(key: pat, **rest)`. A key without a pattern binds the value
+    /// to the local variable of the same name.
+    Hash {
+        constant: Option<Box<Node>>,
+        pairs: Vec<(IdentId, Option<Pattern>)>,
+        rest: PatternRest,
+    },
+}
+
+/// The rest of an array pattern (`*rest`) or a hash pattern (`**rest`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PatternRest {
+    None,
+    /// `*` or `**` without a name.
+    Anonymous,
+    Bind(IdentId),
+    /// `**nil`. The hash must not have any other keys.
+    Nil,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RescueEntry {
+    /// The exception classes to be rescued. Empty for StandardError.
+    pub exception_list: Vec<Node>,
+    /// The target of `=> LHS`.
+    pub assign: Option<Box<Node>>,
+    pub body: Box<Node>,
+}
+
+impl RescueEntry {
+    pub fn new(exception_list: Vec<Node>, assign: Option<Node>, body: Node) -> Self {
+        RescueEntry {
+            exception_list,
+            assign: assign.map(Box::new),
+            body: Box::new(body),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum BinOp {
     Add,
@@ -185,6 +296,14 @@ impl Node {
         Node::new(NodeKind::Float(num), loc)
     }
 
+    pub fn new_rational(num: i64, den: i64, loc: Loc) -> Self {
+        Node::new(NodeKind::Rational(num, den), loc)
+    }
+
+    pub fn new_imaginary(num: Node, loc: Loc) -> Self {
+        Node::new(NodeKind::Imaginary(Box::new(num)), loc)
+    }
+
     pub fn new_string(s: String, loc: Loc) -> Self {
         Node::new(NodeKind::String(s), loc)
     }
@@ -238,6 +357,10 @@ impl Node {
         Node::new(NodeKind::CompStmt(vec![]), loc)
     }
 
+    pub fn is_nop(&self) -> bool {
+        self.kind == NodeKind::CompStmt(vec![])
+    }
+
     pub fn new_binop(op: BinOp, lhs: Node, rhs: Node) -> Self {
         let loc = (lhs.loc()).merge(rhs.loc());
         let kind = NodeKind::BinOp(op, Box::new(lhs), Box::new(rhs));
@@ -287,6 +410,14 @@ impl Node {
         Node::new(NodeKind::KeywordParam(id, Box::new(default)), loc)
     }
 
+    pub fn new_kwrest_param(id: IdentId, loc: Loc) -> Self {
+        Node::new(NodeKind::KWRestParam(id), loc)
+    }
+
+    pub fn new_no_kw_param(loc: Loc) -> Self {
+        Node::new(NodeKind::NoKWParam, loc)
+    }
+
     pub fn new_block_param(id: IdentId, loc: Loc) -> Self {
         Node::new(NodeKind::BlockParam(id), loc)
     }
@@ -320,13 +451,44 @@ impl Node {
         Node::new(NodeKind::MulAssign(lhs, rhs), loc)
     }
 
+    pub fn new_mlhs(mlhs: Vec<Node>, loc: Loc) -> Self {
+        Node::new(NodeKind::Mlhs(mlhs), loc)
+    }
+
+    pub fn new_assign_op(op: BinOp, lhs: Node, rhs: Node) -> Self {
+        let loc = lhs.loc().merge(rhs.loc());
+        Node::new(NodeKind::AssignOp(op, Box::new(lhs), Box::new(rhs)), loc)
+    }
+
+    /// Whether `self` is an attribute (`receiver.name`) or an index (`receiver[index]`).
+    pub fn is_attr_or_index(&self) -> bool {
+        match &self.kind {
+            NodeKind::Send {
+                send_args,
+                completed,
+                ..
+            } => !completed && send_args.args.is_empty(),
+            NodeKind::ArrayMember { .. } => true,
+            _ => false,
+        }
+    }
+
+    /// Make the method call a safe navigation call (`receiver&.method`) if `safe_nav` is true.
+    pub fn with_safe_nav(mut self, safe_nav: bool) -> Self {
+        if let NodeKind::Send { safe_nav: flag, .. } = &mut self.kind {
+            *flag = safe_nav;
+        }
+        self
+    }
+
     pub fn new_method_decl(
         id: IdentId,
         params: Vec<Node>,
         body: Node,
         lvar: LvarCollector,
+        loc: Loc,
     ) -> Self {
-        let loc = body.loc();
+        let loc = loc.merge(body.loc());
         Node::new(NodeKind::MethodDef(id, params, Box::new(body), lvar), loc)
     }
 
@@ -336,8 +498,9 @@ impl Node {
         params: Vec<Node>,
         body: Node,
         lvar: LvarCollector,
+        loc: Loc,
     ) -> Self {
-        let loc = body.loc();
+        let loc = loc.merge(body.loc());
         Node::new(
             NodeKind::SingletonMethodDef(Box::new(singleton), id, params, Box::new(body), lvar),
             loc,
@@ -382,6 +545,7 @@ impl Node {
                 method,
                 send_args,
                 completed,
+                safe_nav: false,
             },
             loc,
         )
@@ -391,6 +555,7 @@ impl Node {
         let send_args = SendArgs {
             args: vec![],
             kw_args: vec![],
+            kw_splat: vec![],
             block: None,
         };
         Node::new(
@@ -399,6 +564,7 @@ impl Node {
                 method,
                 send_args,
                 completed,
+                safe_nav: false,
             },
             loc,
         )
@@ -422,6 +588,19 @@ impl Node {
             NodeKind::While {
                 cond: Box::new(cond),
                 body: Box::new(body),
+                do_while: false,
+            },
+            loc,
+        )
+    }
+
+    pub fn new_do_while(cond: Node, body: Node, loc: Loc) -> Self {
+        let loc = loc.merge(body.loc());
+        Node::new(
+            NodeKind::While {
+                cond: Box::new(cond),
+                body: Box::new(body),
+                do_while: true,
             },
             loc,
         )
@@ -439,9 +618,20 @@ impl Node {
         )
     }
 
+    pub fn new_case_in(cond: Node, in_: Vec<InBranch>, else_: Option<Node>, loc: Loc) -> Self {
+        Node::new(
+            NodeKind::CaseIn {
+                cond: Box::new(cond),
+                in_,
+                else_: else_.map(Box::new),
+            },
+            loc,
+        )
+    }
+
     pub fn new_begin(
         body: Node,
-        rescue: Vec<(Node, Node)>,
+        rescue: Vec<RescueEntry>,
         else_: Node,
         ensure: Node,
         loc: Loc,
@@ -457,6 +647,13 @@ impl Node {
         )
     }
 
+    /// `body rescue rescue_body`, which rescues StandardError.
+    pub fn new_rescue_modifier(body: Node, rescue_body: Node, loc: Loc) -> Self {
+        let loc = body.loc().merge(loc).merge(rescue_body.loc());
+        let rescue = vec![RescueEntry::new(vec![], None, rescue_body)];
+        Node::new_begin(body, rescue, Node::new_nop(loc), Node::new_nop(loc), loc)
+    }
+
     pub fn new_break(val: Node, loc: Loc) -> Self {
         Node::new(NodeKind::Break(Box::new(val)), loc)
     }
@@ -469,6 +666,18 @@ impl Node {
         Node::new(NodeKind::Return(Box::new(val)), loc)
     }
 
+    pub fn new_alias(new: IdentId, old: IdentId, loc: Loc) -> Self {
+        Node::new(NodeKind::Alias(new, old), loc)
+    }
+
+    pub fn new_undef(ids: Vec<IdentId>, loc: Loc) -> Self {
+        Node::new(NodeKind::Undef(ids), loc)
+    }
+
+    pub fn new_defined(expr: Node, loc: Loc) -> Self {
+        Node::new(NodeKind::Defined(Box::new(expr)), loc)
+    }
+
     pub fn new_yield(mut args: SendArgs, loc: Loc) -> Self {
         args.args.reverse();
         Node::new(NodeKind::Yield(args), loc)
@@ -10,6 +10,8 @@ pub struct Parser {
     prev_loc: Loc,
     context_stack: Vec<Context>,
     pub ident_table: IdentifierTable,
+    /// The bodies of `BEGIN {}` blocks, which are executed before the rest of the program.
+    begin_blocks: Vec<Node>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -81,6 +83,15 @@ impl LvarCollector {
         }
     }
 
+    /// Restore a collector from the table of local variables, e.g. of a compiled ISeq cache.
+    pub fn from_table(table: HashMap<IdentId, LvarId>, block: Option<LvarId>) -> Self {
+        LvarCollector {
+            id: table.len(),
+            table,
+            block,
+        }
+    }
+
     fn insert(&mut self, val: IdentId) -> LvarId {
         match self.table.get(&val) {
             Some(id) => *id,
@@ -140,12 +151,39 @@ impl LvarCollector {
     pub fn clone_table(&self) -> HashMap<IdentId, LvarId> {
         self.table.clone()
     }
+
+    /// Renumber the local variables so that `params` come first in this order,
+    /// e.g. for the implicit block parameters found while parsing the block body.
+    fn prepend_params(&mut self, params: &[IdentId]) {
+        let mut others: Vec<(IdentId, LvarId)> = self
+            .table
+            .iter()
+            .filter(|(id, _)| !params.contains(id))
+            .map(|(id, lvar)| (*id, *lvar))
+            .collect();
+        others.sort_by_key(|(_, lvar)| lvar.0);
+        let block = self.block.and_then(|lvar| self.get_name(lvar));
+        self.table.clear();
+        self.id = 0;
+        for id in params.iter().chain(others.iter().map(|(id, _)| id)) {
+            self.insert(*id);
+        }
+        self.block = block.map(|id| self.table[&id]);
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 struct Context {
     lvar: LvarCollector,
     kind: ContextKind,
+    /// True if the block has an explicit parameter list.
+    explicit_params: bool,
+    /// The largest N of the numbered parameters `_N` used in the block.
+    numbered_params: usize,
+    /// True if the implicit parameter `it` is used in the block.
+    it_param: bool,
+    /// Assignments which destructure parameters like `(a, b)` at the beginning of the body.
+    destructured_params: Vec<Node>,
 }
 
 impl Context {
@@ -153,18 +191,30 @@ impl Context {
         Context {
             lvar: LvarCollector::new(),
             kind: ContextKind::Method,
+            explicit_params: false,
+            numbered_params: 0,
+            it_param: false,
+            destructured_params: vec![],
         }
     }
     fn new_class(lvar_collector: Option<LvarCollector>) -> Self {
         Context {
             lvar: lvar_collector.unwrap_or(LvarCollector::new()),
             kind: ContextKind::Class,
+            explicit_params: false,
+            numbered_params: 0,
+            it_param: false,
+            destructured_params: vec![],
         }
     }
     fn new_block() -> Self {
         Context {
             lvar: LvarCollector::new(),
             kind: ContextKind::Block,
+            explicit_params: false,
+            numbered_params: 0,
+            it_param: false,
+            destructured_params: vec![],
         }
     }
 }
@@ -180,9 +230,18 @@ enum ContextKind {
 struct ArgList {
     args: Vec<Node>,
     kw_args: Vec<(IdentId, Node)>,
+    kw_splat: Vec<Node>,
     block: Option<Box<Node>>,
 }
 
+/// An element of array patterns.
+#[derive(Debug, Clone, PartialEq)]
+enum PatternElem {
+    Pattern(Pattern),
+    /// `*` or `*var`
+    Splat(Option<IdentId>),
+}
+
 impl Parser {
     pub fn new() -> Self {
         let lexer = Lexer::new();
@@ -191,6 +250,7 @@ impl Parser {
             prev_loc: Loc(0, 0),
             context_stack: vec![],
             ident_table: IdentifierTable::new(),
+            begin_blocks: vec![],
         }
     }
 
@@ -236,6 +296,49 @@ impl Parser {
         Ok(())
     }
 
+    // If `name` is an implicit parameter (`_1`..`_9` or `it`) of the current block,
+    // register it in the block context and return true.
+    fn add_implicit_param(&mut self, name: &str, loc: Loc) -> Result<bool, RubyError> {
+        match self.peek_no_term()?.kind {
+            TokenKind::Punct(Punct::LParen)
+            | TokenKind::Punct(Punct::Assign)
+            | TokenKind::Punct(Punct::AssignOp(_)) => return Ok(false),
+            _ => {}
+        }
+        // Numbered parameters can not be used in both a block and a block nested in it.
+        let used_in_outer = self
+            .context_stack
+            .iter()
+            .rev()
+            .skip(1)
+            .take_while(|context| context.kind == ContextKind::Block)
+            .any(|context| context.numbered_params != 0);
+        let context = match self.context_stack.last_mut() {
+            Some(context) if context.kind == ContextKind::Block => context,
+            _ => return Ok(false),
+        };
+        let bytes = name.as_bytes();
+        if bytes.len() == 2 && bytes[0] == b'_' && (b'1'..=b'9').contains(&bytes[1]) {
+            let msg = if used_in_outer {
+                "numbered parameter is already used in outer block"
+            } else if context.explicit_params {
+                "Ordinary parameter is defined."
+            } else if context.it_param {
+                "`it` is already used."
+            } else {
+                let n = (bytes[1] - b'0') as usize;
+                context.numbered_params = context.numbered_params.max(n);
+                return Ok(true);
+            };
+            Err(self.error_unexpected(loc, msg))
+        } else if name == "it" && !context.explicit_params && context.numbered_params == 0 {
+            context.it_param = true;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
     // Add the identifier(IdentId) as a new block parameter in the current context.
     // If a parameter with the same name already exists, return error.
     fn new_block_param(&mut self, id: IdentId, loc: Loc) -> Result<(), RubyError> {
@@ -344,16 +447,6 @@ impl Parser {
         }
     }
 
-    fn consume_const(&mut self) -> Result<bool, RubyError> {
-        match self.peek()?.kind {
-            TokenKind::Const(_, _, _) => {
-                self.get()?;
-                Ok(true)
-            }
-            _ => Ok(false),
-        }
-    }
-
     fn consume_reserved_no_skip_line_term(&mut self, expect: Reserved) -> Result<bool, RubyError> {
         if TokenKind::Reserved(expect) == self.peek_no_term()?.kind {
             self.get()?;
@@ -500,7 +593,36 @@ impl Parser {
         self.context_stack.push(Context::new_class(lvar));
         let node = self.parse_comp_stmt()?;
         let lvar = self.context_stack.pop().unwrap().lvar;
-        Ok((node, lvar))
+        let mut prologue = vec![];
+        if let Some(data) = self.lexer.data.take() {
+            // DATA = StringIO.new(<text after __END__>, "r")
+            let loc = node.loc();
+            let data_id = self.get_ident_id("DATA");
+            let stringio = self.get_ident_id("StringIO");
+            let new = self.get_ident_id("new");
+            let args = vec![
+                Node::new_string(data, loc),
+                Node::new_string("r".to_string(), loc),
+            ];
+            let send_args = SendArgs {
+                args,
+                ..SendArgs::default()
+            };
+            let stringio = Node::new_const(stringio, true, loc);
+            let rhs = Node::new_send(stringio, new, send_args, true, loc);
+            let lhs = Node::new_const(data_id, false, loc);
+            prologue.push(Node::new_mul_assign(vec![lhs], vec![rhs]));
+        }
+        prologue.append(&mut self.begin_blocks);
+        if prologue.is_empty() {
+            return Ok((node, lvar));
+        }
+        let loc = node.loc();
+        match node.kind {
+            NodeKind::CompStmt(mut nodes) => prologue.append(&mut nodes),
+            _ => prologue.push(node),
+        };
+        Ok((Node::new_comp_stmt(prologue, loc), lvar))
     }
 
     pub fn parse_program_eval(
@@ -548,6 +670,8 @@ impl Parser {
 
     fn parse_stmt(&mut self) -> Result<Node, RubyError> {
         let mut node = self.parse_expr()?;
+        // `begin ... end while EXPR` executes the body at least once.
+        let mut is_begin = matches!(node.kind, NodeKind::Begin { .. });
         loop {
             if self.consume_reserved_no_skip_line_term(Reserved::If)? {
                 // STMT : STMT if EXPR
@@ -564,16 +688,30 @@ impl Parser {
                 let loc = self.prev_loc();
                 let cond = self.parse_expr()?;
                 let loc = loc.merge(self.prev_loc());
-                node = Node::new_while(cond, node, loc);
+                node = if is_begin {
+                    Node::new_do_while(cond, node, loc)
+                } else {
+                    Node::new_while(cond, node, loc)
+                };
             } else if self.consume_reserved_no_skip_line_term(Reserved::Until)? {
                 // STMT : STMT until EXPR
                 let loc = self.prev_loc();
                 let cond = Node::new_unop(UnOp::Not, self.parse_expr()?, loc);
                 let loc = loc.merge(self.prev_loc());
-                node = Node::new_while(cond, node, loc);
+                node = if is_begin {
+                    Node::new_do_while(cond, node, loc)
+                } else {
+                    Node::new_while(cond, node, loc)
+                };
+            } else if self.consume_reserved_no_skip_line_term(Reserved::Rescue)? {
+                // STMT : STMT rescue STMT
+                let loc = self.prev_loc();
+                let rescue_body = self.parse_expr()?;
+                node = Node::new_rescue_modifier(node, rescue_body, loc);
             } else {
                 break;
             }
+            is_begin = false;
         }
         // STMT : EXPR
         Ok(node)
@@ -603,12 +741,41 @@ impl Parser {
         // | :: FNAME( ARGS )
         // COMMAND-WITH-DO-BLOCK : FNAME ARGS DO-BLOCK
         // | PRIMARY . FNAME ARGS DO-BLOCK [CHAIN-METHOD]* [ . FNAME ARGS]
+        // KEYWORD-AND : EXPR and NOT
+        // KEYWORD-OR : EXPR or NOT
+        let mut node = self.parse_not()?;
+        loop {
+            let op = if self.consume_reserved_no_skip_line_term(Reserved::And)? {
+                BinOp::LAnd
+            } else if self.consume_reserved_no_skip_line_term(Reserved::Or)? {
+                BinOp::LOr
+            } else {
+                return Ok(node);
+            };
+            let rhs = self.parse_not()?;
+            node = Node::new_binop(op, node, rhs);
+        }
+    }
+
+    fn parse_not(&mut self) -> Result<Node, RubyError> {
+        // KEYWORD-NOT : not NOT
+        if self.consume_reserved(Reserved::Not)? {
+            let loc = self.prev_loc();
+            let node = self.parse_not()?;
+            return Ok(Node::new_unop(UnOp::Not, node, loc));
+        }
+        if self.peek()?.kind == TokenKind::Punct(Punct::Mul) || self.is_mlhs_group()? {
+            // EXPR : MLHS `=' MRHS, where MLHS begins with `*target' or `(MLHS)'
+            let node = self.parse_mlhs_item()?;
+            let comma = self.consume_punct_no_term(Punct::Comma)?;
+            return Ok(self.parse_mul_assign(node, comma)?);
+        }
         let node = self.parse_arg()?;
         if self.consume_punct_no_term(Punct::Comma)?
         /*&& node.is_lvar()*/
         {
             // EXPR : MLHS `=' MRHS
-            return Ok(self.parse_mul_assign(node)?);
+            return Ok(self.parse_mul_assign(node, true)?);
         }
         if node.is_operation() && self.is_command()? {
             // FNAME ARGS
@@ -623,32 +790,53 @@ impl Parser {
                     receiver,
                     mut send_args,
                     completed: false,
-                    ..
+                    safe_nav,
                 },
             loc,
         } = node.clone()
         {
             if self.is_command()? {
                 send_args = self.parse_arglist()?;
-            } else {
-                send_args.block = self.parse_block()?
-            };
-            let node = Node::new_send(*receiver, method, send_args, true, loc);
-            Ok(node)
+                return Ok(Node::new_send(*receiver, method, send_args, true, loc).with_safe_nav(safe_nav));
+            }
+            send_args.block = self.parse_block()?;
+            let node = Node::new_send(*receiver, method, send_args, true, loc).with_safe_nav(safe_nav);
+            self.parse_one_line_pattern(node)
         } else {
             // EXPR : ARG
+            self.parse_one_line_pattern(node)
+        }
+    }
+
+    fn parse_one_line_pattern(&mut self, node: Node) -> Result<Node, RubyError> {
+        // EXPR : ARG in PATTERN
+        // | ARG => PATTERN
+        let loc = node.loc();
+        if self.consume_reserved_no_skip_line_term(Reserved::In)? {
+            let pattern = self.parse_pattern_top()?;
+            let loc = loc.merge(self.prev_loc());
+            let branch = InBranch::new(pattern, None, Node::new_bool(true, loc));
+            Ok(Node::new_case_in(node, vec![branch], Some(Node::new_bool(false, loc)), loc))
+        } else if self.consume_punct_no_term(Punct::FatArrow)? {
+            let pattern = self.parse_pattern_top()?;
+            let loc = loc.merge(self.prev_loc());
+            let branch = InBranch::new(pattern, None, Node::new_nil(loc));
+            Ok(Node::new_case_in(node, vec![branch], None, loc))
+        } else {
             Ok(node)
         }
     }
 
-    fn parse_mul_assign(&mut self, node: Node) -> Result<Node, RubyError> {
+    /// Parse multiple assignment after the first target `node`,
+    /// which is followed by a comma if `comma` is true.
+    fn parse_mul_assign(&mut self, node: Node, comma: bool) -> Result<Node, RubyError> {
         // EXPR : MLHS `=' MRHS
         let mut mlhs = vec![node];
         loop {
             if self.peek_no_term()?.kind == TokenKind::Punct(Punct::Assign) {
                 break;
             }
-            let node = self.parse_function()?;
+            let node = self.parse_mlhs_item()?;
             mlhs.push(node);
             if !self.consume_punct_no_term(Punct::Comma)? {
                 break;
@@ -661,12 +849,98 @@ impl Parser {
         }
 
         let mrhs = self.parse_arg_list(None)?;
-        for lhs in &mlhs {
-            self.check_lhs(lhs)?;
+        self.check_mlhs(&mlhs)?;
+        if comma && mlhs.len() == 1 && !matches!(mlhs[0].kind, NodeKind::Splat(_)) {
+            // `a, = rhs` destructures rhs like `(a) = rhs`, unlike `a = rhs`.
+            let loc = mlhs[0].loc();
+            mlhs = vec![Node::new_mlhs(mlhs, loc)];
         }
         return Ok(Node::new_mul_assign(mlhs, mrhs));
     }
 
+    /// Parse a target of multiple assignment.
+    /// MLHS-ITEM : `*' LHS
+    /// | `(' MLHS-ITEM [, MLHS-ITEM]* `)'
+    /// | LHS
+    fn parse_mlhs_item(&mut self) -> Result<Node, RubyError> {
+        let loc = self.loc();
+        if self.consume_punct(Punct::Mul)? {
+            let target = self.parse_function()?;
+            Ok(Node::new_splat(target, loc))
+        } else if self.consume_punct(Punct::LParen)? {
+            let mut mlhs = vec![];
+            loop {
+                mlhs.push(self.parse_mlhs_item()?);
+                if !self.consume_punct(Punct::Comma)? {
+                    break;
+                }
+            }
+            self.expect_punct(Punct::RParen)?;
+            Ok(Node::new_mlhs(mlhs, loc.merge(self.prev_loc())))
+        } else {
+            self.parse_function()
+        }
+    }
+
+    /// Examine whether the next tokens are a nested target of multiple assignment
+    /// such as `(a, b), c =` or `(a, *b) =`, without consuming them.
+    fn is_mlhs_group(&mut self) -> Result<bool, RubyError> {
+        if self.peek()?.kind != TokenKind::Punct(Punct::LParen) {
+            return Ok(false);
+        }
+        self.save_state();
+        self.get()?;
+        let mut depth = 1;
+        // Tokens are peeked before consumed, since some tokens change the state of the lexer.
+        let res = loop {
+            match self.peek_no_term()?.kind {
+                TokenKind::Punct(Punct::LParen) => depth += 1,
+                TokenKind::Punct(Punct::RParen) => depth -= 1,
+                TokenKind::Ident(_, _, _)
+                | TokenKind::Const(_, _, _)
+                | TokenKind::InstanceVar(_)
+                | TokenKind::GlobalVar(_)
+                | TokenKind::NumLit(_)
+                | TokenKind::Punct(Punct::Comma)
+                | TokenKind::Punct(Punct::Mul)
+                | TokenKind::Punct(Punct::Dot)
+                | TokenKind::Punct(Punct::Scope)
+                | TokenKind::Punct(Punct::LBracket)
+                | TokenKind::Punct(Punct::RBracket) => {}
+                _ => break false,
+            }
+            self.get_no_skip_line_term()?;
+            if depth == 0 {
+                break matches!(
+                    self.peek_no_term()?.kind,
+                    TokenKind::Punct(Punct::Comma) | TokenKind::Punct(Punct::Assign)
+                );
+            }
+        };
+        self.restore_state();
+        Ok(res)
+    }
+
+    /// Check the targets of multiple assignment, registering new local variables.
+    fn check_mlhs(&mut self, mlhs: &[Node]) -> Result<(), RubyError> {
+        let mut splat = false;
+        for lhs in mlhs {
+            match &lhs.kind {
+                NodeKind::Splat(target) => {
+                    if splat {
+                        let msg = "Multiple splats in multiple assignment.";
+                        return Err(self.error_unexpected(lhs.loc(), msg));
+                    }
+                    splat = true;
+                    self.check_lhs(target)?;
+                }
+                NodeKind::Mlhs(mlhs) => self.check_mlhs(mlhs)?,
+                _ => self.check_lhs(lhs)?,
+            }
+        }
+        Ok(())
+    }
+
     fn parse_arg_list(
         &mut self,
         punct: impl Into<Option<Punct>>,
@@ -715,22 +989,24 @@ impl Parser {
     fn parse_arglist(&mut self) -> Result<SendArgs, RubyError> {
         let first_arg = self.parse_arg()?;
         if self.is_line_term()? {
-            return Ok(SendArgs{args:vec![first_arg], kw_args:vec![], block:None});
+            return Ok(SendArgs{args:vec![first_arg], kw_args:vec![], kw_splat:vec![], block:None});
         }
 
         if first_arg.is_operation() && self.is_command()? {
             let args =
                 vec![self.parse_command(first_arg.as_method_name().unwrap(), first_arg.loc())?];
-            return Ok(SendArgs{args, kw_args:vec![], block:None});
+            return Ok(SendArgs{args, kw_args:vec![], kw_splat:vec![], block:None});
         }
 
         let mut args = vec![first_arg];
         let mut kw_args = vec![];
+        let mut kw_splat = vec![];
         let mut block = None;
         if self.consume_punct_no_term(Punct::Comma)? {
             let res = self.parse_argument_list(None)?;
             let mut new_args = res.args;
             kw_args = res.kw_args;
+            kw_splat = res.kw_splat;
             block = res.block;
             args.append(&mut new_args);
         }
@@ -741,7 +1017,7 @@ impl Parser {
             }
             None => {}
         };
-        Ok(SendArgs{args, kw_args, block})
+        Ok(SendArgs{args, kw_args, kw_splat, block})
     }
 
     fn is_command(&mut self) -> Result<bool, RubyError> {
@@ -753,6 +1029,8 @@ impl Parser {
             | TokenKind::Const(_, _, _)
             | TokenKind::NumLit(_)
             | TokenKind::FloatLit(_)
+            | TokenKind::RationalLit(_, _)
+            | TokenKind::ImaginaryLit(_)
             | TokenKind::StringLit(_)
             | TokenKind::OpenString(_) => Ok(true),
             TokenKind::Punct(p) => match p {
@@ -763,11 +1041,12 @@ impl Parser {
                 | Punct::Scope
                 | Punct::Plus
                 | Punct::Minus
+                | Punct::BackQuote
                 | Punct::Arrow => Ok(true),
                 _ => Ok(false),
             },
             TokenKind::Reserved(r) => match r {
-                Reserved::False | Reserved::Nil | Reserved::True => Ok(true),
+                Reserved::False | Reserved::Nil | Reserved::True | Reserved::Defined => Ok(true),
                 _ => Ok(false),
             },
             _ => Ok(false),
@@ -785,14 +1064,24 @@ impl Parser {
             return Ok(lhs);
         }
         if self.consume_punct_no_term(Punct::Assign)? {
-            let mrhs = self.parse_arg_list(None)?;
+            let mut mrhs = self.parse_arg_list(None)?;
+            if mrhs.len() == 1 {
+                let rhs = mrhs.pop().unwrap();
+                mrhs.push(self.parse_rescue_modifier(rhs)?);
+            }
             self.check_lhs(&lhs)?;
             Ok(Node::new_mul_assign(vec![lhs], mrhs))
         } else if let TokenKind::Punct(Punct::AssignOp(op)) = self.peek_no_term()?.kind {
             match op {
+                _ if lhs.is_attr_or_index() => {
+                    // The receiver and the indexes must be evaluated only once.
+                    self.get()?;
+                    let rhs = self.parse_arg_rhs()?;
+                    Ok(Node::new_assign_op(op, lhs, rhs))
+                }
                 BinOp::LOr => {
                     self.get()?;
-                    let rhs = self.parse_arg()?;
+                    let rhs = self.parse_arg_rhs()?;
                     self.check_lhs(&lhs)?;
                     if let NodeKind::Ident(id) = lhs.kind {
                         lhs = Node::new_lvar(id, lhs.loc());
@@ -804,9 +1093,23 @@ impl Parser {
                     );
                     Ok(node)
                 }
+                BinOp::LAnd => {
+                    self.get()?;
+                    let rhs = self.parse_arg_rhs()?;
+                    self.check_lhs(&lhs)?;
+                    if let NodeKind::Ident(id) = lhs.kind {
+                        lhs = Node::new_lvar(id, lhs.loc());
+                    };
+                    let node = Node::new_binop(
+                        BinOp::LAnd,
+                        lhs.clone(),
+                        Node::new_mul_assign(vec![lhs.clone()], vec![rhs]),
+                    );
+                    Ok(node)
+                }
                 _ => {
                     self.get()?;
-                    let rhs = self.parse_arg()?;
+                    let rhs = self.parse_arg_rhs()?;
                     self.check_lhs(&lhs)?;
                     Ok(Node::new_mul_assign(
                         vec![lhs.clone()],
@@ -819,6 +1122,22 @@ impl Parser {
         }
     }
 
+    fn parse_arg_rhs(&mut self) -> Result<Node, RubyError> {
+        // ARG-RHS : ARG [rescue ARG]
+        let node = self.parse_arg()?;
+        self.parse_rescue_modifier(node)
+    }
+
+    fn parse_rescue_modifier(&mut self, node: Node) -> Result<Node, RubyError> {
+        if self.consume_reserved_no_skip_line_term(Reserved::Rescue)? {
+            let loc = self.prev_loc();
+            let rescue_body = self.parse_arg()?;
+            Ok(Node::new_rescue_modifier(node, rescue_body, loc))
+        } else {
+            Ok(node)
+        }
+    }
+
     fn check_lhs(&mut self, lhs: &Node) -> Result<(), RubyError> {
         if let NodeKind::Ident(id) = lhs.kind {
             self.add_local_var_if_new(id);
@@ -1019,7 +1338,10 @@ impl Parser {
         if self.consume_punct(Punct::Minus)? {
             let loc = self.prev_loc();
             match self.peek()?.kind {
-                TokenKind::NumLit(_) | TokenKind::FloatLit(_) => {
+                TokenKind::NumLit(_)
+                | TokenKind::FloatLit(_)
+                | TokenKind::RationalLit(_, _)
+                | TokenKind::ImaginaryLit(_) => {
                     self.restore_state();
                     let lhs = self.parse_exponent()?;
                     return Ok(lhs);
@@ -1069,7 +1391,7 @@ impl Parser {
         let loc = node.loc();
         if self.consume_punct_no_term(Punct::LParen)? {
             // PRIMARY-METHOD : FNAME ( ARGS ) BLOCK?
-            let ArgList{args, kw_args, mut block} = self.parse_argument_list(Punct::RParen)?;
+            let ArgList{args, kw_args, kw_splat, mut block} = self.parse_argument_list(Punct::RParen)?;
             match self.parse_block()? {
                 Some(actual_block) => {
                     if block.is_some() {return Err(self.error_unexpected(actual_block.loc(), "Both block arg and actual block given."))}
@@ -1077,7 +1399,7 @@ impl Parser {
                 }
                 None => {}
             };
-            let send_args = SendArgs {args, kw_args, block};
+            let send_args = SendArgs {args, kw_args, kw_splat, block};
 
             Ok(Node::new_send(
                 Node::new_self(loc),
@@ -1088,7 +1410,7 @@ impl Parser {
             ))
         } else if let Some(block) = self.parse_block()? {
             // PRIMARY-METHOD : FNAME BLOCK
-            let send_args = SendArgs {args:vec![], kw_args:vec![], block: Some(block)};
+            let send_args = SendArgs {args:vec![], kw_args:vec![], kw_splat:vec![], block: Some(block)};
             Ok(Node::new_send(
                 Node::new_self(loc),
                 node.as_method_name().unwrap(),
@@ -1130,9 +1452,11 @@ impl Parser {
         }*/
         loop {
             //let tok = self.peek()?;
-            node = if self.consume_punct(Punct::Dot)? {
+            let safe_nav = self.consume_punct(Punct::SafeNav)?;
+            node = if safe_nav || self.consume_punct(Punct::Dot)? {
                 // PRIMARY-METHOD :
                 // | PRIMARY . FNAME BLOCK => completed: true
+                // | PRIMARY &. FNAME ... => safe navigation
                 // | PRIMARY . FNAME ( ARGS ) BLOCK? => completed: true
                 // | PRIMARY . FNAME => completed: false
                 let tok = self.get()?;
@@ -1164,12 +1488,14 @@ impl Parser {
                 };
                 let mut args = vec![];
                 let mut kw_args = vec![];
+                let mut kw_splat = vec![];
                 let mut block = None;
                 let mut completed = false;
                 if self.consume_punct_no_term(Punct::LParen)? {
                     let res = self.parse_argument_list(Punct::RParen)?;
                     args = res.args;
                     kw_args = res.kw_args;
+                    kw_splat = res.kw_splat;
                     block = res.block;
                     completed = true;
                 }
@@ -1189,7 +1515,7 @@ impl Parser {
                     }
                     _ => node,
                 };
-                let send_args = SendArgs {args, kw_args, block};
+                let send_args = SendArgs {args, kw_args, kw_splat, block};
                 Node::new_send(
                     node,
                     id,
@@ -1197,6 +1523,7 @@ impl Parser {
                     completed,
                     loc.merge(self.prev_loc()),
                 )
+                .with_safe_nav(safe_nav)
             } else if self.consume_punct_no_term(Punct::Scope)? {
                 let id = self.expect_const()?;
                 Node::new_scope(node, id, self.prev_loc())
@@ -1215,7 +1542,7 @@ impl Parser {
     }
 
     /// Parse argument list.
-    /// arg, *splat_arg, kw: kw_arg, &block <punct>
+    /// arg, *splat_arg, kw: kw_arg, **double_splat_arg, &block <punct>
     /// punct: punctuator for terminating arg list. Set None for unparenthesized argument list.
     fn parse_argument_list(
         &mut self,
@@ -1227,16 +1554,20 @@ impl Parser {
         };
         let mut args = vec![];
         let mut kw_args = vec![];
+        let mut kw_splat = vec![];
         let mut block = None;
         loop {
             if flag && self.consume_punct(punct)? {
-                return Ok(ArgList {args, kw_args, block});
+                return Ok(ArgList {args, kw_args, kw_splat, block});
             }
             if self.consume_punct(Punct::Mul)? {
                 // splat argument
                 let loc = self.prev_loc();
                 let array = self.parse_arg()?;
                 args.push(Node::new_splat(array, loc));
+            } else if self.consume_punct(Punct::DMul)? {
+                // double splat argument
+                kw_splat.push(self.parse_arg()?);
             } else if self.consume_punct(Punct::BitAnd)? {
                 // block argument
                 let arg = self.parse_arg()?;
@@ -1266,7 +1597,7 @@ impl Parser {
         if flag {
             self.expect_punct(punct)?
         };
-        Ok(ArgList {args, kw_args, block})
+        Ok(ArgList {args, kw_args, kw_splat, block})
     }
 
     fn parse_block(&mut self) -> Result<Option<Box<Node>>, RubyError> {
@@ -1283,16 +1614,35 @@ impl Parser {
         let loc = self.prev_loc();
         self.context_stack.push(Context::new_block());
 
-        let params = if self.consume_punct(Punct::BitOr)? {
+        let mut params = if self.consume_punct(Punct::BitOr)? {
+            self.context_mut().explicit_params = true;
             if self.consume_punct(Punct::BitOr)? {
                 vec![]
             } else {
-                let params = self.parse_params(TokenKind::Punct(Punct::BitOr))?;
-                self.consume_punct(Punct::BitOr)?;
+                let semi = TokenKind::Punct(Punct::Semi);
+                let params = if self.peek_no_term()?.kind == semi {
+                    vec![]
+                } else {
+                    self.parse_params(TokenKind::Punct(Punct::BitOr))?
+                };
+                if self.peek_no_term()?.kind == semi {
+                    // Block-local variables: `|x; y, z|`
+                    self.get_no_skip_line_term()?;
+                    loop {
+                        let id = self.expect_ident()?;
+                        self.new_param(id, self.prev_loc())?;
+                        if !self.consume_punct(Punct::Comma)? {
+                            break;
+                        }
+                    }
+                }
+                self.expect_punct(Punct::BitOr)?;
                 params
             }
         } else {
-            self.consume_punct(Punct::LOr)?;
+            if self.consume_punct(Punct::LOr)? {
+                self.context_mut().explicit_params = true;
+            }
             vec![]
         };
 
@@ -1302,12 +1652,42 @@ impl Parser {
         } else {
             self.expect_punct(Punct::RBrace)?;
         };
-        let lvar = self.context_stack.pop().unwrap().lvar;
         let loc = loc.merge(self.prev_loc());
+        let mut context = self.context_stack.pop().unwrap();
+        let body = Parser::destructure_params(&mut context, body);
+        let mut lvar = context.lvar;
+        let implicit_params: Vec<IdentId> = if context.it_param {
+            vec![self.get_ident_id("it")]
+        } else {
+            (1..=context.numbered_params)
+                .map(|i| self.get_ident_id(format!("_{}", i)))
+                .collect()
+        };
+        if !implicit_params.is_empty() {
+            lvar.prepend_params(&implicit_params);
+            params = implicit_params
+                .into_iter()
+                .map(|id| Node::new_param(id, loc))
+                .collect();
+        }
         let node = Node::new_proc(params, body, lvar, loc);
         Ok(Some(Box::new(node)))
     }
 
+    /// Build a node for a number literal token, negated if `minus` is true.
+    fn new_number_literal(kind: &TokenKind, minus: bool, loc: Loc) -> Node {
+        let sign = if minus { -1 } else { 1 };
+        match kind {
+            TokenKind::NumLit(num) => Node::new_integer(sign * num, loc),
+            TokenKind::FloatLit(num) => Node::new_float(sign as f64 * num, loc),
+            TokenKind::RationalLit(num, den) => Node::new_rational(sign * num, *den, loc),
+            TokenKind::ImaginaryLit(num) => {
+                Node::new_imaginary(Self::new_number_literal(num, minus, loc), loc)
+            }
+            _ => unreachable!(),
+        }
+    }
+
     fn parse_primary(&mut self) -> Result<Node, RubyError> {
         let tok = self.get()?;
         let loc = tok.loc();
@@ -1322,6 +1702,11 @@ impl Parser {
                 };
                 if self.is_local_var(id) {
                     Ok(Node::new_lvar(id, loc))
+                } else if !(*trailing_space && self.is_command_()?)
+                    && self.add_implicit_param(name, loc)?
+                {
+                    // Implicit block parameter: `_1`..`_9` or `it`
+                    Ok(Node::new_lvar(id, loc))
                 } else {
                     // FUNCTION or COMMAND or LHS for assignment
                     let node = Node::new_identifier(id, loc);
@@ -1361,16 +1746,17 @@ impl Parser {
                 };
                 Ok(Node::new_const(id, false, loc))
             }
-            TokenKind::NumLit(num) => Ok(Node::new_integer(*num, loc)),
-            TokenKind::FloatLit(num) => Ok(Node::new_float(*num, loc)),
+            TokenKind::NumLit(_)
+            | TokenKind::FloatLit(_)
+            | TokenKind::RationalLit(_, _)
+            | TokenKind::ImaginaryLit(_) => Ok(Self::new_number_literal(&tok.kind, false, loc)),
             TokenKind::StringLit(s) => Ok(self.parse_string_literal(s)?),
             TokenKind::OpenString(s) => Ok(self.parse_interporated_string_literal(s)?),
             TokenKind::Punct(punct) => match punct {
-                Punct::Minus => match self.get()?.kind {
-                    TokenKind::NumLit(num) => Ok(Node::new_integer(-num, loc)),
-                    TokenKind::FloatLit(num) => Ok(Node::new_float(-num, loc)),
-                    _ => unreachable!(),
-                },
+                Punct::Minus => {
+                    let kind = self.get()?.kind;
+                    Ok(Self::new_number_literal(&kind, true, loc))
+                }
                 Punct::LParen => {
                     let node = self.parse_comp_stmt()?;
                     self.expect_punct(Punct::RParen)?;
@@ -1419,14 +1805,7 @@ impl Parser {
                     self.context_stack.push(Context::new_block());
                     if self.consume_punct(Punct::LParen)? {
                         if !self.consume_punct(Punct::RParen)? {
-                            loop {
-                                let id = self.expect_ident()?;
-                                params.push(Node::new_param(id, self.prev_loc()));
-                                self.new_param(id, self.prev_loc())?;
-                                if !self.consume_punct(Punct::Comma)? {
-                                    break;
-                                }
-                            }
+                            params = self.parse_params(TokenKind::Punct(Punct::RParen))?;
                             self.expect_punct(Punct::RParen)?;
                         }
                     } else if let TokenKind::Ident(_, _, _) = self.peek()?.kind {
@@ -1437,8 +1816,9 @@ impl Parser {
                     self.expect_punct(Punct::LBrace)?;
                     let body = self.parse_comp_stmt()?;
                     self.expect_punct(Punct::RBrace)?;
-                    let lvar = self.context_stack.pop().unwrap().lvar;
-                    Ok(Node::new_proc(params, body, lvar, loc))
+                    let mut context = self.context_stack.pop().unwrap();
+                    let body = Parser::destructure_params(&mut context, body);
+                    Ok(Node::new_proc(params, body, context.lvar, loc))
                 }
                 Punct::Scope => {
                     let id = self.expect_const()?;
@@ -1452,6 +1832,11 @@ impl Parser {
                     let node = self.parse_percent_notation()?;
                     Ok(node)
                 }
+                Punct::BackQuote => {
+                    let tok = self.lexer.lex_command('`')?;
+                    let node = self.parse_command_literal(tok)?;
+                    Ok(node)
+                }
                 _ => {
                     return Err(
                         self.error_unexpected(loc, format!("Unexpected token: {:?}", tok.kind))
@@ -1511,6 +1896,9 @@ impl Parser {
                 let loc = self.prev_loc();
                 let cond = self.parse_expr()?;
                 self.consume_term()?;
+                if self.peek()?.kind == TokenKind::Reserved(Reserved::In) {
+                    return self.parse_case_in(cond, loc);
+                }
                 let mut when_ = vec![];
                 while self.consume_reserved(Reserved::When)? {
                     let arg = self.parse_arg_list(None)?;
@@ -1618,11 +2006,64 @@ impl Parser {
                     Ok(Node::new_next(val, loc))
                 }
             }
+            TokenKind::Reserved(Reserved::Redo) => Ok(Node::new(NodeKind::Redo, loc)),
+            TokenKind::Reserved(Reserved::Retry) => Ok(Node::new(NodeKind::Retry, loc)),
             TokenKind::Reserved(Reserved::True) => Ok(Node::new_bool(true, loc)),
             TokenKind::Reserved(Reserved::False) => Ok(Node::new_bool(false, loc)),
             TokenKind::Reserved(Reserved::Nil) => Ok(Node::new_nil(loc)),
             TokenKind::Reserved(Reserved::Self_) => Ok(Node::new_self(loc)),
             TokenKind::Reserved(Reserved::Begin) => Ok(self.parse_begin()?),
+            TokenKind::Reserved(Reserved::Alias) => {
+                let new = self.parse_alias_name()?;
+                let old = self.parse_alias_name()?;
+                let is_gvar = |parser: &Self, id| parser.ident_table.get_name(id).starts_with('$');
+                if is_gvar(self, new) != is_gvar(self, old) {
+                    return Err(self.error_unexpected(self.prev_loc(), "Can not alias between a method and a global variable."));
+                }
+                Ok(Node::new_alias(new, old, loc.merge(self.prev_loc())))
+            }
+            TokenKind::Reserved(Reserved::Undef) => {
+                let mut ids = vec![self.parse_alias_name()?];
+                while self.consume_punct_no_term(Punct::Comma)? {
+                    ids.push(self.parse_alias_name()?);
+                }
+                Ok(Node::new_undef(ids, loc.merge(self.prev_loc())))
+            }
+            TokenKind::Reserved(Reserved::Defined) => {
+                let expr = if self.consume_punct_no_term(Punct::LParen)? {
+                    let expr = self.parse_expr()?;
+                    self.expect_punct(Punct::RParen)?;
+                    expr
+                } else {
+                    self.parse_arg()?
+                };
+                Ok(Node::new_defined(expr, loc.merge(self.prev_loc())))
+            }
+            TokenKind::Reserved(Reserved::BEGIN) => {
+                if self.context_stack.len() != 1 {
+                    return Err(self.error_unexpected(loc, "BEGIN is permitted only at toplevel."));
+                }
+                self.expect_punct(Punct::LBrace)?;
+                let body = self.parse_comp_stmt()?;
+                self.expect_punct(Punct::RBrace)?;
+                self.begin_blocks.push(body);
+                Ok(Node::new_nop(loc))
+            }
+            TokenKind::Reserved(Reserved::END) => {
+                // END { body } is executed at exit, as `at_exit { body }`.
+                if self.peek_no_term()?.kind != TokenKind::Punct(Punct::LBrace) {
+                    let loc = self.loc();
+                    return Err(self.error_unexpected(loc, "Expect '{'"));
+                }
+                let block = self.parse_block()?;
+                let at_exit = self.get_ident_id("at_exit");
+                let send_args = SendArgs {
+                    block,
+                    ..SendArgs::default()
+                };
+                let loc = loc.merge(self.prev_loc());
+                Ok(Node::new_send(Node::new_self(loc), at_exit, send_args, true, loc))
+            }
             TokenKind::EOF => return Err(self.error_eof(loc)),
             _ => {
                 return Err(self.error_unexpected(loc, format!("Unexpected token: {:?}", tok.kind)))
@@ -1638,6 +2079,8 @@ impl Parser {
             | TokenKind::Const(_, _, _)
             | TokenKind::NumLit(_)
             | TokenKind::FloatLit(_)
+            | TokenKind::RationalLit(_, _)
+            | TokenKind::ImaginaryLit(_)
             | TokenKind::StringLit(_)
             | TokenKind::OpenString(_) => Ok(true),
             TokenKind::Punct(p) => match p {
@@ -1645,6 +2088,7 @@ impl Parser {
                 | Punct::LBracket
                 | Punct::Colon
                 | Punct::Scope
+                | Punct::BackQuote
                 | Punct::Arrow => Ok(true),
                 _ => Ok(false),
             },
@@ -1738,16 +2182,65 @@ impl Parser {
     fn parse_percent_notation(&mut self) -> Result<Node, RubyError> {
         let tok = self.lexer.lex_percent_notation()?;
         let loc = tok.loc;
-        if let TokenKind::PercentNotation(_kind, content) = tok.kind {
-            let ary = content
-                .split(' ')
-                .map(|x| Node::new_string(x.to_string(), loc))
-                .rev()
-                .collect();
-            Ok(Node::new_array(ary, tok.loc))
-        } else {
-            panic!();
-        }
+        match tok.kind {
+            TokenKind::PercentNotation(_kind, content) => {
+                let ary = content
+                    .split(' ')
+                    .map(|x| Node::new_string(x.to_string(), loc))
+                    .rev()
+                    .collect();
+                Ok(Node::new_array(ary, tok.loc))
+            }
+            _ => self.parse_command_literal(tok),
+        }
+    }
+
+    /// Parse command literal (`cmd` or %x{cmd}) as a call of Kernel#` with the command string.
+    fn parse_command_literal(&mut self, tok: Token) -> Result<Node, RubyError> {
+        let mut loc = tok.loc;
+        let arg = match tok.kind {
+            TokenKind::StringLit(s) => Node::new_string(s, loc),
+            TokenKind::OpenString(s) => {
+                let mut nodes = vec![Node::new_string(s, loc)];
+                loop {
+                    match self.peek()?.kind {
+                        TokenKind::CloseString(s) => {
+                            self.get()?;
+                            loc = loc.merge(self.prev_loc());
+                            nodes.push(Node::new_string(s.clone(), self.prev_loc()));
+                            break;
+                        }
+                        TokenKind::InterString(s) => {
+                            self.get()?;
+                            nodes.push(Node::new_string(s.clone(), self.prev_loc()));
+                        }
+                        TokenKind::EOF => {
+                            let loc = self.loc();
+                            return Err(self.error_unexpected(loc, "Unexpectd EOF."));
+                        }
+                        _ => {
+                            nodes.push(self.parse_comp_stmt()?);
+                        }
+                    }
+                }
+                Node::new_interporated_string(nodes, loc)
+            }
+            _ => panic!(),
+        };
+        let method = self.get_ident_id("`");
+        let send_args = SendArgs {
+            args: vec![arg],
+            kw_args: vec![],
+            kw_splat: vec![],
+            block: None,
+        };
+        Ok(Node::new_send(
+            Node::new_self(loc),
+            method,
+            send_args,
+            true,
+            loc,
+        ))
     }
 
     fn parse_hash_literal(&mut self) -> Result<Node, RubyError> {
@@ -1851,56 +2344,26 @@ impl Parser {
         //  end
         let mut is_singleton_method = None;
         let tok = self.get()?;
+        let loc = tok.loc();
         let id = match tok.kind {
             TokenKind::Reserved(Reserved::Self_) => {
                 is_singleton_method = Some(Node::new_self(tok.loc()));
                 self.expect_punct(Punct::Dot)?;
                 self.expect_ident()?
             }
-            TokenKind::Reserved(r) => {
-                let string = self.lexer.get_string_from_reserved(r).to_owned();
-                self.get_ident_id(string)
-            }
-            TokenKind::Ident(name, has_suffix, _) => {
-                if has_suffix {
-                    match self.peek_no_term()?.kind {
-                        TokenKind::Punct(Punct::Assign) => {
-                            self.get()?;
-                            self.get_ident_id(name + "=")
-                        }
-                        _ => self.get_ident_id(name),
-                    }
-                } else {
-                    self.get_ident_id(name)
-                }
-            }
-            TokenKind::Punct(Punct::Plus) => self.get_ident_id("+"),
-            TokenKind::Punct(Punct::Minus) => self.get_ident_id("-"),
-            TokenKind::Punct(Punct::Mul) => self.get_ident_id("*"),
-            TokenKind::Punct(Punct::LBracket) => {
-                if self.consume_punct_no_term(Punct::RBracket)? {
-                    if self.consume_punct_no_term(Punct::Assign)? {
-                        self.get_ident_id("[]=")
-                    } else {
-                        self.get_ident_id("[]")
-                    }
-                } else {
-                    let loc = self.loc();
-                    return Err(self.error_unexpected(loc, "Expected `]'"));
-                }
-            }
-            _ => {
-                let loc = self.loc();
-                return Err(self.error_unexpected(loc, "Expected identifier or operator."));
-            }
+            _ => self.method_name_from_token(tok)?,
         };
         self.context_stack.push(Context::new_method());
         let args = self.parse_def_params()?;
         let body = self.parse_begin()?;
-        let lvar = self.context_stack.pop().unwrap().lvar;
+        let mut context = self.context_stack.pop().unwrap();
+        let body = Parser::destructure_params(&mut context, body);
+        let lvar = context.lvar;
         match is_singleton_method {
-            Some(singleton) => Ok(Node::new_singleton_method_decl(singleton, id, args, body, lvar)),
-            None => Ok(Node::new_method_decl(id, args, body, lvar)),
+            Some(singleton) => Ok(Node::new_singleton_method_decl(
+                singleton, id, args, body, lvar, loc,
+            )),
+            None => Ok(Node::new_method_decl(id, args, body, lvar, loc)),
         }
     }
 
@@ -1919,6 +2382,9 @@ impl Parser {
 
         let mut args = vec![];
         let mut state = Kind::Reqired;
+        // Local variables in destructuring params, which are defined after all params.
+        let mut mlhs_vars = vec![];
+        let mut destructured = vec![];
         loop {
             let mut loc = self.loc();
             if self.consume_punct(Punct::BitAnd)? {
@@ -1928,6 +2394,23 @@ impl Parser {
                 args.push(Node::new_block_param(id, loc));
                 self.new_block_param(id, loc)?;
                 break;
+            } else if self.consume_punct(Punct::DMul)? {
+                // Keyword rest param
+                if state >= Kind::KWRest {
+                    return Err(self.error_unexpected(
+                        loc,
+                        "Keyword rest parameter is not allowed in ths position.",
+                    ));
+                }
+                state = Kind::KWRest;
+                if self.consume_reserved(Reserved::Nil)? {
+                    args.push(Node::new_no_kw_param(loc.merge(self.prev_loc())));
+                } else {
+                    let id = self.expect_ident()?;
+                    loc = loc.merge(self.prev_loc());
+                    args.push(Node::new_kwrest_param(id, loc));
+                    self.new_param(id, loc)?;
+                }
             } else if self.consume_punct(Punct::Mul)? {
                 // Splat(Rest) param
                 let id = self.expect_ident()?;
@@ -1941,6 +2424,35 @@ impl Parser {
 
                 args.push(Node::new_splat_param(id, loc));
                 self.new_param(id, self.prev_loc())?;
+            } else if self.consume_punct(Punct::LParen)? {
+                // Destructuring param: `(a, (b, *c))`
+                let mut mlhs = vec![];
+                loop {
+                    mlhs.push(self.parse_param_mlhs_item(&mut mlhs_vars)?);
+                    if !self.consume_punct(Punct::Comma)? {
+                        break;
+                    }
+                }
+                self.expect_punct(Punct::RParen)?;
+                loc = loc.merge(self.prev_loc());
+                let id = self.get_ident_id(format!("%destructure{}", args.len()));
+                match state {
+                    Kind::Reqired => args.push(Node::new_param(id, loc)),
+                    Kind::PostReq | Kind::Optional | Kind::Rest => {
+                        args.push(Node::new_post_param(id, loc));
+                        state = Kind::PostReq;
+                    }
+                    _ => {
+                        return Err(self.error_unexpected(
+                            loc,
+                            "Required parameter is not allowed in ths position.",
+                        ))
+                    }
+                }
+                self.new_param(id, loc)?;
+                let target = Node::new_mlhs(mlhs, loc);
+                let param = Node::new_lvar(id, loc);
+                destructured.push(Node::new_mul_assign(vec![target], vec![param]));
             } else {
                 let id = self.expect_ident()?;
                 if self.consume_punct(Punct::Assign)? {
@@ -2004,9 +2516,50 @@ impl Parser {
                 break;
             }
         };
+        for (id, loc) in mlhs_vars {
+            self.new_param(id, loc)?;
+        }
+        self.context_mut().destructured_params = destructured;
         Ok(args)
     }
 
+    /// Parse a target of a destructuring param, collecting local variables into `vars`.
+    fn parse_param_mlhs_item(&mut self, vars: &mut Vec<(IdentId, Loc)>) -> Result<Node, RubyError> {
+        let loc = self.loc();
+        if self.consume_punct(Punct::LParen)? {
+            let mut mlhs = vec![];
+            loop {
+                mlhs.push(self.parse_param_mlhs_item(vars)?);
+                if !self.consume_punct(Punct::Comma)? {
+                    break;
+                }
+            }
+            self.expect_punct(Punct::RParen)?;
+            return Ok(Node::new_mlhs(mlhs, loc.merge(self.prev_loc())));
+        }
+        let splat = self.consume_punct(Punct::Mul)?;
+        let id = self.expect_ident()?;
+        let loc = loc.merge(self.prev_loc());
+        vars.push((id, loc));
+        let lvar = Node::new_lvar(id, loc);
+        if splat {
+            Ok(Node::new_splat(lvar, loc))
+        } else {
+            Ok(lvar)
+        }
+    }
+
+    /// Prepend the assignments of destructuring params in `context` to `body`.
+    fn destructure_params(context: &mut Context, body: Node) -> Node {
+        if context.destructured_params.is_empty() {
+            return body;
+        }
+        let mut nodes = std::mem::take(&mut context.destructured_params);
+        let loc = body.loc();
+        nodes.push(body);
+        Node::new_comp_stmt(nodes, loc)
+    }
+
     // ( )
     // ( ident [, ident]* )
     fn parse_def_params(&mut self) -> Result<Vec<Node>, RubyError> {
@@ -2070,6 +2623,79 @@ impl Parser {
         ))
     }
 
+    /// Get a method name from `tok`. Setter names (`name=`) consume the following `=`.
+    fn method_name_from_token(&mut self, tok: Token) -> Result<IdentId, RubyError> {
+        let id = match tok.kind {
+            TokenKind::Reserved(r) => {
+                let string = self.lexer.get_string_from_reserved(r).to_owned();
+                self.get_ident_id(string)
+            }
+            TokenKind::Ident(name, has_suffix, _) | TokenKind::Const(name, has_suffix, _) => {
+                if has_suffix {
+                    match self.peek_no_term()?.kind {
+                        TokenKind::Punct(Punct::Assign) => {
+                            self.get()?;
+                            self.get_ident_id(name + "=")
+                        }
+                        _ => self.get_ident_id(name),
+                    }
+                } else {
+                    self.get_ident_id(name)
+                }
+            }
+            TokenKind::Punct(Punct::Plus) => self.get_ident_id("+"),
+            TokenKind::Punct(Punct::Minus) => self.get_ident_id("-"),
+            TokenKind::Punct(Punct::Mul) => self.get_ident_id("*"),
+            TokenKind::Punct(Punct::Div) => self.get_ident_id("/"),
+            TokenKind::Punct(Punct::Rem) => self.get_ident_id("%"),
+            TokenKind::Punct(Punct::Eq) => self.get_ident_id("=="),
+            TokenKind::Punct(Punct::Ne) => self.get_ident_id("!="),
+            TokenKind::Punct(Punct::TEq) => self.get_ident_id("==="),
+            TokenKind::Punct(Punct::Gt) => self.get_ident_id(">"),
+            TokenKind::Punct(Punct::Ge) => self.get_ident_id(">="),
+            TokenKind::Punct(Punct::Lt) => self.get_ident_id("<"),
+            TokenKind::Punct(Punct::Le) => self.get_ident_id("<="),
+            TokenKind::Punct(Punct::Cmp) => self.get_ident_id("<=>"),
+            TokenKind::Punct(Punct::LBracket) => {
+                if self.consume_punct_no_term(Punct::RBracket)? {
+                    if self.consume_punct_no_term(Punct::Assign)? {
+                        self.get_ident_id("[]=")
+                    } else {
+                        self.get_ident_id("[]")
+                    }
+                } else {
+                    let loc = self.loc();
+                    return Err(self.error_unexpected(loc, "Expected `]'"));
+                }
+            }
+            _ => {
+                let loc = self.loc();
+                return Err(self.error_unexpected(loc, "Expected identifier or operator."));
+            }
+        };
+        Ok(id)
+    }
+
+    /// Parse a name for `alias` or `undef`: a method name, a symbol or a global variable.
+    fn parse_alias_name(&mut self) -> Result<IdentId, RubyError> {
+        let tok = self.get()?;
+        match tok.kind {
+            TokenKind::GlobalVar(name) => Ok(self.get_ident_id(name)),
+            TokenKind::Punct(Punct::Colon) => {
+                let tok = self.get()?;
+                match &tok.kind {
+                    TokenKind::Punct(punct) => self.parse_op_definable(punct),
+                    _ if tok.can_be_symbol() => {
+                        let ident = self.token_as_symbol(&tok);
+                        Ok(self.get_ident_id(ident))
+                    }
+                    _ => Err(self.error_unexpected(tok.loc(), "Expect identifier.")),
+                }
+            }
+            _ => self.method_name_from_token(tok),
+        }
+    }
+
     fn parse_op_definable(&mut self, punct: &Punct) -> Result<IdentId, RubyError> {
         match punct {
             Punct::LBracket => {
@@ -2089,27 +2715,455 @@ impl Parser {
     }
 
     fn parse_begin(&mut self) -> Result<Node, RubyError> {
+        //  COMPSTMT
+        //  [rescue [ARGS] [`=>' LHS] THEN COMPSTMT]+
+        //  [else COMPSTMT]
+        //  [ensure COMPSTMT]
+        //  end
         let body = self.parse_comp_stmt()?;
-        if self.consume_reserved(Reserved::Rescue)? {
-            if self.consume_const()? {}
-            if self.consume_punct_no_term(Punct::FatArrow)? {
-                self.expect_ident()?;
+        let mut rescue = vec![];
+        while self.consume_reserved(Reserved::Rescue)? {
+            let mut exception_list = vec![];
+            if !self.is_line_term()? {
+                match self.peek_no_term()?.kind {
+                    TokenKind::Punct(Punct::FatArrow) | TokenKind::Reserved(Reserved::Then) => {}
+                    _ => loop {
+                        exception_list.push(self.parse_arg()?);
+                        if !self.consume_punct_no_term(Punct::Comma)? {
+                            break;
+                        }
+                    },
+                }
             }
-            self.parse_comp_stmt()?;
+            let assign = if self.consume_punct_no_term(Punct::FatArrow)? {
+                let lhs = self.parse_function()?;
+                self.check_lhs(&lhs)?;
+                Some(lhs)
+            } else {
+                None
+            };
+            self.consume_reserved(Reserved::Then)?;
+            let rescue_body = self.parse_comp_stmt()?;
+            rescue.push(RescueEntry::new(exception_list, assign, rescue_body));
         }
+        let loc = body.loc();
+        let else_ = if self.consume_reserved(Reserved::Else)? {
+            self.parse_comp_stmt()?
+        } else {
+            Node::new_nop(loc)
+        };
         let ensure = if self.consume_reserved(Reserved::Ensure)? {
             self.parse_comp_stmt()?
         } else {
-            Node::new_nop(body.loc())
+            Node::new_nop(loc)
         };
         self.expect_reserved(Reserved::End)?;
-        let loc = body.loc();
-        Ok(Node::new_begin(
-            body,
-            vec![],
-            Node::new_nop(loc),
-            ensure,
-            loc,
+        Ok(Node::new_begin(body, rescue, else_, ensure, loc))
+    }
+
+    fn parse_case_in(&mut self, cond: Node, loc: Loc) -> Result<Node, RubyError> {
+        //  case EXPR
+        //  [in PATTERN [(if|unless) EXPR] THEN COMPSTMT]+
+        //  [else COMPSTMT]
+        //  end
+        let mut in_ = vec![];
+        while self.consume_reserved(Reserved::In)? {
+            let pattern = self.parse_pattern_top()?;
+            let guard = if self.consume_reserved_no_skip_line_term(Reserved::If)? {
+                Some(self.parse_expr()?)
+            } else if self.consume_reserved_no_skip_line_term(Reserved::Unless)? {
+                let loc = self.prev_loc();
+                Some(Node::new_unop(UnOp::Not, self.parse_expr()?, loc))
+            } else {
+                None
+            };
+            self.parse_then()?;
+            let body = self.parse_comp_stmt()?;
+            in_.push(InBranch::new(pattern, guard, body));
+        }
+        let else_ = if self.consume_reserved(Reserved::Else)? {
+            Some(self.parse_comp_stmt()?)
+        } else {
+            None
+        };
+        self.expect_reserved(Reserved::End)?;
+        Ok(Node::new_case_in(
+            cond,
+            in_,
+            else_,
+            loc.merge(self.prev_loc()),
         ))
     }
+
+    /// Parse a top-level pattern, in which brackets of array patterns and braces of hash patterns
+    /// can be omitted.
+    fn parse_pattern_top(&mut self) -> Result<Pattern, RubyError> {
+        if self.is_pattern_key()? {
+            return self.parse_hash_pattern(None, None);
+        }
+        if self.peek_no_term()?.kind != TokenKind::Punct(Punct::Mul) {
+            let pattern = self.parse_pattern()?;
+            if self.peek_no_term()?.kind != TokenKind::Punct(Punct::Comma) {
+                return Ok(pattern);
+            }
+            self.get()?;
+            return self.parse_array_pattern(None, None, vec![PatternElem::Pattern(pattern)]);
+        }
+        self.parse_array_pattern(None, None, vec![])
+    }
+
+    fn parse_pattern(&mut self) -> Result<Pattern, RubyError> {
+        // PATTERN : PRIMARY-PATTERN (`|' PRIMARY-PATTERN)* (`=>' IDENT)?
+        let mut pattern = self.parse_pattern_primary()?;
+        if self.peek_no_term()?.kind == TokenKind::Punct(Punct::BitOr) {
+            let mut alt = vec![pattern];
+            while self.consume_punct_no_term(Punct::BitOr)? {
+                alt.push(self.parse_pattern_primary()?);
+            }
+            let mut vars = vec![];
+            for pattern in &alt {
+                Parser::pattern_variables(pattern, &mut vars);
+            }
+            // Only variables which start with `_` are allowed in alternative patterns.
+            if let Some(id) = vars
+                .into_iter()
+                .find(|id| !self.ident_table.get_name(*id).starts_with('_'))
+            {
+                let msg = format!(
+                    "illegal variable in alternative pattern ({})",
+                    self.ident_table.get_name(id)
+                );
+                return Err(self.error_unexpected(self.prev_loc(), msg));
+            }
+            pattern = Pattern::Alt(alt);
+        }
+        while self.consume_punct_no_term(Punct::FatArrow)? {
+            let id = self.expect_ident()?;
+            self.add_local_var_if_new(id);
+            pattern = Pattern::As(Box::new(pattern), id);
+        }
+        Ok(pattern)
+    }
+
+    fn parse_pattern_primary(&mut self) -> Result<Pattern, RubyError> {
+        let loc = self.loc();
+        match self.peek()?.kind {
+            TokenKind::Punct(Punct::LBracket) => {
+                self.get()?;
+                self.parse_array_pattern(None, Punct::RBracket, vec![])
+            }
+            TokenKind::Punct(Punct::LBrace) => {
+                self.get()?;
+                self.parse_hash_pattern(None, Punct::RBrace)
+            }
+            TokenKind::Punct(Punct::BitXor) => {
+                // Pinned variable or expression.
+                self.get()?;
+                let tok = self.get()?;
+                let node = match &tok.kind {
+                    TokenKind::Ident(name, _, _) => {
+                        let id = self.get_ident_id(name);
+                        if !self.is_local_var(id) {
+                            let msg = format!("{}: no such local variable", name);
+                            return Err(self.error_unexpected(tok.loc(), msg));
+                        }
+                        Node::new_lvar(id, tok.loc())
+                    }
+                    TokenKind::InstanceVar(name) => {
+                        Node::new_instance_var(self.get_ident_id(name), tok.loc())
+                    }
+                    TokenKind::GlobalVar(name) => {
+                        Node::new_global_var(self.get_ident_id(name), tok.loc())
+                    }
+                    TokenKind::Punct(Punct::LParen) => {
+                        let node = self.parse_expr()?;
+                        self.expect_punct(Punct::RParen)?;
+                        node
+                    }
+                    _ => {
+                        return Err(self.error_unexpected(
+                            tok.loc(),
+                            "Expect variable or (expression) after ^.",
+                        ))
+                    }
+                };
+                Ok(Pattern::Value(node))
+            }
+            TokenKind::Ident(ref name, false, _) => {
+                self.get()?;
+                let id = self.get_ident_id(name.as_str());
+                self.add_local_var_if_new(id);
+                Ok(Pattern::Bind(id))
+            }
+            TokenKind::Const(_, _, _) | TokenKind::Punct(Punct::Scope) => {
+                let mut constant = match self.get()?.kind {
+                    TokenKind::Const(name, _, _) => {
+                        Node::new_const(self.get_ident_id(name), false, loc)
+                    }
+                    _ => Node::new_const(self.expect_const()?, true, loc),
+                };
+                while self.consume_punct_no_term(Punct::Scope)? {
+                    let id = self.expect_const()?;
+                    constant = Node::new_scope(constant, id, self.prev_loc());
+                }
+                // Const(pat, ..) Const[pat, ..] Const(key: pat, ..)
+                let term = if self.consume_punct_no_term(Punct::LParen)? {
+                    Punct::RParen
+                } else if self.consume_punct_no_term(Punct::LBracket)? {
+                    Punct::RBracket
+                } else {
+                    return Ok(Pattern::Value(constant));
+                };
+                if self.is_pattern_key()? {
+                    self.parse_hash_pattern(Some(constant), term)
+                } else {
+                    self.parse_array_pattern(Some(constant), term, vec![])
+                }
+            }
+            _ => {
+                // Literals and ranges.
+                let node = self.parse_primary()?;
+                let node = if self.consume_punct_no_term(Punct::Range2)? {
+                    let end = self.parse_primary()?;
+                    let loc = node.loc().merge(end.loc());
+                    Node::new_range(node, end, false, loc)
+                } else if self.consume_punct_no_term(Punct::Range3)? {
+                    let end = self.parse_primary()?;
+                    let loc = node.loc().merge(end.loc());
+                    Node::new_range(node, end, true, loc)
+                } else {
+                    node
+                };
+                match node.kind {
+                    NodeKind::Ident(_) | NodeKind::LocalVar(_) | NodeKind::Send { .. } => {
+                        Err(self.error_unexpected(node.loc(), "Illegal pattern."))
+                    }
+                    _ => Ok(Pattern::Value(node)),
+                }
+            }
+        }
+    }
+
+    /// Parse an array pattern or a find pattern after `elems` until `term`.
+    /// If `term` is None, parse until the end of the comma-separated list.
+    fn parse_array_pattern(
+        &mut self,
+        constant: Option<Node>,
+        term: impl Into<Option<Punct>>,
+        mut elems: Vec<PatternElem>,
+    ) -> Result<Pattern, RubyError> {
+        let term = term.into();
+        let loc = self.loc();
+        loop {
+            if let Some(term) = term {
+                if self.consume_punct(term)? {
+                    break;
+                }
+            }
+            if self.consume_punct(Punct::Mul)? {
+                let id = match self.peek_no_term()?.kind {
+                    TokenKind::Ident(_, _, _) => {
+                        let id = self.expect_ident()?;
+                        self.add_local_var_if_new(id);
+                        Some(id)
+                    }
+                    _ => None,
+                };
+                elems.push(PatternElem::Splat(id));
+            } else {
+                elems.push(PatternElem::Pattern(self.parse_pattern()?));
+            }
+            let comma = match term {
+                Some(_) => self.consume_punct(Punct::Comma)?,
+                None => self.consume_punct_no_term(Punct::Comma)?,
+            };
+            if !comma {
+                if let Some(term) = term {
+                    self.expect_punct(term)?;
+                }
+                break;
+            }
+        }
+        let constant = constant.map(Box::new);
+        let splats: Vec<usize> = elems
+            .iter()
+            .enumerate()
+            .filter(|(_, elem)| match elem {
+                PatternElem::Splat(_) => true,
+                _ => false,
+            })
+            .map(|(i, _)| i)
+            .collect();
+        let mut patterns = vec![];
+        let mut rest = vec![];
+        for elem in elems {
+            match elem {
+                PatternElem::Pattern(pattern) => patterns.push(pattern),
+                PatternElem::Splat(id) => rest.push(id),
+            }
+        }
+        match splats.len() {
+            0 => Ok(Pattern::Array {
+                constant,
+                pre: patterns,
+                rest: PatternRest::None,
+                post: vec![],
+            }),
+            1 => {
+                let post = patterns.split_off(splats[0]);
+                let rest = match rest[0] {
+                    Some(id) => PatternRest::Bind(id),
+                    None => PatternRest::Anonymous,
+                };
+                Ok(Pattern::Array {
+                    constant,
+                    pre: patterns,
+                    rest,
+                    post,
+                })
+            }
+            2 if splats[0] == 0 && splats[1] == patterns.len() + 1 && !patterns.is_empty() => {
+                Ok(Pattern::Find {
+                    constant,
+                    pre: rest[0],
+                    mid: patterns,
+                    post: rest[1],
+                })
+            }
+            _ => Err(self.error_unexpected(loc, "Illegal splats in array pattern.")),
+        }
+    }
+
+    /// Parse a hash pattern until `term`.
+    /// If `term` is None, parse until the end of the comma-separated list.
+    fn parse_hash_pattern(
+        &mut self,
+        constant: Option<Node>,
+        term: impl Into<Option<Punct>>,
+    ) -> Result<Pattern, RubyError> {
+        let term = term.into();
+        let mut pairs = vec![];
+        let mut rest = PatternRest::None;
+        loop {
+            if let Some(term) = term {
+                if self.consume_punct(term)? {
+                    break;
+                }
+            }
+            if self.consume_punct(Punct::DMul)? {
+                if self.consume_reserved(Reserved::Nil)? {
+                    rest = PatternRest::Nil;
+                } else {
+                    let id = self.expect_ident()?;
+                    self.add_local_var_if_new(id);
+                    rest = PatternRest::Bind(id);
+                }
+            } else {
+                let tok = self.get()?;
+                if !tok.can_be_symbol() {
+                    return Err(self.error_unexpected(tok.loc(), "Expect a key of hash pattern."));
+                }
+                let key = self.token_as_symbol(&tok);
+                let key = self.get_ident_id(key);
+                self.expect_punct(Punct::Colon)?;
+                // `key:` without a pattern binds the value to the local variable `key`.
+                let next = self.peek_no_term()?;
+                let pattern = match next.kind {
+                    _ if next.is_term() => None,
+                    TokenKind::Punct(Punct::Comma) => None,
+                    TokenKind::Punct(punct) if Some(punct) == term => None,
+                    TokenKind::Reserved(Reserved::Then)
+                    | TokenKind::Reserved(Reserved::If)
+                    | TokenKind::Reserved(Reserved::Unless) => None,
+                    _ => Some(self.parse_pattern()?),
+                };
+                if pattern.is_none() {
+                    match &tok.kind {
+                        TokenKind::Ident(name, _, _) if !name.ends_with(&['?', '!'][..]) => {
+                            self.add_local_var_if_new(key)
+                        }
+                        _ => {
+                            return Err(self.error_unexpected(
+                                tok.loc(),
+                                "Key must be a valid local variable name.",
+                            ))
+                        }
+                    }
+                }
+                pairs.push((key, pattern));
+            }
+            let comma = match term {
+                Some(_) => self.consume_punct(Punct::Comma)?,
+                None => self.consume_punct_no_term(Punct::Comma)?,
+            };
+            if !comma {
+                if let Some(term) = term {
+                    self.expect_punct(term)?;
+                }
+                break;
+            }
+        }
+        Ok(Pattern::Hash {
+            constant: constant.map(Box::new),
+            pairs,
+            rest,
+        })
+    }
+
+    /// Examine whether the next tokens are a key of hash patterns, e.g. `name:`.
+    fn is_pattern_key(&mut self) -> Result<bool, RubyError> {
+        match self.peek()?.kind {
+            TokenKind::Ident(_, _, _) | TokenKind::Const(_, _, _) | TokenKind::StringLit(_) => {}
+            _ => return Ok(false),
+        };
+        self.save_state();
+        self.get()?;
+        let res = self.peek_no_term()?.kind == TokenKind::Punct(Punct::Colon);
+        self.restore_state();
+        Ok(res)
+    }
+
+    /// Collect local variables which are bound by `pattern`.
+    fn pattern_variables(pattern: &Pattern, vars: &mut Vec<IdentId>) {
+        match pattern {
+            Pattern::Value(_) => {}
+            Pattern::Bind(id) => vars.push(*id),
+            Pattern::Alt(alt) => {
+                for pattern in alt {
+                    Parser::pattern_variables(pattern, vars);
+                }
+            }
+            Pattern::As(pattern, id) => {
+                Parser::pattern_variables(pattern, vars);
+                vars.push(*id);
+            }
+            Pattern::Array {
+                pre, rest, post, ..
+            } => {
+                for pattern in pre.iter().chain(post) {
+                    Parser::pattern_variables(pattern, vars);
+                }
+                if let PatternRest::Bind(id) = rest {
+                    vars.push(*id);
+                }
+            }
+            Pattern::Find { pre, mid, post, .. } => {
+                for pattern in mid {
+                    Parser::pattern_variables(pattern, vars);
+                }
+                vars.extend(pre.iter().chain(post));
+            }
+            Pattern::Hash { pairs, rest, .. } => {
+                for (key, pattern) in pairs {
+                    match pattern {
+                        Some(pattern) => Parser::pattern_variables(pattern, vars),
+                        None => vars.push(*key),
+                    }
+                }
+                if let PatternRest::Bind(id) = rest {
+                    vars.push(*id);
+                }
+            }
+        }
+    }
 }
@@ -42,6 +42,8 @@ pub enum TokenKind {
     Const(String, bool, bool),
     NumLit(i64),
     FloatLit(f64),
+    RationalLit(i64, i64),
+    ImaginaryLit(Box<TokenKind>),
     StringLit(String),
     Reserved(Reserved),
     Punct(Punct),
@@ -59,6 +61,7 @@ pub enum Reserved {
     BEGIN,
     END,
     Alias,
+    And,
     Begin,
     Break,
     Case,
@@ -77,11 +80,16 @@ pub enum Reserved {
     Module,
     Next,
     Nil,
+    Not,
+    Or,
+    Redo,
     Return,
     Rescue,
+    Retry,
     Self_,
     Then,
     True,
+    Undef,
     Until,
     Unless,
     When,
@@ -102,6 +110,8 @@ pub enum Punct {
     Scope,
     Comma,
     Dot,
+    /// `&.`
+    SafeNav,
     Question,
     Range2,
     Range3,
@@ -134,6 +144,7 @@ pub enum Punct {
     Match,
 
     Backslash,
+    BackQuote,
     Arrow,
     FatArrow,
 }
@@ -184,6 +195,14 @@ impl Token {
         Annot::new(TokenKind::FloatLit(num), loc)
     }
 
+    pub fn new_rationallit(num: i64, den: i64, loc: Loc) -> Self {
+        Annot::new(TokenKind::RationalLit(num, den), loc)
+    }
+
+    pub fn new_imaginarylit(num: TokenKind, loc: Loc) -> Self {
+        Annot::new(TokenKind::ImaginaryLit(Box::new(num)), loc)
+    }
+
     pub fn new_stringlit(string: impl Into<String>, loc: Loc) -> Self {
         Annot::new(TokenKind::StringLit(string.into()), loc)
     }
@@ -267,6 +286,7 @@ impl Token {
                 | Reserved::Elsif
                 | Reserved::End
                 | Reserved::When
+                | Reserved::In
                 | Reserved::Rescue
                 | Reserved::Ensure => true,
                 _ => false,
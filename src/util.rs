@@ -116,6 +116,8 @@ pub type SourceInfoRef = Ref<SourceInfo>;
 #[derive(Debug, Clone, PartialEq)]
 pub struct SourceInfo {
     pub path: PathBuf,
+    /// The path given on the command line, which is shown instead of `path` in backtraces.
+    pub given_path: Option<PathBuf>,
     pub code: Vec<char>,
 }
 
@@ -129,6 +131,7 @@ impl SourceInfo {
     pub fn new(path: PathBuf) -> Self {
         SourceInfo {
             path: path,
+            given_path: None,
             code: vec![],
         }
     }
@@ -136,6 +139,19 @@ impl SourceInfo {
         eprintln!("{}", self.path.to_string_lossy());
     }
 
+    /// Returns the path of the source file as given on the command line if any,
+    /// otherwise the path used for loading.
+    pub fn display_path(&self) -> String {
+        let path = self.given_path.as_ref().unwrap_or(&self.path);
+        path.to_string_lossy().to_string()
+    }
+
+    /// Get the line number (1-origin) of the Loc in the source code.
+    pub fn get_line(&self, loc: &Loc) -> usize {
+        let end = std::cmp::min(loc.0 as usize, self.code.len());
+        self.code[..end].iter().filter(|ch| **ch == '\n').count() + 1
+    }
+
     /// Show the location of the Loc in the source code using '^^^'.
     pub fn show_loc(&self, loc: &Loc) {
         let mut line: u32 = 1;
@@ -289,6 +305,9 @@ impl IdentId {
     pub const _NEQ: IdentId = id!(12);
     pub const _GT: IdentId = id!(13);
     pub const _GE: IdentId = id!(14);
+    pub const _DIV: IdentId = id!(15);
+    pub const _LT: IdentId = id!(16);
+    pub const _LE: IdentId = id!(17);
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -320,6 +339,9 @@ impl IdentifierTable {
         table.set_ident_id("!=", IdentId::_NEQ);
         table.set_ident_id(">", IdentId::_GT);
         table.set_ident_id(">=", IdentId::_GE);
+        table.set_ident_id("/", IdentId::_DIV);
+        table.set_ident_id("<", IdentId::_LT);
+        table.set_ident_id("<=", IdentId::_LE);
         table
     }
 
@@ -6,3 +6,5 @@ mod array;
 pub use array::*;
 mod hash;
 pub use hash::*;
+mod shape;
+pub use shape::*;
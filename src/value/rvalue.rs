@@ -1,4 +1,3 @@
-use std::collections::HashMap;
 //#[macro_use]
 use crate::*;
 
@@ -6,7 +5,10 @@ use crate::*;
 #[derive(Debug, Clone, PartialEq)]
 pub struct RValue {
     class: Value,
-    var_table: Box<ValueTable>,
+    /// The shape of instance variables. `None` if the object has no instance variable.
+    shape: Option<ShapeRef>,
+    /// Values of instance variables, indexed by the slot indices in `shape`.
+    ivars: Vec<Value>,
     pub kind: ObjKind,
 }
 
@@ -27,6 +29,10 @@ pub enum ObjKind {
     Method(MethodObjRef),
     Fiber(FiberRef),
     Enumerator(EnumRef),
+    IO(IORef),
+    Time(TimeInfo),
+    Rational(RationalInfo),
+    Complex(ComplexInfo),
 }
 
 impl RValue {
@@ -41,13 +47,18 @@ impl RValue {
     pub fn dup(&self) -> Self {
         RValue {
             class: self.class,
-            var_table: self.var_table.clone(),
+            shape: self.shape,
+            ivars: self.ivars.clone(),
             kind: match &self.kind {
                 ObjKind::Array(aref) => ObjKind::Array(aref.dup()),
                 ObjKind::Class(cref) => ObjKind::Class(cref.dup()),
                 ObjKind::Enumerator(eref) => ObjKind::Enumerator(eref.dup()),
                 ObjKind::Fiber(_fref) => ObjKind::Ordinary,
                 ObjKind::Integer(num) => ObjKind::Integer(*num),
+                ObjKind::IO(ioref) => ObjKind::IO(*ioref),
+                ObjKind::Time(info) => ObjKind::Time(*info),
+                ObjKind::Rational(info) => ObjKind::Rational(*info),
+                ObjKind::Complex(info) => ObjKind::Complex(*info),
                 ObjKind::Float(num) => ObjKind::Float(*num),
                 ObjKind::Hash(href) => ObjKind::Hash(href.dup()),
                 ObjKind::Method(mref) => ObjKind::Method(mref.dup()),
@@ -72,9 +83,9 @@ impl RValue {
 
     pub fn inspect(&self, vm: &mut VM) -> String {
         let mut s = format! {"#<{}:0x{:x}", self.class_name(&vm.globals), self.id()};
-        for (k, v) in self.var_table() {
-            let inspect = vm.val_to_s(*v);
-            let id = vm.globals.get_ident_name(*k);
+        for (k, v) in self.vars() {
+            let inspect = vm.val_to_s(v);
+            let id = vm.globals.get_ident_name(k);
             s = format!("{} {}={}", s, id, inspect);
         }
         format!("{}>", s)
@@ -84,14 +95,16 @@ impl RValue {
         RValue {
             class: Value::nil(), // dummy for boot strapping
             kind: ObjKind::Class(classref),
-            var_table: Box::new(HashMap::new()),
+            shape: None,
+            ivars: vec![],
         }
     }
 
     pub fn new_fixnum(i: i64) -> Self {
         RValue {
             class: Value::nil(),
-            var_table: Box::new(HashMap::new()),
+            shape: None,
+            ivars: vec![],
             kind: ObjKind::Integer(i),
         }
     }
@@ -99,7 +112,8 @@ impl RValue {
     pub fn new_flonum(f: f64) -> Self {
         RValue {
             class: Value::nil(),
-            var_table: Box::new(HashMap::new()),
+            shape: None,
+            ivars: vec![],
             kind: ObjKind::Float(f),
         }
     }
@@ -107,7 +121,8 @@ impl RValue {
     pub fn new_string(globals: &Globals, s: String) -> Self {
         RValue {
             class: globals.builtins.string,
-            var_table: Box::new(HashMap::new()),
+            shape: None,
+            ivars: vec![],
             kind: ObjKind::String(RString::Str(s)),
         }
     }
@@ -115,7 +130,8 @@ impl RValue {
     pub fn new_bytes(globals: &Globals, b: Vec<u8>) -> Self {
         RValue {
             class: globals.builtins.string,
-            var_table: Box::new(HashMap::new()),
+            shape: None,
+            ivars: vec![],
             kind: ObjKind::String(RString::Bytes(b)),
         }
     }
@@ -123,7 +139,8 @@ impl RValue {
     pub fn new_ordinary(class: Value) -> Self {
         RValue {
             class,
-            var_table: Box::new(HashMap::new()),
+            shape: Some(class.as_class().root_shape),
+            ivars: vec![],
             kind: ObjKind::Ordinary,
         }
     }
@@ -131,7 +148,8 @@ impl RValue {
     pub fn new_class(globals: &Globals, classref: ClassRef) -> Self {
         RValue {
             class: globals.builtins.class,
-            var_table: Box::new(HashMap::new()),
+            shape: None,
+            ivars: vec![],
             kind: ObjKind::Class(classref),
         }
     }
@@ -139,7 +157,8 @@ impl RValue {
     pub fn new_module(globals: &Globals, classref: ClassRef) -> Self {
         RValue {
             class: globals.builtins.module,
-            var_table: Box::new(HashMap::new()),
+            shape: None,
+            ivars: vec![],
             kind: ObjKind::Module(classref),
         }
     }
@@ -147,7 +166,8 @@ impl RValue {
     pub fn new_array(globals: &Globals, arrayref: ArrayRef) -> Self {
         RValue {
             class: globals.builtins.array,
-            var_table: Box::new(HashMap::new()),
+            shape: None,
+            ivars: vec![],
             kind: ObjKind::Array(arrayref),
         }
     }
@@ -155,7 +175,8 @@ impl RValue {
     pub fn new_range(globals: &Globals, range: RangeInfo) -> Self {
         RValue {
             class: globals.builtins.range,
-            var_table: Box::new(HashMap::new()),
+            shape: None,
+            ivars: vec![],
             kind: ObjKind::Range(range),
         }
     }
@@ -163,7 +184,8 @@ impl RValue {
     pub fn new_splat(globals: &Globals, val: Value) -> Self {
         RValue {
             class: globals.builtins.array,
-            var_table: Box::new(HashMap::new()),
+            shape: None,
+            ivars: vec![],
             kind: ObjKind::Splat(val),
         }
     }
@@ -171,7 +193,8 @@ impl RValue {
     pub fn new_hash(globals: &Globals, hashref: HashRef) -> Self {
         RValue {
             class: globals.builtins.hash,
-            var_table: Box::new(HashMap::new()),
+            shape: None,
+            ivars: vec![],
             kind: ObjKind::Hash(hashref),
         }
     }
@@ -179,7 +202,8 @@ impl RValue {
     pub fn new_regexp(globals: &Globals, regexpref: RegexpRef) -> Self {
         RValue {
             class: globals.builtins.regexp,
-            var_table: Box::new(HashMap::new()),
+            shape: None,
+            ivars: vec![],
             kind: ObjKind::Regexp(regexpref),
         }
     }
@@ -187,7 +211,8 @@ impl RValue {
     pub fn new_proc(globals: &Globals, procref: ProcRef) -> Self {
         RValue {
             class: globals.builtins.procobj,
-            var_table: Box::new(HashMap::new()),
+            shape: None,
+            ivars: vec![],
             kind: ObjKind::Proc(procref),
         }
     }
@@ -195,7 +220,8 @@ impl RValue {
     pub fn new_method(globals: &Globals, methodref: MethodObjRef) -> Self {
         RValue {
             class: globals.builtins.method,
-            var_table: Box::new(HashMap::new()),
+            shape: None,
+            ivars: vec![],
             kind: ObjKind::Method(methodref),
         }
     }
@@ -210,7 +236,8 @@ impl RValue {
         let fiber = FiberInfo::new(vm, context, rec, tx);
         RValue {
             class: globals.builtins.fiber,
-            var_table: Box::new(HashMap::new()),
+            shape: None,
+            ivars: vec![],
             kind: ObjKind::Fiber(FiberRef::new(fiber)),
         }
     }
@@ -225,10 +252,47 @@ impl RValue {
         let enum_info = EnumRef::from(method, receiver, args);
         RValue {
             class: globals.builtins.enumerator,
-            var_table: Box::new(HashMap::new()),
+            shape: None,
+            ivars: vec![],
             kind: ObjKind::Enumerator(enum_info),
         }
     }
+
+    pub fn new_io(class: Value, info: IOInfo) -> Self {
+        RValue {
+            class,
+            shape: None,
+            ivars: vec![],
+            kind: ObjKind::IO(IORef::new(info)),
+        }
+    }
+
+    pub fn new_time(globals: &Globals, info: TimeInfo) -> Self {
+        RValue {
+            class: globals.builtins.time,
+            shape: None,
+            ivars: vec![],
+            kind: ObjKind::Time(info),
+        }
+    }
+
+    pub fn new_rational(globals: &Globals, info: RationalInfo) -> Self {
+        RValue {
+            class: globals.builtins.rational,
+            shape: None,
+            ivars: vec![],
+            kind: ObjKind::Rational(info),
+        }
+    }
+
+    pub fn new_complex(globals: &Globals, info: ComplexInfo) -> Self {
+        RValue {
+            class: globals.builtins.complex,
+            shape: None,
+            ivars: vec![],
+            kind: ObjKind::Complex(info),
+        }
+    }
 }
 
 pub type ObjectRef = Ref<RValue>;
@@ -264,23 +328,57 @@ impl RValue {
     }
 
     pub fn get_var(&self, id: IdentId) -> Option<Value> {
-        self.var_table.get(&id).cloned()
+        let index = self.shape?.get_index(id)?;
+        Some(self.ivars[index])
     }
 
     pub fn get_mut_var(&mut self, id: IdentId) -> Option<&mut Value> {
-        self.var_table.get_mut(&id)
+        let index = self.shape?.get_index(id)?;
+        Some(&mut self.ivars[index])
     }
 
     pub fn set_var(&mut self, id: IdentId, val: Value) {
-        self.var_table.insert(id, val);
+        match self.get_mut_var(id) {
+            Some(entry) => *entry = val,
+            None => {
+                let shape = match self.shape {
+                    Some(shape) => shape,
+                    None if self.class.is_nil() => Shape::new_root(),
+                    None => self.search_class().as_class().root_shape,
+                };
+                self.add_var(shape.transition(id), val);
+            }
+        }
+    }
+
+    /// Iterate over the names and values of instance variables in the order of definition.
+    pub fn vars(&self) -> impl Iterator<Item = (IdentId, Value)> + '_ {
+        let ids = match &self.shape {
+            Some(shape) => shape.ids(),
+            None => &[],
+        };
+        ids.iter().cloned().zip(self.ivars.iter().cloned())
+    }
+
+    pub fn shape(&self) -> Option<ShapeRef> {
+        self.shape
+    }
+
+    /// Get the value in the slot at `index`, which must be valid in the current shape.
+    pub fn get_slot(&self, index: usize) -> Value {
+        self.ivars[index]
     }
 
-    pub fn var_table(&self) -> &ValueTable {
-        &self.var_table
+    /// Set `val` to the slot at `index`, which must be valid in the current shape.
+    pub fn set_slot(&mut self, index: usize, val: Value) {
+        self.ivars[index] = val;
     }
 
-    pub fn var_table_mut(&mut self) -> &mut ValueTable {
-        &mut self.var_table
+    /// Move to `shape` which has one more instance variable than the current shape,
+    /// and set `val` to the new slot.
+    pub fn add_var(&mut self, shape: ShapeRef, val: Value) {
+        self.shape = Some(shape);
+        self.ivars.push(val);
     }
 
     pub fn get_instance_method(&self, id: IdentId) -> Option<MethodRef> {
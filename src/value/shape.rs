@@ -0,0 +1,155 @@
+use crate::*;
+use std::collections::HashMap;
+
+/// Hidden class of objects, which maps the names of instance variables to slot indices.
+///
+/// Objects with the same instance variables defined in the same order share the same shape,
+/// and store the values of their instance variables in slots in `RValue`.
+/// Shapes form a tree rooted at the empty shape owned by each class,
+/// and a new shape is derived from the current one when an instance variable is added.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shape {
+    /// Slot indices of the instance variables.
+    table: HashMap<IdentId, usize>,
+    /// Names of the instance variables in the order of slots.
+    ids: Vec<IdentId>,
+    /// Shapes derived from this one by adding an instance variable.
+    transitions: HashMap<IdentId, ShapeRef>,
+}
+
+pub type ShapeRef = Ref<Shape>;
+
+impl Shape {
+    /// Allocate a new empty shape.
+    pub fn new_root() -> ShapeRef {
+        ShapeRef::new(Shape {
+            table: HashMap::new(),
+            ids: vec![],
+            transitions: HashMap::new(),
+        })
+    }
+
+    /// Get the slot index of the instance variable `id`.
+    pub fn get_index(&self, id: IdentId) -> Option<usize> {
+        self.table.get(&id).cloned()
+    }
+
+    /// Names of the instance variables in the order of slots.
+    pub fn ids(&self) -> &[IdentId] {
+        &self.ids
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+}
+
+impl ShapeRef {
+    /// Get the shape which has the instance variable `id` in addition to `self`.
+    /// The slot index of `id` in the new shape is `self.len()`.
+    pub fn transition(mut self, id: IdentId) -> ShapeRef {
+        if let Some(shape) = self.transitions.get(&id) {
+            return *shape;
+        }
+        let mut table = self.table.clone();
+        table.insert(id, self.ids.len());
+        let mut ids = self.ids.clone();
+        ids.push(id);
+        let shape = ShapeRef::new(Shape {
+            table,
+            ids,
+            transitions: HashMap::new(),
+        });
+        self.transitions.insert(id, shape);
+        shape
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::*;
+
+    #[test]
+    fn shape_ivars() {
+        let program = r#"
+        class Base
+            def a; @a; end
+        end
+        class Foo < Base
+            def initialize(a, b)
+                @a = a
+                @b = b
+            end
+            def b; @b; end
+            def set_c(c); @c = c; end
+            def c; @c; end
+            def inc; @a += 1; end
+        end
+        class Bar < Base
+            def initialize(a, b)
+                @b = b
+                @a = a
+            end
+        end
+        f = Foo.new(1, 2)
+        g = Foo.new(3, 4)
+        assert(1, f.a)
+        assert(3, g.a)
+        assert(nil, f.c)
+        g.set_c(5)
+        assert(5, g.c)
+        assert(nil, f.c)
+        f.set_c(6)
+        f.set_c(7)
+        assert(7, f.c)
+        assert(5, g.c)
+        assert([:@a, :@b, :@c], f.instance_variables)
+        assert(2, f.inc)
+        assert(2, f.a)
+        h = Bar.new(10, 20)
+        assert(10, h.a)
+        assert([:@b, :@a], h.instance_variables)
+        5.times do
+            assert(2, f.a)
+            assert(10, h.a)
+            assert(3, g.a)
+            assert(20, h.instance_variable_get(:@b))
+        end
+        d = f.dup
+        d.set_c(8)
+        assert(8, d.c)
+        assert(7, f.c)
+        "#;
+        assert_script(program);
+    }
+
+    #[test]
+    fn shape_ivar_addi() {
+        let program = r#"
+        class Foo
+            def inc
+                @x += 1
+            end
+            def init
+                @x = 0
+            end
+        end
+        f = Foo.new
+        f.init
+        3.times { f.inc }
+        assert(3, f.inc - 1)
+        g = Foo.new
+        begin
+            g.inc
+        rescue NoMethodError
+            g.init
+        end
+        assert(1, g.inc)
+        "#;
+        assert_script(program);
+    }
+}
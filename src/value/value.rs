@@ -96,6 +96,7 @@ impl std::hash::Hash for Value {
                     }
                 }
                 ObjKind::Method(lhs) => lhs.inner().hash(state),
+                ObjKind::Rational(lhs) => lhs.hash(state),
                 _ => self.0.hash(state),
             },
         }
@@ -136,6 +137,7 @@ impl PartialEq for Value {
                 (HashInfo::IdentMap(lhs), HashInfo::IdentMap(rhs)) => *lhs == *rhs,
                 _ => false,
             },
+            (ObjKind::Rational(lhs), ObjKind::Rational(rhs)) => *lhs == *rhs,
             (_, _) => false,
         }
     }
@@ -555,6 +557,46 @@ impl Value {
         }
     }
 
+    pub fn as_io(&self) -> Option<IORef> {
+        match self.is_object() {
+            Some(oref) => match oref.kind {
+                ObjKind::IO(ioref) => Some(ioref),
+                _ => None,
+            },
+            None => None,
+        }
+    }
+
+    pub fn as_time(&self) -> Option<TimeInfo> {
+        match self.is_object() {
+            Some(oref) => match oref.kind {
+                ObjKind::Time(info) => Some(info),
+                _ => None,
+            },
+            None => None,
+        }
+    }
+
+    pub fn as_rational(&self) -> Option<RationalInfo> {
+        match self.is_object() {
+            Some(oref) => match oref.kind {
+                ObjKind::Rational(info) => Some(info),
+                _ => None,
+            },
+            None => None,
+        }
+    }
+
+    pub fn as_complex(&self) -> Option<ComplexInfo> {
+        match self.is_object() {
+            Some(oref) => match oref.kind {
+                ObjKind::Complex(info) => Some(info),
+                _ => None,
+            },
+            None => None,
+        }
+    }
+
     pub fn as_symbol(&self) -> Option<IdentId> {
         if self.is_packed_symbol() {
             Some(self.as_packed_symbol())
@@ -725,6 +767,22 @@ impl Value {
     pub fn enumerator(globals: &Globals, method: IdentId, receiver: Value, args: Args) -> Self {
         Value::object(RValue::new_enumerator(globals, method, receiver, args))
     }
+
+    pub fn io(class: Value, info: IOInfo) -> Self {
+        Value::object(RValue::new_io(class, info))
+    }
+
+    pub fn time(globals: &Globals, info: TimeInfo) -> Self {
+        Value::object(RValue::new_time(globals, info))
+    }
+
+    pub fn rational(globals: &Globals, info: RationalInfo) -> Self {
+        Value::object(RValue::new_rational(globals, info))
+    }
+
+    pub fn complex(globals: &Globals, info: ComplexInfo) -> Self {
+        Value::object(RValue::new_complex(globals, info))
+    }
 }
 
 impl Value {
@@ -745,7 +803,7 @@ impl Value {
                     _ => return false,
                 }
             }
-            return false;
+            return self.num_equal(other);
         };
         match (&self.rvalue().kind, &other.rvalue().kind) {
             (ObjKind::Integer(lhs), ObjKind::Integer(rhs)) => *lhs == *rhs,
@@ -758,10 +816,23 @@ impl Value {
                 lhs.start.equal(rhs.start) && lhs.end.equal(rhs.end) && lhs.exclude == rhs.exclude
             }
             (ObjKind::Hash(lhs), ObjKind::Hash(rhs)) => lhs.inner() == rhs.inner(),
+            (ObjKind::Time(lhs), ObjKind::Time(rhs)) => lhs.time == rhs.time,
+            (ObjKind::Rational(_), _)
+            | (ObjKind::Complex(_), _)
+            | (_, ObjKind::Rational(_))
+            | (_, ObjKind::Complex(_)) => self.num_equal(other),
             (_, _) => false,
         }
     }
 
+    /// `==` of numeric values including Rational and Complex.
+    fn num_equal(self, other: Value) -> bool {
+        match (ComplexInfo::from_value(self), ComplexInfo::from_value(other)) {
+            (Some(lhs), Some(rhs)) => lhs.num_eq(&rhs),
+            _ => false,
+        }
+    }
+
     pub fn to_ordering(&self) -> std::cmp::Ordering {
         use std::cmp::Ordering;
         match self.as_fixnum() {
@@ -2,15 +2,27 @@ mod args;
 mod class;
 mod codegen;
 mod context;
+mod coverage;
+mod debugger;
+mod disasm;
 mod executor;
+mod iseq_cache;
+mod jit;
 mod method;
 #[cfg(feature = "perf")]
 mod perf;
+mod profiler;
 pub mod vm_inst;
 
 pub use args::*;
 pub use class::*;
 pub use codegen::{Codegen, ISeq, ISeqPos};
 pub use context::*;
+pub use coverage::*;
+pub use debugger::*;
+pub use disasm::*;
 pub use executor::*;
+pub use iseq_cache::*;
+pub use jit::*;
 pub use method::*;
+pub use profiler::*;
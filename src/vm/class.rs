@@ -1,5 +1,5 @@
 use crate::*;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 #[derive(Debug, Clone)]
 pub struct ClassInfo {
@@ -8,6 +8,13 @@ pub struct ClassInfo {
     pub superclass: Value,
     pub include: Vec<Value>,
     pub is_singleton: bool,
+    /// The empty shape of instances, from which their shapes are derived.
+    pub root_shape: ShapeRef,
+    /// Incremented when the result of method lookup for this class may have changed.
+    /// Method caches record the serial of the receiver's class and are valid while it is unchanged.
+    pub serial: usize,
+    /// Classes whose method lookup went through this class since the last invalidation.
+    pub dependents: HashSet<Value>,
 }
 
 impl ClassInfo {
@@ -18,6 +25,9 @@ impl ClassInfo {
             superclass,
             include: vec![],
             is_singleton: false,
+            root_shape: Shape::new_root(),
+            serial: 0,
+            dependents: HashSet::new(),
         }
     }
 }
@@ -40,4 +50,21 @@ impl ClassRef {
             Some(self.superclass.as_class())
         }
     }
+
+    /// Record that method lookup for `class` went through this class and its included modules.
+    pub fn add_dependent(&mut self, class: Value) {
+        self.dependents.insert(class);
+        for module in self.include.clone() {
+            module.as_module().unwrap().add_dependent(class);
+        }
+    }
+
+    /// Invalidate method caches for this class and the classes which depend on it.
+    /// This must be called when a method is defined in this class or a module is included.
+    pub fn invalidate_method_cache(&mut self) {
+        self.serial += 1;
+        for class in std::mem::take(&mut self.dependents) {
+            class.as_module().unwrap().serial += 1;
+        }
+    }
 }
@@ -1,6 +1,8 @@
 use super::vm_inst::*;
 use crate::error::{ParseErrKind, RubyError, RuntimeErrKind};
-use crate::parse::node::{BinOp, Node, NodeKind, UnOp};
+use crate::parse::node::{
+    BinOp, Node, NodeKind, Pattern, PatternRest, RescueEntry, SendArgs, UnOp,
+};
 use crate::*;
 use std::collections::HashMap;
 
@@ -59,13 +61,19 @@ impl EscapeInfo {
 enum EscapeKind {
     Break,
     Next,
+    Redo,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Context {
     lvar_info: HashMap<IdentId, LvarId>,
     pub iseq_sourcemap: Vec<(ISeqPos, Loc)>,
+    /// The start of each statement, used for line coverage.
+    line_table: Vec<(ISeqPos, Loc)>,
     exceptions: Vec<Exceptions>,
+    exception_table: Vec<ExceptionEntry>,
+    /// The destinations of `retry`, pushed while rescue clauses are generated.
+    retry_dest: Vec<ISeqPos>,
     kind: ContextKind,
 }
 
@@ -92,7 +100,10 @@ impl Context {
         Context {
             lvar_info: HashMap::new(),
             iseq_sourcemap: vec![],
+            line_table: vec![],
             exceptions: vec![],
+            exception_table: vec![],
+            retry_dest: vec![],
             kind: ContextKind::Eval,
         }
     }
@@ -101,7 +112,10 @@ impl Context {
         Context {
             lvar_info,
             iseq_sourcemap: vec![],
+            line_table: vec![],
             exceptions: vec![],
+            exception_table: vec![],
+            retry_dest: vec![],
             kind,
         }
     }
@@ -165,6 +179,14 @@ impl Codegen {
         self.save_loc(iseq, self.loc)
     }
 
+    fn save_line(&mut self, iseq: &mut ISeq, loc: Loc) {
+        self.context_stack
+            .last_mut()
+            .unwrap()
+            .line_table
+            .push((ISeqPos(iseq.len()), loc));
+    }
+
     pub fn context_push(&mut self, lvar: LvarCollector) {
         self.context_stack
             .push(Context::from(lvar.clone_table(), ContextKind::Method));
@@ -298,6 +320,31 @@ impl Codegen {
         ISeqPos(iseq.len())
     }
 
+    /// Add an entry to the exception table which starts at the current position,
+    /// and returns its index.
+    fn gen_enter_rescue(&mut self, iseq: &mut ISeq) -> usize {
+        let table = &mut self.context_mut().exception_table;
+        let index = table.len();
+        iseq.push(Inst::ENTER_RESCUE);
+        Codegen::push32(iseq, index as u32);
+        let start = Codegen::current(iseq);
+        table.push(ExceptionEntry {
+            start,
+            end: start,
+            dest: start,
+        });
+        index
+    }
+
+    fn gen_rescue_match(&mut self, iseq: &mut ISeq, len: usize) {
+        iseq.push(Inst::RESCUE);
+        Codegen::push32(iseq, len as u32);
+    }
+
+    fn gen_raise(&mut self, iseq: &mut ISeq) {
+        iseq.push(Inst::RAISE);
+    }
+
     fn gen_end(&self, iseq: &mut ISeq) {
         iseq.push(Inst::END);
     }
@@ -371,22 +418,32 @@ impl Codegen {
         None
     }
 
-    fn gen_get_instance_var(&mut self, iseq: &mut ISeq, id: IdentId) {
+    fn gen_get_instance_var(&mut self, iseq: &mut ISeq, globals: &mut Globals, id: IdentId) {
         iseq.push(Inst::GET_IVAR);
         Codegen::push32(iseq, id.into());
+        Codegen::push32(iseq, globals.add_ivar_cache_entry());
     }
 
-    fn gen_set_instance_var(&mut self, iseq: &mut ISeq, id: IdentId) {
+    fn gen_set_instance_var(&mut self, iseq: &mut ISeq, globals: &mut Globals, id: IdentId) {
         iseq.push(Inst::SET_IVAR);
         Codegen::push32(iseq, id.into());
+        Codegen::push32(iseq, globals.add_ivar_cache_entry());
     }
 
-    fn gen_ivar_addi(&mut self, iseq: &mut ISeq, id: IdentId, val: u32, use_value: bool) {
+    fn gen_ivar_addi(
+        &mut self,
+        iseq: &mut ISeq,
+        globals: &mut Globals,
+        id: IdentId,
+        val: u32,
+        use_value: bool,
+    ) {
         iseq.push(Inst::IVAR_ADDI);
         Codegen::push32(iseq, id.into());
         Codegen::push32(iseq, val);
+        Codegen::push32(iseq, globals.add_ivar_cache_entry());
         if use_value {
-            self.gen_get_instance_var(iseq, id);
+            self.gen_get_instance_var(iseq, globals, id);
         }
     }
 
@@ -448,6 +505,61 @@ impl Codegen {
         )
     }
 
+    /// Push arguments, keyword arguments and a block argument of a method call onto the stack,
+    /// and returns the argument flag and the block (if given as a literal block).
+    fn gen_send_args(
+        &mut self,
+        globals: &mut Globals,
+        iseq: &mut ISeq,
+        send_args: &SendArgs,
+    ) -> Result<(usize, Option<MethodRef>), RubyError> {
+        for arg in &send_args.args {
+            self.gen(globals, iseq, arg, true)?;
+        }
+        let kw_flag = !send_args.kw_args.is_empty() || !send_args.kw_splat.is_empty();
+        if kw_flag {
+            for (id, default) in &send_args.kw_args {
+                self.gen_symbol(iseq, *id);
+                self.gen(globals, iseq, default, true)?;
+            }
+            self.gen_create_hash(iseq, send_args.kw_args.len());
+            for kw_splat in &send_args.kw_splat {
+                self.gen(globals, iseq, kw_splat, true)?;
+                self.save_loc(iseq, kw_splat.loc());
+                iseq.push(Inst::DOUBLE_SPLAT);
+            }
+        }
+        let mut block_flag = false;
+        let block_ref = match &send_args.block {
+            Some(block) => match &block.kind {
+                NodeKind::Proc { params, body, lvar } => {
+                    self.loop_stack.push(LoopInfo::new_top());
+                    let methodref =
+                        self.gen_iseq(globals, params, body, lvar, true, ContextKind::Block, None)?;
+                    self.loop_stack.pop().unwrap();
+                    Some(methodref)
+                }
+                _ => {
+                    self.gen(globals, iseq, block, true)?;
+                    block_flag = true;
+                    None
+                }
+            },
+            None => None,
+        };
+
+        /// Create flag for argument info.
+        /// 0b0011
+        ///     ||
+        ///     |+- 1: keyword args exists. 0: no keyword args,
+        ///     +-- 1: a block arg exists. 0: no block arg.
+        fn create_flag(kw_flag: bool, block_flag: bool) -> usize {
+            (if kw_flag { 1usize } else { 0usize }) + (if block_flag { 2usize } else { 0usize })
+        }
+
+        Ok((create_flag(kw_flag, block_flag), block_ref))
+    }
+
     fn gen_send_self(
         &mut self,
         globals: &mut Globals,
@@ -485,21 +597,37 @@ impl Codegen {
                 self.gen_push_nil(iseq);
                 self.gen_set_const(iseq, *id);
             }
-            NodeKind::InstanceVar(id) => self.gen_set_instance_var(iseq, *id),
+            NodeKind::InstanceVar(id) => self.gen_set_instance_var(iseq, globals, *id),
             NodeKind::GlobalVar(id) => self.gen_set_global_var(iseq, *id),
             NodeKind::Scope(parent, id) => {
                 self.gen(globals, iseq, parent, true)?;
                 self.gen_set_const(iseq, *id);
             }
             NodeKind::Send {
-                receiver, method, ..
+                receiver,
+                method,
+                safe_nav,
+                ..
             } => {
                 let name = globals.get_ident_name(*method).to_string() + "=";
                 let assign_id = globals.get_ident_id(name);
                 self.gen(globals, iseq, &receiver, true)?;
-                self.loc = lhs.loc();
-                self.gen_send(globals, iseq, assign_id, 1, 0, None);
-                self.gen_pop(iseq);
+                if *safe_nav {
+                    // When the receiver is nil, discard both the receiver and the value.
+                    let nil_jmp = self.gen_jmp_if_nil(iseq);
+                    self.loc = lhs.loc();
+                    self.gen_send(globals, iseq, assign_id, 1, 0, None);
+                    self.gen_pop(iseq);
+                    let end = Codegen::gen_jmp(iseq);
+                    Codegen::write_disp_from_cur(iseq, nil_jmp);
+                    self.gen_pop(iseq);
+                    self.gen_pop(iseq);
+                    Codegen::write_disp_from_cur(iseq, end);
+                } else {
+                    self.loc = lhs.loc();
+                    self.gen_send(globals, iseq, assign_id, 1, 0, None);
+                    self.gen_pop(iseq);
+                }
             }
             NodeKind::ArrayMember { array, index } => {
                 self.gen(globals, iseq, array, true)?;
@@ -509,11 +637,529 @@ impl Codegen {
                 self.save_loc(iseq, lhs.loc());
                 self.gen_set_array_elem(iseq, index.len());
             }
+            NodeKind::Mlhs(mlhs) => self.gen_mlhs_assign(globals, iseq, mlhs)?,
+            _ => return Err(self.error_syntax(format!("Unimplemented LHS form."), lhs.loc())),
+        }
+        Ok(())
+    }
+
+    /// Destructure the value on the stack top and assign the elements to `mlhs`,
+    /// which may contain one splat target and nested targets.
+    fn gen_mlhs_assign(
+        &mut self,
+        globals: &mut Globals,
+        iseq: &mut ISeq,
+        mlhs: &[Node],
+    ) -> Result<(), RubyError> {
+        let splat_pos = mlhs
+            .iter()
+            .position(|lhs| matches!(lhs.kind, NodeKind::Splat(_)));
+        match splat_pos {
+            Some(pos) => self.gen_expand_array(iseq, pos, mlhs.len() - pos - 1),
+            None => self.gen_take(iseq, mlhs.len()),
+        }
+        for lhs in mlhs.iter().rev() {
+            match &lhs.kind {
+                NodeKind::Splat(target) => self.gen_assign(globals, iseq, target)?,
+                _ => self.gen_assign(globals, iseq, lhs)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Generate `defined?(node)`, which pushes a String describing `node`, or nil if `node`
+    /// is not defined. `node` itself is not evaluated, except for receivers of method calls.
+    fn gen_defined(
+        &mut self,
+        globals: &mut Globals,
+        iseq: &mut ISeq,
+        node: &Node,
+    ) -> Result<(), RubyError> {
+        let super_id = globals.get_ident_id("super");
+        let (id, kind) = match &node.kind {
+            NodeKind::Ident(id) if *id == super_id => (*id, DefinedKind::SUPER),
+            NodeKind::Ident(id) => (*id, DefinedKind::FUNC),
+            NodeKind::InstanceVar(id) => (*id, DefinedKind::IVAR),
+            NodeKind::GlobalVar(id) => (*id, DefinedKind::GVAR),
+            NodeKind::Const { id, .. } => (*id, DefinedKind::CONST),
+            NodeKind::Yield(_) => (globals.get_ident_id("yield"), DefinedKind::YIELD),
+            NodeKind::Send {
+                receiver, method, ..
+            } if receiver.kind == NodeKind::SelfValue && *method == super_id => {
+                (*method, DefinedKind::SUPER)
+            }
+            NodeKind::Send {
+                receiver, method, ..
+            } if receiver.kind == NodeKind::SelfValue => (*method, DefinedKind::FUNC),
+            NodeKind::Send {
+                receiver, method, ..
+            } => {
+                let end = self.gen_defined_receiver(globals, iseq, receiver)?;
+                self.gen_defined_inst(iseq, *method, DefinedKind::METHOD);
+                Codegen::write_disp_from_cur(iseq, end);
+                return Ok(());
+            }
+            NodeKind::Scope(parent, id) => {
+                let end = self.gen_defined_receiver(globals, iseq, parent)?;
+                self.gen_defined_inst(iseq, *id, DefinedKind::SCOPED_CONST);
+                Codegen::write_disp_from_cur(iseq, end);
+                return Ok(());
+            }
+            NodeKind::ArrayMember { array, .. } => {
+                let end = self.gen_defined_receiver(globals, iseq, array)?;
+                self.gen_pop(iseq);
+                self.gen_string(globals, iseq, "method");
+                Codegen::write_disp_from_cur(iseq, end);
+                return Ok(());
+            }
+            kind => {
+                let name = match kind {
+                    NodeKind::LocalVar(_) => "local-variable",
+                    NodeKind::SelfValue => "self",
+                    NodeKind::Nil => "nil",
+                    NodeKind::Bool(true) => "true",
+                    NodeKind::Bool(false) => "false",
+                    NodeKind::MulAssign(..) | NodeKind::AssignOp(..) => "assignment",
+                    NodeKind::BinOp(BinOp::LAnd, ..) | NodeKind::BinOp(BinOp::LOr, ..) => {
+                        "expression"
+                    }
+                    NodeKind::BinOp(..) | NodeKind::UnOp(..) => "method",
+                    _ => "expression",
+                };
+                self.gen_string(globals, iseq, name);
+                return Ok(());
+            }
+        };
+        self.gen_defined_inst(iseq, id, kind);
+        Ok(())
+    }
+
+    /// Push `receiver` if it is defined. Otherwise, jump to the returned position with nil
+    /// on the stack.
+    fn gen_defined_receiver(
+        &mut self,
+        globals: &mut Globals,
+        iseq: &mut ISeq,
+        receiver: &Node,
+    ) -> Result<ISeqPos, RubyError> {
+        self.gen_defined(globals, iseq, receiver)?;
+        self.gen_dup(iseq, 1);
+        let end = self.gen_jmp_if_false(iseq);
+        self.gen_pop(iseq);
+        self.gen(globals, iseq, receiver, true)?;
+        Ok(end)
+    }
+
+    fn gen_defined_inst(&mut self, iseq: &mut ISeq, id: IdentId, kind: u32) {
+        iseq.push(Inst::DEFINED);
+        Codegen::push32(iseq, id.into());
+        Codegen::push32(iseq, kind);
+    }
+
+    /// Generate `lhs op= rhs` for an attribute or an index.
+    /// The receiver and the indexes are evaluated only once, and the value of the whole
+    /// expression is left on the stack.
+    fn gen_assign_op(
+        &mut self,
+        globals: &mut Globals,
+        iseq: &mut ISeq,
+        op: BinOp,
+        lhs: &Node,
+        rhs: &Node,
+        loc: Loc,
+    ) -> Result<(), RubyError> {
+        // A slot for the value of the whole expression.
+        self.gen_push_nil(iseq);
+        // Push the receiver (and the indexes), and read the current value.
+        let (len, nil_jmp) = match &lhs.kind {
+            NodeKind::Send {
+                receiver,
+                method,
+                safe_nav,
+                ..
+            } => {
+                self.gen(globals, iseq, receiver, true)?;
+                let nil_jmp = if *safe_nav {
+                    Some(self.gen_jmp_if_nil(iseq))
+                } else {
+                    None
+                };
+                self.gen_topn(iseq, 0);
+                self.loc = lhs.loc();
+                self.gen_send(globals, iseq, *method, 0, 0, None);
+                (1, nil_jmp)
+            }
+            NodeKind::ArrayMember { array, index } => {
+                self.gen(globals, iseq, array, true)?;
+                for i in index {
+                    self.gen(globals, iseq, i, true)?;
+                }
+                self.gen_dup(iseq, index.len() + 1);
+                self.save_loc(iseq, lhs.loc());
+                self.gen_get_array_elem(iseq, index.len());
+                (index.len() + 1, None)
+            }
             _ => return Err(self.error_syntax(format!("Unimplemented LHS form."), lhs.loc())),
+        };
+        // Compute the new value, or skip the assignment.
+        let skip = match op {
+            BinOp::LOr => {
+                self.gen_topn(iseq, 0);
+                let assign = self.gen_jmp_if_false(iseq);
+                self.gen_setn(iseq, len + 1);
+                self.gen_pop(iseq);
+                let skip = Codegen::gen_jmp(iseq);
+                Codegen::write_disp_from_cur(iseq, assign);
+                self.gen_pop(iseq);
+                self.gen(globals, iseq, rhs, true)?;
+                Some(skip)
+            }
+            BinOp::LAnd => {
+                self.gen_topn(iseq, 0);
+                let no_assign = self.gen_jmp_if_false(iseq);
+                self.gen_pop(iseq);
+                self.gen(globals, iseq, rhs, true)?;
+                let assign = Codegen::gen_jmp(iseq);
+                Codegen::write_disp_from_cur(iseq, no_assign);
+                self.gen_setn(iseq, len + 1);
+                self.gen_pop(iseq);
+                let skip = Codegen::gen_jmp(iseq);
+                Codegen::write_disp_from_cur(iseq, assign);
+                Some(skip)
+            }
+            _ => {
+                self.gen(globals, iseq, rhs, true)?;
+                self.gen_binop(globals, iseq, op, loc)?;
+                None
+            }
+        };
+        // Write back the new value.
+        self.gen_setn(iseq, len + 1);
+        match &lhs.kind {
+            NodeKind::Send { method, .. } => {
+                let name = globals.get_ident_name(*method).to_string() + "=";
+                let assign_id = globals.get_ident_id(name);
+                self.gen_topn(iseq, 1);
+                self.loc = lhs.loc();
+                self.gen_send(globals, iseq, assign_id, 1, 0, None);
+                self.gen_pop(iseq);
+            }
+            _ => {
+                for _ in 0..len {
+                    self.gen_topn(iseq, len);
+                }
+                self.save_loc(iseq, lhs.loc());
+                self.gen_set_array_elem(iseq, len - 1);
+            }
+        }
+        if let Some(skip) = skip {
+            Codegen::write_disp_from_cur(iseq, skip);
+        }
+        if let Some(nil_jmp) = nil_jmp {
+            Codegen::write_disp_from_cur(iseq, nil_jmp);
+        }
+        for _ in 0..len {
+            self.gen_pop(iseq);
+        }
+        Ok(())
+    }
+
+    /// Generate `body` protected by `rescue` clauses, followed by the `else` clause.
+    fn gen_rescue(
+        &mut self,
+        globals: &mut Globals,
+        iseq: &mut ISeq,
+        body: &Node,
+        rescue: &[RescueEntry],
+        else_: &Node,
+        use_value: bool,
+    ) -> Result<(), RubyError> {
+        let has_else = !else_.is_nop();
+        let retry_dest = Codegen::current(iseq);
+        let entry = self.gen_enter_rescue(iseq);
+        self.gen(globals, iseq, body, use_value && !has_else)?;
+        self.context_mut().exception_table[entry].end = Codegen::current(iseq);
+        if has_else {
+            self.gen(globals, iseq, else_, use_value)?;
+        }
+        let mut end = vec![Codegen::gen_jmp(iseq)];
+        // The exception object is on the stack.
+        self.context_mut().exception_table[entry].dest = Codegen::current(iseq);
+        self.context_mut().retry_dest.push(retry_dest);
+        for entry in rescue {
+            for class in &entry.exception_list {
+                self.gen(globals, iseq, class, true)?;
+            }
+            self.save_cur_loc(iseq);
+            self.gen_rescue_match(iseq, entry.exception_list.len());
+            let next = self.gen_jmp_if_false(iseq);
+            match &entry.assign {
+                Some(lhs) => self.gen_assign(globals, iseq, lhs)?,
+                None => self.gen_pop(iseq),
+            }
+            self.gen(globals, iseq, &entry.body, use_value)?;
+            end.push(Codegen::gen_jmp(iseq));
+            Codegen::write_disp_from_cur(iseq, next);
+        }
+        self.context_mut().retry_dest.pop().unwrap();
+        self.gen_raise(iseq);
+        for src in end {
+            Codegen::write_disp_from_cur(iseq, src);
         }
         Ok(())
     }
 
+    /// Generate the code which matches the value on the top of the stack against `pattern`.
+    /// The value is consumed and the result (true or false) is pushed.
+    fn gen_pattern(
+        &mut self,
+        globals: &mut Globals,
+        iseq: &mut ISeq,
+        pattern: &Pattern,
+    ) -> Result<(), RubyError> {
+        match pattern {
+            Pattern::Value(node) => {
+                self.gen(globals, iseq, node, true)?;
+                self.save_loc(iseq, node.loc());
+                iseq.push(Inst::TEQ);
+            }
+            Pattern::Bind(id) => {
+                self.gen_set_local(iseq, *id);
+                iseq.push(Inst::PUSH_TRUE);
+            }
+            Pattern::Alt(alts) => {
+                let mut end = vec![];
+                let (last, alts) = alts.split_last().unwrap();
+                for alt in alts {
+                    self.gen_topn(iseq, 0);
+                    self.gen_pattern(globals, iseq, alt)?;
+                    let next = self.gen_jmp_if_false(iseq);
+                    self.gen_pop(iseq);
+                    iseq.push(Inst::PUSH_TRUE);
+                    end.push(Codegen::gen_jmp(iseq));
+                    Codegen::write_disp_from_cur(iseq, next);
+                }
+                self.gen_pattern(globals, iseq, last)?;
+                for src in end {
+                    Codegen::write_disp_from_cur(iseq, src);
+                }
+            }
+            Pattern::As(pattern, id) => {
+                self.gen_topn(iseq, 0);
+                self.gen_pattern(globals, iseq, pattern)?;
+                let fail = self.gen_jmp_if_false(iseq);
+                self.gen_topn(iseq, 0);
+                self.gen_set_local(iseq, *id);
+                self.gen_pattern_end(iseq, vec![fail]);
+            }
+            Pattern::Array {
+                constant,
+                pre,
+                rest,
+                post,
+            } => {
+                let mut fails = vec![];
+                self.gen_pattern_const(globals, iseq, constant, &mut fails)?;
+                // Replace the value with the deconstructed Array, or nil.
+                let len = pre.len() + post.len();
+                self.save_cur_loc(iseq);
+                iseq.push(Inst::DECONSTRUCT);
+                Codegen::push32(iseq, len as u32);
+                Codegen::push32(iseq, (*rest == PatternRest::None) as u32);
+                self.gen_topn(iseq, 0);
+                fails.push(self.gen_jmp_if_false(iseq));
+                for (i, pattern) in pre.iter().enumerate() {
+                    self.gen_topn(iseq, 0);
+                    self.gen_fixnum(iseq, i as i64);
+                    self.gen_get_array_elem(iseq, 1);
+                    self.gen_pattern(globals, iseq, pattern)?;
+                    fails.push(self.gen_jmp_if_false(iseq));
+                }
+                if let PatternRest::Bind(id) = rest {
+                    let size = globals.get_ident_id("size");
+                    self.gen_topn(iseq, 0);
+                    self.gen_topn(iseq, 0);
+                    self.gen_send(globals, iseq, size, 0, 0, None);
+                    self.gen_subi(iseq, len as i32);
+                    self.gen_fixnum(iseq, pre.len() as i64);
+                    self.gen_get_array_elem(iseq, 2);
+                    self.gen_set_local(iseq, *id);
+                }
+                for (i, pattern) in post.iter().enumerate() {
+                    self.gen_topn(iseq, 0);
+                    self.gen_fixnum(iseq, i as i64 - post.len() as i64);
+                    self.gen_get_array_elem(iseq, 1);
+                    self.gen_pattern(globals, iseq, pattern)?;
+                    fails.push(self.gen_jmp_if_false(iseq));
+                }
+                self.gen_pattern_end(iseq, fails);
+            }
+            Pattern::Find {
+                constant,
+                pre,
+                mid,
+                post,
+            } => {
+                let mut fails = vec![];
+                self.gen_pattern_const(globals, iseq, constant, &mut fails)?;
+                let len = mid.len();
+                let size = globals.get_ident_id("size");
+                self.save_cur_loc(iseq);
+                iseq.push(Inst::DECONSTRUCT);
+                Codegen::push32(iseq, len as u32);
+                Codegen::push32(iseq, 0);
+                self.gen_topn(iseq, 0);
+                fails.push(self.gen_jmp_if_false(iseq));
+                // Try each start position with the stack [ary, start].
+                self.gen_fixnum(iseq, 0);
+                let loop_start = Codegen::current(iseq);
+                self.gen_topn(iseq, 1);
+                self.gen_send(globals, iseq, size, 0, 0, None);
+                self.gen_topn(iseq, 1);
+                self.gen_addi(iseq, len as i32);
+                iseq.push(Inst::GE);
+                let exhausted = self.gen_jmp_if_false(iseq);
+                let mut next = vec![];
+                for (i, pattern) in mid.iter().enumerate() {
+                    self.gen_topn(iseq, 1);
+                    self.gen_topn(iseq, 1);
+                    self.gen_addi(iseq, i as i32);
+                    self.gen_get_array_elem(iseq, 1);
+                    self.gen_pattern(globals, iseq, pattern)?;
+                    next.push(self.gen_jmp_if_false(iseq));
+                }
+                if let Some(id) = pre {
+                    self.gen_topn(iseq, 1);
+                    self.gen_topn(iseq, 1);
+                    self.gen_fixnum(iseq, 0);
+                    self.gen_get_array_elem(iseq, 2);
+                    self.gen_set_local(iseq, *id);
+                }
+                if let Some(id) = post {
+                    self.gen_topn(iseq, 1);
+                    self.gen_topn(iseq, 0);
+                    self.gen_send(globals, iseq, size, 0, 0, None);
+                    self.gen_topn(iseq, 2);
+                    self.gen_addi(iseq, len as i32);
+                    self.gen_get_array_elem(iseq, 2);
+                    self.gen_set_local(iseq, *id);
+                }
+                self.gen_pop(iseq);
+                let success = Codegen::gen_jmp(iseq);
+                for src in next {
+                    Codegen::write_disp_from_cur(iseq, src);
+                }
+                self.gen_addi(iseq, 1);
+                self.gen_jmp_back(iseq, loop_start);
+                Codegen::write_disp_from_cur(iseq, exhausted);
+                self.gen_pop(iseq);
+                fails.push(Codegen::gen_jmp(iseq));
+                Codegen::write_disp_from_cur(iseq, success);
+                self.gen_pattern_end(iseq, fails);
+            }
+            Pattern::Hash {
+                constant,
+                pairs,
+                rest,
+            } => {
+                let mut fails = vec![];
+                self.gen_pattern_const(globals, iseq, constant, &mut fails)?;
+                // Replace the value with the deconstructed Hash, or nil.
+                match rest {
+                    PatternRest::Bind(_) | PatternRest::Anonymous => self.gen_push_nil(iseq),
+                    _ => {
+                        // CREATE_ARRAY takes the elements from the stack top.
+                        for (key, _) in pairs.iter().rev() {
+                            self.gen_symbol(iseq, *key);
+                        }
+                        self.gen_create_array(iseq, pairs.len());
+                    }
+                }
+                self.save_cur_loc(iseq);
+                iseq.push(Inst::DECONSTRUCT_KEYS);
+                self.gen_topn(iseq, 0);
+                fails.push(self.gen_jmp_if_false(iseq));
+                let has_key = globals.get_ident_id("key?");
+                for (key, pattern) in pairs {
+                    self.gen_symbol(iseq, *key);
+                    self.gen_topn(iseq, 1);
+                    self.gen_send(globals, iseq, has_key, 1, 0, None);
+                    fails.push(self.gen_jmp_if_false(iseq));
+                    self.gen_topn(iseq, 0);
+                    self.gen_symbol(iseq, *key);
+                    self.gen_get_array_elem(iseq, 1);
+                    match pattern {
+                        Some(pattern) => {
+                            self.gen_pattern(globals, iseq, pattern)?;
+                            fails.push(self.gen_jmp_if_false(iseq));
+                        }
+                        None => self.gen_set_local(iseq, *key),
+                    }
+                }
+                match rest {
+                    PatternRest::Bind(id) => {
+                        let dup = globals.get_ident_id("dup");
+                        let delete = globals.get_ident_id("delete");
+                        self.gen_topn(iseq, 0);
+                        self.gen_send(globals, iseq, dup, 0, 0, None);
+                        for (key, _) in pairs {
+                            self.gen_symbol(iseq, *key);
+                            self.gen_topn(iseq, 1);
+                            self.gen_send(globals, iseq, delete, 1, 0, None);
+                            self.gen_pop(iseq);
+                        }
+                        self.gen_set_local(iseq, *id);
+                    }
+                    PatternRest::Nil | PatternRest::None
+                        if *rest == PatternRest::Nil || pairs.is_empty() =>
+                    {
+                        // `**nil` and `{}` reject any other keys.
+                        let size = globals.get_ident_id("size");
+                        self.gen_topn(iseq, 0);
+                        self.gen_send(globals, iseq, size, 0, 0, None);
+                        self.gen_fixnum(iseq, pairs.len() as i64);
+                        iseq.push(Inst::EQ);
+                        fails.push(self.gen_jmp_if_false(iseq));
+                    }
+                    _ => {}
+                }
+                self.gen_pattern_end(iseq, fails);
+            }
+        }
+        Ok(())
+    }
+
+    /// Check `constant === value` for `Const(...)` and `Const[...]` patterns.
+    fn gen_pattern_const(
+        &mut self,
+        globals: &mut Globals,
+        iseq: &mut ISeq,
+        constant: &Option<Box<Node>>,
+        fails: &mut Vec<ISeqPos>,
+    ) -> Result<(), RubyError> {
+        if let Some(constant) = constant {
+            self.gen_topn(iseq, 0);
+            self.gen(globals, iseq, constant, true)?;
+            self.save_loc(iseq, constant.loc());
+            iseq.push(Inst::TEQ);
+            fails.push(self.gen_jmp_if_false(iseq));
+        }
+        Ok(())
+    }
+
+    /// Replace the value on the top of the stack with true, or with false when jumped from `fails`.
+    fn gen_pattern_end(&mut self, iseq: &mut ISeq, fails: Vec<ISeqPos>) {
+        self.gen_pop(iseq);
+        iseq.push(Inst::PUSH_TRUE);
+        let end = Codegen::gen_jmp(iseq);
+        for src in fails {
+            Codegen::write_disp_from_cur(iseq, src);
+        }
+        self.gen_pop(iseq);
+        iseq.push(Inst::PUSH_FALSE);
+        Codegen::write_disp_from_cur(iseq, end);
+    }
+
     fn gen_pop(&mut self, iseq: &mut ISeq) {
         iseq.push(Inst::POP);
     }
@@ -523,11 +1169,126 @@ impl Codegen {
         Codegen::push32(iseq, len as u32);
     }
 
+    fn gen_topn(&mut self, iseq: &mut ISeq, pos: usize) {
+        iseq.push(Inst::TOPN);
+        Codegen::push32(iseq, pos as u32);
+    }
+
+    fn gen_setn(&mut self, iseq: &mut ISeq, pos: usize) {
+        iseq.push(Inst::SETN);
+        Codegen::push32(iseq, pos as u32);
+    }
+
+    /// Jump if the top of the stack is nil. The value is left on the stack in both cases.
+    fn gen_jmp_if_nil(&mut self, iseq: &mut ISeq) -> ISeqPos {
+        self.gen_topn(iseq, 0);
+        self.gen_push_nil(iseq);
+        iseq.push(Inst::NE);
+        self.gen_jmp_if_false(iseq)
+    }
+
+    /// Returns true if `node` is a method call whose receiver chain contains `&.`,
+    /// e.g. `a&.foo` or `a&.foo.bar`.
+    fn in_safe_nav_chain(node: &Node) -> bool {
+        match &node.kind {
+            NodeKind::Send {
+                receiver, safe_nav, ..
+            } => *safe_nav || Codegen::in_safe_nav_chain(receiver),
+            _ => false,
+        }
+    }
+
+    /// Generate a method call in a call chain containing `&.`.
+    /// The receiver is evaluated before the arguments, and the call (including the evaluation
+    /// of arguments) is skipped if the receiver of `&.` is nil.
+    /// Returns the jumps taken when a receiver is nil, with the nil left on the stack,
+    /// which the caller points to the end of the whole chain.
+    fn gen_safe_nav_chain(
+        &mut self,
+        globals: &mut Globals,
+        iseq: &mut ISeq,
+        node: &Node,
+    ) -> Result<Vec<ISeqPos>, RubyError> {
+        let (receiver, method, send_args, safe_nav) = match &node.kind {
+            NodeKind::Send {
+                receiver,
+                method,
+                send_args,
+                safe_nav,
+                ..
+            } => (receiver, method, send_args, safe_nav),
+            _ => unreachable!(),
+        };
+        let loc = node.loc();
+        let mut nil_jmps = if Codegen::in_safe_nav_chain(receiver) {
+            self.gen_safe_nav_chain(globals, iseq, receiver)?
+        } else {
+            self.gen(globals, iseq, receiver, true)?;
+            vec![]
+        };
+        if *safe_nav {
+            nil_jmps.push(self.gen_jmp_if_nil(iseq));
+        }
+        let (flag, block_ref) = self.gen_send_args(globals, iseq, send_args)?;
+        let stack_len = send_args.args.len() + (flag & 1) + (flag >> 1);
+        self.gen_topn(iseq, stack_len);
+        self.loc = loc;
+        self.gen_send(
+            globals,
+            iseq,
+            *method,
+            send_args.args.len(),
+            flag,
+            block_ref,
+        );
+        self.gen_setn(iseq, 1);
+        self.gen_pop(iseq);
+        Ok(nil_jmps)
+    }
+
+    /// Generate an instruction for a binary operator of `op=`.
+    fn gen_binop(
+        &mut self,
+        globals: &mut Globals,
+        iseq: &mut ISeq,
+        op: BinOp,
+        loc: Loc,
+    ) -> Result<(), RubyError> {
+        self.save_loc(iseq, loc);
+        match op {
+            BinOp::Add => self.gen_add(iseq, globals),
+            BinOp::Sub => self.gen_sub(iseq, globals),
+            BinOp::Mul => {
+                iseq.push(Inst::MUL);
+                Codegen::push32(iseq, globals.add_inline_cache_entry() as u32);
+            }
+            BinOp::Shl => {
+                iseq.push(Inst::SHL);
+                Codegen::push32(iseq, globals.add_inline_cache_entry() as u32);
+            }
+            BinOp::Div => iseq.push(Inst::DIV),
+            BinOp::Exp => iseq.push(Inst::POW),
+            BinOp::Rem => iseq.push(Inst::REM),
+            BinOp::Shr => iseq.push(Inst::SHR),
+            BinOp::BitOr => iseq.push(Inst::BIT_OR),
+            BinOp::BitAnd => iseq.push(Inst::BIT_AND),
+            BinOp::BitXor => iseq.push(Inst::BIT_XOR),
+            _ => return Err(self.error_syntax(format!("Unsupported operator {:?}=.", op), loc)),
+        }
+        Ok(())
+    }
+
     fn gen_take(&mut self, iseq: &mut ISeq, len: usize) {
         iseq.push(Inst::TAKE);
         Codegen::push32(iseq, len as u32);
     }
 
+    fn gen_expand_array(&mut self, iseq: &mut ISeq, pre_len: usize, post_len: usize) {
+        iseq.push(Inst::EXPAND_ARRAY);
+        Codegen::push32(iseq, pre_len as u32);
+        Codegen::push32(iseq, post_len as u32);
+    }
+
     fn gen_concat(&mut self, iseq: &mut ISeq) {
         iseq.push(Inst::CONCAT_STRING);
     }
@@ -545,14 +1306,11 @@ impl Codegen {
                     self.gen_push_nil(iseq)
                 }
             }
-            1 => {
-                self.gen(globals, iseq, &nodes[0], use_value)?;
-            }
-            _ => {
-                for i in 0..nodes.len() - 1 {
-                    self.gen(globals, iseq, &nodes[i], false)?;
+            len => {
+                for (i, node) in nodes.iter().enumerate() {
+                    self.save_line(iseq, node.loc());
+                    self.gen(globals, iseq, node, use_value && i == len - 1)?;
                 }
-                self.gen(globals, iseq, &nodes[nodes.len() - 1], use_value)?;
             }
         }
         Ok(())
@@ -585,6 +1343,9 @@ impl Codegen {
         let mut block_param = false;
         let mut param_ident = vec![];
         let mut keyword_params = HashMap::new();
+        let mut req_keyword_params = vec![];
+        let mut kwrest_param = None;
+        let mut no_kw_param = false;
         let mut iseq = ISeq::new();
 
         self.context_stack
@@ -623,9 +1384,14 @@ impl Codegen {
                             self.gen_set_local(&mut iseq, *id);
                             Codegen::write_disp_from_cur(&mut iseq, src1);
                         }
-                        None => {}
+                        None => req_keyword_params.push(*id),
                     }
                 }
+                NodeKind::KWRestParam(id) => {
+                    param_ident.push(*id);
+                    kwrest_param = Some(LvarId::from_usize(lvar_id));
+                }
+                NodeKind::NoKWParam => no_kw_param = true,
                 NodeKind::BlockParam(id) => {
                     param_ident.push(*id);
                     block_param = true;
@@ -634,9 +1400,18 @@ impl Codegen {
             }
         }
 
+        let body_start = Codegen::current(&iseq);
         self.gen(globals, &mut iseq, node, use_value)?;
+        if is_block {
+            // `redo` outer of loops restarts the block without evaluating the parameters.
+            for p in std::mem::take(&mut self.loop_stack.last_mut().unwrap().escape) {
+                Codegen::write_disp(&mut iseq, p.pos, body_start);
+            }
+        }
         let context = self.context_stack.pop().unwrap();
         let iseq_sourcemap = context.iseq_sourcemap;
+        let line_table = context.line_table;
+        let exception_table = context.exception_table;
         self.gen_end(&mut iseq);
         self.loc = save_loc;
 
@@ -650,10 +1425,16 @@ impl Codegen {
                 block_param,
                 param_ident,
                 keyword_params,
+                req_keyword_params,
+                kwrest_param,
+                no_kw_param,
                 iseq,
                 lvar_collector.clone(),
                 iseq_sourcemap,
+                line_table,
+                exception_table,
                 self.source_info,
+                save_loc,
                 match kind {
                     ContextKind::Block => ISeqKind::Block(*self.method_stack.last().unwrap()),
                     ContextKind::Eval => ISeqKind::Other,
@@ -672,28 +1453,6 @@ impl Codegen {
             self.method_stack.pop();
         }
         globals.set_method(methodref, info);
-        #[cfg(feature = "emit-iseq")]
-        {
-            let info = globals.get_method_info(methodref);
-            let iseq = if let MethodInfo::RubyFunc { iseq } = info {
-                *iseq
-            } else {
-                panic!("CodeGen: Illegal methodref.")
-            };
-            eprintln!("-----------------------------------------");
-            eprintln!("{:?}", methodref);
-            for (k, v) in iseq.lvar.table() {
-                eprint!("local var({}): {} ", v.as_u32(), globals.get_ident_name(*k));
-            }
-            eprintln!("");
-            eprintln!("block: {:?}", iseq.lvar.block());
-            //let iseq = &iseq.iseq;
-            let mut pc = 0;
-            while pc < iseq.iseq.len() {
-                eprintln!("  {:05x} {}", pc, Inst::inst_info(globals, iseq, pc));
-                pc += Inst::inst_size(iseq.iseq[pc]);
-            }
-        }
         Ok(methodref)
     }
 
@@ -711,6 +1470,8 @@ impl Codegen {
                 | NodeKind::Bool(_)
                 | NodeKind::Integer(_)
                 | NodeKind::Float(_)
+                | NodeKind::Rational(_, _)
+                | NodeKind::Imaginary(_)
                 | NodeKind::String(_)
                 | NodeKind::Symbol(_)
                 | NodeKind::SelfValue => return Ok(()),
@@ -733,6 +1494,16 @@ impl Codegen {
                 iseq.push(Inst::PUSH_FLONUM);
                 Codegen::push64(iseq, f64::to_bits(*num));
             }
+            NodeKind::Rational(num, den) => {
+                self.gen_fixnum(iseq, *num);
+                self.gen_fixnum(iseq, *den);
+                iseq.push(Inst::CREATE_RATIONAL);
+            }
+            NodeKind::Imaginary(num) => {
+                self.gen_fixnum(iseq, 0);
+                self.gen(globals, iseq, num, true)?;
+                iseq.push(Inst::CREATE_COMPLEX);
+            }
             NodeKind::String(s) => {
                 self.gen_string(globals, iseq, s);
             }
@@ -859,7 +1630,7 @@ impl Codegen {
                 };
             }
             NodeKind::InstanceVar(id) => {
-                self.gen_get_instance_var(iseq, *id);
+                self.gen_get_instance_var(iseq, globals, *id);
                 if !use_value {
                     self.gen_pop(iseq)
                 };
@@ -987,16 +1758,16 @@ impl Codegen {
                         iseq.push(Inst::GT);
                     }
                     BinOp::Le => {
-                        self.gen(globals, iseq, rhs, true)?;
                         self.gen(globals, iseq, lhs, true)?;
+                        self.gen(globals, iseq, rhs, true)?;
                         self.save_loc(iseq, loc);
-                        iseq.push(Inst::GE);
+                        iseq.push(Inst::LE);
                     }
                     BinOp::Lt => {
-                        self.gen(globals, iseq, rhs, true)?;
                         self.gen(globals, iseq, lhs, true)?;
+                        self.gen(globals, iseq, rhs, true)?;
                         self.save_loc(iseq, loc);
-                        iseq.push(Inst::GT);
+                        iseq.push(Inst::LT);
                     }
                     BinOp::Cmp => {
                         self.gen(globals, iseq, rhs, true)?;
@@ -1006,12 +1777,11 @@ impl Codegen {
                     }
                     BinOp::LAnd => {
                         self.gen(globals, iseq, lhs, true)?;
-                        let src1 = self.gen_jmp_if_false(iseq);
+                        self.gen_dup(iseq, 1);
+                        let src = self.gen_jmp_if_false(iseq);
+                        self.gen_pop(iseq);
                         self.gen(globals, iseq, rhs, true)?;
-                        let src2 = Codegen::gen_jmp(iseq);
-                        Codegen::write_disp_from_cur(iseq, src1);
-                        iseq.push(Inst::PUSH_FALSE);
-                        Codegen::write_disp_from_cur(iseq, src2);
+                        Codegen::write_disp_from_cur(iseq, src);
                     }
                     BinOp::LOr => {
                         self.gen(globals, iseq, lhs, true)?;
@@ -1067,6 +1837,8 @@ impl Codegen {
             }
             NodeKind::CompStmt(nodes) => self.gen_comp_stmt(globals, iseq, nodes, use_value)?,
             NodeKind::If { cond, then_, else_ } => {
+                // `elsif` is not a statement, so the line of the condition is saved here.
+                self.save_line(iseq, cond.loc());
                 self.gen(globals, iseq, &cond, true)?;
                 let src1 = self.gen_jmp_if_false(iseq);
                 self.gen(globals, iseq, &then_, use_value)?;
@@ -1081,6 +1853,7 @@ impl Codegen {
                     _ => return Err(self.error_syntax("Expected an identifier.", param.loc())),
                 };
                 self.loop_stack.push(LoopInfo::new_loop());
+                let body_start;
                 let loop_continue;
                 match &iter.kind {
                     NodeKind::Range {
@@ -1095,6 +1868,7 @@ impl Codegen {
                         self.gen_get_local(iseq, id)?;
                         iseq.push(if *exclude_end { Inst::GT } else { Inst::GE });
                         let src = self.gen_jmp_if_false(iseq);
+                        body_start = Codegen::current(iseq);
                         self.gen(globals, iseq, body, false)?;
                         loop_continue = Codegen::current(iseq);
                         self.gen_get_local(iseq, id)?;
@@ -1116,6 +1890,7 @@ impl Codegen {
                             Codegen::write_disp_from_cur(iseq, p.pos);
                         }
                         EscapeKind::Next => Codegen::write_disp(iseq, p.pos, loop_continue),
+                        EscapeKind::Redo => Codegen::write_disp(iseq, p.pos, body_start),
                     }
                 }
                 if !use_value {
@@ -1124,12 +1899,25 @@ impl Codegen {
 
                 Codegen::write_disp_from_cur(iseq, src);
             }
-            NodeKind::While { cond, body } => {
+            NodeKind::While {
+                cond,
+                body,
+                do_while,
+            } => {
                 self.loop_stack.push(LoopInfo::new_loop());
 
+                let body_entry = if *do_while {
+                    Some(Codegen::gen_jmp(iseq))
+                } else {
+                    None
+                };
                 let loop_start = Codegen::current(iseq);
                 self.gen(globals, iseq, cond, true)?;
                 let src = self.gen_jmp_if_false(iseq);
+                if let Some(body_entry) = body_entry {
+                    Codegen::write_disp_from_cur(iseq, body_entry);
+                }
+                let body_start = Codegen::current(iseq);
                 self.gen(globals, iseq, body, false)?;
                 self.gen_jmp_back(iseq, loop_start);
                 Codegen::write_disp_from_cur(iseq, src);
@@ -1144,6 +1932,7 @@ impl Codegen {
                             Codegen::write_disp_from_cur(iseq, p.pos);
                         }
                         EscapeKind::Next => Codegen::write_disp(iseq, p.pos, loop_start),
+                        EscapeKind::Redo => Codegen::write_disp(iseq, p.pos, body_start),
                     }
                 }
                 if !use_value {
@@ -1154,18 +1943,41 @@ impl Codegen {
             }
             NodeKind::Begin {
                 body,
-                rescue: _,
-                else_: _,
+                rescue,
+                else_,
                 ensure,
             } => {
+                let has_ensure = !ensure.is_nop();
+                let ensure_entry = if has_ensure {
+                    Some(self.gen_enter_rescue(iseq))
+                } else {
+                    None
+                };
                 self.context_mut().exceptions.push(Exceptions::new());
-                self.gen(globals, iseq, body, use_value)?;
+                if rescue.is_empty() {
+                    self.gen(globals, iseq, body, use_value)?;
+                    self.gen(globals, iseq, else_, false)?;
+                } else {
+                    self.gen_rescue(globals, iseq, body, rescue, else_, use_value)?;
+                }
                 let exceptions = self.context_mut().exceptions.pop().unwrap();
                 for src in exceptions.entry {
                     Codegen::write_disp_from_cur(iseq, src);
                 }
                 // Ensure clauses must not return value.
-                self.gen(globals, iseq, ensure, false)?;
+                match ensure_entry {
+                    Some(entry) => {
+                        self.context_mut().exception_table[entry].end = Codegen::current(iseq);
+                        self.gen(globals, iseq, ensure, false)?;
+                        let src = Codegen::gen_jmp(iseq);
+                        // Run the ensure clause and re-raise when an exception was raised.
+                        self.context_mut().exception_table[entry].dest = Codegen::current(iseq);
+                        self.gen(globals, iseq, ensure, false)?;
+                        self.gen_raise(iseq);
+                        Codegen::write_disp_from_cur(iseq, src);
+                    }
+                    None => {}
+                }
             }
             NodeKind::Case { cond, when_, else_ } => {
                 let mut end = vec![];
@@ -1242,6 +2054,50 @@ impl Codegen {
                     Codegen::write_disp_from_cur(iseq, dest);
                 }
             }
+            NodeKind::CaseIn { cond, in_, else_ } => {
+                let mut end = vec![];
+                self.gen(globals, iseq, cond, true)?;
+                for branch in in_ {
+                    self.gen_topn(iseq, 0);
+                    self.gen_pattern(globals, iseq, &branch.pattern)?;
+                    let mut next = vec![self.gen_jmp_if_false(iseq)];
+                    if let Some(guard) = &branch.guard {
+                        self.gen(globals, iseq, guard, true)?;
+                        next.push(self.gen_jmp_if_false(iseq));
+                    }
+                    self.gen_pop(iseq);
+                    self.gen(globals, iseq, &branch.body, use_value)?;
+                    end.push(Codegen::gen_jmp(iseq));
+                    for src in next {
+                        Codegen::write_disp_from_cur(iseq, src);
+                    }
+                }
+                match else_ {
+                    Some(else_) => {
+                        self.gen_pop(iseq);
+                        self.gen(globals, iseq, else_, use_value)?;
+                    }
+                    None => {
+                        let inspect = globals.get_ident_id("inspect");
+                        let new = globals.get_ident_id("new");
+                        let error = globals.get_ident_id("NoMatchingPatternError");
+                        self.loc = node.loc();
+                        self.gen_send(globals, iseq, inspect, 0, 0, None);
+                        self.gen_get_const_top(iseq, error);
+                        self.gen_send(globals, iseq, new, 1, 0, None);
+                        self.gen_raise(iseq);
+                    }
+                }
+                for src in end {
+                    Codegen::write_disp_from_cur(iseq, src);
+                }
+            }
+            NodeKind::AssignOp(op, lhs, rhs) => {
+                self.gen_assign_op(globals, iseq, *op, lhs, rhs, node.loc())?;
+                if !use_value {
+                    self.gen_pop(iseq)
+                };
+            }
             NodeKind::MulAssign(mlhs, mrhs) => {
                 let lhs_len = mlhs.len();
                 let rhs_len = mrhs.len();
@@ -1252,7 +2108,10 @@ impl Codegen {
                         false
                     }
                 });
-                if lhs_len == rhs_len && !splat_flag {
+                let lhs_splat = mlhs
+                    .iter()
+                    .any(|lhs| matches!(lhs.kind, NodeKind::Splat(_)));
+                if lhs_len == rhs_len && !splat_flag && !lhs_splat {
                     if lhs_len == 1 {
                         match (&mlhs[0].kind, &mrhs[0].kind) {
                             (
@@ -1271,7 +2130,13 @@ impl Codegen {
                             ) if *id1 == *id2 && *i as i32 as i64 == *i => {
                                 let loc = mlhs[0].loc.merge(mrhs[0].loc);
                                 self.save_loc(iseq, loc);
-                                self.gen_ivar_addi(iseq, *id1, *i as i32 as u32, use_value);
+                                self.gen_ivar_addi(
+                                    iseq,
+                                    globals,
+                                    *id1,
+                                    *i as i32 as u32,
+                                    use_value,
+                                );
                             }
                             _ => {
                                 self.gen(globals, iseq, &mrhs[0], true)?;
@@ -1295,7 +2160,7 @@ impl Codegen {
                             self.gen_create_array(iseq, rhs_len);
                         };
                     }
-                } else if lhs_len == 1 {
+                } else if lhs_len == 1 && !lhs_splat {
                     for rhs in mrhs.iter().rev() {
                         self.gen(globals, iseq, rhs, true)?;
                     }
@@ -1314,12 +2179,19 @@ impl Codegen {
                     if use_value {
                         self.gen_dup(iseq, 1);
                     };
-                    self.gen_take(iseq, lhs_len);
-
-                    for lhs in mlhs.iter().rev() {
-                        self.gen_assign(globals, iseq, lhs)?;
-                    }
+                    self.gen_mlhs_assign(globals, iseq, mlhs)?;
+                }
+            }
+            NodeKind::Send { .. } if Codegen::in_safe_nav_chain(node) => {
+                // When a receiver of `&.` is nil, the rest of the call chain is skipped
+                // and the chain evaluates to nil.
+                let nil_jmps = self.gen_safe_nav_chain(globals, iseq, node)?;
+                for nil_jmp in nil_jmps {
+                    Codegen::write_disp_from_cur(iseq, nil_jmp);
                 }
+                if !use_value {
+                    self.gen_pop(iseq)
+                };
             }
             NodeKind::Send {
                 receiver,
@@ -1328,42 +2200,7 @@ impl Codegen {
                 ..
             } => {
                 let loc = self.loc;
-                for arg in &send_args.args {
-                    self.gen(globals, iseq, arg, true)?;
-                }
-                let kw_flag = send_args.kw_args.len() != 0;
-                if kw_flag {
-                    for (id, default) in &send_args.kw_args {
-                        self.gen_symbol(iseq, *id);
-                        self.gen(globals, iseq, default, true)?;
-                    }
-                    self.gen_create_hash(iseq, send_args.kw_args.len());
-                }
-                let mut block_flag = false;
-                let block_ref = match &send_args.block {
-                    Some(block) => match &block.kind {
-                        NodeKind::Proc { params, body, lvar } => {
-                            self.loop_stack.push(LoopInfo::new_top());
-                            let methodref = self.gen_iseq(
-                                globals,
-                                params,
-                                body,
-                                lvar,
-                                true,
-                                ContextKind::Block,
-                                None,
-                            )?;
-                            self.loop_stack.pop().unwrap();
-                            Some(methodref)
-                        }
-                        _ => {
-                            self.gen(globals, iseq, block, true)?;
-                            block_flag = true;
-                            None
-                        }
-                    },
-                    None => None,
-                };
+                let (flag, block_ref) = self.gen_send_args(globals, iseq, send_args)?;
                 if NodeKind::SelfValue == receiver.kind {
                     self.loc = loc;
                     self.gen_send_self(
@@ -1371,7 +2208,7 @@ impl Codegen {
                         iseq,
                         *method,
                         send_args.args.len(),
-                        create_flag(kw_flag, block_flag),
+                        flag,
                         block_ref,
                     );
                 } else {
@@ -1382,23 +2219,41 @@ impl Codegen {
                         iseq,
                         *method,
                         send_args.args.len(),
-                        create_flag(kw_flag, block_flag),
+                        flag,
                         block_ref,
                     );
                 };
                 if !use_value {
                     self.gen_pop(iseq)
                 };
-
-                /// Create flag for argument info.
-                /// 0b0011
-                ///     ||
-                ///     |+- 1: keyword args exists. 0: no keyword args,
-                ///     +-- 1: a block arg exists. 0: no block arg.
-                fn create_flag(kw_flag: bool, block_flag: bool) -> usize {
-                    (if kw_flag { 1usize } else { 0usize })
-                        + (if block_flag { 2usize } else { 0usize })
+            }
+            NodeKind::Alias(new, old) => {
+                self.save_loc(iseq, node.loc());
+                iseq.push(Inst::ALIAS);
+                Codegen::push32(iseq, (*new).into());
+                Codegen::push32(iseq, (*old).into());
+                if !use_value {
+                    self.gen_pop(iseq);
+                };
+            }
+            NodeKind::Undef(ids) => {
+                self.save_loc(iseq, node.loc());
+                for (i, id) in ids.iter().enumerate() {
+                    if i != 0 {
+                        self.gen_pop(iseq);
+                    }
+                    iseq.push(Inst::UNDEF);
+                    Codegen::push32(iseq, (*id).into());
                 }
+                if !use_value {
+                    self.gen_pop(iseq);
+                };
+            }
+            NodeKind::Defined(expr) => {
+                self.gen_defined(globals, iseq, expr)?;
+                if !use_value {
+                    self.gen_pop(iseq);
+                };
             }
             NodeKind::Yield(send_args) => {
                 //let loc = self.loc;
@@ -1553,6 +2408,27 @@ impl Codegen {
                     x.escape.push(EscapeInfo::new(src, EscapeKind::Next));
                 }
             }
+            NodeKind::Redo => {
+                let loc = node.loc();
+                if self.loop_stack.last().unwrap().state == LoopState::Top
+                    && self.context().kind != ContextKind::Block
+                {
+                    return Err(self.error_syntax("Invalid redo.", loc.merge(self.loc)));
+                }
+                let src = Codegen::gen_jmp(iseq);
+                let x = self.loop_stack.last_mut().unwrap();
+                x.escape.push(EscapeInfo::new(src, EscapeKind::Redo));
+            }
+            NodeKind::Retry => match self.context().retry_dest.last() {
+                Some(dest) => {
+                    let dest = *dest;
+                    self.gen_jmp_back(iseq, dest);
+                }
+                None => {
+                    let loc = node.loc();
+                    return Err(self.error_syntax("Invalid retry.", loc.merge(self.loc)));
+                }
+            },
             NodeKind::Proc { params, body, lvar } => {
                 self.loop_stack.push(LoopInfo::new_top());
                 let methodref =
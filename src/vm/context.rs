@@ -1,7 +1,8 @@
 pub use crate::*;
+use std::collections::HashMap;
 use std::ops::{Index, IndexMut, Range};
 
-const LVAR_ARRAY_SIZE: usize = 32;
+pub const LVAR_ARRAY_SIZE: usize = 32;
 
 #[derive(Debug, Clone)]
 pub struct Context {
@@ -16,6 +17,12 @@ pub struct Context {
     pub on_stack: bool,
     pub stack_len: usize,
     pub kind: ISeqKind,
+    /// The source line last counted by Coverage.
+    pub cov_line: usize,
+    /// The source line of the last line event reported to the debugger.
+    pub debug_line: usize,
+    /// Stack lengths recorded by ENTER_RESCUE, indexed by exception table entry.
+    pub rescue_sp: Vec<usize>,
 }
 
 pub type ContextRef = Ref<Context>;
@@ -91,9 +98,18 @@ impl Context {
             on_stack: true,
             stack_len: 0,
             kind: iseq_ref.kind.clone(),
+            cov_line: 0,
+            debug_line: 0,
+            rescue_sp: vec![],
         }
     }
 
+    /// Returns the pointer to the local variables stored in the array, which are
+    /// the first `LVAR_ARRAY_SIZE` ones.
+    pub fn lvar_ptr(&mut self) -> *mut Value {
+        self.lvar_ary.as_mut_ptr()
+    }
+
     pub fn from_args(
         vm: &mut VM,
         self_value: Value,
@@ -103,10 +119,10 @@ impl Context {
     ) -> Result<Self, RubyError> {
         let mut context = Context::new(self_value, args.block, iseq, outer);
         let params = &iseq.params;
-        let kw = if params.keyword_params.is_empty() {
-            args.kw_arg
-        } else {
+        let kw = if params.accepts_keywords() {
             None
+        } else {
+            args.kw_arg
         };
         if !iseq.is_block() {
             let len = args.len() + if kw.is_some() { 1 } else { 0 };
@@ -117,22 +133,10 @@ impl Context {
                 vm.check_args_range(len, min, min + params.opt_params)?;
             }
         }
-        context.set_arguments(&vm.globals, args, kw);
-        match args.kw_arg {
-            Some(kw_arg) if kw.is_none() => {
-                let keyword = kw_arg.as_hash().unwrap();
-                for (k, v) in keyword.iter() {
-                    let id = k.as_symbol().unwrap();
-                    match params.keyword_params.get(&id) {
-                        Some(lvar) => {
-                            context[*lvar] = v;
-                        }
-                        None => return Err(vm.error_argument("Undefined keyword.")),
-                    };
-                }
-            }
-            _ => {}
-        };
+        context.set_arguments(vm, args, kw)?;
+        if params.accepts_keywords() {
+            context.set_keyword_arguments(vm, args.kw_arg)?;
+        }
         if let Some(id) = iseq.lvar.block_param() {
             context[id] = match args.block {
                 Some(block) => {
@@ -145,26 +149,86 @@ impl Context {
         Ok(context)
     }
 
-    fn set_arguments(&mut self, globals: &Globals, args: &Args, kw_arg: Option<Value>) {
+    /// Bind `kw_arg` to the keyword parameters and the keyword rest parameter,
+    /// raising ArgumentError for unknown or missing keywords.
+    fn set_keyword_arguments(
+        &mut self,
+        vm: &mut VM,
+        kw_arg: Option<Value>,
+    ) -> Result<(), RubyError> {
+        let iseq = self.iseq_ref;
+        let params = &iseq.params;
+        if params.no_kw_param && kw_arg.is_some() {
+            return Err(vm.error_argument("no keywords accepted"));
+        }
+        let keyword = kw_arg.map(|kw_arg| kw_arg.as_hash().unwrap());
+        let mut kwrest = HashMap::new();
+        let mut unknown = vec![];
+        if let Some(keyword) = keyword {
+            for (k, v) in keyword.iter() {
+                match k.as_symbol().and_then(|id| params.keyword_params.get(&id)) {
+                    Some(lvar) => self[*lvar] = v,
+                    None if params.kwrest_param.is_some() => {
+                        kwrest.insert(HashKey(k), v);
+                    }
+                    None => unknown.push(k),
+                }
+            }
+        }
+        if !unknown.is_empty() {
+            return Err(Context::keyword_error(vm, "unknown", unknown));
+        }
+        let missing: Vec<Value> = params
+            .req_keyword_params
+            .iter()
+            .map(|id| Value::symbol(*id))
+            .filter(|id| keyword.map_or(true, |keyword| !keyword.contains_key(*id)))
+            .collect();
+        if !missing.is_empty() {
+            return Err(Context::keyword_error(vm, "missing", missing));
+        }
+        if let Some(lvar) = params.kwrest_param {
+            self[lvar] = Value::hash_from(&vm.globals, kwrest);
+        }
+        Ok(())
+    }
+
+    /// Returns ArgumentError like "unknown keywords: :a, :b".
+    fn keyword_error(vm: &mut VM, kind: &str, keys: Vec<Value>) -> RubyError {
+        let plural = if keys.len() == 1 { "" } else { "s" };
+        let keys: Vec<String> = keys.into_iter().map(|key| vm.val_inspect(key)).collect();
+        vm.error_argument(format!("{} keyword{}: {}", kind, plural, keys.join(", ")))
+    }
+
+    /// A block which takes more than one parameter, e.g. `|a, b|` or `|a, *b|`,
+    /// receives the elements of an Array (or an object converted by `to_ary`)
+    /// passed as the only argument.
+    fn set_arguments(
+        &mut self,
+        vm: &mut VM,
+        args: &Args,
+        kw_arg: Option<Value>,
+    ) -> Result<(), RubyError> {
         let iseq = self.iseq_ref;
-        let req_len = iseq.params.req_params;
-        let post_len = iseq.params.post_params;
+        let params = &iseq.params;
+        let params_len = params.req_params
+            + params.opt_params
+            + params.post_params
+            + if params.rest_param { 1 } else { 0 };
 
         match self.kind {
-            ISeqKind::Block(_) if args.len() == 1 && req_len + post_len > 1 => {
-                match args[0].as_array() {
-                    Some(ary) => {
-                        let args = &ary.elements;
-                        self.fill_arguments(globals, args, args.len(), iseq, kw_arg);
-                        return;
-                    }
-                    _ => {}
+            ISeqKind::Block(_) if args.len() == 1 && params_len > 1 => {
+                if let Some(ary) = vm.implicit_to_ary(args[0])? {
+                    let args = &ary.elements;
+                    self.fill_arguments(&vm.globals, args, args.len(), iseq, kw_arg);
+                    return Ok(());
                 }
             }
             _ => {}
         }
 
-        self.fill_arguments(globals, args, args.len(), iseq, kw_arg);
+        self.fill_arguments(&vm.globals, args, args.len(), iseq, kw_arg);
+        Ok(())
     }
 
     fn fill_arguments(
@@ -0,0 +1,283 @@
+use super::vm_inst::Inst;
+use crate::*;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+
+/// Line-level code coverage, collected while `Coverage` is running.
+///
+/// Each ISeq is registered when it is compiled: its `line_table` and `iseq_sourcemap` give
+/// the relevant source lines, conditional jumps give the branches, and method ISeqs give
+/// the methods.
+/// The VM then calls the `trace_xxx()` functions while executing instructions.
+#[derive(Debug, Clone)]
+pub struct Coverage {
+    pub mode: CoverageMode,
+    files: Vec<FileCoverage>,
+    file_ids: HashMap<PathBuf, usize>,
+    iseqs: HashMap<MethodRef, ISeqTable>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CoverageMode {
+    /// `Coverage.start` without options: results are arrays of line counts.
+    pub legacy: bool,
+    pub lines: bool,
+    pub branches: bool,
+    pub methods: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct FileCoverage {
+    pub path: PathBuf,
+    /// The number of lines in the source file.
+    pub line_count: usize,
+    /// Execution counts of relevant lines.
+    pub lines: BTreeMap<usize, u64>,
+    /// Counts of [then, else] of each conditional branch, keyed by (line, pc, method).
+    pub branches: BTreeMap<(usize, usize, u32), [u64; 2]>,
+    /// Call counts of methods, keyed by (line, method).
+    pub methods: BTreeMap<(usize, u32), u64>,
+}
+
+/// Lookup tables of a registered ISeq.
+#[derive(Debug, Clone)]
+struct ISeqTable {
+    file: usize,
+    /// pc -> line
+    lines: HashMap<usize, usize>,
+    /// pc of conditional jumps -> line
+    branches: HashMap<usize, usize>,
+    /// The line of the method definition, if the ISeq is a method.
+    method: Option<usize>,
+}
+
+impl CoverageMode {
+    pub fn all() -> Self {
+        CoverageMode {
+            legacy: false,
+            lines: true,
+            branches: true,
+            methods: true,
+        }
+    }
+}
+
+impl FileCoverage {
+    fn new(path: PathBuf, line_count: usize) -> Self {
+        FileCoverage {
+            path,
+            line_count,
+            lines: BTreeMap::new(),
+            branches: BTreeMap::new(),
+            methods: BTreeMap::new(),
+        }
+    }
+}
+
+impl Coverage {
+    pub fn new(mode: CoverageMode) -> Self {
+        Coverage {
+            mode,
+            files: vec![],
+            file_ids: HashMap::new(),
+            iseqs: HashMap::new(),
+        }
+    }
+
+    pub fn files(&self) -> &[FileCoverage] {
+        &self.files
+    }
+
+    /// Register a compiled ISeq. ISeqs without a source path (e.g. REPL input) are ignored.
+    pub fn register(&mut self, iseq: ISeqRef) {
+        let info = &*iseq.source_info;
+        if info.path.as_os_str().is_empty() {
+            return;
+        }
+        let file = match self.file_ids.get(&info.path) {
+            Some(id) => *id,
+            None => {
+                let id = self.files.len();
+                let mut line_count = info.code.iter().filter(|ch| **ch == '\n').count();
+                if info.code.last().map_or(false, |ch| *ch != '\n') {
+                    line_count += 1;
+                }
+                self.files
+                    .push(FileCoverage::new(info.path.clone(), line_count));
+                self.file_ids.insert(info.path.clone(), id);
+                id
+            }
+        };
+        let method_id: u32 = iseq.method.into();
+        let mut table = ISeqTable {
+            file,
+            lines: HashMap::new(),
+            branches: HashMap::new(),
+            method: None,
+        };
+        let coverage = &mut self.files[file];
+        for (pos, loc) in iseq.line_table.iter().chain(&iseq.iseq_sourcemap) {
+            let line = info.get_line(loc);
+            table.lines.entry(pos.to_usize()).or_insert(line);
+            coverage.lines.entry(line).or_insert(0);
+        }
+        let def_line = info.get_line(&iseq.loc);
+        let mut pc = 0;
+        while pc < iseq.iseq.len() {
+            let inst = iseq.iseq[pc];
+            if inst == Inst::JMP_IF_FALSE {
+                // The line of the condition is the nearest preceding line in the line table.
+                let line = iseq
+                    .line_table
+                    .iter()
+                    .filter(|(pos, _)| pos.to_usize() <= pc)
+                    .last()
+                    .map_or(def_line, |(_, loc)| info.get_line(loc));
+                table.branches.insert(pc, line);
+                coverage.branches.insert((line, pc, method_id), [0, 0]);
+            }
+            pc += Inst::inst_size(inst);
+        }
+        if let ISeqKind::Method(_) = iseq.kind {
+            table.method = Some(def_line);
+            coverage.methods.insert((def_line, method_id), 0);
+        }
+        self.iseqs.insert(iseq.method, table);
+    }
+
+    /// Count the line of the instruction at `pc`, if the context has moved to a new line.
+    pub fn trace_line(&mut self, mut context: ContextRef, pc: usize) {
+        let table = match self.iseqs.get(&context.iseq_ref.method) {
+            Some(table) => table,
+            None => return,
+        };
+        if let Some(line) = table.lines.get(&pc) {
+            if *line != context.cov_line {
+                context.cov_line = *line;
+                *self.files[table.file].lines.get_mut(line).unwrap() += 1;
+            }
+        }
+    }
+
+    /// Count the branch taken by the conditional jump at `pc`.
+    pub fn trace_branch(&mut self, iseq: ISeqRef, pc: usize, then_taken: bool) {
+        let table = match self.iseqs.get(&iseq.method) {
+            Some(table) => table,
+            None => return,
+        };
+        if let Some(line) = table.branches.get(&pc) {
+            let key = (*line, pc, iseq.method.into());
+            let counts = self.files[table.file].branches.get_mut(&key).unwrap();
+            counts[if then_taken { 0 } else { 1 }] += 1;
+        }
+    }
+
+    /// Count a call of the method.
+    pub fn trace_method(&mut self, iseq: ISeqRef) {
+        let table = match self.iseqs.get(&iseq.method) {
+            Some(table) => table,
+            None => return,
+        };
+        if let Some(line) = table.method {
+            let key = (line, iseq.method.into());
+            *self.files[table.file].methods.get_mut(&key).unwrap() += 1;
+        }
+    }
+}
+
+// Reports
+
+impl Coverage {
+    /// Returns the name of a method for reports, e.g. "Foo#bar".
+    pub fn method_name(globals: &Globals, method: u32) -> String {
+        match globals.get_method_info(MethodRef::from(method)) {
+            MethodInfo::RubyFunc { iseq } => {
+                let name = match iseq.kind {
+                    ISeqKind::Method(id) => globals.get_ident_name(id),
+                    _ => "",
+                };
+                match iseq.class_defined.map(|list| list.class.as_module()) {
+                    Some(Some(cref)) => format!("{}#{}", globals.get_ident_name(cref.name), name),
+                    _ => format!("Object#{}", name),
+                }
+            }
+            _ => "".to_string(),
+        }
+    }
+
+    /// Returns the coverage data in LCOV tracefile format.
+    pub fn lcov(&self, globals: &Globals) -> String {
+        let mut s = String::new();
+        for file in &self.files {
+            s += "TN:\n";
+            s += &format!("SF:{}\n", file.path.to_string_lossy());
+            if self.mode.methods {
+                for ((line, method), _) in &file.methods {
+                    s += &format!("FN:{},{}\n", line, Coverage::method_name(globals, *method));
+                }
+                for ((_, method), count) in &file.methods {
+                    s += &format!(
+                        "FNDA:{},{}\n",
+                        count,
+                        Coverage::method_name(globals, *method)
+                    );
+                }
+                let hit = file.methods.values().filter(|c| **c > 0).count();
+                s += &format!("FNF:{}\nFNH:{}\n", file.methods.len(), hit);
+            }
+            if self.mode.branches {
+                for (i, ((line, _, _), counts)) in file.branches.iter().enumerate() {
+                    for (branch, count) in counts.iter().enumerate() {
+                        s += &format!("BRDA:{},{},{},{}\n", line, i, branch, count);
+                    }
+                }
+                let hit: usize = file
+                    .branches
+                    .values()
+                    .map(|c| c.iter().filter(|n| **n > 0).count())
+                    .sum();
+                s += &format!("BRF:{}\nBRH:{}\n", file.branches.len() * 2, hit);
+            }
+            for (line, count) in &file.lines {
+                s += &format!("DA:{},{}\n", line, count);
+            }
+            let hit = file.lines.values().filter(|c| **c > 0).count();
+            s += &format!("LF:{}\nLH:{}\n", file.lines.len(), hit);
+            s += "end_of_record\n";
+        }
+        s
+    }
+
+    /// Returns a summary of line (and branch and method) coverage per file.
+    pub fn summary(&self) -> String {
+        let ratio = |hit: usize, all: usize| {
+            if all == 0 {
+                "-".to_string()
+            } else {
+                format!("{:.1}% ({}/{})", hit as f64 / all as f64 * 100.0, hit, all)
+            }
+        };
+        let mut s = String::new();
+        for file in &self.files {
+            let lines = file.lines.values().filter(|c| **c > 0).count();
+            s += &format!(
+                "{}\n  lines:    {}\n",
+                file.path.to_string_lossy(),
+                ratio(lines, file.lines.len())
+            );
+            if self.mode.branches {
+                let hit: usize = file
+                    .branches
+                    .values()
+                    .map(|c| c.iter().filter(|n| **n > 0).count())
+                    .sum();
+                s += &format!("  branches: {}\n", ratio(hit, file.branches.len() * 2));
+            }
+            if self.mode.methods {
+                let hit = file.methods.values().filter(|c| **c > 0).count();
+                s += &format!("  methods:  {}\n", ratio(hit, file.methods.len()));
+            }
+        }
+        s
+    }
+}
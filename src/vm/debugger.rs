@@ -0,0 +1,501 @@
+use crate::builtin::errorobj;
+use crate::*;
+use ansi_term::Colour::Red;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Reads debugger commands and writes debugger messages.
+pub trait DebuggerIO {
+    /// Read a command line. Returns None at the end of input.
+    fn read_line(&mut self, prompt: &str) -> Option<String>;
+    fn write(&mut self, msg: &str);
+}
+
+/// The default DebuggerIO: a rustyline editor, as used by the REPL.
+pub struct DebuggerRepl {
+    editor: rustyline::Editor<()>,
+}
+
+impl DebuggerRepl {
+    pub fn new() -> Self {
+        DebuggerRepl {
+            editor: rustyline::Editor::<()>::new(),
+        }
+    }
+}
+
+impl Default for DebuggerRepl {
+    fn default() -> Self {
+        DebuggerRepl::new()
+    }
+}
+
+impl DebuggerIO for DebuggerRepl {
+    fn read_line(&mut self, prompt: &str) -> Option<String> {
+        match self.editor.readline(&Red.bold().paint(prompt).to_string()) {
+            Ok(line) => {
+                self.editor.add_history_entry(line.clone());
+                Some(line)
+            }
+            Err(_) => None,
+        }
+    }
+
+    fn write(&mut self, msg: &str) {
+        println!("{}", msg);
+    }
+}
+
+/// The state of the debugger: breakpoints and the current stepping mode.
+///
+/// While the debugger is enabled, the VM reports line events to it: a line event occurs
+/// when a context executes an instruction recorded in `iseq_sourcemap` with a source line
+/// other than the one of its last line event.
+pub struct Debugger {
+    io: Box<dyn DebuggerIO>,
+    mode: StepMode,
+    breakpoints: Vec<Breakpoint>,
+    /// Line tables of ISeqs: pc -> line.
+    lines: HashMap<MethodRef, HashMap<usize, usize>>,
+}
+
+impl std::fmt::Debug for Debugger {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Debugger {:?} {:?}", self.mode, self.breakpoints)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum StepMode {
+    /// Stop only at breakpoints.
+    Continue,
+    /// Stop at the next line event.
+    Step,
+    /// Stop at the next line event in a context at the given depth or shallower.
+    Next(usize),
+    /// Stop at the next line event in a context shallower than the given depth.
+    Finish(usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Breakpoint {
+    /// file:line
+    Line(String, usize),
+    /// Class#method or method
+    Method(Option<String>, String),
+}
+
+impl std::fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Breakpoint::Line(file, line) => write!(f, "{}:{}", file, line),
+            Breakpoint::Method(Some(class), name) => write!(f, "{}#{}", class, name),
+            Breakpoint::Method(None, name) => write!(f, "{}", name),
+        }
+    }
+}
+
+impl Debugger {
+    /// Create a new debugger which stops at the first line event.
+    pub fn new(io: Box<dyn DebuggerIO>) -> Self {
+        Debugger {
+            io,
+            mode: StepMode::Step,
+            breakpoints: vec![],
+            lines: HashMap::new(),
+        }
+    }
+
+    /// Returns the line and whether it is the first line event in the context,
+    /// if the instruction at `pc` causes a line event.
+    fn line_event(&mut self, mut context: ContextRef, pc: usize) -> Option<(usize, bool)> {
+        let iseq = context.iseq_ref;
+        let lines = self.lines.entry(iseq.method).or_insert_with(|| {
+            // The first entry for a pc takes precedence, as in VM::get_loc().
+            iseq.iseq_sourcemap
+                .iter()
+                .rev()
+                .map(|(pos, loc)| (pos.to_usize(), iseq.source_info.get_line(loc)))
+                .collect()
+        });
+        let line = *lines.get(&pc)?;
+        if line == context.debug_line {
+            return None;
+        }
+        let first = context.debug_line == 0;
+        context.debug_line = line;
+        Some((line, first))
+    }
+
+    fn should_stop(
+        &self,
+        globals: &Globals,
+        context: ContextRef,
+        line: usize,
+        first: bool,
+        depth: usize,
+    ) -> bool {
+        match self.mode {
+            StepMode::Step => return true,
+            StepMode::Next(d) if depth <= d => return true,
+            StepMode::Finish(d) if depth < d => return true,
+            _ => {}
+        }
+        let iseq = context.iseq_ref;
+        self.breakpoints.iter().any(|bp| match bp {
+            Breakpoint::Line(file, l) => *l == line && iseq.source_info.path.ends_with(file),
+            Breakpoint::Method(class, name) => match iseq.kind {
+                ISeqKind::Method(id) if first && globals.get_ident_name(id) == name => {
+                    match class {
+                        Some(class) => iseq.label(globals) == format!("{}#{}", class, name),
+                        None => true,
+                    }
+                }
+                _ => false,
+            },
+        })
+    }
+
+    fn parse_breakpoint(&self, arg: &str, path: &Path) -> Option<Breakpoint> {
+        if let Ok(line) = arg.parse::<usize>() {
+            let file = path.to_string_lossy().to_string();
+            return Some(Breakpoint::Line(file, line));
+        }
+        if let Some(pos) = arg.rfind(':') {
+            let line = arg[pos + 1..].parse::<usize>().ok()?;
+            return Some(Breakpoint::Line(arg[..pos].to_string(), line));
+        }
+        match arg.find('#') {
+            Some(pos) => Some(Breakpoint::Method(
+                Some(arg[..pos].to_string()),
+                arg[pos + 1..].to_string(),
+            )),
+            None if !arg.is_empty() => Some(Breakpoint::Method(None, arg.to_string())),
+            None => None,
+        }
+    }
+}
+
+const HELP: &str = "\
+Commands:
+  s[tep]              step into the next line
+  n[ext]              step over to the next line
+  fin[ish]            run until the current frame returns
+  c[ontinue]          resume the program
+  b[reak]             list breakpoints
+  b[reak] LINE        set a breakpoint at LINE of the current file
+  b[reak] FILE:LINE   set a breakpoint at LINE of FILE
+  b[reak] [CLASS#]METHOD
+                      set a breakpoint at the start of METHOD
+  del[ete] [N]        delete breakpoint N, or all breakpoints
+  bt, backtrace       show the backtrace
+  i[nfo] [locals]     show local variables
+  l[ist]              show the source around the current line
+  p EXPR              evaluate EXPR in the current frame and print the result
+  q[uit]              exit the program
+  h[elp]              show this message
+Any other input is evaluated as a Ruby expression.";
+
+impl VM {
+    /// Report the instruction at the current pc to the debugger, and stop
+    /// if it causes a line event at a breakpoint or while stepping.
+    pub fn debugger_trace(&mut self, context: ContextRef) {
+        let mut debugger = match self.debugger.take() {
+            Some(debugger) => debugger,
+            None => return,
+        };
+        if let Some((line, first)) = debugger.line_event(context, self.pc) {
+            let depth = self.exec_context.len();
+            if debugger.should_stop(&self.globals, context, line, first, depth) {
+                self.debugger_repl(&mut debugger, line);
+            }
+        }
+        self.debugger = Some(debugger);
+    }
+
+    /// Stop in the current context (`binding.break` or `debugger`).
+    /// The debugger is enabled with the default DebuggerIO if it is not yet.
+    pub fn debugger_break(&mut self) {
+        let mut debugger = match self.debugger.take() {
+            Some(debugger) => debugger,
+            None => Debugger::new(Box::new(DebuggerRepl::new())),
+        };
+        let line = self.context().iseq_ref.get_line(self.pc);
+        self.debugger_repl(&mut debugger, line);
+        self.debugger = Some(debugger);
+    }
+
+    fn debugger_repl(&mut self, debugger: &mut Debugger, line: usize) {
+        let context = self.context();
+        let path = context.iseq_ref.source_info.path.clone();
+        let label = context.iseq_ref.label(&self.globals);
+        debugger.io.write(&format!(
+            "Stopped at {}:{} ({})",
+            path.to_string_lossy(),
+            line,
+            label
+        ));
+        debugger
+            .io
+            .write(&self.debugger_source(context.iseq_ref, line, 0));
+        let depth = self.exec_context.len();
+        loop {
+            let input = match debugger.io.read_line("(rdbg) ") {
+                Some(input) => input,
+                None => {
+                    debugger.mode = StepMode::Continue;
+                    debugger.breakpoints.clear();
+                    return;
+                }
+            };
+            let input = input.trim();
+            let (cmd, arg) = match input.find(char::is_whitespace) {
+                Some(pos) => (&input[..pos], input[pos..].trim()),
+                None => (input, ""),
+            };
+            match cmd {
+                "" => {}
+                "c" | "continue" => {
+                    debugger.mode = StepMode::Continue;
+                    return;
+                }
+                "s" | "step" => {
+                    debugger.mode = StepMode::Step;
+                    return;
+                }
+                "n" | "next" => {
+                    debugger.mode = StepMode::Next(depth);
+                    return;
+                }
+                "fin" | "finish" => {
+                    debugger.mode = StepMode::Finish(depth);
+                    return;
+                }
+                "b" | "break" if arg.is_empty() => {
+                    for (i, bp) in debugger.breakpoints.iter().enumerate() {
+                        debugger.io.write(&format!("#{} {}", i + 1, bp));
+                    }
+                }
+                "b" | "break" => match debugger.parse_breakpoint(arg, &path) {
+                    Some(bp) => {
+                        let msg = format!("#{} {}", debugger.breakpoints.len() + 1, bp);
+                        debugger.breakpoints.push(bp);
+                        debugger.io.write(&msg);
+                    }
+                    None => debugger.io.write(&format!("Invalid breakpoint: {}", arg)),
+                },
+                "del" | "delete" if arg.is_empty() => debugger.breakpoints.clear(),
+                "del" | "delete" => match arg.parse::<usize>() {
+                    Ok(n) if 1 <= n && n <= debugger.breakpoints.len() => {
+                        debugger.breakpoints.remove(n - 1);
+                    }
+                    _ => debugger.io.write(&format!("No breakpoint: {}", arg)),
+                },
+                "bt" | "backtrace" => {
+                    let bt = self.debugger_backtrace();
+                    debugger.io.write(&bt);
+                }
+                "i" | "info" if arg.is_empty() || arg == "locals" => {
+                    let locals = self.debugger_locals();
+                    debugger.io.write(&locals);
+                }
+                "l" | "list" => {
+                    let source = self.debugger_source(context.iseq_ref, line, 5);
+                    debugger.io.write(&source);
+                }
+                "p" | "eval" => {
+                    let res = self.debugger_eval(arg);
+                    debugger.io.write(&res);
+                }
+                "q" | "quit" => std::process::exit(0),
+                "h" | "help" => debugger.io.write(HELP),
+                _ => {
+                    let res = self.debugger_eval(input);
+                    debugger.io.write(&res);
+                }
+            }
+        }
+    }
+
+    /// Returns the lines of the source around `line`, marking `line` with "=>".
+    fn debugger_source(&self, iseq: ISeqRef, line: usize, around: usize) -> String {
+        let code: String = iseq.source_info.code.iter().collect();
+        let start = if line > around { line - around } else { 1 };
+        code.lines()
+            .enumerate()
+            .map(|(i, src)| (i + 1, src))
+            .filter(|(i, _)| start <= *i && *i <= line + around)
+            .map(|(i, src)| {
+                let mark = if i == line { "=>" } else { "  " };
+                format!("{} {:>4}| {}", mark, i, src)
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    fn debugger_backtrace(&self) -> String {
+        let mut lines = vec![];
+        for (i, (path, line, label)) in self.stack_frames().iter().enumerate() {
+            lines.push(format!(
+                "{}#{:<3} {}",
+                if i == 0 { "=>" } else { "  " },
+                i,
+                errorobj::format_location(path, line, label)
+            ));
+        }
+        lines.join("\n")
+    }
+
+    /// Returns local variables of the current context and its outer contexts.
+    fn debugger_locals(&mut self) -> String {
+        let mut lines = vec![];
+        let mut names = vec![];
+        let mut context = Some(self.context());
+        while let Some(ctx) = context {
+            let mut lvars: Vec<(IdentId, LvarId)> = ctx
+                .iseq_ref
+                .lvar
+                .table()
+                .iter()
+                .map(|(id, lvar)| (*id, *lvar))
+                .collect();
+            lvars.sort_by_key(|(_, lvar)| lvar.as_usize());
+            for (id, lvar) in lvars {
+                if names.contains(&id) {
+                    continue;
+                }
+                names.push(id);
+                let val = ctx[lvar];
+                let val = if val.is_uninitialized() {
+                    "nil".to_string()
+                } else {
+                    self.val_inspect(val)
+                };
+                lines.push(format!("{} = {}", self.globals.get_ident_name(id), val));
+            }
+            context = ctx.outer;
+        }
+        if lines.is_empty() {
+            "(no local variables)".to_string()
+        } else {
+            lines.join("\n")
+        }
+    }
+
+    /// Evaluate `code` in the current context, and returns the result or the error message.
+    fn debugger_eval(&mut self, code: &str) -> String {
+        let pc = self.pc;
+        let stack_len = self.exec_stack.len();
+        let context_len = self.exec_context.len();
+        let res = match self.parse_program_eval(PathBuf::from("(rdbg)"), code) {
+            Ok(method) => self.eval_block(method, &Args::new0()),
+            Err(err) => Err(err),
+        };
+        self.pc = pc;
+        self.exec_stack.truncate(stack_len);
+        self.exec_context.truncate(context_len);
+        match res {
+            Ok(val) => format!("=> {}", self.val_inspect(val)),
+            Err(err) => err.message(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::StepMode;
+    use crate::*;
+    use std::cell::RefCell;
+    use std::path::PathBuf;
+    use std::rc::Rc;
+
+    struct ScriptedIO {
+        input: Vec<&'static str>,
+        output: Rc<RefCell<Vec<String>>>,
+    }
+
+    impl DebuggerIO for ScriptedIO {
+        fn read_line(&mut self, _: &str) -> Option<String> {
+            if self.input.is_empty() {
+                None
+            } else {
+                Some(self.input.remove(0).to_string())
+            }
+        }
+
+        fn write(&mut self, msg: &str) {
+            self.output
+                .borrow_mut()
+                .extend(msg.lines().map(|l| l.to_string()));
+        }
+    }
+
+    fn run_debugger(program: &str, input: Vec<&'static str>, start: bool) -> Vec<String> {
+        let output = Rc::new(RefCell::new(vec![]));
+        let io = ScriptedIO {
+            input,
+            output: output.clone(),
+        };
+        let mut vm = VM::new();
+        if start {
+            vm.debugger = Some(Debugger::new(Box::new(io)));
+        } else {
+            let mut debugger = Debugger::new(Box::new(io));
+            debugger.mode = StepMode::Continue;
+            vm.debugger = Some(debugger);
+        }
+        vm.run(PathBuf::from("debug.rb"), program, None).unwrap();
+        let output = output.borrow().clone();
+        output
+    }
+
+    #[test]
+    fn debugger_step() {
+        let program = "def foo(a)\n  b = a * 2\n  b + 1\nend\nx = [10]\ny = foo(x[0])\nputs y\n";
+        let input = vec![
+            "b foo", "c", "bt", "info", "p a * 3", "n", "i locals", "fin", "x",
+        ];
+        let output = run_debugger(program, input, true);
+        let expected = vec![
+            "Stopped at debug.rb:6 (<main>)",
+            "=>    6| y = foo(x[0])",
+            "#1 foo",
+            "Stopped at debug.rb:2 (Object#foo)",
+            "=>    2|   b = a * 2",
+            "=>#0   debug.rb:2:in 'Object#foo'",
+            "  #1   debug.rb:6:in '<main>'",
+            "a = 10",
+            "b = nil",
+            "=> 30",
+            "Stopped at debug.rb:3 (Object#foo)",
+            "=>    3|   b + 1",
+            "a = 10",
+            "b = 20",
+            "Stopped at debug.rb:7 (<main>)",
+            "=>    7| puts y",
+            "=> [10]",
+        ];
+        assert_eq!(expected, output[..expected.len()].to_vec());
+    }
+
+    #[test]
+    fn debugger_break() {
+        let program = "[1, 2].each do |i|\n  j = i * 10\n  binding.break if i == 1\nend\n";
+        let input = vec!["info", "j + i", "b 2", "b", "c", "p i", "del 2", "del", "c"];
+        let output = run_debugger(program, input, false);
+        let expected = vec![
+            "Stopped at debug.rb:3 (block in <main>)",
+            "=>    3|   binding.break if i == 1",
+            "i = 1",
+            "j = 10",
+            "=> 11",
+            "#1 debug.rb:2",
+            "#1 debug.rb:2",
+            "Stopped at debug.rb:2 (block in <main>)",
+            "=>    2|   j = i * 10",
+            "=> 2",
+            "No breakpoint: 2",
+        ];
+        assert_eq!(expected, output);
+    }
+}
@@ -0,0 +1,464 @@
+use crate::*;
+use super::vm_inst::Inst;
+use std::collections::{HashMap, HashSet};
+
+/// An ISeq to be disassembled, with the label and the type shown in the listing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ISeqEntry {
+    pub method: MethodRef,
+    pub label: String,
+    pub kind: &'static str,
+}
+
+impl ISeqEntry {
+    pub fn new(method: MethodRef, label: impl Into<String>, kind: &'static str) -> Self {
+        ISeqEntry {
+            method,
+            label: label.into(),
+            kind,
+        }
+    }
+}
+
+pub struct Disasm;
+
+impl Disasm {
+    pub fn get_iseq(globals: &Globals, method: MethodRef) -> Option<ISeqRef> {
+        match globals.get_method_info(method) {
+            MethodInfo::RubyFunc { iseq } => Some(*iseq),
+            _ => None,
+        }
+    }
+
+    /// Returns methods, blocks and class bodies which are defined in `entry`, in order of appearance.
+    pub fn children(globals: &Globals, entry: &ISeqEntry) -> Vec<ISeqEntry> {
+        let iseq_ref = match Disasm::get_iseq(globals, entry.method) {
+            Some(iseq) => iseq,
+            None => return vec![],
+        };
+        let iseq = &iseq_ref.iseq;
+        let mut children = vec![];
+        let mut pc = 0;
+        while pc < iseq.len() {
+            match Inst::generic(iseq[pc]) {
+                Inst::CREATE_PROC => {
+                    let method = MethodRef::from(read32(iseq, pc + 1));
+                    children.push(ISeqEntry::new(method, block_label(&entry.label), "block"));
+                }
+                Inst::SEND | Inst::SEND_SELF => {
+                    let block = read32(iseq, pc + 13);
+                    if block != 0 {
+                        let method = MethodRef::from(block);
+                        children.push(ISeqEntry::new(method, block_label(&entry.label), "block"));
+                    }
+                }
+                Inst::DEF_METHOD | Inst::DEF_SMETHOD => {
+                    let name = ident_name(globals, iseq, pc + 1);
+                    let method = MethodRef::from(read32(iseq, pc + 5));
+                    children.push(ISeqEntry::new(method, name, "method"));
+                }
+                Inst::DEF_CLASS => {
+                    let kind = if iseq[pc + 1] == 1 { "module" } else { "class" };
+                    let name = ident_name(globals, iseq, pc + 2);
+                    let method = MethodRef::from(read32(iseq, pc + 6));
+                    children.push(ISeqEntry::new(
+                        method,
+                        format!("<{}:{}>", kind, name),
+                        "class",
+                    ));
+                }
+                _ => {}
+            }
+            pc += Inst::inst_size(iseq[pc]);
+        }
+        children
+    }
+
+    /// Disassemble `entry` and all ISeqs defined in it.
+    pub fn disasm(globals: &Globals, entry: &ISeqEntry) -> String {
+        Disasm::disasm_scope(globals, entry, &[])
+    }
+
+    fn disasm_scope(globals: &Globals, entry: &ISeqEntry, outer: &[ISeqRef]) -> String {
+        let mut s = Disasm::disasm_one(globals, entry, outer);
+        let iseq_ref = match Disasm::get_iseq(globals, entry.method) {
+            Some(iseq) => iseq,
+            None => return s,
+        };
+        for child in Disasm::children(globals, entry) {
+            // Blocks can refer local variables of the enclosing scopes.
+            let child_outer = if child.kind == "block" {
+                let mut child_outer = vec![iseq_ref];
+                child_outer.extend_from_slice(outer);
+                child_outer
+            } else {
+                vec![]
+            };
+            s += "\n";
+            s += &Disasm::disasm_scope(globals, &child, &child_outer);
+        }
+        s
+    }
+
+    fn disasm_one(globals: &Globals, entry: &ISeqEntry, outer: &[ISeqRef]) -> String {
+        let iseq_ref = match Disasm::get_iseq(globals, entry.method) {
+            Some(iseq) => iseq,
+            None => return format!("== disasm: {} (builtin)\n", entry.label),
+        };
+        let source_info = iseq_ref.source_info;
+        let mut s = format!(
+            "== disasm: {}@{} ({:?})\n",
+            entry.label,
+            source_info.path.to_string_lossy(),
+            entry.method
+        );
+        let params = &iseq_ref.params;
+        s += &format!(
+            "local table (size: {}, argc: {} [opts: {}, rest: {}, post: {}, kw: {}, block: {}])\n",
+            iseq_ref.lvars,
+            params.req_params,
+            params.opt_params,
+            params.rest_param,
+            params.post_params,
+            params.keyword_params.len(),
+            params.block_param,
+        );
+        let locals: Vec<String> = Disasm::local_table(globals, iseq_ref)
+            .iter()
+            .enumerate()
+            .map(|(i, (name, kind))| format!("[{:>2}] {}{}", i, name, kind))
+            .collect();
+        if !locals.is_empty() {
+            s += &locals.join("  ");
+            s += "\n";
+        }
+        for (i, entry) in iseq_ref.exception_table.iter().enumerate() {
+            s += &format!(
+                "catch table [{:>2}] start: {:05x} end: {:05x} cont: {:05x}\n",
+                i,
+                entry.start.to_usize(),
+                entry.end.to_usize(),
+                entry.dest.to_usize()
+            );
+        }
+        let lines = Disasm::line_table(iseq_ref);
+        let iseq = &iseq_ref.iseq;
+        let mut pc = 0;
+        while pc < iseq.len() {
+            let inst = format!("{:05x} {}", pc, Inst::inst_info(globals, iseq_ref, outer, pc));
+            match lines.get(&pc) {
+                Some(line) => s += &format!("{:<60}({:>4})\n", inst, line),
+                None => s += &format!("{}\n", inst),
+            }
+            pc += Inst::inst_size(iseq[pc]);
+        }
+        s
+    }
+
+    /// Local variable names in LvarId order, with a parameter kind.
+    fn local_table(globals: &Globals, iseq_ref: ISeqRef) -> Vec<(String, &'static str)> {
+        let params = &iseq_ref.params;
+        let mut locals: Vec<(LvarId, IdentId)> =
+            iseq_ref.lvar.table().iter().map(|(k, v)| (*v, *k)).collect();
+        locals.sort_by_key(|(lvar, _)| lvar.as_usize());
+        locals
+            .iter()
+            .map(|(_, id)| {
+                let name = globals.get_ident_name(*id).to_string();
+                let pos = params.param_ident.iter().position(|param| param == id);
+                let kind = match pos {
+                    None => "",
+                    Some(i) => {
+                        let mut i = i;
+                        let rest = if params.rest_param { 1 } else { 0 };
+                        let kw = params.keyword_params.len();
+                        let kwrest = if params.kwrest_param.is_some() { 1 } else { 0 };
+                        let mut kind = "<Block>";
+                        for (num, name) in &[
+                            (params.req_params, "<Arg>"),
+                            (params.opt_params, "<Opt>"),
+                            (rest, "<Rest>"),
+                            (params.post_params, "<Post>"),
+                            (kw, "<Kw>"),
+                            (kwrest, "<KwRest>"),
+                        ] {
+                            if i < *num {
+                                kind = name;
+                                break;
+                            }
+                            i -= num;
+                        }
+                        kind
+                    }
+                };
+                (name, kind)
+            })
+            .collect()
+    }
+
+    /// Map from pc to the source line, built from `iseq_sourcemap`.
+    fn line_table(iseq_ref: ISeqRef) -> HashMap<usize, usize> {
+        iseq_ref
+            .iseq_sourcemap
+            .iter()
+            .map(|(pos, loc)| (pos.to_usize(), iseq_ref.source_info.get_line(loc)))
+            .collect()
+    }
+
+    /// Convert `entry` into an Array like RubyVM::InstructionSequence#to_a.
+    pub fn to_a(globals: &mut Globals, entry: &ISeqEntry) -> Value {
+        let iseq_ref = match Disasm::get_iseq(globals, entry.method) {
+            Some(iseq) => iseq,
+            None => return Value::nil(),
+        };
+        let path = iseq_ref.source_info.path.to_string_lossy().to_string();
+        let locals: Vec<Value> = Disasm::local_table(globals, iseq_ref)
+            .iter()
+            .map(|(name, _)| Value::symbol(globals.get_ident_id(name.as_str())))
+            .collect();
+        let params = &iseq_ref.params;
+        let misc = Disasm::sym_hash(
+            globals,
+            vec![
+                ("arg_size", Value::fixnum(params.param_ident.len() as i64)),
+                ("local_size", Value::fixnum(iseq_ref.lvars as i64)),
+            ],
+        );
+        let mut param_info = vec![
+            ("lead_num", Value::fixnum(params.req_params as i64)),
+            ("opt_num", Value::fixnum(params.opt_params as i64)),
+            ("post_num", Value::fixnum(params.post_params as i64)),
+            ("kw_num", Value::fixnum(params.keyword_params.len() as i64)),
+        ];
+        if params.rest_param {
+            param_info.push(("rest", Value::true_val()));
+        }
+        if params.kwrest_param.is_some() {
+            param_info.push(("kwrest", Value::true_val()));
+        }
+        if params.block_param {
+            param_info.push(("block", Value::true_val()));
+        }
+        let param_info = Disasm::sym_hash(globals, param_info);
+        let first_line = iseq_ref
+            .iseq_sourcemap
+            .first()
+            .map_or(1, |(_, loc)| iseq_ref.source_info.get_line(loc));
+        let body = Disasm::body_to_a(globals, entry, iseq_ref);
+        let ary = vec![
+            Value::string(globals, "YARVInstructionSequence/SimpleDataFormat".to_string()),
+            Value::fixnum(1),
+            Value::fixnum(0),
+            Value::fixnum(1),
+            misc,
+            Value::string(globals, entry.label.clone()),
+            Value::string(globals, path.clone()),
+            Value::string(globals, path),
+            Value::fixnum(first_line as i64),
+            Value::symbol(globals.get_ident_id(entry.kind)),
+            Value::array_from(globals, locals),
+            param_info,
+            Value::array_from(globals, vec![]),
+            body,
+        ];
+        Value::array_from(globals, ary)
+    }
+
+    /// The instruction list: line numbers, jump labels and instructions in order.
+    fn body_to_a(globals: &mut Globals, entry: &ISeqEntry, iseq_ref: ISeqRef) -> Value {
+        let iseq = &iseq_ref.iseq;
+        let lines = Disasm::line_table(iseq_ref);
+        let mut targets = HashSet::new();
+        let mut pc = 0;
+        while pc < iseq.len() {
+            match iseq[pc] {
+                Inst::JMP | Inst::JMP_IF_FALSE => {
+                    targets.insert(jump_dest(iseq, pc, 1, 5));
+                }
+                Inst::OPT_CASE => {
+                    targets.insert(jump_dest(iseq, pc, 5, 9));
+                    let map = globals.get_case_dispatch_map(read32(iseq, pc + 1));
+                    for disp in map.values() {
+                        targets.insert((pc as i64 + 9 + *disp as i64) as usize);
+                    }
+                }
+                _ => {}
+            }
+            pc += Inst::inst_size(iseq[pc]);
+        }
+        let mut children = Disasm::children(globals, entry).into_iter();
+        let mut body = vec![];
+        let mut prev_line = 0;
+        let mut pc = 0;
+        while pc < iseq.len() {
+            if targets.contains(&pc) {
+                body.push(label_sym(globals, pc));
+            }
+            if let Some(line) = lines.get(&pc) {
+                if *line != prev_line {
+                    body.push(Value::fixnum(*line as i64));
+                    prev_line = *line;
+                }
+            }
+            let inst = Disasm::inst_to_a(globals, iseq_ref, pc, &mut children);
+            body.push(inst);
+            pc += Inst::inst_size(iseq[pc]);
+        }
+        Value::array_from(globals, body)
+    }
+
+    fn inst_to_a(
+        globals: &mut Globals,
+        iseq_ref: ISeqRef,
+        pc: usize,
+        children: &mut impl Iterator<Item = ISeqEntry>,
+    ) -> Value {
+        let iseq = &iseq_ref.iseq;
+        let inst = Inst::generic(iseq[pc]);
+        let name = Inst::inst_name(inst).to_lowercase();
+        let mut ary = vec![Value::symbol(globals.get_ident_id(name))];
+        let ident = |pos: usize| Value::symbol(IdentId::from(read32(iseq, pos)));
+        match inst {
+            Inst::PUSH_FIXNUM => ary.push(Value::fixnum(read64(iseq, pc + 1) as i64)),
+            Inst::PUSH_FLONUM => ary.push(Value::flonum(f64::from_bits(read64(iseq, pc + 1)))),
+            Inst::PUSH_STRING => {
+                let s = ident_name(globals, iseq, pc + 1).to_string();
+                ary.push(Value::string(globals, s));
+            }
+            Inst::PUSH_SYMBOL
+            | Inst::GET_CONST
+            | Inst::SET_CONST
+            | Inst::GET_CONST_TOP
+            | Inst::GET_SCOPE
+            | Inst::GET_GVAR
+            | Inst::SET_GVAR => ary.push(ident(pc + 1)),
+            Inst::GET_IVAR | Inst::SET_IVAR => {
+                ary.push(ident(pc + 1));
+                ary.push(Value::fixnum(read32(iseq, pc + 5) as i64));
+            }
+            Inst::IVAR_ADDI => {
+                ary.push(ident(pc + 1));
+                ary.push(Value::fixnum(read32(iseq, pc + 5) as i32 as i64));
+                ary.push(Value::fixnum(read32(iseq, pc + 9) as i64));
+            }
+            Inst::ADDI | Inst::SUBI => ary.push(Value::fixnum(read32(iseq, pc + 1) as i32 as i64)),
+            Inst::ADD | Inst::SUB | Inst::MUL | Inst::SHL => {
+                ary.push(Value::fixnum(read32(iseq, pc + 1) as i64))
+            }
+            Inst::SET_LOCAL | Inst::GET_LOCAL | Inst::CHECK_LOCAL => {
+                let id = read32(iseq, pc + 1) as usize;
+                let name = iseq_ref.lvar.get_name(LvarId::from_usize(id));
+                ary.push(name.map_or(Value::nil(), Value::symbol));
+                ary.push(Value::fixnum(read32(iseq, pc + 5) as i64));
+            }
+            Inst::GET_INDEX
+            | Inst::SET_INDEX
+            | Inst::CREATE_ARRAY
+            | Inst::CREATE_HASH
+            | Inst::DUP
+            | Inst::TAKE
+            | Inst::YIELD
+            | Inst::ENTER_RESCUE
+            | Inst::RESCUE => ary.push(Value::fixnum(read32(iseq, pc + 1) as i64)),
+            Inst::EXPAND_ARRAY => {
+                ary.push(Value::fixnum(read32(iseq, pc + 1) as i64));
+                ary.push(Value::fixnum(read32(iseq, pc + 5) as i64));
+            }
+            Inst::JMP | Inst::JMP_IF_FALSE => ary.push(label_sym(globals, jump_dest(iseq, pc, 1, 5))),
+            Inst::OPT_CASE => {
+                let map: Vec<(Value, i32)> = globals
+                    .get_case_dispatch_map(read32(iseq, pc + 1))
+                    .iter()
+                    .map(|(k, v)| (*k, *v))
+                    .collect();
+                let hash = Value::hash_from(globals, HashMap::new());
+                let mut href = hash.as_hash().unwrap();
+                for (k, disp) in map {
+                    href.insert(k, label_sym(globals, (pc as i64 + 9 + disp as i64) as usize));
+                }
+                ary.push(hash);
+                ary.push(label_sym(globals, jump_dest(iseq, pc, 5, 9)));
+            }
+            Inst::SEND | Inst::SEND_SELF => {
+                ary.push(ident(pc + 1));
+                ary.push(Value::fixnum(read16(iseq, pc + 5) as i64));
+                ary.push(Value::fixnum(read16(iseq, pc + 7) as i64));
+                ary.push(Value::fixnum(read32(iseq, pc + 9) as i64));
+                if read32(iseq, pc + 13) != 0 {
+                    let child = children.next().unwrap();
+                    ary.push(Disasm::to_a(globals, &child));
+                } else {
+                    ary.push(Value::nil());
+                }
+            }
+            Inst::CREATE_PROC => {
+                let child = children.next().unwrap();
+                ary.push(Disasm::to_a(globals, &child));
+            }
+            Inst::DEF_METHOD | Inst::DEF_SMETHOD => {
+                ary.push(ident(pc + 1));
+                let child = children.next().unwrap();
+                ary.push(Disasm::to_a(globals, &child));
+            }
+            Inst::DEF_CLASS => {
+                ary.push(ident(pc + 2));
+                ary.push(Value::bool(iseq[pc + 1] == 1));
+                let child = children.next().unwrap();
+                ary.push(Disasm::to_a(globals, &child));
+            }
+            _ => {}
+        }
+        Value::array_from(globals, ary)
+    }
+
+    fn sym_hash(globals: &mut Globals, pairs: Vec<(&str, Value)>) -> Value {
+        let hash = Value::hash_from(globals, HashMap::new());
+        let mut href = hash.as_hash().unwrap();
+        for (k, v) in pairs {
+            href.insert(Value::symbol(globals.get_ident_id(k)), v);
+        }
+        hash
+    }
+}
+
+fn block_label(label: &str) -> String {
+    if let Some(rest) = label.strip_prefix("block in ") {
+        return format!("block (2 levels) in {}", rest);
+    }
+    if let Some(rest) = label.strip_prefix("block (") {
+        if let Some(pos) = rest.find(" levels) in ") {
+            if let Ok(level) = rest[..pos].parse::<usize>() {
+                return format!("block ({} levels) in {}", level + 1, &rest[pos + 12..]);
+            }
+        }
+    }
+    format!("block in {}", label)
+}
+
+fn label_sym(globals: &mut Globals, pc: usize) -> Value {
+    Value::symbol(globals.get_ident_id(format!("label_{}", pc)))
+}
+
+/// Destination of the jump instruction at `pc` whose displacement is at `pc + offset`.
+fn jump_dest(iseq: &ISeq, pc: usize, offset: usize, size: usize) -> usize {
+    (pc as i64 + size as i64 + read32(iseq, pc + offset) as i32 as i64) as usize
+}
+
+fn read64(iseq: &ISeq, pc: usize) -> u64 {
+    let ptr = iseq[pc..pc + 1].as_ptr() as *const u64;
+    unsafe { *ptr }
+}
+
+fn read32(iseq: &ISeq, pc: usize) -> u32 {
+    let ptr = iseq[pc..pc + 1].as_ptr() as *const u32;
+    unsafe { *ptr }
+}
+
+fn read16(iseq: &ISeq, pc: usize) -> u16 {
+    let ptr = iseq[pc..pc + 1].as_ptr() as *const u16;
+    unsafe { *ptr }
+}
+
+fn ident_name<'a>(globals: &'a Globals, iseq: &ISeq, pc: usize) -> &'a str {
+    globals.get_ident_name(IdentId::from(read32(iseq, pc)))
+}
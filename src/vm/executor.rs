@@ -1,4 +1,5 @@
 use super::codegen::ContextKind;
+use crate::builtin::errorobj;
 use crate::*;
 
 #[cfg(feature = "perf")]
@@ -18,12 +19,15 @@ pub struct VM {
     pub root_path: Vec<PathBuf>,
     // VM state
     fiber_state: FiberState,
-    exec_context: Vec<ContextRef>,
+    pub exec_context: Vec<ContextRef>,
     class_context: Vec<(Value, DefineMode)>,
-    exec_stack: Vec<Value>,
+    pub exec_stack: Vec<Value>,
     exception: bool,
-    pc: usize,
+    pub pc: usize,
     pub channel: Option<(SyncSender<VMResult>, Receiver<usize>)>,
+    pub profiler: Option<Profiler>,
+    pub debugger: Option<Debugger>,
+    pub jit: Option<Jit>,
     #[cfg(feature = "perf")]
     perf: Perf,
 }
@@ -78,6 +82,7 @@ impl VM {
         set_builtin_class!("Object", object);
         set_builtin_class!("Module", module);
         set_builtin_class!("Class", class);
+        set_builtin_class!("Numeric", numeric);
         set_builtin_class!("Integer", integer);
         set_builtin_class!("Float", float);
         set_builtin_class!("Array", array);
@@ -89,13 +94,22 @@ impl VM {
         set_builtin_class!("Regexp", regexp);
         set_builtin_class!("Fiber", fiber);
         set_builtin_class!("Enumerator", enumerator);
+        set_builtin_class!("IO", io);
+        set_builtin_class!("File", file);
+        set_builtin_class!("Time", time);
+        set_builtin_class!("Rational", rational);
+        set_builtin_class!("Complex", complex);
 
         set_class!("Math", math::init_math(&mut globals));
-        set_class!("File", file::init_file(&mut globals));
+        set_class!("Dir", dir::init_dir(&mut globals));
+        set_class!("StringIO", stringio::init_stringio(&mut globals));
         set_class!("Process", process::init_process(&mut globals));
+        set_class!("Open3", process::init_open3(&mut globals));
+        set_class!("ENV", env::init_env(&mut globals));
+        set_class!("RubyVM", rubyvm::init_rubyvm(&mut globals));
+        set_class!("Coverage", coverage::init_coverage(&mut globals));
+        set_class!("Binding", binding::init_binding(&mut globals));
         set_class!("Struct", structobj::init_struct(&mut globals));
-        set_class!("StandardError", Value::class(&globals, globals.class_class));
-        set_class!("RuntimeError", errorobj::init_error(&mut globals));
 
         let vm = VM {
             globals: GlobalsRef::new(globals),
@@ -107,6 +121,9 @@ impl VM {
             exception: false,
             pc: 0,
             channel: None,
+            profiler: None,
+            debugger: None,
+            jit: None,
             #[cfg(feature = "perf")]
             perf: Perf::new(),
         };
@@ -125,6 +142,9 @@ impl VM {
             exception: false,
             pc: 0,
             channel: Some((tx, rx)),
+            profiler: None,
+            debugger: None,
+            jit: None,
             #[cfg(feature = "perf")]
             perf: self.perf.clone(),
         }
@@ -329,6 +349,44 @@ impl VM {
 
     pub fn run(&mut self, path: PathBuf, program: &str, self_value: Option<Value>) -> VMResult {
         let method = self.parse_program(path, program)?;
+        self.run_method(method, self_value)
+    }
+
+    /// Run the program of the source file at `path`.
+    /// If the compiled ISeq cache of the file is newer than the source, it is used
+    /// instead of compiling `program`.
+    pub fn run_file(
+        &mut self,
+        path: PathBuf,
+        program: &str,
+        self_value: Option<Value>,
+    ) -> VMResult {
+        let method = self.compile_file(path, program)?;
+        self.run_method(method, self_value)
+    }
+
+    /// Compile the program of the source file at `path`, or load the compiled ISeq cache
+    /// of the file if it is newer than the source.
+    pub fn compile_file(&mut self, path: PathBuf, program: &str) -> Result<MethodRef, RubyError> {
+        let cache = loader::load_cache(&path)
+            .and_then(|bytes| ISeqCache::load(&mut self.globals, &bytes, Some(path.clone())).ok());
+        match cache {
+            Some(method) => Ok(method),
+            None => self.parse_program(path, program),
+        }
+    }
+
+    /// Run the procs registered by `at_exit` or `END {}` in reverse order of registration.
+    pub fn run_at_exit(&mut self) -> Result<(), RubyError> {
+        let call = self.globals.get_ident_id("call");
+        while let Some(procobj) = self.globals.at_exit.pop() {
+            self.send0(procobj, call)?;
+        }
+        Ok(())
+    }
+
+    /// Run the top-level ISeq `method`.
+    pub fn run_method(&mut self, method: MethodRef, self_value: Option<Value>) -> VMResult {
         let self_value = match self_value {
             Some(val) => val,
             None => self.globals.main_object,
@@ -391,31 +449,26 @@ macro_rules! try_err {
     ($self:ident, $eval:expr) => {
         match $eval {
             Ok(val) => $self.stack_push(val),
-            Err(err) if err.kind == RubyErrorKind::BlockReturn => {}
-            Err(mut err) => {
-                let m = $self.context().iseq_ref.method;
-                let res = if RubyErrorKind::MethodReturn(m) == err.kind {
+            Err(err) => match err.kind {
+                RubyErrorKind::BlockReturn(outer) if outer == $self.context() => {
+                    // `break` in the block given to this method call.
+                    let result = $self.stack_pop();
+                    let prev_len = $self.context().stack_len;
+                    $self.exec_stack.truncate(prev_len);
+                    $self.stack_push(result);
+                }
+                RubyErrorKind::MethodReturn(m) if m == $self.context().iseq_ref.method => {
                     let result = $self.stack_pop();
                     let prev_len = $self.context().stack_len;
                     $self.exec_stack.truncate(prev_len);
-                    $self.unwind_context(&mut err);
                     #[cfg(feature = "trace")]
                     {
                         println!("<--- METHOD_RETURN Ok({})", $self.val_inspect(result),);
                     }
-                    Ok(result)
-                } else {
-                    $self.unwind_context(&mut err);
-                    #[cfg(feature = "trace")]
-                    {
-                        println!("<--- Err({:?})", err.kind);
-                    }
-                    Err(err)
-                };
-                $self.fiberstate_dead();
-                $self.fiber_send_to_parent(res.clone());
-                return res;
-            }
+                    return Ok(result);
+                }
+                _ => return Err(err),
+            },
         };
     };
 }
@@ -437,9 +490,70 @@ impl VM {
         };
         self.context_push(context);
         self.pc = context.pc;
+        if let Some(coverage) = &mut self.globals.coverage {
+            if context.pc == 0 {
+                coverage.trace_method(context.iseq_ref);
+            }
+        }
+        loop {
+            let mut err = match self.run_context_main(context) {
+                Ok(val) => {
+                    self.context_pop().unwrap();
+                    if let Some(context) = self.exec_context.last() {
+                        self.pc = context.pc;
+                    }
+                    return Ok(val);
+                }
+                Err(err) => err,
+            };
+            // Record the label of this frame, e.g. "Foo#bar".
+            if let Some(frame) = err.info.last_mut() {
+                if frame.2.is_empty() {
+                    frame.2 = context.iseq_ref.label(&self.globals);
+                }
+            }
+            if let Some((index, entry)) = self.find_rescue(context, &err) {
+                // Continue at the rescue or ensure clause with the exception object.
+                let exception = self.exception_from_error(&err);
+                self.exec_stack.truncate(context.rescue_sp[index]);
+                self.stack_push(exception);
+                self.pc = entry.dest.to_usize();
+                continue;
+            }
+            #[cfg(feature = "trace")]
+            {
+                println!("<--- Err({:?})", err.kind);
+            }
+            if self.exec_context.len() == 1 {
+                // if in the final context, the fiber becomes DEAD.
+                self.fiberstate_dead();
+                self.fiber_send_to_parent(Err(err.clone()));
+            }
+            self.unwind_context(&mut err);
+            return Err(err);
+        }
+    }
+
+    /// Execute instructions of `context` from the current pc until the method or block
+    /// returns or an error occurs. The context is popped by the caller, `run_context()`.
+    fn run_context_main(&mut self, context: ContextRef) -> VMResult {
         let iseq = &context.iseq_ref.iseq;
-        let mut self_oref = context.self_value.as_object();
+        let self_oref = context.self_value.as_object();
+        if self.pc == 0 && self.jit.is_some() {
+            if let Some(val) = self.jit_exec(context)? {
+                return Ok(val);
+            }
+        }
         loop {
+            if let Some(coverage) = &mut self.globals.coverage {
+                coverage.trace_line(context, self.pc);
+            }
+            if self.debugger.is_some() {
+                self.debugger_trace(context);
+            }
+            if let Some(profiler) = &mut self.profiler {
+                profiler.sample(&self.globals, &self.exec_context);
+            }
             #[cfg(feature = "perf")]
             {
                 self.perf.get_perf(iseq[self.pc]);
@@ -463,19 +577,15 @@ impl VM {
                         self.fiberstate_dead();
                         self.fiber_send_to_parent(Err(self.error_fiber("Dead fiber called.")));
                     };
-                    let _context = self.context_pop().unwrap();
                     let val = self.stack_pop();
                     #[cfg(feature = "trace")]
                     {
-                        if _context.is_fiber {
+                        if context.is_fiber {
                             println!("<=== Ok({})", self.val_inspect(val));
                         } else {
                             println!("<--- Ok({})", self.val_inspect(val));
                         }
                     }
-                    if !self.exec_context.is_empty() {
-                        self.pc = self.context().pc;
-                    };
                     return Ok(val);
                 }
                 Inst::RETURN => {
@@ -484,7 +594,8 @@ impl VM {
                     // - `break` outer of loops.
                     let res = if let ISeqKind::Block(_) = context.kind {
                         // if in block context, exit with Err(BLOCK_RETURN).
-                        let err = self.error_block_return();
+                        let outer = self.context().outer.unwrap();
+                        let err = self.error_block_return(outer);
                         #[cfg(feature = "trace")]
                         {
                             println!("<--- Err({:?})", err.kind);
@@ -499,11 +610,6 @@ impl VM {
                         }
                         Ok(val)
                     };
-
-                    self.context_pop().unwrap();
-                    if !self.exec_context.is_empty() {
-                        self.pc = self.context().pc;
-                    }
                     return res;
                 }
                 Inst::MRETURN => {
@@ -520,10 +626,6 @@ impl VM {
                     } else {
                         unreachable!()
                     };
-                    self.context_pop().unwrap();
-                    if !self.exec_context.is_empty() {
-                        self.pc = self.context().pc;
-                    }
                     return res;
                 }
                 Inst::PUSH_NIL => {
@@ -661,6 +763,7 @@ impl VM {
                 Inst::EQ => {
                     let lhs = self.stack_pop();
                     let rhs = self.stack_pop();
+                    self.quicken_cmp(iseq, Inst::JMP_F_EQ, lhs, rhs);
                     let val = Value::bool(self.eval_eq(rhs, lhs)?);
                     self.stack_push(val);
                     self.pc += 1;
@@ -668,6 +771,7 @@ impl VM {
                 Inst::NE => {
                     let lhs = self.stack_pop();
                     let rhs = self.stack_pop();
+                    self.quicken_cmp(iseq, Inst::JMP_F_NE, lhs, rhs);
                     let val = Value::bool(!self.eval_eq(rhs, lhs)?);
                     self.stack_push(val);
                     self.pc += 1;
@@ -683,6 +787,7 @@ impl VM {
                 Inst::GT => {
                     let lhs = self.stack_pop();
                     let rhs = self.stack_pop();
+                    self.quicken_cmp(iseq, Inst::JMP_F_GT, lhs, rhs);
                     let val = self.eval_gt(lhs, rhs)?;
                     self.stack_push(val);
                     self.pc += 1;
@@ -690,10 +795,27 @@ impl VM {
                 Inst::GE => {
                     let lhs = self.stack_pop();
                     let rhs = self.stack_pop();
+                    self.quicken_cmp(iseq, Inst::JMP_F_GE, lhs, rhs);
                     let val = self.eval_ge(lhs, rhs)?;
                     self.stack_push(val);
                     self.pc += 1;
                 }
+                Inst::LT => {
+                    let lhs = self.stack_pop();
+                    let rhs = self.stack_pop();
+                    self.quicken_cmp(iseq, Inst::JMP_F_LT, lhs, rhs);
+                    let val = self.eval_lt(lhs, rhs)?;
+                    self.stack_push(val);
+                    self.pc += 1;
+                }
+                Inst::LE => {
+                    let lhs = self.stack_pop();
+                    let rhs = self.stack_pop();
+                    self.quicken_cmp(iseq, Inst::JMP_F_LE, lhs, rhs);
+                    let val = self.eval_le(lhs, rhs)?;
+                    self.stack_push(val);
+                    self.pc += 1;
+                }
                 Inst::CMP => {
                     let lhs = self.stack_pop();
                     let rhs = self.stack_pop();
@@ -728,6 +850,7 @@ impl VM {
                     self.pc += 9;
                 }
                 Inst::GET_LOCAL => {
+                    self.quicken_get_local(iseq);
                     let id = self.read_lvar_id(iseq, 1);
                     let outer = self.read32(iseq, 5);
                     let cref = self.get_outer_context(outer);
@@ -763,10 +886,7 @@ impl VM {
                 }
                 Inst::GET_CONST => {
                     let id = self.read_id(iseq, 1);
-                    let val = match self.get_env_const(id) {
-                        Some(val) => val,
-                        None => self.get_super_const(self.class(), id)?,
-                    };
+                    let val = self.get_const(id)?;
                     self.stack_push(val);
                     self.pc += 5;
                 }
@@ -786,34 +906,26 @@ impl VM {
                 }
                 Inst::SET_IVAR => {
                     let var_id = self.read_id(iseq, 1);
+                    let cache_slot = self.read32(iseq, 5);
                     let new_val = self.stack_pop();
-                    self_oref.set_var(var_id, new_val);
-                    self.pc += 5;
+                    self.set_ivar(self_oref, var_id, new_val, cache_slot);
+                    self.pc += 9;
                 }
                 Inst::GET_IVAR => {
                     let var_id = self.read_id(iseq, 1);
-                    let val = match self_oref.get_var(var_id) {
-                        Some(val) => val.clone(),
-                        None => Value::nil(),
-                    };
+                    let cache_slot = self.read32(iseq, 5);
+                    let val = self.get_ivar(self_oref, var_id, cache_slot);
                     self.stack_push(val);
-                    self.pc += 5;
+                    self.pc += 9;
                 }
                 Inst::IVAR_ADDI => {
                     let var_id = self.read_id(iseq, 1);
                     let i = self.read32(iseq, 5) as i32;
-                    match self_oref.get_mut_var(var_id) {
-                        Some(val) => {
-                            let new_val = self.eval_addi(*val, i)?;
-                            *val = new_val;
-                        }
-                        None => {
-                            let new_val = self.eval_addi(Value::nil(), i)?;
-                            self_oref.set_var(var_id, new_val);
-                        }
-                    };
-
-                    self.pc += 9;
+                    let cache_slot = self.read32(iseq, 9);
+                    let val = self.get_ivar(self_oref, var_id, cache_slot);
+                    let new_val = self.eval_addi(val, i)?;
+                    self.set_ivar(self_oref, var_id, new_val, cache_slot);
+                    self.pc += 13;
                 }
                 Inst::SET_GVAR => {
                     let var_id = self.read_id(iseq, 1);
@@ -840,7 +952,18 @@ impl VM {
                                     aref.set_elem(self, &args)?;
                                 }
                                 ObjKind::Hash(mut href) => href.insert(args[0], val),
-                                _ => return Err(self.error_undefined_method("[]=", receiver)),
+                                _ => {
+                                    let id = self.globals.get_ident_id("[]=");
+                                    match self.get_method(receiver, id) {
+                                        Ok(mref) => {
+                                            args.push(val);
+                                            self.eval_send(mref, receiver, &args)?;
+                                        }
+                                        Err(_) => {
+                                            return Err(self.error_undefined_method("[]=", receiver))
+                                        }
+                                    }
+                                }
                             };
                         }
                         None => return Err(self.error_undefined_method("[]=", receiver)),
@@ -898,6 +1021,26 @@ impl VM {
                     self.stack_push(res);
                     self.pc += 1;
                 }
+                Inst::DOUBLE_SPLAT => {
+                    // Merge `**val` into the keyword arguments below it.
+                    let val = self.stack_pop();
+                    let mut kw = self.exec_stack.last().unwrap().as_hash().unwrap();
+                    if !val.is_nil() {
+                        let hash = match val.as_hash() {
+                            Some(hash) => hash,
+                            None => {
+                                return Err(self.error_type(format!(
+                                    "no implicit conversion of {} into Hash",
+                                    self.globals.get_class_name(val)
+                                )))
+                            }
+                        };
+                        for (k, v) in hash.iter() {
+                            kw.insert(k, v);
+                        }
+                    }
+                    self.pc += 1;
+                }
                 Inst::CREATE_RANGE => {
                     let start = self.stack_pop();
                     let end = self.stack_pop();
@@ -936,13 +1079,37 @@ impl VM {
                     self.stack_push(regexp);
                     self.pc += 1;
                 }
+                Inst::CREATE_RATIONAL => {
+                    let den = self.stack_pop().as_fixnum().unwrap();
+                    let num = self.stack_pop().as_fixnum().unwrap();
+                    let info = match RationalInfo::new(num as i128, den as i128) {
+                        Some(info) => info,
+                        None => return Err(self.error_range("integer overflow")),
+                    };
+                    self.stack_push(Value::rational(&self.globals, info));
+                    self.pc += 1;
+                }
+                Inst::CREATE_COMPLEX => {
+                    let im = self.stack_pop();
+                    let re = self.stack_pop();
+                    let info = match (Real::from_value(re), Real::from_value(im)) {
+                        (Some(re), Some(im)) => ComplexInfo::new(re, im),
+                        _ => return Err(self.error_argument("Illegal argument for CREATE_COMPLEX")),
+                    };
+                    self.stack_push(Value::complex(&self.globals, info));
+                    self.pc += 1;
+                }
                 Inst::JMP => {
                     let disp = self.read_disp(iseq, 1);
                     self.jump_pc(5, disp);
                 }
                 Inst::JMP_IF_FALSE => {
                     let val = self.stack_pop();
-                    if self.val_to_bool(val) {
+                    let cond = self.val_to_bool(val);
+                    if let Some(coverage) = &mut self.globals.coverage {
+                        coverage.trace_branch(context.iseq_ref, self.pc, cond);
+                    }
+                    if cond {
                         self.jump_pc(5, 0);
                     } else {
                         let disp = self.read_disp(iseq, 1);
@@ -960,12 +1127,12 @@ impl VM {
                 }
                 Inst::SEND => {
                     let receiver = self.stack_pop();
-                    try_err!(self, self.vm_send(iseq, receiver));
+                    try_err!(self, self.vm_send(iseq, receiver, Inst::SEND));
                     self.pc += 17;
                 }
                 Inst::SEND_SELF => {
                     let receiver = context.self_value;
-                    try_err!(self, self.vm_send(iseq, receiver));
+                    try_err!(self, self.vm_send(iseq, receiver, Inst::SEND_SELF));
                     self.pc += 17;
                 }
                 Inst::YIELD => {
@@ -1044,6 +1211,67 @@ impl VM {
                     };
                     self.pc += 9;
                 }
+                Inst::ALIAS => {
+                    let new = self.read_id(iseq, 1);
+                    let old = self.read_id(iseq, 5);
+                    if self.globals.get_ident_name(new).starts_with('$') {
+                        self.alias_global_var(new, old);
+                    } else {
+                        self.alias_method(self.class(), new, old)?;
+                    }
+                    self.stack_push(Value::nil());
+                    self.pc += 9;
+                }
+                Inst::UNDEF => {
+                    let id = self.read_id(iseq, 1);
+                    self.undef_method(self.class(), id)?;
+                    self.stack_push(Value::nil());
+                    self.pc += 5;
+                }
+                Inst::DEFINED => {
+                    let id = self.read_id(iseq, 1);
+                    let kind = self.read32(iseq, 5);
+                    let defined = match kind {
+                        DefinedKind::IVAR => self_oref.get_var(id).is_some(),
+                        DefinedKind::GVAR => self.is_global_var_defined(id),
+                        DefinedKind::CONST => self.get_const(id).is_ok(),
+                        DefinedKind::SCOPED_CONST => {
+                            let parent = self.stack_pop();
+                            parent.is_module().is_some() && self.get_super_const(parent, id).is_ok()
+                        }
+                        DefinedKind::METHOD => {
+                            let receiver = self.stack_pop();
+                            self.get_method(receiver, id).is_ok()
+                        }
+                        DefinedKind::FUNC => self.get_method(context.self_value, id).is_ok(),
+                        DefinedKind::SUPER => {
+                            // The superclass method of the method which encloses the current block.
+                            let context = self.enclosing_method_context();
+                            match (&context.kind, context.iseq_ref.class_defined) {
+                                (ISeqKind::Method(method), Some(list)) => {
+                                    match list.class.superclass() {
+                                        Some(class) => {
+                                            self.get_instance_method(class, *method).is_ok()
+                                        }
+                                        None => false,
+                                    }
+                                }
+                                _ => false,
+                            }
+                        }
+                        _ => {
+                            // A block is given to the method which encloses the current block.
+                            self.enclosing_method_context().block.is_some()
+                        }
+                    };
+                    let val = if defined {
+                        Value::string(&self.globals, DefinedKind::name(kind).to_string())
+                    } else {
+                        Value::nil()
+                    };
+                    self.stack_push(val);
+                    self.pc += 9;
+                }
                 Inst::TO_S => {
                     let val = self.stack_pop();
                     let s = self.val_to_s(val);
@@ -1064,10 +1292,22 @@ impl VM {
                     }
                     self.pc += 5;
                 }
+                Inst::TOPN => {
+                    let pos = self.read_usize(iseq, 1);
+                    let val = self.exec_stack[self.exec_stack.len() - 1 - pos];
+                    self.stack_push(val);
+                    self.pc += 5;
+                }
+                Inst::SETN => {
+                    let pos = self.read_usize(iseq, 1);
+                    let len = self.exec_stack.len();
+                    self.exec_stack[len - 1 - pos] = self.exec_stack[len - 1];
+                    self.pc += 5;
+                }
                 Inst::TAKE => {
                     let len = self.read_usize(iseq, 1);
                     let val = self.stack_pop();
-                    match val.as_array() {
+                    match self.implicit_to_ary(val)? {
                         Some(info) => {
                             let elem = &info.elements;
                             let ary_len = elem.len();
@@ -1094,6 +1334,167 @@ impl VM {
 
                     self.pc += 5;
                 }
+                Inst::EXPAND_ARRAY => {
+                    let pre_len = self.read_usize(iseq, 1);
+                    let post_len = self.read_usize(iseq, 5);
+                    let val = self.stack_pop();
+                    let elems = match self.implicit_to_ary(val)? {
+                        Some(info) => info.elements.clone(),
+                        None => vec![val],
+                    };
+                    let post_start = std::cmp::max(pre_len, elems.len().saturating_sub(post_len));
+                    for i in 0..pre_len {
+                        self.stack_push(*elems.get(i).unwrap_or(&Value::nil()));
+                    }
+                    let rest = elems.get(pre_len..post_start).unwrap_or(&[]).to_vec();
+                    let rest = Value::array_from(&self.globals, rest);
+                    self.stack_push(rest);
+                    for i in post_start..post_start + post_len {
+                        self.stack_push(*elems.get(i).unwrap_or(&Value::nil()));
+                    }
+                    self.pc += 9;
+                }
+                Inst::ENTER_RESCUE => {
+                    let index = self.read_usize(iseq, 1);
+                    let mut context = context;
+                    if context.rescue_sp.len() <= index {
+                        context.rescue_sp.resize(index + 1, 0);
+                    }
+                    context.rescue_sp[index] = self.exec_stack.len();
+                    self.pc += 5;
+                }
+                Inst::RESCUE => {
+                    let len = self.read_usize(iseq, 1);
+                    let stack_len = self.exec_stack.len();
+                    let classes = self.exec_stack.split_off(stack_len - len);
+                    let exception = *self.exec_stack.last().unwrap();
+                    let res = self.rescue_match(exception, &classes)?;
+                    self.stack_push(Value::bool(res));
+                    self.pc += 5;
+                }
+                Inst::RAISE => {
+                    let exception = self.stack_pop();
+                    return Err(self.error_exception(exception));
+                }
+                Inst::DECONSTRUCT => {
+                    let len = self.read_usize(iseq, 1);
+                    let exact = self.read32(iseq, 5) != 0;
+                    let val = self.stack_pop();
+                    let res = self.deconstruct(val, len, exact)?;
+                    self.stack_push(res);
+                    self.pc += 9;
+                }
+                Inst::DECONSTRUCT_KEYS => {
+                    let keys = self.stack_pop();
+                    let val = self.stack_pop();
+                    let res = self.deconstruct_keys(val, keys)?;
+                    self.stack_push(res);
+                    self.pc += 1;
+                }
+                Inst::SEND_BUILTIN => {
+                    let receiver = self.stack_pop();
+                    try_err!(self, self.vm_send_builtin(iseq, receiver, Inst::SEND));
+                    self.pc += 17;
+                }
+                Inst::SEND_SELF_BUILTIN => {
+                    let receiver = context.self_value;
+                    try_err!(self, self.vm_send_builtin(iseq, receiver, Inst::SEND_SELF));
+                    self.pc += 17;
+                }
+                Inst::SEND_ATTR_READER => {
+                    let receiver = self.stack_pop();
+                    try_err!(self, self.vm_send_attr_reader(iseq, receiver));
+                    self.pc += 17;
+                }
+                Inst::SEND_RUBY => {
+                    let receiver = self.stack_pop();
+                    try_err!(self, self.vm_send_ruby(iseq, receiver, Inst::SEND));
+                    self.pc += 17;
+                }
+                Inst::SEND_SELF_RUBY => {
+                    let receiver = context.self_value;
+                    try_err!(self, self.vm_send_ruby(iseq, receiver, Inst::SEND_SELF));
+                    self.pc += 17;
+                }
+                Inst::JMP_F_EQ => {
+                    let lhs = self.stack_pop();
+                    let rhs = self.stack_pop();
+                    let cond = if lhs.is_packed_fixnum() && rhs.is_packed_fixnum() {
+                        rhs.as_packed_fixnum() == lhs.as_packed_fixnum()
+                    } else {
+                        self.eval_eq(rhs, lhs)?
+                    };
+                    self.fused_jmp_if_false(iseq, context, cond);
+                }
+                Inst::JMP_F_NE => {
+                    let lhs = self.stack_pop();
+                    let rhs = self.stack_pop();
+                    let cond = if lhs.is_packed_fixnum() && rhs.is_packed_fixnum() {
+                        rhs.as_packed_fixnum() != lhs.as_packed_fixnum()
+                    } else {
+                        !self.eval_eq(rhs, lhs)?
+                    };
+                    self.fused_jmp_if_false(iseq, context, cond);
+                }
+                Inst::JMP_F_GT => {
+                    let lhs = self.stack_pop();
+                    let rhs = self.stack_pop();
+                    let cond = if lhs.is_packed_fixnum() && rhs.is_packed_fixnum() {
+                        rhs.as_packed_fixnum() > lhs.as_packed_fixnum()
+                    } else {
+                        let val = self.eval_gt(lhs, rhs)?;
+                        self.val_to_bool(val)
+                    };
+                    self.fused_jmp_if_false(iseq, context, cond);
+                }
+                Inst::JMP_F_GE => {
+                    let lhs = self.stack_pop();
+                    let rhs = self.stack_pop();
+                    let cond = if lhs.is_packed_fixnum() && rhs.is_packed_fixnum() {
+                        rhs.as_packed_fixnum() >= lhs.as_packed_fixnum()
+                    } else {
+                        let val = self.eval_ge(lhs, rhs)?;
+                        self.val_to_bool(val)
+                    };
+                    self.fused_jmp_if_false(iseq, context, cond);
+                }
+                Inst::JMP_F_LT => {
+                    let lhs = self.stack_pop();
+                    let rhs = self.stack_pop();
+                    let cond = if lhs.is_packed_fixnum() && rhs.is_packed_fixnum() {
+                        rhs.as_packed_fixnum() < lhs.as_packed_fixnum()
+                    } else {
+                        let val = self.eval_lt(lhs, rhs)?;
+                        self.val_to_bool(val)
+                    };
+                    self.fused_jmp_if_false(iseq, context, cond);
+                }
+                Inst::JMP_F_LE => {
+                    let lhs = self.stack_pop();
+                    let rhs = self.stack_pop();
+                    let cond = if lhs.is_packed_fixnum() && rhs.is_packed_fixnum() {
+                        rhs.as_packed_fixnum() <= lhs.as_packed_fixnum()
+                    } else {
+                        let val = self.eval_le(lhs, rhs)?;
+                        self.val_to_bool(val)
+                    };
+                    self.fused_jmp_if_false(iseq, context, cond);
+                }
+                Inst::ADD_LOCALS => {
+                    let lhs = self.get_local(iseq, 1);
+                    let rhs = self.get_local(iseq, 10);
+                    // Continue as the ADD, which has the inline cache and the source location.
+                    self.pc += 18;
+                    try_err!(self, self.eval_add(rhs, lhs, iseq));
+                    self.pc += 5;
+                }
+                Inst::SUB_LOCALS => {
+                    let lhs = self.get_local(iseq, 1);
+                    let rhs = self.get_local(iseq, 10);
+                    self.pc += 18;
+                    try_err!(self, self.eval_sub(rhs, lhs, iseq));
+                    self.pc += 5;
+                }
                 _ => return Err(self.error_unimplemented("Unimplemented instruction.")),
             }
         }
@@ -1195,14 +1596,47 @@ impl VM {
         RubyError::new_runtime_err(RuntimeErrKind::Fiber(msg.into()), self.source_info(), loc)
     }
 
+    pub fn error_domain(&self, msg: impl Into<String>) -> RubyError {
+        let loc = self.get_loc();
+        RubyError::new_runtime_err(RuntimeErrKind::Domain(msg.into()), self.source_info(), loc)
+    }
+
+    pub fn error_float_domain(&self, msg: impl Into<String>) -> RubyError {
+        let loc = self.get_loc();
+        RubyError::new_runtime_err(
+            RuntimeErrKind::FloatDomain(msg.into()),
+            self.source_info(),
+            loc,
+        )
+    }
+
+    pub fn error_range(&self, msg: impl Into<String>) -> RubyError {
+        let loc = self.get_loc();
+        RubyError::new_runtime_err(RuntimeErrKind::Range(msg.into()), self.source_info(), loc)
+    }
+
+    pub fn error_zero_div(&self, msg: impl Into<String>) -> RubyError {
+        let loc = self.get_loc();
+        RubyError::new_runtime_err(
+            RuntimeErrKind::ZeroDivision(msg.into()),
+            self.source_info(),
+            loc,
+        )
+    }
+
+    pub fn error_exception(&self, exception: Value) -> RubyError {
+        let loc = self.get_loc();
+        RubyError::new_exception(exception, self.source_info(), loc)
+    }
+
     pub fn error_method_return(&self, method: MethodRef) -> RubyError {
         let loc = self.get_loc();
         RubyError::new_method_return(method, self.source_info(), loc)
     }
 
-    pub fn error_block_return(&self) -> RubyError {
+    pub fn error_block_return(&self, outer: ContextRef) -> RubyError {
         let loc = self.get_loc();
-        RubyError::new_block_return(self.source_info(), loc)
+        RubyError::new_block_return(outer, self.source_info(), loc)
     }
 
     pub fn check_args_num(&self, len: usize, num: usize) -> Result<(), RubyError> {
@@ -1333,12 +1767,7 @@ impl VM {
 
 impl VM {
     fn get_loc(&self) -> Loc {
-        let sourcemap = &self.context().iseq_ref.iseq_sourcemap;
-        sourcemap
-            .iter()
-            .find(|x| x.0 == ISeqPos::from(self.pc))
-            .unwrap_or(&(ISeqPos::from(0), Loc(0, 0)))
-            .1
+        self.context().iseq_ref.get_loc(self.pc)
     }
 
     fn get_nearest_class_stack(&self) -> Option<ClassListRef> {
@@ -1368,6 +1797,14 @@ impl VM {
         }
     }
 
+    /// Get the constant `id` from the class stack, or from the current class and its superclasses.
+    pub fn get_const(&self, id: IdentId) -> VMResult {
+        match self.get_env_const(id) {
+            Some(val) => Ok(val),
+            None => self.get_super_const(self.class(), id),
+        }
+    }
+
     // Search class stack for the constant.
     fn get_env_const(&self, id: IdentId) -> Option<Value> {
         let mut class_list = match self.get_nearest_class_stack() {
@@ -1407,6 +1844,7 @@ impl VM {
     }
 
     pub fn get_global_var(&self, id: IdentId) -> Value {
+        let id = self.globals.resolve_global_alias(id);
         match self.globals.global_var.get(&id) {
             Some(val) => val.clone(),
             None => Value::nil(),
@@ -1414,8 +1852,22 @@ impl VM {
     }
 
     pub fn set_global_var(&mut self, id: IdentId, val: Value) {
+        let id = self.globals.resolve_global_alias(id);
         self.globals.global_var.insert(id, val);
     }
+
+    pub fn is_global_var_defined(&self, id: IdentId) -> bool {
+        let id = self.globals.resolve_global_alias(id);
+        self.globals.global_var.contains_key(&id)
+    }
+
+    /// Make the global variable `new` refer to the same variable as `old`.
+    pub fn alias_global_var(&mut self, new: IdentId, old: IdentId) {
+        let old = self.globals.resolve_global_alias(old);
+        if new != old {
+            self.globals.global_alias.insert(new, old);
+        }
+    }
 }
 
 // Utilities for method call
@@ -1448,6 +1900,9 @@ impl VM {
     }
 
     fn fallback_to_method(&mut self, method: IdentId, lhs: Value, rhs: Value) -> VMResult {
+        if VM::needs_coerce(method, lhs, rhs) {
+            return self.coerce_binop(method, lhs, rhs);
+        }
         match self.get_method(lhs, method) {
             Ok(mref) => {
                 let arg = Args::new1(rhs);
@@ -1468,10 +1923,70 @@ impl VM {
         method: IdentId,
         cache: u32,
     ) -> VMResult {
+        if VM::needs_coerce(method, lhs, rhs) {
+            return self.coerce_binop(method, lhs, rhs);
+        }
         let methodref = self.get_method_from_cache(cache, lhs, method)?;
         let arg = Args::new1(rhs);
         self.eval_send(methodref, lhs, &arg)
     }
+
+    /// Returns true if the arithmetic or comparison operator `method` for Integer or Float `lhs`
+    /// and other `rhs` must be evaluated with `rhs.coerce(lhs)`.
+    fn needs_coerce(method: IdentId, lhs: Value, rhs: Value) -> bool {
+        let is_num = |val: Value| match val.unpack() {
+            RV::Integer(_) | RV::Float(_) => true,
+            _ => false,
+        };
+        let ops = [
+            IdentId::_ADD,
+            IdentId::_SUB,
+            IdentId::_MUL,
+            IdentId::_DIV,
+            IdentId::_REM,
+            IdentId::_POW,
+        ];
+        is_num(lhs) && !is_num(rhs) && (ops.contains(&method) || VM::is_comparison(method))
+    }
+
+    /// Returns true if `method` is `>`, `>=`, `<` or `<=`.
+    fn is_comparison(method: IdentId) -> bool {
+        [IdentId::_GT, IdentId::_GE, IdentId::_LT, IdentId::_LE].contains(&method)
+    }
+
+    /// Evaluate the binary operator `method` for `lhs` and `rhs`,
+    /// converting them with `rhs.coerce(lhs)`.
+    pub fn coerce_binop(&mut self, method: IdentId, lhs: Value, rhs: Value) -> VMResult {
+        let id = self.globals.get_ident_id("coerce");
+        let coerce = match self.get_method(rhs, id) {
+            Ok(method) => method,
+            Err(_) => {
+                let rhs_class = match rhs.unpack() {
+                    RV::Nil | RV::Bool(_) => self.val_inspect(rhs),
+                    _ => self.globals.get_class_name(rhs),
+                };
+                let lhs_class = self.globals.get_class_name(lhs);
+                if VM::is_comparison(method) {
+                    return Err(self.error_argument(format!(
+                        "Comparison of {} with {} failed.",
+                        lhs_class, rhs_class
+                    )));
+                }
+                return Err(self.error_type(format!(
+                    "{} can't be coerced into {}",
+                    rhs_class, lhs_class
+                )));
+            }
+        };
+        let pair = self.eval_send(coerce, rhs, &Args::new1(lhs))?;
+        match pair.as_array() {
+            Some(aref) if aref.elements.len() == 2 => {
+                let (lhs, rhs) = (aref.elements[0], aref.elements[1]);
+                self.eval_binop(method, lhs, rhs)
+            }
+            _ => Err(self.error_type("coerce must return [x, y]")),
+        }
+    }
 }
 
 macro_rules! eval_op {
@@ -1488,6 +2003,16 @@ macro_rules! eval_op {
         };
         return Ok(val);
     };
+    ($vm:ident, $rhs:expr, $lhs:expr, $op:ident, $id:expr) => {
+        let val = match ($lhs.unpack(), $rhs.unpack()) {
+            (RV::Integer(lhs), RV::Integer(rhs)) => Value::fixnum(lhs.$op(rhs)),
+            (RV::Integer(lhs), RV::Float(rhs)) => Value::flonum((lhs as f64).$op(rhs)),
+            (RV::Float(lhs), RV::Integer(rhs)) => Value::flonum(lhs.$op(rhs as f64)),
+            (RV::Float(lhs), RV::Float(rhs)) => Value::flonum(lhs.$op(rhs)),
+            _ => return $vm.fallback_to_method($id, $lhs, $rhs),
+        };
+        return Ok(val);
+    };
 }
 
 impl VM {
@@ -1506,7 +2031,37 @@ impl VM {
         eval_op!(self, iseq, rhs, lhs, mul, IdentId::_MUL);
     }
 
-    fn eval_addi(&mut self, lhs: Value, i: i32) -> VMResult {
+    /// Evaluate the binary operator `method` for `lhs` and `rhs` without inline caches.
+    pub fn eval_binop(&mut self, method: IdentId, lhs: Value, rhs: Value) -> VMResult {
+        use std::ops::{Add, Mul, Sub};
+        if method == IdentId::_ADD {
+            eval_op!(self, rhs, lhs, add, method);
+        } else if method == IdentId::_SUB {
+            eval_op!(self, rhs, lhs, sub, method);
+        } else if method == IdentId::_MUL {
+            eval_op!(self, rhs, lhs, mul, method);
+        } else if method == IdentId::_DIV {
+            self.eval_div(rhs, lhs)
+        } else if method == IdentId::_REM {
+            self.eval_rem(rhs, lhs)
+        } else if method == IdentId::_POW {
+            self.eval_exp(rhs, lhs)
+        } else if method == IdentId::_GT {
+            self.eval_gt(rhs, lhs)
+        } else if method == IdentId::_GE {
+            self.eval_ge(rhs, lhs)
+        } else if method == IdentId::_LT {
+            self.eval_lt(rhs, lhs)
+        } else if method == IdentId::_LE {
+            self.eval_le(rhs, lhs)
+        } else if method == self.globals.get_ident_id("<=>") {
+            self.eval_cmp(rhs, lhs)
+        } else {
+            self.fallback_to_method(method, lhs, rhs)
+        }
+    }
+
+    pub fn eval_addi(&mut self, lhs: Value, i: i32) -> VMResult {
         use std::ops::Add;
         let val = match lhs.unpack() {
             RV::Integer(lhs) => Value::fixnum(lhs.add(i as i64)),
@@ -1527,12 +2082,14 @@ impl VM {
 
     fn eval_div(&mut self, rhs: Value, lhs: Value) -> VMResult {
         use std::ops::Div;
+        use divrem::*;
         match (lhs.unpack(), rhs.unpack()) {
-            (RV::Integer(lhs), RV::Integer(rhs)) => Ok(Value::fixnum(lhs.div(rhs))),
+            (RV::Integer(_), RV::Integer(0)) => Err(self.error_zero_div("divided by 0")),
+            (RV::Integer(lhs), RV::Integer(rhs)) => Ok(Value::fixnum(DivFloor::div_floor(lhs, rhs))),
             (RV::Integer(lhs), RV::Float(rhs)) => Ok(Value::flonum((lhs as f64).div(rhs))),
             (RV::Float(lhs), RV::Integer(rhs)) => Ok(Value::flonum(lhs.div(rhs as f64))),
             (RV::Float(lhs), RV::Float(rhs)) => Ok(Value::flonum(lhs.div(rhs))),
-            (_, _) => return Err(self.error_undefined_op("/", rhs, lhs)),
+            (_, _) => self.fallback_to_method(IdentId::_DIV, lhs, rhs),
         }
     }
 
@@ -1548,6 +2105,7 @@ impl VM {
         }
         use divrem::*;
         let val = match (lhs.unpack(), rhs.unpack()) {
+            (RV::Integer(_), RV::Integer(0)) => return Err(self.error_zero_div("divided by 0")),
             (RV::Integer(lhs), RV::Integer(rhs)) => Value::fixnum(lhs.rem_floor(rhs)),
             (RV::Integer(lhs), RV::Float(rhs)) => Value::flonum(rem_floorf64(lhs as f64, rhs)),
             (RV::Float(lhs), RV::Integer(rhs)) => Value::flonum(rem_floorf64(lhs, rhs as f64)),
@@ -1653,10 +2211,49 @@ impl VM {
 
     pub fn eval_teq(&mut self, rhs: Value, lhs: Value) -> Result<bool, RubyError> {
         match lhs.is_object() {
-            Some(oref) => match oref.kind {
+            Some(oref) => match &oref.kind {
                 ObjKind::Class(_) => {
-                    let res = rhs.get_class_object(&self.globals).id() == lhs.id();
-                    Ok(res)
+                    let mut class = rhs.get_class_object(&self.globals);
+                    loop {
+                        if class.id() == lhs.id() {
+                            return Ok(true);
+                        }
+                        class = class.as_class().superclass;
+                        if class.is_nil() {
+                            return Ok(false);
+                        }
+                    }
+                }
+                ObjKind::Range(range) => {
+                    use std::cmp::Ordering;
+                    // Integers are compared as i64 so as not to lose precision.
+                    let cmp = |lhs: Value, rhs: Value| match (lhs.unpack(), rhs.unpack()) {
+                        (RV::Integer(lhs), RV::Integer(rhs)) => lhs.partial_cmp(&rhs),
+                        (RV::Integer(lhs), RV::Float(rhs)) => (lhs as f64).partial_cmp(&rhs),
+                        (RV::Float(lhs), RV::Integer(rhs)) => lhs.partial_cmp(&(rhs as f64)),
+                        (RV::Float(lhs), RV::Float(rhs)) => lhs.partial_cmp(&rhs),
+                        _ => None,
+                    };
+                    match (cmp(range.start, rhs), cmp(rhs, range.end)) {
+                        (Some(start), Some(end)) => Ok(start != Ordering::Greater
+                            && if range.exclude {
+                                end == Ordering::Less
+                            } else {
+                                end != Ordering::Greater
+                            }),
+                        _ => Ok(false),
+                    }
+                }
+                // Call `===` if defined, e.g. Proc#=== and user-defined methods.
+                ObjKind::Ordinary | ObjKind::Proc(_) => {
+                    let id = self.globals.get_ident_id("===");
+                    match self.get_method(lhs, id) {
+                        Ok(method) => {
+                            let res = self.eval_send(method, lhs, &Args::new1(rhs))?;
+                            Ok(self.val_to_bool(res))
+                        }
+                        Err(_) => Ok(self.eval_eq(lhs, rhs).unwrap_or(false)),
+                    }
                 }
                 ObjKind::Regexp(re) => {
                     let given = match rhs.unpack() {
@@ -1677,6 +2274,91 @@ impl VM {
         }
     }
 
+    /// Implicitly convert `val` into Array for multiple assignment and auto-splat of block arguments.
+    /// Returns `val` itself if it is an Array, or the result of `val.to_ary` if `val` responds to it.
+    pub fn implicit_to_ary(&mut self, val: Value) -> Result<Option<ArrayRef>, RubyError> {
+        if let Some(info) = val.as_array() {
+            return Ok(Some(info));
+        }
+        let id = self.globals.get_ident_id("to_ary");
+        let method = match self.get_method(val, id) {
+            Ok(method) => method,
+            Err(_) => return Ok(None),
+        };
+        let ary = self.eval_send(method, val, &Args::new0())?;
+        if ary.is_nil() {
+            return Ok(None);
+        }
+        match ary.as_array() {
+            Some(info) => Ok(Some(info)),
+            None => {
+                let class = self.globals.get_class_name(val);
+                let res_class = self.globals.get_class_name(ary);
+                Err(self.error_type(format!(
+                    "can't convert {} to Array ({}#to_ary gives {})",
+                    class, class, res_class
+                )))
+            }
+        }
+    }
+
+    /// Returns the context of the method which encloses the current block,
+    /// or the current context if it is not a block.
+    fn enclosing_method_context(&self) -> ContextRef {
+        let mut context = self.context();
+        while let ISeqKind::Block(_) = context.kind {
+            match context.outer {
+                Some(outer) => context = outer,
+                None => break,
+            }
+        }
+        context
+    }
+
+    /// Returns the Array for array patterns and find patterns, which is `val` itself or
+    /// `val.deconstruct`, or nil if `val` can not be deconstructed or the length does not match.
+    /// The length must be `len` if `exact` is true, or at least `len` otherwise.
+    pub fn deconstruct(&mut self, val: Value, len: usize, exact: bool) -> VMResult {
+        let ary = if val.as_array().is_some() {
+            val
+        } else {
+            let id = self.globals.get_ident_id("deconstruct");
+            let method = match self.get_method(val, id) {
+                Ok(method) => method,
+                Err(_) => return Ok(Value::nil()),
+            };
+            let ary = self.eval_send(method, val, &Args::new0())?;
+            if ary.as_array().is_none() {
+                return Err(self.error_type("deconstruct must return Array"));
+            }
+            ary
+        };
+        let ary_len = ary.as_array().unwrap().elements.len();
+        if ary_len == len || !exact && ary_len > len {
+            Ok(ary)
+        } else {
+            Ok(Value::nil())
+        }
+    }
+
+    /// Returns the Hash for hash patterns, which is `val` itself or `val.deconstruct_keys(keys)`,
+    /// or nil if `val` can not be deconstructed.
+    pub fn deconstruct_keys(&mut self, val: Value, keys: Value) -> VMResult {
+        if val.as_hash().is_some() {
+            return Ok(val);
+        }
+        let id = self.globals.get_ident_id("deconstruct_keys");
+        let method = match self.get_method(val, id) {
+            Ok(method) => method,
+            Err(_) => return Ok(Value::nil()),
+        };
+        let hash = self.eval_send(method, val, &Args::new1(keys))?;
+        if hash.as_hash().is_none() {
+            return Err(self.error_type("deconstruct_keys must return Hash"));
+        }
+        Ok(hash)
+    }
+
     fn eval_ge(&mut self, rhs: Value, lhs: Value) -> VMResult {
         eval_cmp!(self, rhs, lhs, ge, IdentId::_GE)
     }
@@ -1685,17 +2367,25 @@ impl VM {
         eval_cmp!(self, rhs, lhs, gt, IdentId::_GT)
     }
 
+    fn eval_lt(&mut self, rhs: Value, lhs: Value) -> VMResult {
+        eval_cmp!(self, rhs, lhs, lt, IdentId::_LT)
+    }
+
+    fn eval_le(&mut self, rhs: Value, lhs: Value) -> VMResult {
+        eval_cmp!(self, rhs, lhs, le, IdentId::_LE)
+    }
+
     pub fn eval_cmp(&mut self, rhs: Value, lhs: Value) -> VMResult {
         let res = match lhs.unpack() {
-            RV::Integer(lhs) => match rhs.unpack() {
-                RV::Integer(rhs) => lhs.partial_cmp(&rhs),
-                RV::Float(rhs) => (lhs as f64).partial_cmp(&rhs),
-                _ => return Ok(Value::nil()),
+            RV::Integer(l) => match rhs.unpack() {
+                RV::Integer(rhs) => l.partial_cmp(&rhs),
+                RV::Float(rhs) => (l as f64).partial_cmp(&rhs),
+                _ => return self.coerce_cmp(lhs, rhs),
             },
-            RV::Float(lhs) => match rhs.unpack() {
-                RV::Integer(rhs) => lhs.partial_cmp(&(rhs as f64)),
-                RV::Float(rhs) => lhs.partial_cmp(&rhs),
-                _ => return Ok(Value::nil()),
+            RV::Float(l) => match rhs.unpack() {
+                RV::Integer(rhs) => l.partial_cmp(&(rhs as f64)),
+                RV::Float(rhs) => l.partial_cmp(&rhs),
+                _ => return self.coerce_cmp(lhs, rhs),
             },
             _ => {
                 let id = self.globals.get_ident_id("<=>");
@@ -1708,6 +2398,16 @@ impl VM {
         }
     }
 
+    /// `<=>` for Integer or Float `lhs` and other `rhs`, which is nil if `rhs` can not be coerced.
+    fn coerce_cmp(&mut self, lhs: Value, rhs: Value) -> VMResult {
+        let id = self.globals.get_ident_id("coerce");
+        if self.get_method(rhs, id).is_err() {
+            return Ok(Value::nil());
+        }
+        let id = self.globals.get_ident_id("<=>");
+        self.coerce_binop(id, lhs, rhs)
+    }
+
     pub fn sort_array(&mut self, mut aref: ArrayRef) -> Result<(), RubyError> {
         if aref.elements.len() > 0 {
             let val = aref.elements[0];
@@ -1765,13 +2465,7 @@ impl VM {
                 false => "false".to_string(),
             },
             RV::Integer(i) => i.to_string(),
-            RV::Float(f) => {
-                if f.fract() == 0.0 {
-                    format!("{:.1}", f)
-                } else {
-                    f.to_string()
-                }
-            }
+            RV::Float(f) => crate::builtin::float::float_to_s(f),
             RV::Symbol(i) => format!("{}", self.globals.get_ident_name(i)),
             RV::Object(oref) => match &oref.kind {
                 ObjKind::String(s) => s.to_s(),
@@ -1784,7 +2478,13 @@ impl VM {
                 ObjKind::Range(rinfo) => rinfo.to_s(self),
                 ObjKind::Regexp(rref) => format!("({})", rref.regexp.as_str().to_string()),
                 ObjKind::Hash(href) => href.to_s(self),
-                _ => format!("{:?}", oref.kind),
+                _ => {
+                    let id = self.globals.get_ident_id("to_s");
+                    match self.send0(val, id) {
+                        Ok(s) if s.as_string().is_some() => s.as_string().unwrap().to_string(),
+                        _ => format!("{:?}", oref.kind),
+                    }
+                }
             },
         }
     }
@@ -1798,13 +2498,7 @@ impl VM {
                 false => "false".to_string(),
             },
             RV::Integer(i) => i.to_string(),
-            RV::Float(f) => {
-                if f.fract() == 0.0 {
-                    format!("{:.1}", f)
-                } else {
-                    f.to_string()
-                }
-            }
+            RV::Float(f) => crate::builtin::float::float_to_s(f),
             RV::Symbol(sym) => format!(":{}", self.globals.get_ident_name(sym)),
             RV::Object(oref) => match &oref.kind {
                 ObjKind::String(s) => s.inspect(),
@@ -1819,7 +2513,13 @@ impl VM {
                 },
                 ObjKind::Array(aref) => aref.to_s(self),
                 ObjKind::Regexp(rref) => format!("/{}/", rref.regexp.as_str().to_string()),
-                ObjKind::Ordinary => oref.inspect(self),
+                ObjKind::Ordinary => {
+                    let id = self.globals.get_ident_id("inspect");
+                    match self.send0(val, id) {
+                        Ok(s) if s.as_string().is_some() => s.as_string().unwrap().to_string(),
+                        _ => oref.inspect(self),
+                    }
+                }
                 ObjKind::Proc(pref) => format!("#<Proc:0x{:x}>", pref.id()),
                 ObjKind::Hash(href) => href.to_s(self),
                 _ => {
@@ -1843,17 +2543,115 @@ impl VM {
 }
 
 impl VM {
-    fn vm_send(&mut self, iseq: &ISeq, receiver: Value) -> VMResult {
+    /// Send the method for SEND or SEND_SELF (`inst`) at pc.
+    fn vm_send(&mut self, iseq: &ISeq, receiver: Value, inst: u8) -> VMResult {
         let method_id = self.read_id(iseq, 1);
-        let args_num = self.read16(iseq, 5);
-        let flag = self.read16(iseq, 7);
         let cache_slot = self.read32(iseq, 9);
-        let block = self.read32(iseq, 13);
         let methodref = self.get_method_from_cache(cache_slot, receiver, method_id)?;
+        self.quicken_send(iseq, inst, methodref);
+        let args = self.pop_send_args(iseq)?;
+        self.eval_send(methodref, receiver, &args)
+    }
 
+    /// Send the builtin method in the inline cache for SEND_BUILTIN or SEND_SELF_BUILTIN,
+    /// or fall back to the generic `inst` if the cache missed.
+    fn vm_send_builtin(&mut self, iseq: &ISeq, receiver: Value, inst: u8) -> VMResult {
+        let cache_slot = self.read32(iseq, 9);
+        let rec_class = receiver.get_class_object_for_method(&self.globals);
+        if let Some(method) = self
+            .globals
+            .get_method_from_inline_cache(cache_slot, rec_class)
+        {
+            if let MethodInfo::BuiltinFunc { func, .. } = self.globals.get_method_info(method) {
+                let func = *func;
+                let args = self.pop_send_args(iseq)?;
+                return func(self, receiver, &args);
+            }
+        }
+        self.vm_send(iseq, receiver, inst)
+    }
+
+    /// Invoke the Ruby method in the inline cache for SEND_RUBY or SEND_SELF_RUBY,
+    /// setting the arguments on the stack directly to the local variables,
+    /// or fall back to the generic `inst` if the cache missed.
+    fn vm_send_ruby(&mut self, iseq: &ISeq, receiver: Value, inst: u8) -> VMResult {
+        let cache_slot = self.read32(iseq, 9);
+        let rec_class = receiver.get_class_object_for_method(&self.globals);
+        if let Some(method) = self
+            .globals
+            .get_method_from_inline_cache(cache_slot, rec_class)
+        {
+            let method_iseq = match self.globals.get_method_info(method) {
+                MethodInfo::RubyFunc { iseq } => Some(*iseq),
+                _ => None,
+            };
+            // A polymorphic cache may hold other methods than the one checked at quickening.
+            if let Some(method_iseq) = method_iseq.filter(|m| self.is_simple_call(iseq, *m)) {
+                let args_num = self.read16(iseq, 5) as usize;
+                let block = match self.read32(iseq, 13) {
+                    0 => None,
+                    block => Some(MethodRef::from(block)),
+                };
+                let mut context = Context::new(receiver, block, method_iseq, None);
+                // Arguments are pushed in reverse order, so the first one is on the top.
+                let len = self.exec_stack.len();
+                for i in 0..args_num {
+                    context[i] = self.exec_stack[len - 1 - i];
+                }
+                self.exec_stack.truncate(len - args_num);
+                return self.run_context(ContextRef::from_local(&context));
+            }
+        }
+        self.vm_send(iseq, receiver, inst)
+    }
+
+    /// Read the instance variable for SEND_ATTR_READER, or fall back to SEND if the cache missed.
+    fn vm_send_attr_reader(&mut self, iseq: &ISeq, receiver: Value) -> VMResult {
+        let cache_slot = self.read32(iseq, 9);
+        let rec_class = receiver.get_class_object_for_method(&self.globals);
+        if let Some(method) = self
+            .globals
+            .get_method_from_inline_cache(cache_slot, rec_class)
+        {
+            if let (MethodInfo::AttrReader { id }, Some(oref)) =
+                (self.globals.get_method_info(method), receiver.is_object())
+            {
+                return Ok(oref.get_var(*id).unwrap_or_default());
+            }
+        }
+        self.vm_send(iseq, receiver, Inst::SEND)
+    }
+
+    /// Send the method for SEND, SEND_SELF or any of their quickened instructions at pc.
+    pub fn send_inst(&mut self, iseq: &ISeq) -> VMResult {
+        let inst = iseq[self.pc];
+        let receiver = match Inst::generic(inst) {
+            Inst::SEND => self.stack_pop(),
+            _ => self.context().self_value,
+        };
+        match inst {
+            Inst::SEND_BUILTIN => self.vm_send_builtin(iseq, receiver, Inst::SEND),
+            Inst::SEND_SELF_BUILTIN => self.vm_send_builtin(iseq, receiver, Inst::SEND_SELF),
+            Inst::SEND_ATTR_READER => self.vm_send_attr_reader(iseq, receiver),
+            Inst::SEND_RUBY => self.vm_send_ruby(iseq, receiver, Inst::SEND),
+            Inst::SEND_SELF_RUBY => self.vm_send_ruby(iseq, receiver, Inst::SEND_SELF),
+            inst => self.vm_send(iseq, receiver, inst),
+        }
+    }
+
+    /// Pop arguments, the keyword arguments and the block argument of the SEND at pc.
+    fn pop_send_args(&mut self, iseq: &ISeq) -> Result<Args, RubyError> {
+        let args_num = self.read16(iseq, 5);
+        let flag = self.read16(iseq, 7);
+        let block = self.read32(iseq, 13);
         let keyword = if flag & 0b01 == 1 {
             let val = self.stack_pop();
-            Some(val)
+            // `**{}` passes no keyword arguments.
+            if val.as_hash().unwrap().len() == 0 {
+                None
+            } else {
+                Some(val)
+            }
         } else {
             None
         };
@@ -1874,8 +2672,172 @@ impl VM {
         };
         args.block = block;
         args.kw_arg = keyword;
-        let val = self.eval_send(methodref, receiver, &args)?;
-        Ok(val)
+        Ok(args)
+    }
+}
+
+// Quickening: rewriting instructions in place to the specialized ones after the first execution.
+
+impl VM {
+    /// Quickening is disabled while coverage, the profiler or the debugger is running,
+    /// since they observe each instruction or method call.
+    fn quickening_enabled(&self) -> bool {
+        self.globals.coverage.is_none() && self.profiler.is_none() && self.debugger.is_none()
+    }
+
+    /// Rewrite the instruction at pc of the current ISeq to `inst`.
+    fn quicken(&self, inst: u8) {
+        let mut iseq_ref = self.context().iseq_ref;
+        iseq_ref.iseq[self.pc] = inst;
+    }
+
+    /// Fuse the comparison of integers at pc and the following JMP_IF_FALSE into `inst`.
+    fn quicken_cmp(&self, iseq: &ISeq, inst: u8, lhs: Value, rhs: Value) {
+        if iseq[self.pc + 1] == Inst::JMP_IF_FALSE
+            && lhs.is_packed_fixnum()
+            && rhs.is_packed_fixnum()
+            && self.quickening_enabled()
+        {
+            self.quicken(inst);
+        }
+    }
+
+    /// Fuse GET_LOCAL at pc, the following GET_LOCAL and ADD or SUB.
+    fn quicken_get_local(&self, iseq: &ISeq) {
+        if iseq[self.pc + 9] != Inst::GET_LOCAL {
+            return;
+        }
+        let inst = match iseq[self.pc + 18] {
+            Inst::ADD => Inst::ADD_LOCALS,
+            Inst::SUB => Inst::SUB_LOCALS,
+            _ => return,
+        };
+        if self.quickening_enabled() {
+            self.quicken(inst);
+        }
+    }
+
+    /// Specialize SEND or SEND_SELF (`inst`) at pc for the method found in the inline cache,
+    /// or restore it to `inst` if the method is not a builtin or an attr_reader.
+    fn quicken_send(&self, iseq: &ISeq, inst: u8, method: MethodRef) {
+        if !self.quickening_enabled() {
+            return;
+        }
+        let no_args = self.read16(iseq, 5) == 0 && self.read16(iseq, 7) == 0;
+        let new_inst = match (inst, self.globals.get_method_info(method)) {
+            (Inst::SEND, MethodInfo::BuiltinFunc { .. }) => Inst::SEND_BUILTIN,
+            (Inst::SEND_SELF, MethodInfo::BuiltinFunc { .. }) => Inst::SEND_SELF_BUILTIN,
+            (Inst::SEND, MethodInfo::AttrReader { .. })
+                if no_args && self.read32(iseq, 13) == 0 =>
+            {
+                Inst::SEND_ATTR_READER
+            }
+            (Inst::SEND, MethodInfo::RubyFunc { iseq: method_iseq })
+                if self.is_simple_call(iseq, *method_iseq) =>
+            {
+                Inst::SEND_RUBY
+            }
+            (Inst::SEND_SELF, MethodInfo::RubyFunc { iseq: method_iseq })
+                if self.is_simple_call(iseq, *method_iseq) =>
+            {
+                Inst::SEND_SELF_RUBY
+            }
+            _ => inst,
+        };
+        if iseq[self.pc] != new_inst {
+            self.quicken(new_inst);
+        }
+    }
+
+    /// Check whether the send at pc passes only positional arguments without splat,
+    /// exactly as many as the required parameters of `method_iseq` which has no other parameters.
+    /// The stack must hold the arguments of the send.
+    fn is_simple_call(&self, iseq: &ISeq, method_iseq: ISeqRef) -> bool {
+        let params = &method_iseq.params;
+        let args_num = self.read16(iseq, 5) as usize;
+        match method_iseq.kind {
+            ISeqKind::Method(_) => {}
+            _ => return false,
+        }
+        self.read16(iseq, 7) == 0
+            && args_num == params.req_params
+            && params.opt_params == 0
+            && !params.rest_param
+            && params.post_params == 0
+            && !params.block_param
+            && !params.accepts_keywords()
+            && self.exec_stack[self.exec_stack.len() - args_num..]
+                .iter()
+                .all(|arg| arg.as_splat().is_none())
+    }
+
+    fn get_local(&mut self, iseq: &ISeq, offset: usize) -> Value {
+        let id = self.read_lvar_id(iseq, offset);
+        let outer = self.read32(iseq, offset + 4);
+        self.get_outer_context(outer)[id]
+    }
+
+    /// Branch by `cond` as the JMP_IF_FALSE following the fused instruction at pc.
+    fn fused_jmp_if_false(&mut self, iseq: &ISeq, context: ContextRef, cond: bool) {
+        self.pc += 1;
+        if let Some(coverage) = &mut self.globals.coverage {
+            coverage.trace_branch(context.iseq_ref, self.pc, cond);
+        }
+        if cond {
+            self.jump_pc(5, 0);
+        } else {
+            let disp = self.read_disp(iseq, 1);
+            self.jump_pc(5, disp);
+        }
+    }
+}
+
+// Instance variable access with inline caches.
+
+impl VM {
+    /// Get the instance variable `id` of `oref`, using the inline cache at `cache_slot`.
+    pub fn get_ivar(&mut self, oref: ObjectRef, id: IdentId, cache_slot: u32) -> Value {
+        let shape = match oref.shape() {
+            Some(shape) => shape,
+            None => return Value::nil(),
+        };
+        match self.globals.get_ivar_cache_entry(cache_slot, shape) {
+            // An entry for adding a new instance variable can not be used for reading.
+            Some(entry) if entry.new_shape == shape => oref.get_slot(entry.index),
+            _ => match shape.get_index(id) {
+                Some(index) => {
+                    self.globals
+                        .set_ivar_cache_entry(cache_slot, shape, shape, index);
+                    oref.get_slot(index)
+                }
+                None => Value::nil(),
+            },
+        }
+    }
+
+    /// Set `val` to the instance variable `id` of `oref`, using the inline cache at `cache_slot`.
+    pub fn set_ivar(&mut self, mut oref: ObjectRef, id: IdentId, val: Value, cache_slot: u32) {
+        let shape = match oref.shape() {
+            Some(shape) => shape,
+            None => return oref.set_var(id, val),
+        };
+        match self.globals.get_ivar_cache_entry(cache_slot, shape) {
+            Some(entry) if entry.new_shape == shape => oref.set_slot(entry.index, val),
+            Some(entry) => oref.add_var(entry.new_shape, val),
+            None => match shape.get_index(id) {
+                Some(index) => {
+                    self.globals
+                        .set_ivar_cache_entry(cache_slot, shape, shape, index);
+                    oref.set_slot(index, val);
+                }
+                None => {
+                    let new_shape = shape.transition(id);
+                    self.globals
+                        .set_ivar_cache_entry(cache_slot, shape, new_shape, shape.len());
+                    oref.add_var(new_shape, val);
+                }
+            },
+        }
     }
 }
 
@@ -1907,12 +2869,12 @@ impl VM {
         let method = context
             .block
             .ok_or_else(|| self.error_unimplemented("No block given."))?;
-        let res = self.eval_method(
-            method,
-            self.context().self_value,
-            Some(self.caller_context()),
-            &args,
-        )?;
+        // The outer of the block is the caller of the method, even if `yield` is in a nested block.
+        let outer = match self.exec_context.iter().rposition(|c| *c == context) {
+            Some(pos) if pos > 0 => self.exec_context[pos - 1],
+            _ => self.caller_context(),
+        };
+        let res = self.eval_method(method, self.context().self_value, Some(outer), &args)?;
         Ok(res)
     }
 
@@ -1935,6 +2897,29 @@ impl VM {
             };
             return Ok(res);
         };
+        let depth = self.exec_context.len();
+        let builtin = match &mut self.profiler {
+            Some(profiler) => profiler.enter_builtin(&self.globals, methodref, self_val, depth),
+            None => false,
+        };
+        let res = self.invoke_method(methodref, self_val, outer, args);
+        if builtin {
+            // Take a sample before the frame is popped, so that time spent in
+            // the builtin is attributed to it.
+            let profiler = self.profiler.as_mut().unwrap();
+            profiler.sample(&self.globals, &self.exec_context);
+            profiler.exit_builtin();
+        }
+        res
+    }
+
+    fn invoke_method(
+        &mut self,
+        methodref: MethodRef,
+        self_val: Value,
+        outer: Option<ContextRef>,
+        args: &Args,
+    ) -> VMResult {
         let info = self.globals.get_method_info(methodref);
         #[allow(unused_variables, unused_mut)]
         let mut inst: u8;
@@ -2023,10 +3008,10 @@ impl VM {
         id: IdentId,
         info: MethodRef,
     ) -> Result<(), RubyError> {
-        self.globals.class_version += 1;
         let singleton = self.get_singleton_class(obj)?;
         let mut singleton_class = singleton.as_class();
         singleton_class.method_table.insert(id, info);
+        singleton_class.invalidate_method_cache();
         Ok(())
     }
 
@@ -2036,14 +3021,50 @@ impl VM {
         id: IdentId,
         info: MethodRef,
     ) -> Option<MethodRef> {
-        self.globals.class_version += 1;
-        class_obj.as_module().unwrap().method_table.insert(id, info)
+        let mut cref = class_obj.as_module().unwrap();
+        let prev = cref.method_table.insert(id, info);
+        cref.invalidate_method_cache();
+        prev
     }
 
     pub fn add_object_method(&mut self, id: IdentId, info: MethodRef) {
         self.add_instance_method(self.globals.builtins.object, id, info);
     }
 
+    /// Make `new` an alias of the instance method `old` of `class`.
+    pub fn alias_method(
+        &mut self,
+        class: Value,
+        new: IdentId,
+        old: IdentId,
+    ) -> Result<(), RubyError> {
+        let method = match self.get_instance_method(class, old) {
+            Ok(method) => method,
+            Err(_) => return Err(self.error_undefined_method_for_class(class, old)),
+        };
+        self.add_instance_method(class, new, method);
+        Ok(())
+    }
+
+    /// Prevent `class` from responding to the method `id`.
+    /// The method is searched no further, even if a superclass defines it.
+    pub fn undef_method(&mut self, class: Value, id: IdentId) -> Result<(), RubyError> {
+        if self.get_instance_method(class, id).is_err() {
+            return Err(self.error_undefined_method_for_class(class, id));
+        }
+        self.add_instance_method(class, id, MethodRef::from(0));
+        Ok(())
+    }
+
+    fn error_undefined_method_for_class(&mut self, class: Value, id: IdentId) -> RubyError {
+        let class_name = self.val_inspect(class);
+        let method_name = self.globals.get_ident_name(id);
+        self.error_name(format!(
+            "undefined method `{}' for class `{}'",
+            method_name, class_name
+        ))
+    }
+
     /// Get method(MethodRef) for receiver.
     pub fn get_method(
         &mut self,
@@ -2062,8 +3083,8 @@ impl VM {
         method: IdentId,
     ) -> Result<MethodRef, RubyError> {
         match self.globals.get_method_cache_entry(class, method) {
-            Some(MethodCacheEntry { version, method }) => {
-                if *version == self.globals.class_version {
+            Some(MethodCacheEntry { serial, method }) => {
+                if *serial == class.as_module().unwrap().serial {
                     return Ok(*method);
                 }
             }
@@ -2072,7 +3093,18 @@ impl VM {
         let original_class = class;
         let mut singleton_flag = original_class.as_class().is_singleton;
         loop {
+            // The result depends on the classes and modules searched so far.
+            class.as_module().unwrap().add_dependent(original_class);
             match class.get_instance_method(method) {
+                // The method is undefined by `undef`.
+                Some(methodref) if methodref.is_none() => {
+                    let inspect = self.val_inspect(original_class);
+                    let method_name = self.globals.get_ident_name(method);
+                    return Err(self.error_nomethod(format!(
+                        "undefined method `{}' for {}",
+                        method_name, inspect
+                    )));
+                }
                 Some(methodref) => {
                     self.globals
                         .add_method_cache_entry(original_class, method, methodref);
@@ -2110,8 +3142,95 @@ impl VM {
         self.context_pop().unwrap();
         if let Some(context) = self.exec_context.last_mut() {
             self.pc = context.pc;
-            err.info.push((self.source_info(), self.get_loc()));
+            err.info.push((self.source_info(), self.get_loc(), String::new()));
+        };
+    }
+
+    /// Returns the entry of the exception table which handles `err` raised at the current pc.
+    fn find_rescue(&self, context: ContextRef, err: &RubyError) -> Option<(usize, ExceptionEntry)> {
+        match err.kind {
+            RubyErrorKind::ParseErr(_) | RubyErrorKind::RuntimeErr(_) => {
+                context.iseq_ref.exception_entry(self.pc)
+            }
+            RubyErrorKind::MethodReturn(_) | RubyErrorKind::BlockReturn(_) => None,
+        }
+    }
+
+    /// Returns true if `exception` is an instance of one of `classes`,
+    /// or of StandardError if `classes` is empty.
+    fn rescue_match(&self, exception: Value, classes: &[Value]) -> Result<bool, RubyError> {
+        let classes = if classes.is_empty() {
+            vec![self.globals.builtins.standard_error]
+        } else {
+            classes.to_vec()
+        };
+        for class in classes {
+            if class.as_module().is_none() {
+                return Err(self.error_type("class or module required for rescue clause"));
+            }
+            let mut exception_class = exception.get_class_object(&self.globals);
+            while !exception_class.is_nil() {
+                if exception_class.id() == class.id() {
+                    return Ok(true);
+                }
+                exception_class = exception_class.as_class().superclass;
+            }
+        }
+        Ok(false)
+    }
+
+    /// Returns the exception object of `err`.
+    /// Its backtrace is set from the frames `err` has unwound through and the current call stack.
+    pub fn exception_from_error(&mut self, err: &RubyError) -> Value {
+        let exception = match &err.kind {
+            RubyErrorKind::RuntimeErr(RuntimeErrKind::Exception(exception)) => *exception,
+            _ => {
+                let (class_name, msg) = match err.exception_class() {
+                    Some(class) => class,
+                    None => ("LocalJumpError", "unexpected return".to_string()),
+                };
+                let mut class = self.globals.builtins.object;
+                for name in class_name.split("::") {
+                    let id = self.globals.get_ident_id(name);
+                    class = class.get_var(id).unwrap();
+                }
+                errorobj::new_exception(&mut self.globals, class, &msg)
+            }
         };
+        if errorobj::get_backtrace(&mut self.globals, exception).is_none() {
+            let mut frames: Vec<(String, usize, String)> = err
+                .info
+                .iter()
+                .map(|(info, loc, label)| (info.display_path(), info.get_line(loc), label.clone()))
+                .collect();
+            // The current frame is the last of `err.info`.
+            frames.extend(self.stack_frames().into_iter().skip(1));
+            let locations = frames
+                .into_iter()
+                .map(|(path, line, label)| errorobj::new_location(&mut self.globals, path, line, label))
+                .collect();
+            let backtrace = Value::array_from(&self.globals, locations);
+            errorobj::set_backtrace(&mut self.globals, exception, backtrace);
+        }
+        exception
+    }
+
+    /// Returns (path, line, label) of each frame of the Ruby-level call stack, innermost first.
+    pub fn stack_frames(&self) -> Vec<(String, usize, String)> {
+        self.exec_context
+            .iter()
+            .rev()
+            .enumerate()
+            .map(|(i, context)| {
+                let pc = if i == 0 { self.pc } else { context.pc };
+                let iseq = context.iseq_ref;
+                (
+                    iseq.source_info.display_path(),
+                    iseq.get_line(pc),
+                    iseq.label(&self.globals),
+                )
+            })
+            .collect()
     }
 
     pub fn fiber_send_to_parent(&mut self, val: VMResult) {
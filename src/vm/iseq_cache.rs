@@ -0,0 +1,698 @@
+use super::vm_inst::Inst;
+use crate::*;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Binary cache of compiled ISeqs (.rbc file).
+///
+/// A cache holds the top-level ISeq of a source file and all methods, blocks and class
+/// bodies defined in it. Operands which depend on the state of the VM (identifiers,
+/// method references, inline cache slots and case dispatch maps) are rewritten to
+/// indexes local to the file, and resolved again when the cache is loaded.
+///
+/// ```text
+/// header:  magic "RRBC", format version: u32, ruruby version: str, endian: u8,
+///          payload length: u64, checksum of the payload: u64
+/// payload: source path: str, source code: str, symbols: [str],
+///          case dispatch maps: [[(i64, i32)]], ISeqs: [ISeq]
+/// ```
+///
+/// Integers in the file are little endian, except for the operands in instruction
+/// sequences, which are stored as is.
+pub struct ISeqCache;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ISeqCacheError {
+    /// Not a cache file.
+    Magic,
+    /// Written by another version of ruruby, or on another platform.
+    Version,
+    /// The checksum of the payload does not match.
+    Checksum,
+    /// The payload is broken.
+    Corrupt,
+}
+
+impl std::fmt::Display for ISeqCacheError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let msg = match self {
+            ISeqCacheError::Magic => "not a compiled ISeq file",
+            ISeqCacheError::Version => "incompatible version",
+            ISeqCacheError::Checksum => "checksum mismatch",
+            ISeqCacheError::Corrupt => "broken ISeq",
+        };
+        write!(f, "{}", msg)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operand {
+    Ident,
+    /// MethodRef of a method, block or class body. 0 means no block.
+    Method,
+    InlineCache,
+    IvarCache,
+    CaseMap,
+}
+
+const MAGIC: &[u8; 4] = b"RRBC";
+const FORMAT_VERSION: u32 = 9;
+
+impl ISeqCache {
+    /// Returns the path of the cache file for the source file at `path`.
+    pub fn cache_path(path: &std::path::Path) -> PathBuf {
+        path.with_extension("rbc")
+    }
+
+    /// Serialize the ISeq of `method` and all ISeqs defined in it.
+    pub fn dump(globals: &Globals, method: MethodRef) -> Vec<u8> {
+        let mut writer = Writer::new(globals);
+        writer.method_index(method);
+        let mut i = 0;
+        while i < writer.methods.len() {
+            let iseq = ISeqCache::get_iseq(globals, writer.methods[i]);
+            writer.iseq(iseq);
+            i += 1;
+        }
+        let root = ISeqCache::get_iseq(globals, method);
+        let mut payload = Writer::new(globals);
+        payload.str(&root.source_info.path.to_string_lossy());
+        payload.str(&root.source_info.code.iter().collect::<String>());
+        payload.u32(writer.symbols.len() as u32);
+        for id in &writer.symbols {
+            payload.str(globals.get_ident_name(*id));
+        }
+        payload.u32(writer.case_maps.len() as u32);
+        for map in &writer.case_maps {
+            payload.u32(map.len() as u32);
+            for (k, disp) in map {
+                payload.u64(*k as u64);
+                payload.u32(*disp as u32);
+            }
+        }
+        payload.u32(writer.methods.len() as u32);
+        payload.buf.extend_from_slice(&writer.buf);
+
+        let mut header = Writer::new(globals);
+        header.buf.extend_from_slice(MAGIC);
+        header.u32(FORMAT_VERSION);
+        header.str(env!("CARGO_PKG_VERSION"));
+        header.u8(cfg!(target_endian = "big") as u8);
+        header.u64(payload.buf.len() as u64);
+        header.u64(checksum(&payload.buf));
+        header.buf.extend_from_slice(&payload.buf);
+        header.buf
+    }
+
+    /// Deserialize ISeqs and register them to `globals`, returning the top-level ISeq.
+    /// If `path` is given, it replaces the path of the source file recorded in the cache.
+    pub fn load(
+        globals: &mut Globals,
+        bytes: &[u8],
+        path: Option<PathBuf>,
+    ) -> Result<MethodRef, ISeqCacheError> {
+        let mut reader = Reader::new(bytes);
+        if reader.bytes(4).map_err(|_| ISeqCacheError::Magic)? != MAGIC {
+            return Err(ISeqCacheError::Magic);
+        }
+        if reader.u32()? != FORMAT_VERSION
+            || reader.str()? != env!("CARGO_PKG_VERSION")
+            || reader.u8()? != cfg!(target_endian = "big") as u8
+        {
+            return Err(ISeqCacheError::Version);
+        }
+        let len = reader.u64()? as usize;
+        let sum = reader.u64()?;
+        let payload = reader.bytes(len)?;
+        if checksum(payload) != sum {
+            return Err(ISeqCacheError::Checksum);
+        }
+
+        let mut reader = Reader::new(payload);
+        let stored_path = PathBuf::from(reader.str()?);
+        let source_info = SourceInfoRef::new(SourceInfo {
+            path: path.unwrap_or(stored_path),
+            given_path: None,
+            code: reader.str()?.chars().collect(),
+        });
+        let mut symbols = vec![];
+        for _ in 0..reader.u32()? {
+            let name = reader.str()?;
+            symbols.push(globals.get_ident_id(name));
+        }
+        let mut case_maps = vec![];
+        for _ in 0..reader.u32()? {
+            let map_id = globals.new_case_dispatch_map();
+            for _ in 0..reader.u32()? {
+                let k = reader.u64()? as i64;
+                let disp = reader.u32()? as i32;
+                globals
+                    .get_mut_case_dispatch_map(map_id)
+                    .insert(Value::fixnum(k), disp);
+            }
+            case_maps.push(map_id);
+        }
+        let len = reader.u32()? as usize;
+        if len == 0 {
+            return Err(ISeqCacheError::Corrupt);
+        }
+        // Allocate all MethodRefs first, because ISeqs refer to each other.
+        let methods: Vec<MethodRef> = (0..len).map(|_| globals.new_method()).collect();
+        let mut loader = Loader {
+            reader,
+            symbols,
+            methods,
+            case_maps,
+        };
+        for i in 0..len {
+            let iseq = loader.iseq(globals, i, source_info)?;
+            globals.set_method(loader.methods[i], MethodInfo::RubyFunc { iseq });
+        }
+        Ok(loader.methods[0])
+    }
+
+    fn get_iseq(globals: &Globals, method: MethodRef) -> ISeqRef {
+        match globals.get_method_info(method) {
+            MethodInfo::RubyFunc { iseq } => *iseq,
+            _ => unreachable!("ISeqs can only refer to Ruby methods."),
+        }
+    }
+
+    /// Returns operands of `inst` which must be resolved by the VM, with their offsets.
+    fn operands(inst: u8) -> &'static [(usize, Operand)] {
+        match inst {
+            Inst::PUSH_STRING
+            | Inst::PUSH_SYMBOL
+            | Inst::GET_CONST
+            | Inst::SET_CONST
+            | Inst::GET_CONST_TOP
+            | Inst::GET_SCOPE
+            | Inst::GET_GVAR
+            | Inst::SET_GVAR
+            | Inst::UNDEF
+            | Inst::DEFINED => &[(1, Operand::Ident)],
+            Inst::ALIAS => &[(1, Operand::Ident), (5, Operand::Ident)],
+            Inst::GET_IVAR | Inst::SET_IVAR => &[(1, Operand::Ident), (5, Operand::IvarCache)],
+            Inst::IVAR_ADDI => &[(1, Operand::Ident), (9, Operand::IvarCache)],
+            Inst::ADD | Inst::SUB | Inst::MUL | Inst::SHL => &[(1, Operand::InlineCache)],
+            Inst::SEND | Inst::SEND_SELF => &[
+                (1, Operand::Ident),
+                (9, Operand::InlineCache),
+                (13, Operand::Method),
+            ],
+            Inst::CREATE_PROC => &[(1, Operand::Method)],
+            Inst::DEF_METHOD | Inst::DEF_SMETHOD => &[(1, Operand::Ident), (5, Operand::Method)],
+            Inst::DEF_CLASS => &[(2, Operand::Ident), (6, Operand::Method)],
+            Inst::OPT_CASE => &[(1, Operand::CaseMap)],
+            _ => &[],
+        }
+    }
+}
+
+/// FNV-1a hash.
+fn checksum(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+        (hash ^ *b as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+fn read32(iseq: &[u8], pc: usize) -> u32 {
+    let mut bytes = [0u8; 4];
+    bytes.copy_from_slice(&iseq[pc..pc + 4]);
+    u32::from_ne_bytes(bytes)
+}
+
+fn write32(iseq: &mut [u8], pc: usize, val: u32) {
+    iseq[pc..pc + 4].copy_from_slice(&val.to_ne_bytes());
+}
+
+struct Writer<'a> {
+    globals: &'a Globals,
+    buf: Vec<u8>,
+    symbols: Vec<IdentId>,
+    symbol_map: HashMap<IdentId, u32>,
+    methods: Vec<MethodRef>,
+    method_map: HashMap<MethodRef, u32>,
+    case_maps: Vec<Vec<(i64, i32)>>,
+}
+
+impl<'a> Writer<'a> {
+    fn new(globals: &'a Globals) -> Self {
+        Writer {
+            globals,
+            buf: vec![],
+            symbols: vec![],
+            symbol_map: HashMap::new(),
+            methods: vec![],
+            method_map: HashMap::new(),
+            case_maps: vec![],
+        }
+    }
+
+    fn u8(&mut self, val: u8) {
+        self.buf.push(val);
+    }
+
+    fn u32(&mut self, val: u32) {
+        self.buf.extend_from_slice(&val.to_le_bytes());
+    }
+
+    fn u64(&mut self, val: u64) {
+        self.buf.extend_from_slice(&val.to_le_bytes());
+    }
+
+    fn str(&mut self, s: &str) {
+        self.u32(s.len() as u32);
+        self.buf.extend_from_slice(s.as_bytes());
+    }
+
+    fn symbol(&mut self, id: IdentId) -> u32 {
+        match self.symbol_map.get(&id) {
+            Some(i) => *i,
+            None => {
+                let i = self.symbols.len() as u32;
+                self.symbols.push(id);
+                self.symbol_map.insert(id, i);
+                i
+            }
+        }
+    }
+
+    /// Returns the index of `method` in the file. ISeqs are written in order of the index.
+    fn method_index(&mut self, method: MethodRef) -> u32 {
+        match self.method_map.get(&method) {
+            Some(i) => *i,
+            None => {
+                let i = self.methods.len() as u32;
+                self.methods.push(method);
+                self.method_map.insert(method, i);
+                i
+            }
+        }
+    }
+
+    fn lvar(&mut self, id: LvarId) {
+        self.u32(id.as_u32());
+    }
+
+    fn iseq(&mut self, info: ISeqRef) {
+        let params = &info.params;
+        self.u32(params.req_params as u32);
+        self.u32(params.opt_params as u32);
+        self.u8(params.rest_param as u8);
+        self.u32(params.post_params as u32);
+        self.u8(params.block_param as u8);
+        self.u32(params.param_ident.len() as u32);
+        for id in &params.param_ident {
+            let sym = self.symbol(*id);
+            self.u32(sym);
+        }
+        self.u32(params.keyword_params.len() as u32);
+        for (id, lvar) in &params.keyword_params {
+            let sym = self.symbol(*id);
+            self.u32(sym);
+            self.lvar(*lvar);
+        }
+        self.u32(params.req_keyword_params.len() as u32);
+        for id in &params.req_keyword_params {
+            let sym = self.symbol(*id);
+            self.u32(sym);
+        }
+        match params.kwrest_param {
+            Some(lvar) => {
+                self.u8(1);
+                self.lvar(lvar);
+            }
+            None => self.u8(0),
+        }
+        self.u8(params.no_kw_param as u8);
+
+        self.u32(info.lvar.len() as u32);
+        for (id, lvar) in info.lvar.table() {
+            let sym = self.symbol(*id);
+            self.u32(sym);
+            self.lvar(*lvar);
+        }
+        match info.lvar.block() {
+            Some(lvar) => {
+                self.u8(1);
+                self.lvar(*lvar);
+            }
+            None => self.u8(0),
+        }
+
+        let mut iseq = info.iseq.clone();
+        let mut pc = 0;
+        while pc < iseq.len() {
+            // Quickened instructions are stored as the original ones.
+            iseq[pc] = Inst::generic(iseq[pc]);
+            for (offset, operand) in ISeqCache::operands(iseq[pc]) {
+                let pos = pc + offset;
+                let val = read32(&iseq, pos);
+                let new_val = match operand {
+                    Operand::Ident => self.symbol(IdentId::from(val)),
+                    Operand::Method if val == 0 => 0,
+                    Operand::Method => self.method_index(MethodRef::from(val)) + 1,
+                    Operand::InlineCache | Operand::IvarCache => 0,
+                    Operand::CaseMap => {
+                        let map = self
+                            .globals
+                            .get_case_dispatch_map(val)
+                            .iter()
+                            .map(|(k, disp)| (k.as_fixnum().unwrap(), *disp))
+                            .collect();
+                        self.case_maps.push(map);
+                        self.case_maps.len() as u32 - 1
+                    }
+                };
+                write32(&mut iseq, pos, new_val);
+            }
+            pc += Inst::inst_size(iseq[pc]);
+        }
+        self.u32(iseq.len() as u32);
+        self.buf.extend_from_slice(&iseq);
+
+        self.u32(info.iseq_sourcemap.len() as u32);
+        for (pos, loc) in &info.iseq_sourcemap {
+            self.u32(pos.to_usize() as u32);
+            self.u32(loc.0);
+            self.u32(loc.1);
+        }
+        self.u32(info.line_table.len() as u32);
+        for (pos, loc) in &info.line_table {
+            self.u32(pos.to_usize() as u32);
+            self.u32(loc.0);
+            self.u32(loc.1);
+        }
+        self.u32(info.exception_table.len() as u32);
+        for entry in &info.exception_table {
+            self.u32(entry.start.to_usize() as u32);
+            self.u32(entry.end.to_usize() as u32);
+            self.u32(entry.dest.to_usize() as u32);
+        }
+        self.u32(info.loc.0);
+        self.u32(info.loc.1);
+        match info.kind {
+            ISeqKind::Other => self.u8(0),
+            ISeqKind::Method(id) => {
+                self.u8(1);
+                let sym = self.symbol(id);
+                self.u32(sym);
+            }
+            ISeqKind::Block(method) => {
+                self.u8(2);
+                let i = self.method_index(method);
+                self.u32(i);
+            }
+        }
+    }
+}
+
+struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(buf: &'a [u8]) -> Self {
+        Reader { buf, pos: 0 }
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], ISeqCacheError> {
+        if self.buf.len() - self.pos < len {
+            return Err(ISeqCacheError::Corrupt);
+        }
+        let bytes = &self.buf[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, ISeqCacheError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, ISeqCacheError> {
+        let mut bytes = [0u8; 4];
+        bytes.copy_from_slice(self.bytes(4)?);
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    fn u64(&mut self) -> Result<u64, ISeqCacheError> {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(self.bytes(8)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    fn usize(&mut self) -> Result<usize, ISeqCacheError> {
+        Ok(self.u32()? as usize)
+    }
+
+    fn str(&mut self) -> Result<&'a str, ISeqCacheError> {
+        let len = self.usize()?;
+        std::str::from_utf8(self.bytes(len)?).map_err(|_| ISeqCacheError::Corrupt)
+    }
+}
+
+struct Loader<'a> {
+    reader: Reader<'a>,
+    symbols: Vec<IdentId>,
+    methods: Vec<MethodRef>,
+    case_maps: Vec<u32>,
+}
+
+impl<'a> Loader<'a> {
+    fn symbol(&mut self) -> Result<IdentId, ISeqCacheError> {
+        let i = self.reader.usize()?;
+        self.symbols.get(i).cloned().ok_or(ISeqCacheError::Corrupt)
+    }
+
+    fn lvar(&mut self) -> Result<LvarId, ISeqCacheError> {
+        Ok(LvarId::from_usize(self.reader.usize()?))
+    }
+
+    fn pos(&mut self) -> Result<ISeqPos, ISeqCacheError> {
+        Ok(ISeqPos::from(self.reader.usize()?))
+    }
+
+    fn loc(&mut self) -> Result<Loc, ISeqCacheError> {
+        Ok(Loc(self.reader.u32()?, self.reader.u32()?))
+    }
+
+    fn iseq(
+        &mut self,
+        globals: &mut Globals,
+        index: usize,
+        source_info: SourceInfoRef,
+    ) -> Result<ISeqRef, ISeqCacheError> {
+        let req_params = self.reader.usize()?;
+        let opt_params = self.reader.usize()?;
+        let rest_param = self.reader.u8()? != 0;
+        let post_params = self.reader.usize()?;
+        let block_param = self.reader.u8()? != 0;
+        let mut param_ident = vec![];
+        for _ in 0..self.reader.u32()? {
+            param_ident.push(self.symbol()?);
+        }
+        let mut keyword_params = HashMap::new();
+        for _ in 0..self.reader.u32()? {
+            let id = self.symbol()?;
+            keyword_params.insert(id, self.lvar()?);
+        }
+        let mut req_keyword_params = vec![];
+        for _ in 0..self.reader.u32()? {
+            req_keyword_params.push(self.symbol()?);
+        }
+        let kwrest_param = match self.reader.u8()? {
+            0 => None,
+            _ => Some(self.lvar()?),
+        };
+        let no_kw_param = self.reader.u8()? != 0;
+
+        let mut table = HashMap::new();
+        for _ in 0..self.reader.u32()? {
+            let id = self.symbol()?;
+            table.insert(id, self.lvar()?);
+        }
+        let block = match self.reader.u8()? {
+            0 => None,
+            _ => Some(self.lvar()?),
+        };
+        let lvar = LvarCollector::from_table(table, block);
+
+        let len = self.reader.usize()?;
+        let mut iseq = self.reader.bytes(len)?.to_vec();
+        let mut pc = 0;
+        while pc < iseq.len() {
+            let size = Inst::inst_size(iseq[pc]);
+            if pc + size > iseq.len() {
+                return Err(ISeqCacheError::Corrupt);
+            }
+            for (offset, operand) in ISeqCache::operands(iseq[pc]) {
+                let pos = pc + offset;
+                let val = read32(&iseq, pos) as usize;
+                let new_val = match operand {
+                    Operand::Ident => {
+                        (*self.symbols.get(val).ok_or(ISeqCacheError::Corrupt)?).into()
+                    }
+                    Operand::Method if val == 0 => 0,
+                    Operand::Method => {
+                        (*self.methods.get(val - 1).ok_or(ISeqCacheError::Corrupt)?).into()
+                    }
+                    Operand::InlineCache => globals.add_inline_cache_entry(),
+                    Operand::IvarCache => globals.add_ivar_cache_entry(),
+                    Operand::CaseMap => *self.case_maps.get(val).ok_or(ISeqCacheError::Corrupt)?,
+                };
+                write32(&mut iseq, pos, new_val);
+            }
+            pc += size;
+        }
+
+        let mut iseq_sourcemap = vec![];
+        for _ in 0..self.reader.u32()? {
+            let pos = self.pos()?;
+            iseq_sourcemap.push((pos, self.loc()?));
+        }
+        let mut line_table = vec![];
+        for _ in 0..self.reader.u32()? {
+            let pos = self.pos()?;
+            line_table.push((pos, self.loc()?));
+        }
+        let mut exception_table = vec![];
+        for _ in 0..self.reader.u32()? {
+            exception_table.push(ExceptionEntry {
+                start: self.pos()?,
+                end: self.pos()?,
+                dest: self.pos()?,
+            });
+        }
+        let loc = self.loc()?;
+        let kind = match self.reader.u8()? {
+            0 => ISeqKind::Other,
+            1 => ISeqKind::Method(self.symbol()?),
+            2 => {
+                let i = self.reader.usize()?;
+                ISeqKind::Block(*self.methods.get(i).ok_or(ISeqCacheError::Corrupt)?)
+            }
+            _ => return Err(ISeqCacheError::Corrupt),
+        };
+        Ok(ISeqRef::new(ISeqInfo::new(
+            self.methods[index],
+            req_params,
+            opt_params,
+            rest_param,
+            post_params,
+            block_param,
+            param_ident,
+            keyword_params,
+            req_keyword_params,
+            kwrest_param,
+            no_kw_param,
+            iseq,
+            lvar,
+            iseq_sourcemap,
+            line_table,
+            exception_table,
+            source_info,
+            loc,
+            kind,
+        )))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+    use std::path::PathBuf;
+
+    fn compile(program: &str) -> Vec<u8> {
+        let mut vm = VM::new();
+        let method = vm.parse_program(PathBuf::from("cache.rb"), program).unwrap();
+        ISeqCache::dump(&vm.globals, method)
+    }
+
+    #[test]
+    fn iseq_cache_run() {
+        let program = r#"
+        class Foo
+          def initialize(x)
+            @x = x
+          end
+          def bar(y = 2, *rest, k: 5)
+            [1, 2].map { |i| i * @x + y + k }
+          end
+        end
+        def kind(n)
+          case n
+          when 1 then :one
+          when 2, 3 then :few
+          else :many
+          end
+        end
+        assert [10, 13], Foo.new(3).bar
+        assert [:one, :few, :many], [kind(1), kind(3), kind(9)]
+        assert "str", begin; raise "str"; rescue => e; e.message; end
+        assert 2.5, 1.5 + 1.0
+        "#;
+        let bytes = compile(program);
+        let mut vm = VM::new();
+        let method = ISeqCache::load(&mut vm.globals, &bytes, None).unwrap();
+        let iseq = vm.get_iseq(method).unwrap();
+        assert_eq!(PathBuf::from("cache.rb"), iseq.source_info.path);
+        vm.run_method(method, None).unwrap();
+    }
+
+    #[test]
+    fn iseq_cache_invalid() {
+        let bytes = compile("a = 1");
+        let mut vm = VM::new();
+        let res = ISeqCache::load(&mut vm.globals, b"RRB", None);
+        assert_eq!(Err(ISeqCacheError::Magic), res);
+        let mut broken = bytes.clone();
+        broken[4] += 1;
+        let res = ISeqCache::load(&mut vm.globals, &broken, None);
+        assert_eq!(Err(ISeqCacheError::Version), res);
+        let mut broken = bytes.clone();
+        *broken.last_mut().unwrap() ^= 0xff;
+        let res = ISeqCache::load(&mut vm.globals, &broken, None);
+        assert_eq!(Err(ISeqCacheError::Checksum), res);
+        let res = ISeqCache::load(&mut vm.globals, &bytes[..bytes.len() - 1], None);
+        assert_eq!(Err(ISeqCacheError::Corrupt), res);
+    }
+
+    #[test]
+    fn iseq_cache_require() {
+        let dir = std::env::temp_dir().join(format!("ruruby_cache_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("lib.rb");
+        std::fs::write(&source, "$loaded = :source").unwrap();
+        let now = std::time::SystemTime::now();
+        let old = now - std::time::Duration::from_secs(10);
+        std::fs::File::open(&source).unwrap().set_modified(old).unwrap();
+        // The cache is used only if it is newer than the source.
+        let bytes = compile("$loaded = :cache");
+        let cache = ISeqCache::cache_path(&source);
+        std::fs::write(&cache, bytes).unwrap();
+        let program = format!(
+            r#"
+            require "{0}"
+            assert :cache, $loaded
+            "#,
+            source.to_string_lossy()
+        );
+        let mut vm = VM::new();
+        vm.run(PathBuf::from(""), &program, None).unwrap();
+
+        std::fs::File::open(&source).unwrap().set_modified(now).unwrap();
+        std::fs::File::open(&cache).unwrap().set_modified(old).unwrap();
+        let program = format!(
+            r#"
+            require "{0}"
+            assert :source, $loaded
+            "#,
+            source.to_string_lossy()
+        );
+        let mut vm = VM::new();
+        vm.run(PathBuf::from(""), &program, None).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,536 @@
+use super::vm_inst::Inst;
+use crate::*;
+use std::time::{Duration, Instant};
+
+#[cfg(all(target_arch = "x86_64", unix))]
+mod asm;
+#[cfg(all(target_arch = "x86_64", unix))]
+mod compiler;
+
+/// A baseline JIT compiler which translates the ISeqs of hot methods into x86-64 machine code.
+///
+/// A method is compiled when it has been called `threshold` times.
+/// The compiled code uses `VM::exec_stack` and the local variables of the context
+/// just as the interpreter does, so that it can deoptimize at any instruction
+/// by handing the pc over to the interpreter.
+/// Arithmetic and comparison of Integers, local variables and branches are compiled into
+/// native code, Float arithmetic, method calls and instance variable access are compiled into
+/// calls of runtime functions, and the other instructions are compiled into deoptimization exits.
+#[derive(Debug, Clone)]
+pub struct Jit {
+    /// The number of calls after which a method is compiled.
+    pub threshold: usize,
+    /// Statistics of compiled methods, indexed by `JitCode::id`.
+    methods: Vec<JitStat>,
+}
+
+#[derive(Debug, Clone)]
+struct JitStat {
+    label: String,
+    code_size: usize,
+    compile_time: Duration,
+    calls: u64,
+    deopts: u64,
+}
+
+/// The native code of a compiled method.
+#[derive(Debug, Clone, Copy)]
+pub struct JitCode {
+    entry: JitEntry,
+    /// The maximum number of values which the code pushes on the stack without calling the VM.
+    max_stack: usize,
+    id: usize,
+}
+
+type JitEntry = unsafe extern "C" fn(*mut JitFrame) -> u64;
+
+/// The state shared by the compiled code and the runtime functions.
+/// The compiled code accesses `sp`, `lvars`, `self_value` and `pc` at fixed offsets.
+#[repr(C)]
+struct JitFrame {
+    /// The next free slot of the stack.
+    sp: *mut Value,
+    /// The local variables of the current context.
+    lvars: *mut Value,
+    self_value: u64,
+    /// The pc where the code deoptimized or an error occurred.
+    pc: usize,
+    vm: *mut VM,
+    max_stack: usize,
+    error: Option<RubyError>,
+}
+
+/// The default number of calls after which a method is compiled.
+pub const JIT_THRESHOLD: usize = 10;
+
+// Exit status of the compiled code.
+const JIT_RETURN: u64 = 0;
+const JIT_DEOPT: u64 = 1;
+const JIT_ERROR: u64 = 2;
+
+impl Jit {
+    pub fn new(threshold: usize) -> Self {
+        Jit {
+            threshold,
+            methods: vec![],
+        }
+    }
+
+    /// Compile `iseq_ref`. Returns None if the JIT is not supported on this platform.
+    #[cfg(all(target_arch = "x86_64", unix))]
+    fn compile(&mut self, iseq_ref: ISeqRef, label: String) -> Option<JitCode> {
+        let start = Instant::now();
+        let (code, max_stack) = compiler::Compiler::compile(&iseq_ref.iseq);
+        let entry = compiler::alloc_code(&code)?;
+        self.methods.push(JitStat {
+            label,
+            code_size: code.len(),
+            compile_time: start.elapsed(),
+            calls: 0,
+            deopts: 0,
+        });
+        Some(JitCode {
+            entry,
+            max_stack,
+            id: self.methods.len() - 1,
+        })
+    }
+
+    #[cfg(not(all(target_arch = "x86_64", unix)))]
+    fn compile(&mut self, _iseq_ref: ISeqRef, _label: String) -> Option<JitCode> {
+        None
+    }
+
+    /// Returns the number of compiled methods.
+    pub fn compiled(&self) -> usize {
+        self.methods.len()
+    }
+
+    /// Returns the number of deoptimizations of all compiled methods.
+    pub fn deopts(&self) -> u64 {
+        self.methods.iter().map(|stat| stat.deopts).sum()
+    }
+
+    /// Returns a summary of compiled methods sorted by the number of calls.
+    pub fn summary(&self) -> String {
+        let code_size: usize = self.methods.iter().map(|stat| stat.code_size).sum();
+        let compile_time: Duration = self.methods.iter().map(|stat| stat.compile_time).sum();
+        let mut stats: Vec<&JitStat> = self.methods.iter().collect();
+        stats.sort_by(|s1, s2| s2.calls.cmp(&s1.calls).then(s1.label.cmp(&s2.label)));
+        let mut s = format!(
+            "JIT: {} methods compiled, {} bytes, {:.3} ms\n",
+            self.methods.len(),
+            code_size,
+            compile_time.as_secs_f64() * 1000.0
+        );
+        s += &format!(
+            "{:>10} {:>10} {:>8} {:>12}  {}\n",
+            "calls", "deopts", "bytes", "compile(ms)", "method"
+        );
+        for stat in stats {
+            s += &format!(
+                "{:>10} {:>10} {:>8} {:>12.3}  {}\n",
+                stat.calls,
+                stat.deopts,
+                stat.code_size,
+                stat.compile_time.as_secs_f64() * 1000.0,
+                stat.label
+            );
+        }
+        s
+    }
+}
+
+impl VM {
+    /// Run the compiled code of the method of `context` from the beginning,
+    /// compiling the method when it has been called `threshold` times.
+    /// Returns None if the method is not compiled or the code deoptimized,
+    /// then the interpreter continues from `self.pc`.
+    pub fn jit_exec(&mut self, mut context: ContextRef) -> Result<Option<Value>, RubyError> {
+        match context.kind {
+            ISeqKind::Method(_) => {}
+            _ => return Ok(None),
+        }
+        if self.globals.coverage.is_some() || self.profiler.is_some() || self.debugger.is_some()
+        {
+            return Ok(None);
+        }
+        let jit = match &mut self.jit {
+            Some(jit) => jit,
+            None => return Ok(None),
+        };
+        let mut iseq_ref = context.iseq_ref;
+        let code = match iseq_ref.jit_code {
+            Some(code) => code,
+            None => {
+                iseq_ref.jit_count += 1;
+                if iseq_ref.jit_count != jit.threshold {
+                    return Ok(None);
+                }
+                let label = iseq_ref.label(&self.globals);
+                match jit.compile(iseq_ref, label) {
+                    Some(code) => {
+                        iseq_ref.jit_code = Some(code);
+                        code
+                    }
+                    None => return Ok(None),
+                }
+            }
+        };
+        jit.methods[code.id].calls += 1;
+
+        let len = self.exec_stack.len();
+        self.exec_stack.reserve(code.max_stack);
+        let mut frame = JitFrame {
+            sp: unsafe { self.exec_stack.as_mut_ptr().add(len) },
+            lvars: context.lvar_ptr(),
+            self_value: context.self_value.id(),
+            pc: 0,
+            vm: self as *mut VM,
+            max_stack: code.max_stack,
+            error: None,
+        };
+        let status = unsafe { (code.entry)(&mut frame) };
+        match status {
+            JIT_RETURN => {
+                self.set_stack_top(frame.sp);
+                Ok(Some(self.stack_pop()))
+            }
+            JIT_DEOPT => {
+                self.set_stack_top(frame.sp);
+                self.pc = frame.pc;
+                if let Some(jit) = &mut self.jit {
+                    jit.methods[code.id].deopts += 1;
+                }
+                Ok(None)
+            }
+            JIT_ERROR => {
+                // The stack has been synchronized by the runtime function.
+                self.pc = frame.pc;
+                let err = frame.error.take().unwrap();
+                match err.kind {
+                    RubyErrorKind::BlockReturn(outer) if outer == self.context() => {
+                        // Continue at the next instruction in the interpreter
+                        // with the value of `break` as the result of the call.
+                        let result = self.stack_pop();
+                        let prev_len = self.context().stack_len;
+                        self.exec_stack.truncate(prev_len);
+                        self.stack_push(result);
+                        self.pc += Inst::inst_size(context.iseq_ref.iseq[self.pc]);
+                        Ok(None)
+                    }
+                    RubyErrorKind::MethodReturn(m) if m == context.iseq_ref.method => {
+                        let result = self.stack_pop();
+                        let prev_len = self.context().stack_len;
+                        self.exec_stack.truncate(prev_len);
+                        Ok(Some(result))
+                    }
+                    _ => Err(err),
+                }
+            }
+            _ => unreachable!("Illegal JIT status {}", status),
+        }
+    }
+
+    /// Set the length of the stack to the position of the stack pointer `sp`.
+    fn set_stack_top(&mut self, sp: *mut Value) {
+        unsafe {
+            let len = sp.offset_from(self.exec_stack.as_ptr()) as usize;
+            self.exec_stack.set_len(len);
+        }
+    }
+}
+
+// Runtime functions called from the compiled code.
+
+/// Perform the binary operation `inst` on Integers and Floats, or compare any values by EQ or NE.
+/// Returns `Value::uninitialized()` if the operation needs the VM, then the code deoptimizes.
+extern "C" fn jit_binop(inst: u64, lhs: u64, rhs: u64) -> u64 {
+    let lhs = Value::from(lhs);
+    let rhs = Value::from(rhs);
+    let val = match inst as u8 {
+        Inst::EQ => Some(Value::bool(lhs.equal(rhs))),
+        Inst::NE => Some(Value::bool(!lhs.equal(rhs))),
+        inst => match (lhs.unpack(), rhs.unpack()) {
+            (RV::Integer(lhs), RV::Integer(rhs)) => integer_binop(inst, lhs, rhs),
+            (RV::Integer(lhs), RV::Float(rhs)) => float_binop(inst, lhs as f64, rhs),
+            (RV::Float(lhs), RV::Integer(rhs)) => float_binop(inst, lhs, rhs as f64),
+            (RV::Float(lhs), RV::Float(rhs)) => float_binop(inst, lhs, rhs),
+            _ => None,
+        },
+    };
+    val.unwrap_or_else(Value::uninitialized).id()
+}
+
+/// Overflow and division by zero are left to the interpreter.
+fn integer_binop(inst: u8, lhs: i64, rhs: i64) -> Option<Value> {
+    use divrem::DivFloor;
+    let val = match inst {
+        Inst::ADD => Value::fixnum(lhs.checked_add(rhs)?),
+        Inst::SUB => Value::fixnum(lhs.checked_sub(rhs)?),
+        Inst::MUL => Value::fixnum(lhs.checked_mul(rhs)?),
+        Inst::DIV => {
+            lhs.checked_div(rhs)?;
+            Value::fixnum(DivFloor::div_floor(lhs, rhs))
+        }
+        Inst::GT => Value::bool(lhs > rhs),
+        Inst::GE => Value::bool(lhs >= rhs),
+        Inst::LT => Value::bool(lhs < rhs),
+        Inst::LE => Value::bool(lhs <= rhs),
+        _ => return None,
+    };
+    Some(val)
+}
+
+fn float_binop(inst: u8, lhs: f64, rhs: f64) -> Option<Value> {
+    let val = match inst {
+        Inst::ADD => Value::flonum(lhs + rhs),
+        Inst::SUB => Value::flonum(lhs - rhs),
+        Inst::MUL => Value::flonum(lhs * rhs),
+        Inst::DIV => Value::flonum(lhs / rhs),
+        Inst::GT => Value::bool(lhs > rhs),
+        Inst::GE => Value::bool(lhs >= rhs),
+        Inst::LT => Value::bool(lhs < rhs),
+        Inst::LE => Value::bool(lhs <= rhs),
+        _ => return None,
+    };
+    Some(val)
+}
+
+/// Synchronize the stack and the pc of the VM with the compiled code before calling the VM.
+unsafe fn enter_vm<'a>(frame: *mut JitFrame, sp: *mut Value, pc: usize) -> &'a mut VM {
+    let vm = &mut *(*frame).vm;
+    vm.set_stack_top(sp);
+    vm.pc = pc;
+    vm
+}
+
+/// Returns the new stack pointer to the compiled code after calling the VM,
+/// or null if an error occurred.
+/// The stack may have been reallocated and the context may have been moved to the heap.
+unsafe fn leave_vm(
+    frame: *mut JitFrame,
+    vm: &mut VM,
+    pc: usize,
+    res: Result<(), RubyError>,
+) -> *mut Value {
+    if let Err(err) = res {
+        (*frame).pc = pc;
+        (*frame).error = Some(err);
+        return std::ptr::null_mut();
+    }
+    vm.exec_stack.reserve((*frame).max_stack);
+    (*frame).lvars = vm.context().lvar_ptr();
+    let len = vm.exec_stack.len();
+    vm.exec_stack.as_mut_ptr().add(len)
+}
+
+/// SEND, SEND_SELF and their quickened instructions.
+extern "C" fn jit_send(frame: *mut JitFrame, sp: *mut Value, pc: usize) -> *mut Value {
+    unsafe {
+        let vm = enter_vm(frame, sp, pc);
+        let iseq_ref = vm.context().iseq_ref;
+        let res = vm.send_inst(&iseq_ref.iseq).map(|val| vm.stack_push(val));
+        leave_vm(frame, vm, pc, res)
+    }
+}
+
+extern "C" fn jit_get_const(frame: *mut JitFrame, sp: *mut Value, pc: usize) -> *mut Value {
+    unsafe {
+        let vm = enter_vm(frame, sp, pc);
+        let id = IdentId::from(Inst::read32(&vm.context().iseq_ref.iseq, pc + 1));
+        let res = vm.get_const(id).map(|val| vm.stack_push(val));
+        leave_vm(frame, vm, pc, res)
+    }
+}
+
+extern "C" fn jit_get_ivar(frame: *mut JitFrame, sp: *mut Value, pc: usize) -> *mut Value {
+    unsafe {
+        let vm = enter_vm(frame, sp, pc);
+        let iseq_ref = vm.context().iseq_ref;
+        let id = IdentId::from(Inst::read32(&iseq_ref.iseq, pc + 1));
+        let cache_slot = Inst::read32(&iseq_ref.iseq, pc + 5);
+        let oref = Value::from((*frame).self_value).as_object();
+        let val = vm.get_ivar(oref, id, cache_slot);
+        vm.stack_push(val);
+        leave_vm(frame, vm, pc, Ok(()))
+    }
+}
+
+extern "C" fn jit_set_ivar(frame: *mut JitFrame, sp: *mut Value, pc: usize) -> *mut Value {
+    unsafe {
+        let vm = enter_vm(frame, sp, pc);
+        let iseq_ref = vm.context().iseq_ref;
+        let id = IdentId::from(Inst::read32(&iseq_ref.iseq, pc + 1));
+        let cache_slot = Inst::read32(&iseq_ref.iseq, pc + 5);
+        let oref = Value::from((*frame).self_value).as_object();
+        let val = vm.stack_pop();
+        vm.set_ivar(oref, id, val, cache_slot);
+        leave_vm(frame, vm, pc, Ok(()))
+    }
+}
+
+extern "C" fn jit_ivar_addi(frame: *mut JitFrame, sp: *mut Value, pc: usize) -> *mut Value {
+    unsafe {
+        let vm = enter_vm(frame, sp, pc);
+        let iseq_ref = vm.context().iseq_ref;
+        let id = IdentId::from(Inst::read32(&iseq_ref.iseq, pc + 1));
+        let i = Inst::read32(&iseq_ref.iseq, pc + 5) as i32;
+        let cache_slot = Inst::read32(&iseq_ref.iseq, pc + 9);
+        let oref = Value::from((*frame).self_value).as_object();
+        let val = vm.get_ivar(oref, id, cache_slot);
+        let res = vm
+            .eval_addi(val, i)
+            .map(|new_val| vm.set_ivar(oref, id, new_val, cache_slot));
+        leave_vm(frame, vm, pc, res)
+    }
+}
+
+#[cfg(all(test, target_arch = "x86_64", unix))]
+mod test {
+    use crate::*;
+    use std::path::PathBuf;
+
+    /// Run `program` compiling every method at the first call, and returns the JIT statistics.
+    fn assert_jit_script(program: &str) -> Jit {
+        let mut vm = VM::new();
+        vm.jit = Some(Jit::new(1));
+        if let Err(err) = vm.run(PathBuf::from(""), program, None) {
+            err.show_err();
+            err.show_loc(0);
+            panic!("Got error: {:?}", err);
+        }
+        vm.jit.take().unwrap()
+    }
+
+    #[test]
+    fn jit_fib() {
+        let program = r#"
+        def fib(n)
+            if n < 2
+                n
+            else
+                fib(n - 1) + fib(n - 2)
+            end
+        end
+        def sum(n)
+            i = 0
+            s = 0
+            while i <= n
+                s += i * 2
+                i += 1
+            end
+            s
+        end
+        assert(6765, fib(20))
+        assert(110, sum(10))
+        assert(-3, sum(-3) - 3)
+        "#;
+        let jit = assert_jit_script(program);
+        assert_eq!(2, jit.compiled());
+        assert_eq!(0, jit.deopts());
+        assert!(jit.summary().contains("Object#fib"));
+    }
+
+    #[test]
+    fn jit_float() {
+        let program = r#"
+        def calc(a, b)
+            (a + b) * (a - b) / 2
+        end
+        def cmp(a, b)
+            a > b
+        end
+        assert(-8.125, calc(2, 4.5))
+        assert(-8.125, calc(2.0, 4.5))
+        assert(-6, calc(2, 4))
+        assert(-11, calc(2, 5))
+        assert(true, cmp(2.5, 2))
+        assert(false, cmp(2, 2.5))
+        assert(true, 1.0 == 1)
+        "#;
+        let jit = assert_jit_script(program);
+        assert_eq!(0, jit.deopts());
+    }
+
+    #[test]
+    fn jit_deopt() {
+        let program = r#"
+        def add(a, b)
+            c = a + b
+            c + c
+        end
+        def div(a, b)
+            a / b
+        end
+        def big(a)
+            a * 4 + 1
+        end
+        assert(6, add(1, 2))
+        assert("abab", add("a", "b"))
+        assert([1, 2, 1, 2], add([1], [2]))
+        assert(6, add(1, 2))
+        assert(4611686018427387905, big(1152921504606846976))
+        assert(4611686018427387904, big(1152921504606846976) - 1)
+        assert(-4611686018427387903, big(-1152921504606846976))
+        begin
+            div(1, 0)
+            assert(true, false)
+        rescue ZeroDivisionError
+        end
+        assert(3, div(7, 2))
+        "#;
+        let jit = assert_jit_script(program);
+        assert!(jit.deopts() >= 3);
+    }
+
+    #[test]
+    fn jit_send_ivar() {
+        let program = r#"
+        class Vec
+            ORIGIN = 0
+            attr_reader :x
+            def initialize(x, y)
+                @x = x
+                @y = y
+            end
+            def y; @y; end
+            def len2
+                @x * @x + @y * @y + ORIGIN
+            end
+            def inc
+                @x += 1
+                self
+            end
+            def add(v)
+                Vec.new(@x + v.x, @y + v.y)
+            end
+            def find(a)
+                a.each { |e| return e if e > @x }
+                nil
+            end
+            def each_y
+                [1, 2, 3].each { |e| break e * 10 if e == @y }
+            end
+            def fail
+                @x.foo
+            end
+        end
+        v = Vec.new(3, 4)
+        assert(25, v.len2)
+        assert(32, v.inc.len2)
+        w = v.add(Vec.new(1, 2))
+        assert(5, w.x)
+        assert(6, w.y)
+        assert(7, w.find([1, 5, 7, 9]))
+        assert(nil, w.find([1, 2]))
+        assert(20, Vec.new(0, 2).each_y)
+        begin
+            w.fail
+            assert(true, false)
+        rescue NoMethodError
+        end
+        "#;
+        assert_jit_script(program);
+    }
+}
@@ -0,0 +1,294 @@
+//! A minimal x86-64 assembler for the JIT compiler.
+//!
+//! Only the instructions used by the code generator are supported.
+//! All memory operands are in the form of `[base + disp32]`.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Reg {
+    Rax = 0,
+    Rcx = 1,
+    Rdx = 2,
+    Rbx = 3,
+    Rbp = 5,
+    Rsi = 6,
+    Rdi = 7,
+    R12 = 12,
+    R13 = 13,
+    R14 = 14,
+}
+
+impl Reg {
+    fn low(self) -> u8 {
+        self as u8 & 7
+    }
+
+    fn ext(self) -> u8 {
+        (self as u8 >> 3) & 1
+    }
+}
+
+/// Condition codes for Jcc and SETcc.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Cond {
+    O = 0x0,
+    E = 0x4,
+    Ne = 0x5,
+    A = 0x7,
+    L = 0xc,
+    Ge = 0xd,
+    Le = 0xe,
+    G = 0xf,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Label(usize);
+
+#[derive(Debug, Clone, Default)]
+pub struct Assembler {
+    code: Vec<u8>,
+    /// Positions of labels. None if not bound yet.
+    labels: Vec<Option<usize>>,
+    /// Positions of rel32 displacements to be patched with labels.
+    fixups: Vec<(usize, Label)>,
+}
+
+impl Assembler {
+    pub fn new() -> Self {
+        Assembler::default()
+    }
+
+    pub fn new_label(&mut self) -> Label {
+        self.labels.push(None);
+        Label(self.labels.len() - 1)
+    }
+
+    pub fn bind(&mut self, label: Label) {
+        self.labels[label.0] = Some(self.code.len());
+    }
+
+    /// Resolve all labels and return the machine code.
+    pub fn finalize(mut self) -> Vec<u8> {
+        for (pos, label) in std::mem::take(&mut self.fixups) {
+            let dest = self.labels[label.0].expect("Unbound label.");
+            let disp = dest as i64 - (pos as i64 + 4);
+            self.code[pos..pos + 4].copy_from_slice(&(disp as i32).to_le_bytes());
+        }
+        self.code
+    }
+
+    fn emit(&mut self, bytes: &[u8]) {
+        self.code.extend_from_slice(bytes);
+    }
+
+    fn imm32(&mut self, imm: i32) {
+        self.emit(&imm.to_le_bytes());
+    }
+
+    fn rel32(&mut self, label: Label) {
+        self.fixups.push((self.code.len(), label));
+        self.imm32(0);
+    }
+
+    /// REX.W prefix with `reg` in ModRM.reg and `rm` in ModRM.rm.
+    fn rex_w(&mut self, reg: Reg, rm: Reg) {
+        self.emit(&[0x48 | reg.ext() << 2 | rm.ext()]);
+    }
+
+    /// ModRM for register-direct operands.
+    fn modrm_rr(&mut self, reg: Reg, rm: Reg) {
+        self.emit(&[0xc0 | reg.low() << 3 | rm.low()]);
+    }
+
+    /// ModRM (and SIB) for `[base + disp32]`.
+    fn modrm_mem(&mut self, reg: u8, base: Reg, disp: i32) {
+        self.emit(&[0x80 | (reg & 7) << 3 | base.low()]);
+        if base.low() == 4 {
+            // rsp and r12 need a SIB byte.
+            self.emit(&[0x24]);
+        }
+        self.imm32(disp);
+    }
+
+    /// mov dst, src
+    pub fn mov_rr(&mut self, dst: Reg, src: Reg) {
+        self.rex_w(src, dst);
+        self.emit(&[0x89]);
+        self.modrm_rr(src, dst);
+    }
+
+    /// mov dst, [base + disp]
+    pub fn load(&mut self, dst: Reg, base: Reg, disp: i32) {
+        self.rex_w(dst, base);
+        self.emit(&[0x8b]);
+        self.modrm_mem(dst.low(), base, disp);
+    }
+
+    /// mov [base + disp], src
+    pub fn store(&mut self, base: Reg, disp: i32, src: Reg) {
+        self.rex_w(src, base);
+        self.emit(&[0x89]);
+        self.modrm_mem(src.low(), base, disp);
+    }
+
+    /// mov dst, imm64
+    pub fn mov_ri(&mut self, dst: Reg, imm: u64) {
+        self.emit(&[0x48 | dst.ext(), 0xb8 | dst.low()]);
+        self.emit(&imm.to_le_bytes());
+    }
+
+    /// Binary operation `op dst, src` with the opcode of the `r/m64, r64` form.
+    fn binop_rr(&mut self, opcode: u8, dst: Reg, src: Reg) {
+        self.rex_w(src, dst);
+        self.emit(&[opcode]);
+        self.modrm_rr(src, dst);
+    }
+
+    /// Binary operation `op dst, imm32` with the ModRM.reg extension of the `81 /n` form.
+    fn binop_ri(&mut self, ext: u8, dst: Reg, imm: i32) {
+        self.emit(&[0x48 | dst.ext(), 0x81, 0xc0 | ext << 3 | dst.low()]);
+        self.imm32(imm);
+    }
+
+    pub fn add_rr(&mut self, dst: Reg, src: Reg) {
+        self.binop_rr(0x01, dst, src);
+    }
+
+    pub fn sub_rr(&mut self, dst: Reg, src: Reg) {
+        self.binop_rr(0x29, dst, src);
+    }
+
+    pub fn and_rr(&mut self, dst: Reg, src: Reg) {
+        self.binop_rr(0x21, dst, src);
+    }
+
+    pub fn cmp_rr(&mut self, dst: Reg, src: Reg) {
+        self.binop_rr(0x39, dst, src);
+    }
+
+    pub fn test_rr(&mut self, dst: Reg, src: Reg) {
+        self.binop_rr(0x85, dst, src);
+    }
+
+    pub fn add_ri(&mut self, dst: Reg, imm: i32) {
+        self.binop_ri(0, dst, imm);
+    }
+
+    pub fn or_ri(&mut self, dst: Reg, imm: i32) {
+        self.binop_ri(1, dst, imm);
+    }
+
+    pub fn sub_ri(&mut self, dst: Reg, imm: i32) {
+        self.binop_ri(5, dst, imm);
+    }
+
+    pub fn cmp_ri(&mut self, dst: Reg, imm: i32) {
+        self.binop_ri(7, dst, imm);
+    }
+
+    /// imul dst, src
+    pub fn imul_rr(&mut self, dst: Reg, src: Reg) {
+        self.rex_w(dst, src);
+        self.emit(&[0x0f, 0xaf]);
+        self.modrm_rr(dst, src);
+    }
+
+    /// sar dst, imm8
+    pub fn sar_ri(&mut self, dst: Reg, imm: u8) {
+        self.emit(&[0x48 | dst.ext(), 0xc1, 0xf8 | dst.low(), imm]);
+    }
+
+    /// test dst8, imm8 (the low byte of `dst`)
+    pub fn test_r8i(&mut self, dst: Reg, imm: u8) {
+        if dst == Reg::Rax {
+            self.emit(&[0xa8, imm]);
+        } else {
+            // REX prefix is needed to address the low byte of rsi, rdi and r8-r15.
+            self.emit(&[0x40 | dst.ext(), 0xf6, 0xc0 | dst.low(), imm]);
+        }
+    }
+
+    /// setcc al; movzx eax, al
+    pub fn setcc_rax(&mut self, cond: Cond) {
+        self.emit(&[0x0f, 0x90 | cond as u8, 0xc0]);
+        self.emit(&[0x0f, 0xb6, 0xc0]);
+    }
+
+    /// imul eax, eax, imm8
+    pub fn imul_eax_i8(&mut self, imm: i8) {
+        self.emit(&[0x6b, 0xc0, imm as u8]);
+    }
+
+    pub fn jmp(&mut self, label: Label) {
+        self.emit(&[0xe9]);
+        self.rel32(label);
+    }
+
+    pub fn jcc(&mut self, cond: Cond, label: Label) {
+        self.emit(&[0x0f, 0x80 | cond as u8]);
+        self.rel32(label);
+    }
+
+    /// Call the function at the absolute address `addr`, clobbering rax.
+    pub fn call_abs(&mut self, addr: u64) {
+        self.mov_ri(Reg::Rax, addr);
+        // call rax
+        self.emit(&[0xff, 0xd0]);
+    }
+
+    pub fn push(&mut self, reg: Reg) {
+        if reg.ext() == 1 {
+            self.emit(&[0x41]);
+        }
+        self.emit(&[0x50 | reg.low()]);
+    }
+
+    pub fn pop(&mut self, reg: Reg) {
+        if reg.ext() == 1 {
+            self.emit(&[0x41]);
+        }
+        self.emit(&[0x58 | reg.low()]);
+    }
+
+    pub fn ret(&mut self) {
+        self.emit(&[0xc3]);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn asm_encoding() {
+        let mut asm = Assembler::new();
+        let label = asm.new_label();
+        asm.bind(label);
+        asm.mov_rr(Reg::Rbx, Reg::Rax);
+        asm.load(Reg::Rax, Reg::R12, 8);
+        asm.store(Reg::R13, -16, Reg::Rcx);
+        asm.add_ri(Reg::Rbx, 8);
+        asm.imul_rr(Reg::Rax, Reg::Rcx);
+        asm.sar_ri(Reg::Rax, 1);
+        asm.test_rr(Reg::Rax, Reg::Rax);
+        asm.push(Reg::R12);
+        asm.pop(Reg::Rbx);
+        asm.jcc(Cond::O, label);
+        asm.jmp(label);
+        assert_eq!(
+            asm.finalize(),
+            vec![
+                0x48, 0x89, 0xc3, // mov rbx, rax
+                0x49, 0x8b, 0x84, 0x24, 0x08, 0x00, 0x00, 0x00, // mov rax, [r12 + 8]
+                0x49, 0x89, 0x8d, 0xf0, 0xff, 0xff, 0xff, // mov [r13 - 16], rcx
+                0x48, 0x81, 0xc3, 0x08, 0x00, 0x00, 0x00, // add rbx, 8
+                0x48, 0x0f, 0xaf, 0xc1, // imul rax, rcx
+                0x48, 0xc1, 0xf8, 0x01, // sar rax, 1
+                0x48, 0x85, 0xc0, // test rax, rax
+                0x41, 0x54, // push r12
+                0x5b, // pop rbx
+                0x0f, 0x80, 0xd3, 0xff, 0xff, 0xff, // jo label
+                0xe9, 0xce, 0xff, 0xff, 0xff, // jmp label
+            ]
+        );
+    }
+}
@@ -0,0 +1,403 @@
+use super::asm::*;
+use super::*;
+use std::collections::HashMap;
+
+// Offsets of the fields of JitFrame.
+const FRAME_SP: i32 = 0;
+const FRAME_LVARS: i32 = 8;
+const FRAME_SELF: i32 = 16;
+const FRAME_PC: i32 = 24;
+
+/// Runtime functions which take the stack pointer and the pc, and return the new stack pointer.
+type VMFunc = extern "C" fn(*mut JitFrame, *mut Value, usize) -> *mut Value;
+
+/// Code generator for an ISeq.
+///
+/// The compiled code is a function `extern "C" fn(*mut JitFrame) -> u64`
+/// which returns JIT_RETURN, JIT_DEOPT or JIT_ERROR, and uses the registers as follows.
+/// - rbx: the next free slot of the stack
+/// - r12: the JitFrame
+/// - r13: the local variables
+///
+/// They are reloaded from the JitFrame after calling the VM.
+pub struct Compiler<'a> {
+    asm: Assembler,
+    iseq: &'a ISeq,
+    /// Labels of the instructions, indexed by pc.
+    labels: HashMap<usize, Label>,
+    /// Labels of the deoptimization exits, indexed by pc.
+    deopts: HashMap<usize, Label>,
+    epilogue: Label,
+    error_exit: Label,
+}
+
+impl<'a> Compiler<'a> {
+    /// Compile `iseq` into machine code.
+    /// Returns the code and the maximum number of values pushed without calling the VM.
+    pub fn compile(iseq: &'a ISeq) -> (Vec<u8>, usize) {
+        let mut asm = Assembler::new();
+        let epilogue = asm.new_label();
+        let error_exit = asm.new_label();
+        let mut compiler = Compiler {
+            asm,
+            iseq,
+            labels: HashMap::new(),
+            deopts: HashMap::new(),
+            epilogue,
+            error_exit,
+        };
+        let mut max_stack = 1;
+        let mut pc = 0;
+        while pc < iseq.len() {
+            let label = compiler.asm.new_label();
+            compiler.labels.insert(pc, label);
+            max_stack += match Inst::generic(iseq[pc]) {
+                Inst::DUP => Inst::read32(iseq, pc + 1) as usize,
+                _ => 1,
+            };
+            pc += Inst::inst_size(iseq[pc]);
+        }
+
+        compiler.prologue();
+        let mut pc = 0;
+        while pc < iseq.len() {
+            compiler.asm.bind(compiler.labels[&pc]);
+            compiler.compile_inst(pc);
+            pc += Inst::inst_size(iseq[pc]);
+        }
+        compiler.epilogue();
+        (compiler.asm.finalize(), max_stack)
+    }
+
+    fn prologue(&mut self) {
+        // Five pushes keep rsp aligned to 16 bytes at calls.
+        self.asm.push(Reg::Rbp);
+        self.asm.push(Reg::Rbx);
+        self.asm.push(Reg::R12);
+        self.asm.push(Reg::R13);
+        self.asm.push(Reg::R14);
+        self.asm.mov_rr(Reg::R12, Reg::Rdi);
+        self.asm.load(Reg::Rbx, Reg::R12, FRAME_SP);
+        self.asm.load(Reg::R13, Reg::R12, FRAME_LVARS);
+    }
+
+    fn epilogue(&mut self) {
+        self.asm.bind(self.error_exit);
+        self.asm.mov_ri(Reg::Rax, JIT_ERROR);
+        self.asm.jmp(self.epilogue);
+
+        let mut deopts: Vec<(usize, Label)> = self.deopts.iter().map(|(k, v)| (*k, *v)).collect();
+        deopts.sort_by_key(|(pc, _)| *pc);
+        for (pc, label) in deopts {
+            self.asm.bind(label);
+            self.asm.mov_ri(Reg::Rax, pc as u64);
+            self.asm.store(Reg::R12, FRAME_PC, Reg::Rax);
+            self.asm.mov_ri(Reg::Rax, JIT_DEOPT);
+            self.asm.jmp(self.epilogue);
+        }
+
+        self.asm.bind(self.epilogue);
+        self.asm.store(Reg::R12, FRAME_SP, Reg::Rbx);
+        self.asm.pop(Reg::R14);
+        self.asm.pop(Reg::R13);
+        self.asm.pop(Reg::R12);
+        self.asm.pop(Reg::Rbx);
+        self.asm.pop(Reg::Rbp);
+        self.asm.ret();
+    }
+
+    fn compile_inst(&mut self, pc: usize) {
+        let iseq = self.iseq;
+        match Inst::generic(iseq[pc]) {
+            Inst::PUSH_FIXNUM => {
+                let val = Value::fixnum(Inst::read64(iseq, pc + 1) as i64);
+                self.push_imm(val);
+            }
+            Inst::PUSH_FLONUM => {
+                let val = Value::flonum(f64::from_bits(Inst::read64(iseq, pc + 1)));
+                self.push_imm(val);
+            }
+            Inst::PUSH_TRUE => self.push_imm(Value::true_val()),
+            Inst::PUSH_FALSE => self.push_imm(Value::false_val()),
+            Inst::PUSH_NIL => self.push_imm(Value::nil()),
+            Inst::PUSH_SELF => {
+                self.asm.load(Reg::Rax, Reg::R12, FRAME_SELF);
+                self.push(Reg::Rax);
+            }
+            Inst::POP => self.asm.sub_ri(Reg::Rbx, 8),
+            Inst::DUP => {
+                let len = Inst::read32(iseq, pc + 1) as i32;
+                for i in 0..len {
+                    self.asm.load(Reg::Rax, Reg::Rbx, (i - len) * 8);
+                    self.asm.store(Reg::Rbx, i * 8, Reg::Rax);
+                }
+                self.asm.add_ri(Reg::Rbx, len * 8);
+            }
+            Inst::GET_LOCAL => match self.local_offset(pc) {
+                Some(offset) => {
+                    self.asm.load(Reg::Rax, Reg::R13, offset);
+                    self.push(Reg::Rax);
+                }
+                None => self.deopt(pc),
+            },
+            Inst::SET_LOCAL => match self.local_offset(pc) {
+                Some(offset) => {
+                    self.pop(Reg::Rax);
+                    self.asm.store(Reg::R13, offset, Reg::Rax);
+                }
+                None => self.deopt(pc),
+            },
+            inst @ Inst::ADD | inst @ Inst::SUB | inst @ Inst::MUL => self.arith(inst, pc),
+            Inst::DIV => {
+                self.asm.load(Reg::Rcx, Reg::Rbx, -16);
+                self.asm.load(Reg::Rdx, Reg::Rbx, -8);
+                self.call_binop(Inst::DIV, pc);
+                self.asm.store(Reg::Rbx, -16, Reg::Rax);
+                self.asm.sub_ri(Reg::Rbx, 8);
+            }
+            Inst::ADDI => self.arith_imm(Inst::ADD, pc),
+            Inst::SUBI => self.arith_imm(Inst::SUB, pc),
+            Inst::EQ => self.compare(Inst::EQ, Cond::E, pc),
+            Inst::NE => self.compare(Inst::NE, Cond::Ne, pc),
+            Inst::GT => self.compare(Inst::GT, Cond::G, pc),
+            Inst::GE => self.compare(Inst::GE, Cond::Ge, pc),
+            Inst::LT => self.compare(Inst::LT, Cond::L, pc),
+            Inst::LE => self.compare(Inst::LE, Cond::Le, pc),
+            Inst::JMP => {
+                let dest = self.jump_dest(pc);
+                self.asm.jmp(dest);
+            }
+            Inst::JMP_IF_FALSE => {
+                let dest = self.jump_dest(pc);
+                let next = self.asm.new_label();
+                self.pop(Reg::Rax);
+                // nil, false and uninitialized are the only values which are <= nil
+                // and whose lowest two bits are zero.
+                self.asm.cmp_ri(Reg::Rax, Value::nil().id() as i32);
+                self.asm.jcc(Cond::A, next);
+                self.asm.test_r8i(Reg::Rax, 0b11);
+                self.asm.jcc(Cond::E, dest);
+                self.asm.bind(next);
+            }
+            Inst::END | Inst::RETURN => {
+                self.asm.mov_ri(Reg::Rax, JIT_RETURN);
+                self.asm.jmp(self.epilogue);
+            }
+            Inst::SEND | Inst::SEND_SELF => self.call_vm(jit_send, pc),
+            Inst::GET_CONST => self.call_vm(jit_get_const, pc),
+            Inst::GET_IVAR => self.call_vm(jit_get_ivar, pc),
+            Inst::SET_IVAR => self.call_vm(jit_set_ivar, pc),
+            Inst::IVAR_ADDI => self.call_vm(jit_ivar_addi, pc),
+            _ => self.deopt(pc),
+        }
+    }
+}
+
+// Code generation utilities.
+
+impl<'a> Compiler<'a> {
+    fn push(&mut self, reg: Reg) {
+        self.asm.store(Reg::Rbx, 0, reg);
+        self.asm.add_ri(Reg::Rbx, 8);
+    }
+
+    fn pop(&mut self, reg: Reg) {
+        self.asm.sub_ri(Reg::Rbx, 8);
+        self.asm.load(reg, Reg::Rbx, 0);
+    }
+
+    fn push_imm(&mut self, val: Value) {
+        self.asm.mov_ri(Reg::Rax, val.id());
+        self.push(Reg::Rax);
+    }
+
+    /// Returns the offset of the local variable of GET_LOCAL or SET_LOCAL at `pc`,
+    /// or None if it is not in the array of the current context.
+    fn local_offset(&self, pc: usize) -> Option<i32> {
+        let id = Inst::read32(self.iseq, pc + 1) as usize;
+        let outer = Inst::read32(self.iseq, pc + 5);
+        if outer == 0 && id < LVAR_ARRAY_SIZE {
+            Some(id as i32 * 8)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the label of the destination of the jump instruction at `pc`.
+    fn jump_dest(&self, pc: usize) -> Label {
+        let disp = Inst::read32(self.iseq, pc + 1) as i32 as i64;
+        self.labels[&((pc as i64 + 5 + disp) as usize)]
+    }
+
+    fn deopt_label(&mut self, pc: usize) -> Label {
+        match self.deopts.get(&pc) {
+            Some(label) => *label,
+            None => {
+                let label = self.asm.new_label();
+                self.deopts.insert(pc, label);
+                label
+            }
+        }
+    }
+
+    /// Return to the interpreter which resumes at `pc`.
+    fn deopt(&mut self, pc: usize) {
+        let label = self.deopt_label(pc);
+        self.asm.jmp(label);
+    }
+
+    /// Call a runtime function which executes the instruction at `pc` on the VM.
+    fn call_vm(&mut self, func: VMFunc, pc: usize) {
+        self.asm.mov_rr(Reg::Rdi, Reg::R12);
+        self.asm.mov_rr(Reg::Rsi, Reg::Rbx);
+        self.asm.mov_ri(Reg::Rdx, pc as u64);
+        self.asm.call_abs(func as usize as u64);
+        self.asm.test_rr(Reg::Rax, Reg::Rax);
+        self.asm.jcc(Cond::E, self.error_exit);
+        self.asm.mov_rr(Reg::Rbx, Reg::Rax);
+        self.asm.load(Reg::R13, Reg::R12, FRAME_LVARS);
+    }
+
+    /// Call `jit_binop()` for `inst` with the lhs in rcx and the rhs in rdx,
+    /// and deoptimize at `pc` if it failed. The result is in rax.
+    fn call_binop(&mut self, inst: u8, pc: usize) {
+        self.asm.mov_rr(Reg::Rsi, Reg::Rcx);
+        self.asm.mov_ri(Reg::Rdi, inst as u64);
+        let func: extern "C" fn(u64, u64, u64) -> u64 = jit_binop;
+        self.asm.call_abs(func as usize as u64);
+        self.asm
+            .cmp_ri(Reg::Rax, Value::uninitialized().id() as i32);
+        let deopt = self.deopt_label(pc);
+        self.asm.jcc(Cond::E, deopt);
+    }
+
+    /// Load the two operands of a binary operation into rcx (lhs) and rdx (rhs),
+    /// and jump to `slow` unless both are fixnums.
+    fn load_fixnums(&mut self, slow: Label) {
+        self.asm.load(Reg::Rcx, Reg::Rbx, -16);
+        self.asm.load(Reg::Rdx, Reg::Rbx, -8);
+        self.asm.mov_rr(Reg::Rax, Reg::Rcx);
+        self.asm.and_rr(Reg::Rax, Reg::Rdx);
+        self.asm.test_r8i(Reg::Rax, 1);
+        self.asm.jcc(Cond::E, slow);
+    }
+
+    /// ADD, SUB or MUL.
+    fn arith(&mut self, inst: u8, pc: usize) {
+        let slow = self.asm.new_label();
+        let done = self.asm.new_label();
+        self.load_fixnums(slow);
+        self.asm.mov_rr(Reg::Rax, Reg::Rcx);
+        match inst {
+            Inst::ADD => {
+                self.asm.sub_ri(Reg::Rax, 1);
+                self.asm.add_rr(Reg::Rax, Reg::Rdx);
+                self.asm.jcc(Cond::O, slow);
+            }
+            Inst::SUB => {
+                self.asm.sub_rr(Reg::Rax, Reg::Rdx);
+                self.asm.jcc(Cond::O, slow);
+                self.asm.add_ri(Reg::Rax, 1);
+            }
+            Inst::MUL => {
+                self.asm.sar_ri(Reg::Rax, 1);
+                self.asm.mov_rr(Reg::Rsi, Reg::Rdx);
+                self.asm.sub_ri(Reg::Rsi, 1);
+                self.asm.imul_rr(Reg::Rax, Reg::Rsi);
+                self.asm.jcc(Cond::O, slow);
+                self.asm.or_ri(Reg::Rax, 1);
+            }
+            _ => unreachable!(),
+        }
+        self.asm.store(Reg::Rbx, -16, Reg::Rax);
+        self.asm.sub_ri(Reg::Rbx, 8);
+        self.asm.jmp(done);
+
+        self.asm.bind(slow);
+        self.call_binop(inst, pc);
+        self.asm.store(Reg::Rbx, -16, Reg::Rax);
+        self.asm.sub_ri(Reg::Rbx, 8);
+        self.asm.bind(done);
+    }
+
+    /// ADDI or SUBI (`inst` is ADD or SUB).
+    fn arith_imm(&mut self, inst: u8, pc: usize) {
+        let imm = Inst::read32(self.iseq, pc + 1) as i32;
+        let slow = self.asm.new_label();
+        let done = self.asm.new_label();
+        self.asm.load(Reg::Rcx, Reg::Rbx, -8);
+        if let Some(tagged) = imm.checked_mul(2) {
+            self.asm.test_r8i(Reg::Rcx, 1);
+            self.asm.jcc(Cond::E, slow);
+            self.asm.mov_rr(Reg::Rax, Reg::Rcx);
+            match inst {
+                Inst::ADD => self.asm.add_ri(Reg::Rax, tagged),
+                _ => self.asm.sub_ri(Reg::Rax, tagged),
+            }
+            self.asm.jcc(Cond::O, slow);
+            self.asm.store(Reg::Rbx, -8, Reg::Rax);
+            self.asm.jmp(done);
+        }
+
+        self.asm.bind(slow);
+        self.asm.mov_ri(Reg::Rdx, Value::fixnum(imm as i64).id());
+        self.call_binop(inst, pc);
+        self.asm.store(Reg::Rbx, -8, Reg::Rax);
+        self.asm.bind(done);
+    }
+
+    /// EQ, NE, GT or GE, which is fused with the following JMP_IF_FALSE for fixnums.
+    fn compare(&mut self, inst: u8, cond: Cond, pc: usize) {
+        let slow = self.asm.new_label();
+        let done = self.asm.new_label();
+        self.load_fixnums(slow);
+        if Inst::generic(self.iseq[pc + 1]) == Inst::JMP_IF_FALSE {
+            let dest = self.jump_dest(pc + 1);
+            let next = self.labels[&(pc + 6)];
+            self.asm.sub_ri(Reg::Rbx, 16);
+            self.asm.cmp_rr(Reg::Rcx, Reg::Rdx);
+            self.asm.jcc(cond, next);
+            self.asm.jmp(dest);
+        } else {
+            self.asm.cmp_rr(Reg::Rcx, Reg::Rdx);
+            self.asm.setcc_rax(cond);
+            self.asm.imul_eax_i8(Value::true_val().id() as i8);
+            self.asm.store(Reg::Rbx, -16, Reg::Rax);
+            self.asm.sub_ri(Reg::Rbx, 8);
+            self.asm.jmp(done);
+        }
+
+        // The result is pushed and checked by the following JMP_IF_FALSE if any.
+        self.asm.bind(slow);
+        self.call_binop(inst, pc);
+        self.asm.store(Reg::Rbx, -16, Reg::Rax);
+        self.asm.sub_ri(Reg::Rbx, 8);
+        self.asm.bind(done);
+    }
+}
+
+/// Copy `code` to newly mapped executable memory and returns the entry point.
+/// The memory is never freed.
+pub fn alloc_code(code: &[u8]) -> Option<JitEntry> {
+    unsafe {
+        let page_size = libc::sysconf(libc::_SC_PAGESIZE) as usize;
+        let size = code.len().div_ceil(page_size) * page_size;
+        let ptr = libc::mmap(
+            std::ptr::null_mut(),
+            size,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+            -1,
+            0,
+        );
+        if ptr == libc::MAP_FAILED {
+            return None;
+        }
+        std::ptr::copy_nonoverlapping(code.as_ptr(), ptr as *mut u8, code.len());
+        if libc::mprotect(ptr, size, libc::PROT_READ | libc::PROT_EXEC) != 0 {
+            libc::munmap(ptr, size);
+            return None;
+        }
+        Some(std::mem::transmute::<*mut libc::c_void, JitEntry>(ptr))
+    }
+}
@@ -88,8 +88,27 @@ pub struct ISeqInfo {
     /// Set to Some() in class definition context, or None in the top level.
     pub class_defined: Option<ClassListRef>,
     pub iseq_sourcemap: Vec<(ISeqPos, Loc)>,
+    /// The start of each statement, used for line coverage.
+    pub line_table: Vec<(ISeqPos, Loc)>,
+    pub exception_table: Vec<ExceptionEntry>,
     pub source_info: SourceInfoRef,
+    /// The location where this ISeq was defined.
+    pub loc: Loc,
     pub kind: ISeqKind,
+    /// The number of calls counted until the method is compiled by the JIT.
+    pub jit_count: usize,
+    pub jit_code: Option<JitCode>,
+}
+
+/// An entry of the exception table.
+/// Errors raised in [start, end) are handled by the code at `dest` with the exception
+/// object pushed on the stack, after the stack is restored to the length recorded
+/// by ENTER_RESCUE at the start of the region.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExceptionEntry {
+    pub start: ISeqPos,
+    pub end: ISeqPos,
+    pub dest: ISeqPos,
 }
 
 #[derive(Debug, Clone)]
@@ -101,6 +120,19 @@ pub struct ISeqParams {
     pub block_param: bool,
     pub param_ident: Vec<IdentId>,
     pub keyword_params: HashMap<IdentId, LvarId>,
+    /// Keyword parameters without a default value.
+    pub req_keyword_params: Vec<IdentId>,
+    pub kwrest_param: Option<LvarId>,
+    /// `**nil`: keyword arguments are not accepted.
+    pub no_kw_param: bool,
+}
+
+impl ISeqParams {
+    /// Returns true if the method takes keyword arguments as keywords,
+    /// otherwise they are passed as a Hash in the last positional argument.
+    pub fn accepts_keywords(&self) -> bool {
+        !self.keyword_params.is_empty() || self.kwrest_param.is_some() || self.no_kw_param
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -136,10 +168,16 @@ impl ISeqInfo {
         block_param: bool,
         param_ident: Vec<IdentId>,
         keyword_params: HashMap<IdentId, LvarId>,
+        req_keyword_params: Vec<IdentId>,
+        kwrest_param: Option<LvarId>,
+        no_kw_param: bool,
         iseq: ISeq,
         lvar: LvarCollector,
         iseq_sourcemap: Vec<(ISeqPos, Loc)>,
+        line_table: Vec<(ISeqPos, Loc)>,
+        exception_table: Vec<ExceptionEntry>,
         source_info: SourceInfoRef,
+        loc: Loc,
         kind: ISeqKind,
     ) -> Self {
         let lvars = lvar.len();
@@ -153,14 +191,22 @@ impl ISeqInfo {
                 block_param,
                 param_ident,
                 keyword_params,
+                req_keyword_params,
+                kwrest_param,
+                no_kw_param,
             },
             iseq,
             lvar,
             lvars,
             class_defined: None,
             iseq_sourcemap,
+            line_table,
+            exception_table,
             source_info,
+            loc,
             kind,
+            jit_count: 0,
+            jit_code: None,
         }
     }
 
@@ -175,9 +221,15 @@ impl ISeqInfo {
             vec![],
             std::collections::HashMap::new(),
             vec![],
+            None,
+            false,
+            vec![],
             LvarCollector::new(),
             vec![],
+            vec![],
+            vec![],
             SourceInfoRef::empty(),
+            Loc(0, 0),
             ISeqKind::Method(IdentId::from(0)),
         )
     }
@@ -188,6 +240,56 @@ impl ISeqInfo {
             _ => false,
         }
     }
+
+    /// Returns the label of the ISeq, e.g. "Foo#bar", "block in Foo#bar", "<class:Foo>" or "<main>".
+    pub fn label(&self, globals: &Globals) -> String {
+        let class_name = match self.class_defined {
+            Some(list) => match list.class.as_module() {
+                Some(cref) => Some(globals.get_ident_name(cref.name).to_string()),
+                None => None,
+            },
+            None => None,
+        };
+        match self.kind {
+            ISeqKind::Method(id) => match class_name {
+                Some(class) => format!("{}#{}", class, globals.get_ident_name(id)),
+                None => format!("Object#{}", globals.get_ident_name(id)),
+            },
+            ISeqKind::Block(parent) => match globals.get_method_info(parent) {
+                MethodInfo::RubyFunc { iseq } => format!("block in {}", iseq.label(globals)),
+                _ => "block".to_string(),
+            },
+            ISeqKind::Other => match class_name {
+                Some(class) => format!("<class:{}>", class),
+                None => "<main>".to_string(),
+            },
+        }
+    }
+
+    /// Returns the source line (1-origin) of the instruction at `pc`.
+    /// The nearest preceding entry in the sourcemap is used if `pc` has no entry.
+    pub fn get_line(&self, pc: usize) -> usize {
+        self.source_info.get_line(&self.get_loc(pc))
+    }
+
+    /// Returns the source location of the nearest preceding sourcemap entry of `pc`.
+    pub fn get_loc(&self, pc: usize) -> Loc {
+        self.iseq_sourcemap
+            .iter()
+            .filter(|(pos, _)| pos.to_usize() <= pc)
+            .min_by_key(|(pos, _)| pc - pos.to_usize())
+            .map_or(self.loc, |(_, loc)| *loc)
+    }
+
+    /// Returns the innermost entry of the exception table which covers `pc`.
+    pub fn exception_entry(&self, pc: usize) -> Option<(usize, ExceptionEntry)> {
+        self.exception_table
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| e.start.to_usize() <= pc && pc < e.end.to_usize())
+            .min_by_key(|(i, e)| (e.end.to_usize() - e.start.to_usize(), usize::MAX - i))
+            .map(|(i, e)| (i, *e))
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -0,0 +1,333 @@
+use crate::*;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// A sampling profiler which attributes time to Ruby-level call stacks.
+///
+/// A timer thread requests a sample every `interval`, and the VM takes it at the next
+/// instruction or when a builtin method returns. A sample is the call stack built from
+/// the execution contexts of the VM and the builtin methods being executed.
+/// Calls are not timed, so the profile of small methods is not distorted by the overhead.
+#[derive(Debug)]
+pub struct Profiler {
+    /// Frame labels, indexed by label id.
+    labels: Vec<String>,
+    label_ids: HashMap<String, usize>,
+    /// Label ids of Ruby methods and blocks.
+    method_labels: HashMap<MethodRef, usize>,
+    /// Builtin methods being executed, innermost last.
+    builtins: Vec<BuiltinFrame>,
+    /// Sample counts per call stack (a sequence of label ids from the root).
+    stacks: HashMap<Vec<usize>, u64>,
+    stats: HashMap<usize, MethodStat>,
+    samples: u64,
+    interval: Duration,
+    timer: Instant,
+    sampler: Sampler,
+}
+
+#[derive(Debug, Clone)]
+struct BuiltinFrame {
+    method: MethodRef,
+    self_val: Value,
+    /// The number of execution contexts when the method was called.
+    depth: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+struct MethodStat {
+    /// The number of samples in which the method is the innermost frame.
+    self_samples: u64,
+    /// The number of samples in which the method is on the call stack.
+    total_samples: u64,
+    builtin: bool,
+}
+
+/// Requests a sample every interval from a timer thread, which stops when dropped.
+#[derive(Debug)]
+struct Sampler {
+    requested: Arc<AtomicBool>,
+    stop: Arc<AtomicBool>,
+}
+
+impl Sampler {
+    /// A zero `interval` requests a sample at every chance without the timer thread.
+    fn new(interval: Duration) -> Self {
+        let requested = Arc::new(AtomicBool::new(interval == Duration::default()));
+        let stop = Arc::new(AtomicBool::new(false));
+        if interval != Duration::default() {
+            let (requested, stop) = (requested.clone(), stop.clone());
+            std::thread::spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    std::thread::sleep(interval);
+                    requested.store(true, Ordering::Relaxed);
+                }
+            });
+        }
+        Sampler { requested, stop }
+    }
+}
+
+impl Drop for Sampler {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Profiler::new()
+    }
+}
+
+impl Profiler {
+    pub const INTERVAL: Duration = Duration::from_millis(1);
+
+    pub fn new() -> Self {
+        Profiler::with_interval(Profiler::INTERVAL)
+    }
+
+    pub fn with_interval(interval: Duration) -> Self {
+        Profiler {
+            labels: vec![],
+            label_ids: HashMap::new(),
+            method_labels: HashMap::new(),
+            builtins: vec![],
+            stacks: HashMap::new(),
+            stats: HashMap::new(),
+            samples: 0,
+            interval,
+            timer: Instant::now(),
+            sampler: Sampler::new(interval),
+        }
+    }
+
+    /// Push a frame if `methodref` is a builtin method, which is called with the receiver
+    /// `self_val` when the VM has `depth` execution contexts.
+    /// Returns true if the frame was pushed.
+    pub fn enter_builtin(
+        &mut self,
+        globals: &Globals,
+        methodref: MethodRef,
+        self_val: Value,
+        depth: usize,
+    ) -> bool {
+        match globals.get_method_info(methodref) {
+            MethodInfo::BuiltinFunc { .. } => {
+                self.builtins.push(BuiltinFrame {
+                    method: methodref,
+                    self_val,
+                    depth,
+                });
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Pop the frame pushed by the last `enter_builtin()`.
+    pub fn exit_builtin(&mut self) {
+        self.builtins.pop();
+    }
+
+    /// Take a sample of the call stack whose Ruby-level frames are `contexts`,
+    /// if the timer has requested it.
+    pub fn sample(&mut self, globals: &Globals, contexts: &[ContextRef]) {
+        if !self.sampler.requested.swap(false, Ordering::Relaxed) {
+            return;
+        }
+        if self.interval == Duration::default() {
+            self.sampler.requested.store(true, Ordering::Relaxed);
+        }
+        // A builtin called when the VM had `depth` contexts comes after the context `depth - 1`.
+        let builtins = self.builtins.clone();
+        let mut next = 0;
+        let mut path = vec![];
+        for (i, context) in contexts.iter().enumerate() {
+            while next < builtins.len() && builtins[next].depth <= i {
+                path.push(self.builtin_label(globals, &builtins[next]));
+                next += 1;
+            }
+            path.push(self.method_label(globals, context.iseq_ref.method));
+        }
+        for frame in &builtins[next..] {
+            path.push(self.builtin_label(globals, frame));
+        }
+        let last = match path.last() {
+            Some(label) => *label,
+            None => return,
+        };
+        self.samples += 1;
+        self.stats.entry(last).or_default().self_samples += 1;
+        let mut counted = vec![];
+        for label in &path {
+            // Recursive calls are counted once.
+            if !counted.contains(label) {
+                counted.push(*label);
+                self.stats.entry(*label).or_default().total_samples += 1;
+            }
+        }
+        *self.stacks.entry(path).or_default() += 1;
+    }
+
+    /// Returns the call stacks in collapsed-stack (folded) format.
+    /// Each line consists of frame labels separated by ';' and the number of samples.
+    pub fn folded(&self) -> String {
+        let mut lines: Vec<String> = self
+            .stacks
+            .iter()
+            .map(|(path, count)| {
+                let frames: Vec<&str> = path.iter().map(|id| self.labels[*id].as_str()).collect();
+                format!("{} {}", frames.join(";"), count)
+            })
+            .collect();
+        lines.sort();
+        let mut s = lines.join("\n");
+        s.push('\n');
+        s
+    }
+
+    /// Returns a summary of methods and builtins sorted by self time,
+    /// which is estimated from the number of samples.
+    pub fn summary(&self) -> String {
+        let whole = self.timer.elapsed().as_secs_f64() * 1000.0;
+        let ms = |samples: u64| samples as f64 * self.interval.as_secs_f64() * 1000.0;
+        let percent = |samples: u64| {
+            if self.samples == 0 {
+                0.0
+            } else {
+                samples as f64 / self.samples as f64 * 100.0
+            }
+        };
+        let mut stats: Vec<(&usize, &MethodStat)> = self.stats.iter().collect();
+        stats.sort_by(|(id1, s1), (id2, s2)| {
+            s2.self_samples
+                .cmp(&s1.self_samples)
+                .then(self.labels[**id1].cmp(&self.labels[**id2]))
+        });
+        let mut s = format!("Profile: {:.3} ms, {} samples\n", whole, self.samples);
+        s += &format!(
+            "{:>12} {:>6} {:>12} {:>6} {:>10}  {}\n",
+            "self(ms)", "%", "total(ms)", "%", "samples", "method"
+        );
+        for (id, stat) in stats {
+            s += &format!(
+                "{:>12.3} {:>6.1} {:>12.3} {:>6.1} {:>10}  {}{}\n",
+                ms(stat.self_samples),
+                percent(stat.self_samples),
+                ms(stat.total_samples),
+                percent(stat.total_samples),
+                stat.self_samples,
+                self.labels[*id],
+                if stat.builtin { " [builtin]" } else { "" }
+            );
+        }
+        s
+    }
+}
+
+// Utils
+
+impl Profiler {
+    fn intern(&mut self, label: String) -> usize {
+        match self.label_ids.get(&label) {
+            Some(id) => *id,
+            None => {
+                let id = self.labels.len();
+                self.labels.push(label.clone());
+                self.label_ids.insert(label, id);
+                id
+            }
+        }
+    }
+
+    /// Returns the label id of a Ruby method or block, e.g. "Foo#bar (foo.rb:3)".
+    fn method_label(&mut self, globals: &Globals, methodref: MethodRef) -> usize {
+        if let Some(id) = self.method_labels.get(&methodref) {
+            return *id;
+        }
+        let label = match globals.get_method_info(methodref) {
+            MethodInfo::RubyFunc { iseq } => {
+                let line = iseq.source_info.get_line(&iseq.loc);
+                format!(
+                    "{} ({}:{})",
+                    iseq.label(globals),
+                    iseq.source_info.display_path(),
+                    line
+                )
+            }
+            _ => unreachable!(),
+        };
+        let id = self.intern(label);
+        self.method_labels.insert(methodref, id);
+        id
+    }
+
+    /// Returns the label id of a builtin method, e.g. "Integer#times" or "Math.sqrt".
+    fn builtin_label(&mut self, globals: &Globals, frame: &BuiltinFrame) -> usize {
+        let name = match globals.get_method_info(frame.method) {
+            MethodInfo::BuiltinFunc { name, .. } => name,
+            _ => unreachable!(),
+        };
+        let label = match frame.self_val.as_module() {
+            Some(cref) => format!("{}.{}", globals.get_ident_name(cref.name), name),
+            None => format!("{}#{}", globals.get_class_name(frame.self_val), name),
+        };
+        let id = self.intern(label);
+        self.stats.entry(id).or_default().builtin = true;
+        id
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    #[test]
+    fn profiler() {
+        let program = r#"
+        class Foo
+          def bar(n)
+            n.times { baz }
+          end
+          def baz
+            Math.sqrt(2)
+          end
+        end
+        Foo.new.bar(3)
+        "#;
+        let mut vm = VM::new();
+        // Take a sample at every instruction and every return from builtin methods.
+        vm.profiler = Some(Profiler::with_interval(Duration::default()));
+        vm.run(PathBuf::from("profile.rb"), program, None).unwrap();
+        let profiler = vm.profiler.take().unwrap();
+        let stacks: Vec<String> = profiler
+            .folded()
+            .lines()
+            .map(|line| line.rsplitn(2, ' ').nth(1).unwrap().to_string())
+            .collect();
+        let main = "<main> (profile.rb:1)";
+        let bar = "Foo#bar (profile.rb:3)";
+        let block = "block in Foo#bar (profile.rb:4)";
+        let baz = "Foo#baz (profile.rb:6)";
+        for expected in &[
+            format!("{};<class:Foo> (profile.rb:2)", main),
+            format!("{};Foo.new", main),
+            format!("{};{};Integer#times;{};{};Math.sqrt", main, bar, block, baz),
+        ] {
+            assert!(
+                stacks.contains(expected),
+                "{} not in {:?}",
+                expected,
+                stacks
+            );
+        }
+        let summary = profiler.summary();
+        assert!(summary.lines().any(|l| l.ends_with(baz)));
+        assert!(summary.contains("Math.sqrt [builtin]"));
+    }
+}
@@ -35,6 +35,9 @@ impl Inst {
     pub const SUBI: u8 = 31;
     pub const IVAR_ADDI: u8 = 32;
 
+    pub const LT: u8 = 36;
+    pub const LE: u8 = 37;
+
     pub const SET_LOCAL: u8 = 40;
     pub const GET_LOCAL: u8 = 41;
     pub const GET_CONST: u8 = 42;
@@ -59,6 +62,8 @@ impl Inst {
     pub const CREATE_PROC: u8 = 72;
     pub const CREATE_HASH: u8 = 73;
     pub const CREATE_REGEXP: u8 = 74;
+    pub const CREATE_RATIONAL: u8 = 75;
+    pub const CREATE_COMPLEX: u8 = 76;
 
     pub const POP: u8 = 80;
     pub const DUP: u8 = 81;
@@ -66,10 +71,16 @@ impl Inst {
     pub const SPLAT: u8 = 83;
     pub const CONCAT_STRING: u8 = 84;
     pub const TO_S: u8 = 85;
+    pub const TOPN: u8 = 86;
+    pub const DOUBLE_SPLAT: u8 = 87;
+    pub const SETN: u8 = 88;
+    pub const EXPAND_ARRAY: u8 = 89;
 
     pub const DEF_CLASS: u8 = 90;
     pub const DEF_METHOD: u8 = 91;
     pub const DEF_SMETHOD: u8 = 92;
+    pub const ALIAS: u8 = 93;
+    pub const UNDEF: u8 = 94;
 
     pub const JMP: u8 = 100;
     pub const JMP_IF_FALSE: u8 = 101;
@@ -78,6 +89,60 @@ impl Inst {
     pub const OPT_CASE: u8 = 104;
     pub const MRETURN: u8 = 105;
     pub const YIELD: u8 = 106;
+    pub const ENTER_RESCUE: u8 = 107;
+    pub const RESCUE: u8 = 108;
+    pub const RAISE: u8 = 109;
+    pub const DECONSTRUCT: u8 = 110;
+    pub const DECONSTRUCT_KEYS: u8 = 111;
+    pub const DEFINED: u8 = 112;
+
+    // Quickened instructions.
+    // They are rewritten in place from generic instructions after the first execution,
+    // and have the same size and operands as the original instruction.
+    // Fused ones skip the following original instructions, which are left as they are.
+    pub const SEND_BUILTIN: u8 = 120; // SEND with a builtin method in the inline cache
+    pub const SEND_SELF_BUILTIN: u8 = 121; // SEND_SELF with a builtin method in the inline cache
+    pub const SEND_ATTR_READER: u8 = 122; // SEND with an attr_reader in the inline cache
+    pub const JMP_F_EQ: u8 = 123; // EQ; JMP_IF_FALSE
+    pub const JMP_F_NE: u8 = 124; // NE; JMP_IF_FALSE
+    pub const JMP_F_GT: u8 = 125; // GT; JMP_IF_FALSE
+    pub const JMP_F_GE: u8 = 126; // GE; JMP_IF_FALSE
+    pub const ADD_LOCALS: u8 = 127; // GET_LOCAL; GET_LOCAL; ADD
+    pub const SUB_LOCALS: u8 = 128; // GET_LOCAL; GET_LOCAL; SUB
+    pub const SEND_RUBY: u8 = 129; // SEND with a Ruby method with only required params in the inline cache
+    pub const SEND_SELF_RUBY: u8 = 130; // SEND_SELF with a Ruby method with only required params in the inline cache
+    pub const JMP_F_LT: u8 = 131; // LT; JMP_IF_FALSE
+    pub const JMP_F_LE: u8 = 132; // LE; JMP_IF_FALSE
+}
+
+/// Kinds of the expression checked by DEFINED.
+pub struct DefinedKind;
+impl DefinedKind {
+    pub const IVAR: u32 = 0;
+    pub const GVAR: u32 = 1;
+    pub const CONST: u32 = 2;
+    /// A constant in the module on the top of the stack.
+    pub const SCOPED_CONST: u32 = 3;
+    /// A public method of the receiver on the top of the stack.
+    pub const METHOD: u32 = 4;
+    /// A method of self.
+    pub const FUNC: u32 = 5;
+    pub const YIELD: u32 = 6;
+    /// The superclass method of the current method.
+    pub const SUPER: u32 = 7;
+
+    /// Returns the result of `defined?` for `kind`.
+    pub fn name(kind: u32) -> &'static str {
+        match kind {
+            DefinedKind::IVAR => "instance-variable",
+            DefinedKind::GVAR => "global-variable",
+            DefinedKind::CONST | DefinedKind::SCOPED_CONST => "constant",
+            DefinedKind::METHOD | DefinedKind::FUNC => "method",
+            DefinedKind::YIELD => "yield",
+            DefinedKind::SUPER => "super",
+            _ => unreachable!("Illegal kind of DEFINED: {}", kind),
+        }
+    }
 }
 
 #[allow(dead_code)]
@@ -103,6 +168,8 @@ impl Inst {
             Inst::TEQ => "TEQ",
             Inst::GT => "GT",
             Inst::GE => "GE",
+            Inst::LT => "LT",
+            Inst::LE => "LE",
             Inst::CMP => "CMP",
             Inst::NOT => "NOT",
             Inst::SHR => "SHR",
@@ -141,6 +208,8 @@ impl Inst {
             Inst::CREATE_PROC => "CREATE_PROC",
             Inst::CREATE_HASH => "CREATE_HASH",
             Inst::CREATE_REGEXP => "CREATE_REGEX",
+            Inst::CREATE_RATIONAL => "CREATE_RATIONAL",
+            Inst::CREATE_COMPLEX => "CREATE_COMPLEX",
 
             Inst::POP => "POP",
             Inst::DUP => "DUP",
@@ -148,6 +217,13 @@ impl Inst {
             Inst::SPLAT => "SPLAT",
             Inst::CONCAT_STRING => "CONCAT_STR",
             Inst::TO_S => "TO_S",
+            Inst::TOPN => "TOPN",
+            Inst::DOUBLE_SPLAT => "DOUBLE_SPLAT",
+            Inst::SETN => "SETN",
+            Inst::EXPAND_ARRAY => "EXPAND_ARRAY",
+            Inst::ALIAS => "ALIAS",
+            Inst::UNDEF => "UNDEF",
+            Inst::DEFINED => "DEFINED",
 
             Inst::DEF_CLASS => "DEF_CLASS",
             Inst::DEF_METHOD => "DEF_METHOD",
@@ -160,6 +236,25 @@ impl Inst {
             Inst::OPT_CASE => "OPT_CASE",
             Inst::MRETURN => "MRETURN",
             Inst::YIELD => "YIELD",
+            Inst::ENTER_RESCUE => "ENTER_RESCUE",
+            Inst::RESCUE => "RESCUE",
+            Inst::RAISE => "RAISE",
+            Inst::DECONSTRUCT => "DECONSTRUCT",
+            Inst::DECONSTRUCT_KEYS => "DECONSTRUCT_KEYS",
+
+            Inst::SEND_BUILTIN => "SEND_BUILTIN",
+            Inst::SEND_SELF_BUILTIN => "SEND_SELF_BUILTIN",
+            Inst::SEND_ATTR_READER => "SEND_ATTR_READER",
+            Inst::JMP_F_EQ => "JMP_F_EQ",
+            Inst::JMP_F_NE => "JMP_F_NE",
+            Inst::JMP_F_GT => "JMP_F_GT",
+            Inst::JMP_F_GE => "JMP_F_GE",
+            Inst::JMP_F_LT => "JMP_F_LT",
+            Inst::JMP_F_LE => "JMP_F_LE",
+            Inst::ADD_LOCALS => "ADD_LOCALS",
+            Inst::SUB_LOCALS => "SUB_LOCALS",
+            Inst::SEND_RUBY => "SEND_RUBY",
+            Inst::SEND_SELF_RUBY => "SEND_SELF_RUBY",
 
             _ => "undefined",
         }
@@ -179,6 +274,8 @@ impl Inst {
             | Inst::NE
             | Inst::GT
             | Inst::GE
+            | Inst::LT
+            | Inst::LE
             | Inst::CMP
             | Inst::NOT
             | Inst::SHR
@@ -189,11 +286,22 @@ impl Inst {
             | Inst::CONCAT_STRING
             | Inst::CREATE_RANGE
             | Inst::CREATE_REGEXP
+            | Inst::CREATE_RATIONAL
+            | Inst::CREATE_COMPLEX
             | Inst::TO_S
             | Inst::SPLAT
+            | Inst::DOUBLE_SPLAT
             | Inst::POP
             | Inst::RETURN
-            | Inst::MRETURN => 1,
+            | Inst::MRETURN
+            | Inst::RAISE
+            | Inst::DECONSTRUCT_KEYS
+            | Inst::JMP_F_EQ
+            | Inst::JMP_F_NE
+            | Inst::JMP_F_GT
+            | Inst::JMP_F_GE
+            | Inst::JMP_F_LT
+            | Inst::JMP_F_LE => 1,
                                         // operand
             Inst::PUSH_STRING           // IdentId: u32
             | Inst::PUSH_SYMBOL         // IdentId: u32
@@ -201,8 +309,6 @@ impl Inst {
             | Inst::SET_CONST           // IdentId: u32
             | Inst::GET_CONST_TOP       // IdentId: u32
             | Inst::GET_SCOPE           // IdentId: u32
-            | Inst::GET_IVAR            // IdentId: u32
-            | Inst::SET_IVAR            // IdentId: u32
             | Inst::GET_GVAR            // IdentId: u32
             | Inst::SET_GVAR            // IdentId: u32
             | Inst::GET_INDEX
@@ -213,6 +319,8 @@ impl Inst {
             | Inst::JMP_IF_FALSE        // disp: u32
             | Inst::DUP                 // number of items: u32
             | Inst::TAKE                // number of items: u32
+            | Inst::TOPN                // position from the top: u32
+            | Inst::SETN                // position from the top: u32
             | Inst::ADD                 // inline cache: u32
             | Inst::SUB                 // inline cache: u32
             | Inst::MUL                 // inline cache: u32
@@ -221,43 +329,96 @@ impl Inst {
             | Inst::SHL                 // inline cache: u32
             | Inst::CREATE_HASH         // number of items: u32
             | Inst::YIELD               // number of items: u32
+            | Inst::ENTER_RESCUE        // exception table index: u32
+            | Inst::RESCUE              // number of classes: u32
+            | Inst::UNDEF               // IdentId: u32
             => 5,
 
             Inst::PUSH_FIXNUM
             | Inst::PUSH_FLONUM
             | Inst::SET_LOCAL
             | Inst::GET_LOCAL
+            | Inst::CHECK_LOCAL
             | Inst::DEF_METHOD
             | Inst::DEF_SMETHOD
             | Inst::OPT_CASE
-            | Inst::IVAR_ADDI => 9,
+            | Inst::DECONSTRUCT         // number of items: u32, exact: u32
+            | Inst::EXPAND_ARRAY        // number of items before/after the splat: u32, u32
+            | Inst::GET_IVAR            // IdentId: u32, ivar cache: u32
+            | Inst::SET_IVAR            // IdentId: u32, ivar cache: u32
+            | Inst::ALIAS               // new IdentId: u32, old IdentId: u32
+            | Inst::DEFINED             // IdentId: u32, kind: u32
+            | Inst::ADD_LOCALS
+            | Inst::SUB_LOCALS => 9,
             Inst::DEF_CLASS => 10,
-            Inst::SEND | Inst::SEND_SELF => 17,
+            Inst::IVAR_ADDI => 13,      // IdentId: u32, immediate: i32, ivar cache: u32
+            Inst::SEND
+            | Inst::SEND_SELF
+            | Inst::SEND_BUILTIN
+            | Inst::SEND_SELF_BUILTIN
+            | Inst::SEND_ATTR_READER
+            | Inst::SEND_RUBY
+            | Inst::SEND_SELF_RUBY => 17,
             _ => 1,
         }
     }
 
-    pub fn inst_info(globals: &mut Globals, iseq_ref: ISeqRef, pc: usize) -> String {
+    /// Returns the generic instruction from which the quickened `inst` was rewritten,
+    /// or `inst` itself if it is not quickened.
+    pub fn generic(inst: u8) -> u8 {
+        match inst {
+            Inst::SEND_BUILTIN | Inst::SEND_ATTR_READER | Inst::SEND_RUBY => Inst::SEND,
+            Inst::SEND_SELF_BUILTIN | Inst::SEND_SELF_RUBY => Inst::SEND_SELF,
+            Inst::JMP_F_EQ => Inst::EQ,
+            Inst::JMP_F_NE => Inst::NE,
+            Inst::JMP_F_GT => Inst::GT,
+            Inst::JMP_F_GE => Inst::GE,
+            Inst::JMP_F_LT => Inst::LT,
+            Inst::JMP_F_LE => Inst::LE,
+            Inst::ADD_LOCALS | Inst::SUB_LOCALS => Inst::GET_LOCAL,
+            inst => inst,
+        }
+    }
+
+    /// Returns a human-readable form of the instruction at `pc`.
+    /// `outer` is a list of the ISeqs which enclose `iseq_ref`, innermost first,
+    /// which is used for resolving local variable names in outer scopes.
+    pub fn inst_info(globals: &Globals, iseq_ref: ISeqRef, outer: &[ISeqRef], pc: usize) -> String {
         let iseq = &iseq_ref.iseq;
-        match iseq[pc] {
+        let lvar_name = |pc: usize| {
+            let id = Inst::read32(iseq, pc + 1) as usize;
+            let frame = Inst::read32(iseq, pc + 5) as usize;
+            let scope = if frame == 0 {
+                Some(iseq_ref)
+            } else {
+                outer.get(frame - 1).cloned()
+            };
+            let name = scope
+                .and_then(|scope| scope.lvar.get_name(LvarId::from_usize(id)))
+                .map_or("?", |id| globals.get_ident_name(id));
+            format!("'{}' outer:{} LvarId:{}", name, frame, id)
+        };
+        // Quickened instructions are shown as the original ones.
+        let inst = Inst::generic(iseq[pc]);
+        match inst {
             Inst::END
             | Inst::PUSH_NIL
             | Inst::PUSH_TRUE
             | Inst::PUSH_FALSE
             | Inst::PUSH_SELF
-            | Inst::ADD
-            | Inst::SUB
-            | Inst::MUL
             | Inst::DIV
             | Inst::REM
+            | Inst::POW
             | Inst::EQ
             | Inst::NE
+            | Inst::TEQ
             | Inst::GT
             | Inst::GE
+            | Inst::LT
+            | Inst::LE
             | Inst::CMP
             | Inst::NOT
             | Inst::SHR
-            | Inst::SHL
             | Inst::BIT_OR
             | Inst::BIT_AND
             | Inst::BIT_XOR
@@ -265,13 +426,29 @@ impl Inst {
             | Inst::CONCAT_STRING
             | Inst::CREATE_RANGE
             | Inst::CREATE_REGEXP
+            | Inst::CREATE_RATIONAL
+            | Inst::CREATE_COMPLEX
             | Inst::RETURN
+            | Inst::MRETURN
+            | Inst::RAISE
+            | Inst::DECONSTRUCT_KEYS
             | Inst::TO_S
             | Inst::SPLAT
-            | Inst::POP
-            | Inst::YIELD => format!("{}", Inst::inst_name(iseq[pc])),
-            Inst::PUSH_STRING => format!("PUSH_STRING {}", Inst::read32(iseq, pc + 1) as i32),
-            Inst::PUSH_SYMBOL => format!("PUSH_SYMBOL {}", Inst::read32(iseq, pc + 1) as i32),
+            | Inst::DOUBLE_SPLAT
+            | Inst::POP => format!("{}", Inst::inst_name(inst)),
+            Inst::ADD | Inst::SUB | Inst::MUL | Inst::SHL => format!(
+                "{} cache:{}",
+                Inst::inst_name(inst),
+                Inst::read32(iseq, pc + 1)
+            ),
+            Inst::YIELD => format!("YIELD {} items", Inst::read32(iseq, pc + 1)),
+            Inst::ENTER_RESCUE => format!("ENTER_RESCUE {}", Inst::read32(iseq, pc + 1)),
+            Inst::RESCUE => format!("RESCUE {} classes", Inst::read32(iseq, pc + 1)),
+            Inst::PUSH_STRING => format!(
+                "PUSH_STRING {:?}",
+                Inst::ident_name(globals, iseq, pc + 1)
+            ),
+            Inst::PUSH_SYMBOL => format!("PUSH_SYMBOL :{}", Inst::ident_name(globals, iseq, pc + 1)),
             Inst::ADDI => format!("ADDI {}", Inst::read32(iseq, pc + 1) as i32),
             Inst::SUBI => format!("SUBI {}", Inst::read32(iseq, pc + 1) as i32),
             Inst::PUSH_FIXNUM => format!("PUSH_FIXNUM {}", Inst::read64(iseq, pc + 1) as i64),
@@ -287,39 +464,14 @@ impl Inst {
                 "JMP_IF_FALSE {:>05x}",
                 pc as i32 + 5 + Inst::read32(iseq, pc + 1) as i32
             ),
-            Inst::OPT_CASE => {
-                //let val = Value::from(Inst::read64(iseq, pc + 1));
-                //let info = val.as_hash().unwrap();
-                /*let map = match info.inner_mut() {
-                    HashInfo::Map(map) => map,
-                    _ => panic!(),
-                };*/
-                format!(
-                    "OPT_CASE {:>05}",
-                    pc as i32 + 13 + Inst::read32(iseq, pc + 9) as i32,
-                )
-            }
-            Inst::SET_LOCAL => {
-                let frame = Inst::read32(iseq, pc + 5);
-                let id = Inst::read32(iseq, pc + 1) as usize;
-                let ident_id = iseq_ref.lvar.get_name(LvarId::from_usize(id));
-                let name = globals.get_ident_name(ident_id);
-                format!("SET_LOCAL '{}' outer:{} LvarId:{}", name, frame, id)
-            }
-            Inst::GET_LOCAL => {
-                let frame = Inst::read32(iseq, pc + 5);
-                let id = Inst::read32(iseq, pc + 1) as usize;
-                let ident_id = iseq_ref.lvar.get_name(LvarId::from_usize(id));
-                let name = globals.get_ident_name(ident_id);
-                format!("GET_LOCAL '{}' outer:{} LvarId:{}", name, frame, id)
-            }
-            Inst::CHECK_LOCAL => {
-                let frame = Inst::read32(iseq, pc + 5);
-                let id = Inst::read32(iseq, pc + 1) as usize;
-                let ident_id = iseq_ref.lvar.get_name(LvarId::from_usize(id));
-                let name = globals.get_ident_name(ident_id);
-                format!("CHECK_LOCAL '{}' outer:{} LvarId:{}", name, frame, id)
-            }
+            Inst::OPT_CASE => format!(
+                "OPT_CASE map:{} else:{:>05x}",
+                Inst::read32(iseq, pc + 1),
+                pc as i32 + 9 + Inst::read32(iseq, pc + 5) as i32,
+            ),
+            Inst::SET_LOCAL => format!("SET_LOCAL {}", lvar_name(pc)),
+            Inst::GET_LOCAL => format!("GET_LOCAL {}", lvar_name(pc)),
+            Inst::CHECK_LOCAL => format!("CHECK_LOCAL {}", lvar_name(pc)),
             Inst::GET_CONST => format!("GET_CONST '{}'", Inst::ident_name(globals, iseq, pc + 1)),
             Inst::GET_CONST_TOP => format!(
                 "GET_CONST_TOP '{}'",
@@ -327,26 +479,73 @@ impl Inst {
             ),
             Inst::SET_CONST => format!("SET_CONST '{}'", Inst::ident_name(globals, iseq, pc + 1)),
             Inst::GET_SCOPE => format!("GET_SCOPE '{}'", Inst::ident_name(globals, iseq, pc + 1)),
-            Inst::GET_IVAR => format!("GET_IVAR '{}'", Inst::ident_name(globals, iseq, pc + 1)),
-            Inst::SET_IVAR => format!("SET_IVAR '{}'", Inst::ident_name(globals, iseq, pc + 1)),
-            Inst::GET_INDEX => format!("GET_INDEX {} items", Inst::read32(iseq, pc + 1)),
-            Inst::SET_INDEX => format!("SET_INDEX {} items", Inst::read32(iseq, pc + 1)),
-            Inst::SEND => format!(
-                "SEND '{}' {} items",
+            Inst::GET_IVAR | Inst::SET_IVAR => format!(
+                "{} '{}' cache:{}",
+                Inst::inst_name(inst),
                 Inst::ident_name(globals, iseq, pc + 1),
                 Inst::read32(iseq, pc + 5)
             ),
-            Inst::SEND_SELF => format!(
-                "SEND_SELF '{}' {} items",
+            Inst::IVAR_ADDI => format!(
+                "IVAR_ADDI '{}' {} cache:{}",
                 Inst::ident_name(globals, iseq, pc + 1),
-                Inst::read32(iseq, pc + 5)
+                Inst::read32(iseq, pc + 5) as i32,
+                Inst::read32(iseq, pc + 9)
             ),
+            Inst::GET_GVAR => format!("GET_GVAR '{}'", Inst::ident_name(globals, iseq, pc + 1)),
+            Inst::SET_GVAR => format!("SET_GVAR '{}'", Inst::ident_name(globals, iseq, pc + 1)),
+            Inst::GET_INDEX => format!("GET_INDEX {} items", Inst::read32(iseq, pc + 1)),
+            Inst::SET_INDEX => format!("SET_INDEX {} items", Inst::read32(iseq, pc + 1)),
+            Inst::SEND | Inst::SEND_SELF => {
+                let flag = Inst::read16(iseq, pc + 7);
+                let block = Inst::read32(iseq, pc + 13);
+                format!(
+                    "{} '{}' {} items{}{} cache:{}{}",
+                    Inst::inst_name(inst),
+                    Inst::ident_name(globals, iseq, pc + 1),
+                    Inst::read16(iseq, pc + 5),
+                    if flag & 0b01 != 0 { " kw" } else { "" },
+                    if flag & 0b10 != 0 { " &block" } else { "" },
+                    Inst::read32(iseq, pc + 9),
+                    if block != 0 {
+                        format!(" block:{}", block)
+                    } else {
+                        "".to_string()
+                    }
+                )
+            }
 
             Inst::CREATE_ARRAY => format!("CREATE_ARRAY {} items", Inst::read32(iseq, pc + 1)),
             Inst::CREATE_PROC => format!("CREATE_PROC method:{}", Inst::read32(iseq, pc + 1)),
             Inst::CREATE_HASH => format!("CREATE_HASH {} items", Inst::read32(iseq, pc + 1)),
             Inst::DUP => format!("DUP {}", Inst::read32(iseq, pc + 1)),
             Inst::TAKE => format!("TAKE {}", Inst::read32(iseq, pc + 1)),
+            Inst::TOPN => format!("TOPN {}", Inst::read32(iseq, pc + 1)),
+            Inst::SETN => format!("SETN {}", Inst::read32(iseq, pc + 1)),
+            Inst::ALIAS => format!(
+                "ALIAS '{}' '{}'",
+                Inst::ident_name(globals, iseq, pc + 1),
+                Inst::ident_name(globals, iseq, pc + 5)
+            ),
+            Inst::UNDEF => format!("UNDEF '{}'", Inst::ident_name(globals, iseq, pc + 1)),
+            Inst::DEFINED => format!(
+                "DEFINED {} '{}'",
+                DefinedKind::name(Inst::read32(iseq, pc + 5)),
+                Inst::ident_name(globals, iseq, pc + 1)
+            ),
+            Inst::EXPAND_ARRAY => format!(
+                "EXPAND_ARRAY {} *rest {}",
+                Inst::read32(iseq, pc + 1),
+                Inst::read32(iseq, pc + 5)
+            ),
+            Inst::DECONSTRUCT => format!(
+                "DECONSTRUCT {}{} items",
+                if Inst::read32(iseq, pc + 5) == 0 {
+                    ">= "
+                } else {
+                    ""
+                },
+                Inst::read32(iseq, pc + 1)
+            ),
             Inst::DEF_CLASS => format!(
                 "DEF_CLASS {} '{}' method:{}",
                 if Inst::read8(iseq, pc + 1) == 1 {
@@ -355,26 +554,37 @@ impl Inst {
                     "class"
                 },
                 Inst::ident_name(globals, iseq, pc + 2),
-                Inst::read32(iseq, pc)
+                Inst::read32(iseq, pc + 6)
+            ),
+            Inst::DEF_METHOD => format!(
+                "DEF_METHOD '{}' method:{}",
+                Inst::ident_name(globals, iseq, pc + 1),
+                Inst::read32(iseq, pc + 5)
+            ),
+            Inst::DEF_SMETHOD => format!(
+                "DEF_SMETHOD '{}' method:{}",
+                Inst::ident_name(globals, iseq, pc + 1),
+                Inst::read32(iseq, pc + 5)
             ),
-            Inst::DEF_METHOD => format!("DEF_METHOD '{}'", Inst::ident_name(globals, iseq, pc + 1)),
-            Inst::DEF_SMETHOD => {
-                format!("DEF_SMETHOD '{}'", Inst::ident_name(globals, iseq, pc + 1))
-            }
             _ => format!("undefined"),
         }
     }
 
-    fn read64(iseq: &ISeq, pc: usize) -> u64 {
+    pub fn read64(iseq: &ISeq, pc: usize) -> u64 {
         let ptr = iseq[pc..pc + 1].as_ptr() as *const u64;
         unsafe { *ptr }
     }
 
-    fn read32(iseq: &ISeq, pc: usize) -> u32 {
+    pub fn read32(iseq: &ISeq, pc: usize) -> u32 {
         let ptr = iseq[pc..pc + 1].as_ptr() as *const u32;
         unsafe { *ptr }
     }
 
+    fn read16(iseq: &ISeq, pc: usize) -> u16 {
+        let ptr = iseq[pc..pc + 1].as_ptr() as *const u16;
+        unsafe { *ptr }
+    }
+
     fn read8(iseq: &ISeq, pc: usize) -> u8 {
         iseq[pc]
     }
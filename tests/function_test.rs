@@ -102,6 +102,45 @@ fn parameters() {
     assert_script(program);
 }
 
+#[test]
+fn keyword_parameters() {
+    let program = r#"
+        def fn(a, b: 2, c:, **opts)
+            [a, b, c, opts]
+        end
+        assert([1, 2, 3, {}], fn(1, c: 3))
+        assert([1, 4, 3, {d: 5}], fn(1, c: 3, b: 4, d: 5))
+        def err
+            yield
+        rescue ArgumentError => e
+            e.message
+        end
+        def g(a:, b:)
+            [a, b]
+        end
+        assert("missing keywords: :a, :b", err { g })
+        assert("missing keyword: :a", err { g(b: 2) })
+        assert("unknown keyword: :x", err { g(a: 1, b: 2, x: 3) })
+        def n(a, **nil)
+            a
+        end
+        assert(1, n(1))
+        assert("no keywords accepted", err { n(1, x: 2) })
+        def h(h, **kw)
+            [h, kw]
+        end
+        assert([{a: 1}, {}], h({a: 1}))
+        assert([{a: 1}, {b: 2}], h({a: 1}, b: 2))
+        def pos(h)
+            h
+        end
+        assert({a: 1}, pos(a: 1))
+        l = ->(x, y: 1, **z) { [x, y, z] }
+        assert([0, 2, {w: 3}], l.call(0, y: 2, w: 3))
+    "#;
+    assert_script(program);
+}
+
 #[test]
 fn return1() {
     let program = "
@@ -137,3 +176,30 @@ fn splat_argument() {
     "#;
     assert_script(program);
 }
+
+#[test]
+fn double_splat_argument() {
+    let program = r#"
+        def foo(a = nil, **kw)
+            [a, kw]
+        end
+        h = {b: 2, c: 3}
+        assert [nil, {b: 2, c: 3}], foo(**h)
+        assert [1, {a: 0, b: 2, c: 3}], foo(1, a: 0, **h)
+        assert [nil, {b: 2, c: 4}], foo(**h, **{c: 4})
+        assert [nil, {}], foo(**{})
+        assert [nil, {}], foo(**nil)
+        assert({b: 2, c: 3}, h)
+        def bar(h)
+            h
+        end
+        assert({b: 2, c: 3}, bar(**h))
+        r = begin
+            foo(**1)
+        rescue TypeError => e
+            e.message
+        end
+        assert "no implicit conversion of Integer into Hash", r
+    "#;
+    assert_script(program);
+}
@@ -218,7 +218,7 @@ fn objects() {
         assert(nil, Object.superclass)
         assert(Object, Module.superclass)
         assert(Module, Class.superclass)
-        assert(Object, Integer.superclass)
+        assert(Numeric, Integer.superclass)
         assert(Object, Regexp.superclass)
         assert(Object, String.superclass)
         assert(Object, Range.superclass)
@@ -426,6 +426,17 @@ fn while2() {
     assert_script(program);
 }
 
+#[test]
+fn do_while() {
+    let program = "
+        assert((a = 10; begin a += 1 end while a < 5; a), 11)
+        assert((a = 0; begin a += 1 end until a >= 3; a), 3)
+        assert((a = 0; begin a += 1; next if a < 3 end while a < 5; a), 5)
+        assert((a = 10; (a += 1) while a < 5; a), 10)
+    ";
+    assert_script(program);
+}
+
 #[test]
 fn until1() {
     let program = "
@@ -593,6 +604,143 @@ fn mul_assign7() {
     assert_script(program);
 }
 
+#[test]
+fn mul_assign_splat() {
+    let program = "
+        a, *b, c = 1, 2, 3, 4
+        assert([1, [2, 3], 4], [a, b, c])
+        a, *b, c = [1]
+        assert([1, [], nil], [a, b, c])
+        *a, b = [1, 2, 3]
+        assert([[1, 2], 3], [a, b])
+        a, *b = 1
+        assert([1, []], [a, b])
+        *a = 1, 2
+        assert([1, 2], a)
+        d = (a, *b = 5, 6, 7)
+        assert([5, 6, 7], d)
+        assert([5, [6, 7]], [a, b])
+        ";
+    assert_script(program);
+}
+
+#[test]
+fn mul_assign_nested() {
+    let program = "
+        (a, b), c = [1, 2], 3
+        assert([1, 2, 3], [a, b, c])
+        a, (b, (c, d)), e = 1, [2, [3, 4]], 5
+        assert([1, 2, 3, 4, 5], [a, b, c, d, e])
+        a, (b, *c), d = [1, [2, 3, 4], 5]
+        assert([1, 2, [3, 4], 5], [a, b, c, d])
+        (a, b) = 7
+        assert([7, nil], [a, b])
+        a, = [5, 6]
+        assert(5, a)
+        a, = 7
+        assert(7, a)
+        a = [5, 6]
+        assert([5, 6], a)
+        d = (a, = [8, 9])
+        assert([[8, 9], 8], [d, a])
+        (a, b), = [[1, 2], 3]
+        assert([1, 2], [a, b])
+        (a, b) = [1, 2]
+        assert([1, 2], [a, b])
+        ";
+    assert_script(program);
+}
+
+#[test]
+fn mul_assign_targets() {
+    let program = "
+        class Foo
+          attr_accessor :x, :y
+          def set(a)
+            @a, @b = a
+            [@a, @b]
+          end
+        end
+        foo = Foo.new
+        assert([1, 2], foo.set([1, 2]))
+        foo.x, foo.y = 3, 4
+        assert([3, 4], [foo.x, foo.y])
+        h = {}
+        ary = [0, 0, 0]
+        h[:a], ary[1], *ary[2] = 5, 6, 7, 8
+        assert(5, h[:a])
+        assert([0, 6, [7, 8]], ary)
+        ";
+    assert_script(program);
+}
+
+#[test]
+fn mul_assign_to_ary() {
+    let program = "
+        class Pair
+          def initialize(a, b)
+            @a = a
+            @b = b
+          end
+          def to_ary
+            [@a, @b]
+          end
+        end
+        a, b = Pair.new(1, 2)
+        assert([1, 2], [a, b])
+        (a, b), c = Pair.new(3, 4), 5
+        assert([3, 4, 5], [a, b, c])
+        a, *b = Pair.new(6, 7)
+        assert([6, [7]], [a, b])
+        [Pair.new(8, 9)].each { |x, y| assert([8, 9], [x, y]) }
+        [Pair.new(10, 11)].each { |(x, y)| assert([10, 11], [x, y]) }
+        [[Pair.new(12, 13), 14]].each { |((x, y), z)| assert([12, 13, 14], [x, y, z]) }
+        [[Pair.new(15, 16)]].each { |(x, y)| assert([Pair, nil], [x.class, y]) }
+        a, = Pair.new(17, 18)
+        assert(17, a)
+        class Bad
+          def to_ary
+            1
+          end
+        end
+        begin
+          a, b = Bad.new
+        rescue TypeError => e
+          assert(\"can't convert Bad to Array (Bad#to_ary gives Integer)\", e.message)
+        end
+        ";
+    assert_script(program);
+}
+
+#[test]
+fn block_param_destructuring() {
+    let program = "
+        h = {a: [1, 2], b: [3, 4]}
+        res = []
+        h.each { |k, (x, y)| res << x + y if k == :b }
+        assert([7], res)
+        res = []
+        [[1, [2, 3]], [4, [5, 6]]].each do |a, (b, c)|
+          res << a + b + c
+        end
+        assert([6, 15], res)
+        res = []
+        [[1, 2, 3]].each { |a, *b| res << b }
+        assert([[2, 3]], res)
+        res = []
+        [[1, [2, 3, 4]]].each { |(a, (b, *c))| res << [a, b, c] }
+        assert([[1, 2, [3, 4]]], res)
+        res = [[1, 2], [3, 4]].map { |(a, b)| a * b }
+        assert([2, 12], res)
+        def sum((a, b), c)
+          a + b + c
+        end
+        assert(6, sum([1, 2], 3))
+        assert(10, ->((a, b)) { a + b }.call([4, 6]))
+        ";
+    assert_script(program);
+}
+
 #[test]
 fn const1() {
     let program = "
@@ -968,3 +1116,668 @@ fn singleton() {
         ";
     assert_script(program);
 }
+
+#[test]
+fn quickening_compare() {
+    let program = "
+    def cmp(a, b)
+        r = []
+        if a == b then r << 1 end
+        if a != b then r << 2 end
+        if a > b then r << 3 end
+        if a >= b then r << 4 end
+        r
+    end
+    assert([2, 3, 4], cmp(5, 3))
+    assert([2, 3, 4], cmp(5, 3))
+    assert([1, 4], cmp(3.0, 3))
+    assert([2], cmp(2, 3.5))
+    assert([2, 3, 4], cmp(3.5, 3))
+    assert([2, 3, 4], cmp(7, 3))
+        ";
+    assert_script(program);
+}
+
+#[test]
+fn quickening_add_locals() {
+    let program = "
+    def add(a, b)
+        x = a + b
+        y = a - b
+        [x, y]
+    end
+    assert([8, 2], add(5, 3))
+    assert([8, 2], add(5, 3))
+    assert([4.5, 1.5], add(3.0, 1.5))
+    assert([[1, 2, 3], [1, 2]], add([1, 2], [3]))
+    assert([2, 0], add(1, 1))
+    r = begin
+        add(\"a\", \"b\")
+    rescue NoMethodError
+        :error
+    end
+    assert(:error, r)
+        ";
+    assert_script(program);
+}
+
+#[test]
+fn quickening_send() {
+    let program = "
+    class Foo
+        attr_reader :a
+        def initialize(a)
+            @a = a
+        end
+        def b(x, y)
+            @a + x * y
+        end
+        def size
+            100
+        end
+    end
+    class Bar
+        def a
+            200
+        end
+        def b(x, y)
+            x - y
+        end
+    end
+    def get(obj)
+        [obj.a, obj.b(3, 4), obj.class]
+    end
+    assert([-5, 7, Foo], get(Foo.new(-5)))
+    assert([-5, 7, Foo], get(Foo.new(-5)))
+    assert([200, -1, Bar], get(Bar.new))
+    assert([1, 13, Foo], get(Foo.new(1)))
+    class Foo
+        def a
+            77
+        end
+        def class
+            :foo
+        end
+    end
+    assert([77, 13, :foo], get(Foo.new(1)))
+    def call(obj, args)
+        obj.b(*args)
+    end
+    assert(14, call(Foo.new(2), [3, 4]))
+    assert(14, call(Foo.new(2), [3, 4]))
+    def s(x)
+        x * 2
+    end
+    def t(x)
+        s(x) + s(x)
+    end
+    assert(8, t(2))
+    assert(8, t(2))
+    def s(x, y = 1)
+        x * y
+    end
+    assert(4, t(2))
+    r = begin
+        Foo.new(1).b(1)
+    rescue ArgumentError
+        :error
+    end
+    assert(:error, r)
+        ";
+    assert_script(program);
+}
+
+#[test]
+fn polymorphic_inline_cache() {
+    let program = "
+    class A; def f; :a; end; end
+    class B; def f; :b; end; end
+    class C; def f; :c; end; end
+    class D; def f; :d; end; end
+    class E; def f; :e; end; end
+    class F < A; end
+    module M; def g; :m; end; end
+    def call(obj)
+        obj.f
+    end
+    objs = [A.new, B.new, C.new, D.new, E.new, F.new]
+    2.times do
+        assert([:a, :b, :c, :d, :e, :a], objs.map { |o| call(o) })
+    end
+    class A; def f; :a2; end; end
+    assert([:a2, :b, :c, :d, :e, :a2], objs.map { |o| call(o) })
+    class F; def f; :f; end; end
+    assert([:a2, :b, :c, :d, :e, :f], objs.map { |o| call(o) })
+    def call_g(obj)
+        obj.g
+    end
+    class B; def g; :b; end; end
+    assert(:b, call_g(B.new))
+    class Object; include M; end
+    assert([:m, :b], [call_g(A.new), call_g(B.new)])
+    class B
+        def make_singleton
+            def self.f
+                :singleton
+            end
+        end
+    end
+    b = B.new
+    assert(:b, call(b))
+    b.make_singleton
+    assert([:singleton, :b], [call(b), call(B.new)])
+        ";
+    assert_script(program);
+}
+
+#[test]
+fn polymorphic_inline_cache_arity() {
+    let program = "
+    class D; def bar(a); a; end; end
+    class C; def bar(a, b); [a, b]; end; end
+    res = []
+    3.times do
+        [D.new, C.new].each do |o|
+            begin
+                res << o.bar(1)
+            rescue ArgumentError
+                res << :error
+            end
+        end
+    end
+    assert([1, :error, 1, :error, 1, :error], res)
+        ";
+    assert_script(program);
+}
+
+#[test]
+fn pattern_matching() {
+    let program = "
+    def m(v)
+        case v
+        in 0 | 1
+            :small
+        in Integer => n if n > 100
+            [:large, n]
+        in 10..20
+            :range
+        in Integer | Float
+            :number
+        in :sym | \"str\"
+            :alt
+        in []
+            :empty
+        in [x]
+            [:one, x]
+        in [1, *rest, 9]
+            [:rest, rest]
+        in [x, [y, *]]
+            [:nest, x, y]
+        in [*, :key, val, *post]
+            [:find, val, post]
+        in [String => s, *]
+            [:str, s]
+        in {name: String => name, age: 18..150 => age}
+            [:person, name, age]
+        in {type: :point, x:, y:, **nil}
+            [:point, x, y]
+        in {type: :point, **rest}
+            [:rest, rest]
+        in {}
+            :empty_hash
+        else
+            :else
+        end
+    end
+    assert(:small, m(1))
+    assert([:large, 200], m(200))
+    assert(:number, m(5))
+    assert(:number, m(1.5))
+    assert(:range, m(15))
+    assert(:range, m(15.5))
+    assert(false, (1152921504606846977 in 0..1152921504606846976))
+    assert(true, (1152921504606846976 in 0..1152921504606846976))
+    assert(false, (20 in 10...20))
+    assert(:alt, m(:sym))
+    assert(:alt, m(\"str\"))
+    assert(:empty, m([]))
+    assert([:one, 3], m([3]))
+    assert([:rest, [2, 3]], m([1, 2, 3, 9]))
+    assert([:rest, []], m([1, 9]))
+    assert([:nest, 1, 2], m([1, [2, 3]]))
+    assert([:find, 5, [6, 7]], m([3, 4, :key, 5, 6, 7]))
+    assert([:str, \"x\"], m([\"x\", 1, 2]))
+    assert([:person, \"bob\", 20], m({name: \"bob\", age: 20}))
+    assert(:else, m({name: \"bob\", age: 17}))
+    assert([:point, 1, 2], m({type: :point, x: 1, y: 2}))
+    assert([:rest, {x: 1, y: 2, z: 3}], m({type: :point, x: 1, y: 2, z: 3}))
+    assert(:empty_hash, m({}))
+    assert(:else, m(nil))
+
+    x = 5
+    r = case 5
+        in ^x then :pinned
+        end
+    assert(:pinned, r)
+    r = case [1, 1]
+        in [a, ^a] then :same
+        end
+    assert(:same, r)
+    r = case 3
+        in n unless n.even? then :odd
+        end
+    assert(:odd, r)
+
+    assert(true, ([1, 2] in [_, _]))
+    assert(false, (1 in String))
+    {name: \"alice\", langs: [\"ruby\", \"rust\"]} => {name:, langs: [*, \"rust\" => lang, *]}
+    assert([\"alice\", \"rust\"], [name, lang])
+
+    r = begin
+        case 42
+        in String then :s
+        end
+    rescue NoMatchingPatternError => e
+        e.message
+    end
+    assert(\"42\", r)
+        ";
+    assert_script(program);
+}
+
+#[test]
+fn pattern_matching_deconstruct() {
+    let program = "
+    class Point
+        def initialize(x, y)
+            @x = x
+            @y = y
+        end
+        def deconstruct
+            [@x, @y]
+        end
+        def deconstruct_keys(keys)
+            $keys = keys
+            {x: @x, y: @y}
+        end
+    end
+    def m(v)
+        case v
+        in Point(0, y)
+            [:on_y, y]
+        in Point[x, 0]
+            [:on_x, x]
+        in Point(x:, y:) if x == y
+            [:diagonal, x]
+        in {x: Integer => x}
+            [:other, x]
+        end
+    end
+    assert([:on_y, 3], m(Point.new(0, 3)))
+    assert([:on_x, 4], m(Point.new(4, 0)))
+    assert([:diagonal, 2], m(Point.new(2, 2)))
+    assert([:other, 1], m(Point.new(1, 2)))
+    assert([:x], $keys)
+    case Point.new(1, 2)
+    in {y:, x:}
+    end
+    assert([:y, :x], $keys)
+    case Point.new(1, 2)
+    in {x:, y:, **rest}
+    end
+    assert(nil, $keys)
+    is_even = ->(x) { x.even? }
+    r = case 4
+        in ^is_even then :even
+        end
+    assert(:even, r)
+        ";
+    assert_script(program);
+}
+
+#[test]
+fn safe_navigation() {
+    let program = "
+    class Foo
+        def bar(x, y)
+            [x, y, block_given?]
+        end
+    end
+    def f
+        $count += 1
+        0
+    end
+    $count = 0
+    a = nil
+    assert(nil, a&.bar)
+    assert(nil, a&.bar(f, f) { 3 })
+    assert(0, $count)
+    assert([1, 2, false], Foo.new&.bar(1, 2))
+    assert([1, 2, true], Foo.new&.bar(1, 2) { 3 })
+    assert(2, [1, 2]&.map { |x| x * 2 }&.first)
+    assert(nil, a&.bar&.baz)
+    assert(nil, a&.foo.bar)
+    assert(nil, a&.foo.bar(f).baz { 3 })
+    assert(0, $count)
+    assert(2, [[1, 2]]&.first.last)
+    assert(true, (a&.foo).nil?)
+    ";
+    assert_script(program);
+}
+
+#[test]
+fn op_assign_attr_index() {
+    let program = "
+    class Foo
+        attr_accessor :v
+    end
+    $foo = Foo.new
+    $count = 0
+    def foo
+        $count += 1
+        $foo
+    end
+    assert(5, foo.v ||= 5)
+    assert(5, foo.v ||= 6)
+    assert(8, foo.v += 3)
+    assert(10, foo.v &&= 10)
+    assert(10, $foo.v)
+    assert(4, $count)
+    a = nil
+    assert(nil, a&.v ||= 1)
+    assert(11, $foo&.v += 1)
+    $foo&.v = 3
+    assert(3, $foo.v)
+    a&.v = 3
+
+    h = {}
+    (h[:k] ||= []) << 1
+    h[:k] ||= 7
+    assert({k: [1]}, h)
+    ary = [1, 2, 3]
+    i = 0
+    ary[i += 1] += 10
+    assert([1, 12, 3], ary)
+    assert(1, i)
+    m = [[1, 2], [3, 4]]
+    m[1][0] *= 5
+    assert([[1, 2], [15, 4]], m)
+
+    x = nil
+    assert(nil, x &&= 3)
+    x = 2
+    x &&= 3
+    assert(3, x)
+    assert(nil, nil && 1)
+    assert(false, false && 1)
+    ";
+    assert_script(program);
+}
+
+#[test]
+fn alias_undef() {
+    let program = r#"
+    class Foo
+        def hello
+            "hello"
+        end
+        alias hi hello
+        alias :hey :hello
+        def bye
+            "bye"
+        end
+        undef bye
+    end
+    class Bar < Foo
+        undef hello
+        alias_method :hello2, :hi
+    end
+    foo = Foo.new
+    assert("hello", foo.hi)
+    assert("hello", foo.hey)
+    assert("hello", Bar.new.hi)
+    assert("hello", Bar.new.hello2)
+    begin
+        Bar.new.hello
+        assert(true, false)
+    rescue NoMethodError
+    end
+    begin
+        foo.bye
+        assert(true, false)
+    rescue NoMethodError
+    end
+    begin
+        class Foo
+            alias x no_such_method
+        end
+        assert(true, false)
+    rescue NameError
+    end
+    $old = 5
+    alias $new $old
+    assert(5, $new)
+    $new = 7
+    assert(7, $old)
+    "#;
+    assert_script(program);
+}
+
+#[test]
+fn defined() {
+    let program = r#"
+    class Foo
+        def bar
+        end
+    end
+    def m
+        defined?(yield)
+    end
+    a = 1
+    foo = Foo.new
+    @iv = 1
+    $gv = 1
+    assert("local-variable", defined?(a))
+    assert(nil, defined?(b))
+    assert("method", defined?(puts))
+    assert("method", defined?(foo.bar))
+    assert(nil, defined?(foo.baz))
+    assert(nil, defined?(b.bar))
+    assert("method", defined? foo.bar)
+    assert("constant", defined?(Foo))
+    assert(nil, defined?(Baz))
+    assert(nil, defined?(Foo::Baz))
+    assert("instance-variable", defined?(@iv))
+    assert(nil, defined?(@nv))
+    assert("global-variable", defined?($gv))
+    assert(nil, defined?($nv))
+    assert("expression", defined?(1))
+    assert("method", defined?(1 + 2))
+    assert("assignment", defined?(a = 2))
+    assert(1, a)
+    assert("self", defined?(self))
+    assert("nil", defined?(nil))
+    assert("true", defined?(true))
+    assert(nil, m)
+    assert("yield", m { })
+    class Foo
+        def baz
+            [defined?(super), [1].map { defined?(super) }]
+        end
+    end
+    class Bar < Foo
+        def bar
+            [defined?(super), [1].map { defined?(super) }]
+        end
+        def qux
+            defined?(super)
+        end
+    end
+    assert(["super", ["super"]], Bar.new.bar)
+    assert(nil, Bar.new.qux)
+    assert([nil, [nil]], Foo.new.baz)
+    assert(nil, defined?(super))
+    "#;
+    assert_script(program);
+}
+
+#[test]
+fn begin_block_and_data() {
+    let program = "
+    assert(1, $x)
+    BEGIN { $x = 1 }
+    assert(\"line1\\nline2\\n\", DATA.read)
+__END__
+line1
+line2
+";
+    assert_script(program);
+}
+
+#[test]
+fn keyword_logical_operators() {
+    let program = "
+    a = true and false
+    assert(true, a)
+    b = nil or 5
+    assert(nil, b)
+    assert(false, (not true))
+    x = 3
+    assert(false, (not x == 3))
+    assert(4, (1 == 1 and 2 == 3 or 4))
+    assert(true, (not false and not nil))
+    c = 0
+    c = 1 if false or true
+    assert(1, c)
+    c = 2 unless true and not false
+    assert(1, c)
+    ";
+    assert_script(program);
+}
+
+#[test]
+fn rescue_modifier() {
+    let program = "
+    def risky
+      raise \"boom\"
+    end
+    v = risky rescue 99
+    assert(99, v)
+    w = 1
+    w += risky rescue 10
+    assert(11, w)
+    assert(\"ok\", (risky rescue \"ok\"))
+    r = nil
+    risky rescue r = :rescued
+    assert(:rescued, r)
+    assert(3, (1 + 2 rescue 0))
+    ";
+    assert_script(program);
+}
+
+#[test]
+fn break_next_with_value() {
+    let program = "
+    assert(42, [1, 2].each { break 42 })
+    def m
+      yield
+      1
+    end
+    assert(5, m { break 5 })
+    assert(20, [1, 2, 3].each { |x| break x * 10 if x == 2 })
+    assert([[1, 2], [3, 4]], [[1, 2], [3, 4]].each { |a| a.each { |b| break b if b == 1 } })
+    assert([3], [1].map { m { break 3 } })
+    def y3
+      [1].each { |x| yield x }
+      :after
+    end
+    assert(:b3, y3 { break :b3 })
+    v = 5
+    res = []
+    assert(:after, y3 { |x| res << x + v })
+    assert([6], res)
+    assert([1, 2], [1].each { break 1, 2 })
+    assert(7, loop { break 7 })
+    assert([1, 10, 3], [1, 2, 3].map { |x| next 10 if x == 2; x })
+    assert([2, 4, 6], (1..3).map { |x| next x * 2 })
+    ";
+    assert_script(program);
+}
+
+#[test]
+fn retry_redo() {
+    let program = "
+    cnt = 0
+    begin
+      cnt += 1
+      raise \"error\" if cnt < 3
+    rescue
+      retry
+    end
+    assert(3, cnt)
+    i = 0
+    res = []
+    while i < 3
+      i += 1
+      res << i
+      if res.size == 2
+        res << :redo
+        redo
+      end
+    end
+    assert([1, 2, :redo, 3], res)
+    t = 0
+    out = []
+    [1, 2].each do |x|
+      t += 1
+      out << [x, t]
+      redo if t == 1
+    end
+    assert([[1, 1], [1, 2], [2, 3]], out)
+    ";
+    assert_script(program);
+}
+
+#[test]
+fn numbered_block_params() {
+    let program = "
+    assert([2, 4, 6], [1, 2, 3].map { _1 * 2 })
+    assert([3, 7], [[1, 2], [3, 4]].map { _1 + _2 })
+    assert([2, 4], [[1, 2], [3, 4]].map { _2 })
+    assert([[1, 2], [3, 4]], [[1, 2], [3, 4]].map { _1 })
+    assert([10, 20, 30], [1, 2, 3].map { it * 10 })
+    assert([[11], [11]], [1, 2].map { [10].map { it + 1 } })
+    assert([4, 5], [1, 2].map { z = 3; _1 + z })
+    assert(2, Proc.new { _2 }.arity)
+    assert(1, Proc.new { it }.arity)
+    assert(0, Proc.new { }.arity)
+    assert(-2, Proc.new { |x, *y| }.arity)
+    def it
+      :method
+    end
+    assert(:method, it)
+    assert([:method], [1].map { |x| it })
+    assert([[2]], [1].map { [2].map { _1 } })
+    assert([[3]], [1].map { |x| [x + 1].map { _1 + 1 } })
+    res = begin
+      eval(\"[1].each { _1; [2].each { p _1 } }\")
+    rescue SyntaxError => e
+      e.message
+    end
+    assert(true, res.include?(\"numbered parameter is already used in outer block\"))
+    ";
+    assert_script(program);
+}
+
+#[test]
+fn block_local_vars() {
+    let program = "
+    y = 100
+    [1, 2].each { |x; y| y = x }
+    assert(100, y)
+    [1].each { |; y, z| y = 5; z = 6 }
+    assert(100, y)
+    assert(1, Proc.new { |x; y| }.arity)
+    ";
+    assert_script(program);
+}